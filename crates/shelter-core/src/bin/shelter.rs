@@ -0,0 +1,9 @@
+//! `shelter` command line tool
+//!
+//! See `shelter --help`; the implementation lives in `shelter_core::cli`.
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    shelter_core::cli::run(std::env::args().skip(1).collect())
+}
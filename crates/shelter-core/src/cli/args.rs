@@ -0,0 +1,153 @@
+//! Command line option parsing shared by the subcommands
+
+use std::io::IsTerminal;
+
+use super::Failure;
+use crate::dialect::Dialect;
+use crate::format::Sort;
+
+/// When to write ANSI colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorChoice {
+    /// When stdout is a terminal and `NO_COLOR` is unset
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output to stdout should be colored
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// Options and file operands of a subcommand
+#[derive(Debug)]
+pub(crate) struct Args {
+    pub files: Vec<String>,
+    pub config: Option<String>,
+    pub color: ColorChoice,
+    pub dialect: Option<Dialect>,
+    /// File name standing in for stdin
+    pub name: Option<String>,
+    /// Only list keys that are missing or differ (`diff`)
+    pub changed: bool,
+    /// Replace values with type hints instead of nothing (`example`)
+    pub hints: bool,
+    /// Keep values that are likely not secret (`example`)
+    pub keep_benign: bool,
+    /// Key order (`fmt`)
+    pub sort: Sort,
+    /// Only report files that are not formatted (`fmt`)
+    pub check: bool,
+    /// Rewrite files in place (`fmt`)
+    pub write: bool,
+    /// Schema file to validate against (`lint`)
+    pub schema: Option<String>,
+    /// Show the usage instead of running the subcommand
+    pub help: bool,
+}
+
+impl Args {
+    pub fn parse(args: Vec<String>) -> Result<Self, Failure> {
+        let mut parsed = Args {
+            files: Vec::new(),
+            config: None,
+            color: ColorChoice::Auto,
+            dialect: None,
+            name: None,
+            changed: false,
+            hints: false,
+            keep_benign: false,
+            sort: Sort::None,
+            check: false,
+            write: false,
+            schema: None,
+            help: false,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.files.extend(args.by_ref());
+                break;
+            }
+            if arg == "-" || !arg.starts_with('-') {
+                parsed.files.push(arg);
+                continue;
+            }
+
+            // `--flag=value` or `--flag value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| Failure::Usage(format!("{} needs a value", flag)))
+            };
+
+            match flag.as_str() {
+                "-c" | "--config" => parsed.config = Some(value()?),
+                "--color" => {
+                    parsed.color = match value()?.as_str() {
+                        "auto" => ColorChoice::Auto,
+                        "always" => ColorChoice::Always,
+                        "never" => ColorChoice::Never,
+                        other => {
+                            return Err(Failure::Usage(format!(
+                                "--color must be auto, always or never, not '{}'",
+                                other
+                            )))
+                        }
+                    }
+                }
+                "--dialect" => {
+                    let name = value()?;
+                    parsed.dialect = match name.as_str() {
+                        "auto" => None,
+                        _ => Some(Dialect::from_name(&name).ok_or_else(|| {
+                            Failure::Usage(format!("unknown dialect '{}'", name))
+                        })?),
+                    }
+                }
+                "--name" => parsed.name = Some(value()?),
+                "--changed" if inline.is_none() => parsed.changed = true,
+                "--hints" if inline.is_none() => parsed.hints = true,
+                "--keep-benign" if inline.is_none() => parsed.keep_benign = true,
+                "--sort" => {
+                    parsed.sort = match value()?.as_str() {
+                        "none" => Sort::None,
+                        "alpha" => Sort::Alphabetical,
+                        "group" => Sort::Grouped,
+                        other => {
+                            return Err(Failure::Usage(format!(
+                                "--sort must be none, alpha or group, not '{}'",
+                                other
+                            )))
+                        }
+                    }
+                }
+                "--check" if inline.is_none() => parsed.check = true,
+                "-w" | "--write" if inline.is_none() => parsed.write = true,
+                "--schema" => parsed.schema = Some(value()?),
+                "-h" | "--help" if inline.is_none() => parsed.help = true,
+                _ => return Err(Failure::Usage(format!("unknown option '{}'", flag))),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
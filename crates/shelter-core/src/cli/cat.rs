@@ -0,0 +1,66 @@
+//! `shelter cat`: print files with their values masked
+
+use std::io::{self, BufWriter, Write};
+
+use korni::ParseOptions;
+
+use super::args::Args;
+use super::config::Config;
+use super::masker::Masker;
+use super::render::render;
+use super::{read_input, Failure};
+use crate::dialect::{detect_dialect, parse_dialect};
+use crate::parse::offset_to_line;
+
+pub(crate) fn run(args: Args) -> Result<(), Failure> {
+    if args.files.is_empty() {
+        return Err(Failure::Usage("cat needs at least one file".to_string()));
+    }
+
+    let config = Config::load(args.config.as_deref())?;
+    let masker = Masker::new(&config).map_err(Failure::Runtime)?;
+    let color = args.color.enabled();
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+
+    let mut unparsed = Vec::new();
+    for file in &args.files {
+        let (content, source) = read_input(file, &args)?;
+
+        let dialect = args
+            .dialect
+            .or(config.dialect)
+            .unwrap_or_else(|| detect_dialect(source, &content));
+        let parsed = parse_dialect(&content, dialect, ParseOptions::full());
+
+        let rendered = render(&content, &parsed, dialect, &masker, source, color);
+        if let Err(e) = out.write_all(&rendered) {
+            return broken_pipe_ok(e);
+        }
+
+        // Unparsed text is printed masked, but the file still failed
+        if let Some(d) = parsed.diagnostics.first() {
+            let line = offset_to_line(&parsed.line_starts, d.start).max(1);
+            let name = source.unwrap_or(file);
+            unparsed.push(format!("{}:{}: {}", name, line, d.message));
+        }
+    }
+
+    out.flush().or_else(broken_pipe_ok)?;
+    match unparsed.is_empty() {
+        true => Ok(()),
+        false => Err(Failure::Runtime(format!(
+            "unparsed text was masked whole\n{}",
+            unparsed.join("\n")
+        ))),
+    }
+}
+
+/// Previewers close the pipe once their window is full; that is not an error
+pub(crate) fn broken_pipe_ok(e: io::Error) -> Result<(), Failure> {
+    match e.kind() {
+        io::ErrorKind::BrokenPipe => Ok(()),
+        _ => Err(e.into()),
+    }
+}
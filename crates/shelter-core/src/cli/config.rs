@@ -0,0 +1,237 @@
+//! CLI configuration file
+//!
+//! Accepts the plugin's config keys (`default_mode`, `mask_char`,
+//! `skip_comments`, `dialect`, `patterns`, `sources`, `credentials` and
+//! `modes.<name>.<option>`) from a TOML or JSON file. The file is read
+//! with the crate's own parsers, so each setting arrives as a dotted key
+//! path. Plugin-only keys (`modules`, `highlight_group`, ...) are
+//! ignored so one file can serve both.
+
+use std::path::PathBuf;
+
+use korni::ParseOptions;
+
+use super::Failure;
+use crate::dialect::{detect_dialect, parse_dialect, Dialect};
+use crate::mask::{MaskMode, MaskOptions};
+
+/// Settings used by `shelter cat` and friends
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    pub default_mode: String,
+    pub skip_comments: bool,
+    /// Dialect for every file (`None` detects it per file)
+    pub dialect: Option<Dialect>,
+    /// Key pattern to mode name
+    pub patterns: Vec<(String, String)>,
+    /// Source file pattern to mode name
+    pub sources: Vec<(String, String)>,
+    /// Credential kind name to mode name
+    pub credentials: Vec<(String, String)>,
+    pub modes: ModeSettings,
+}
+
+/// Options of the built-in modes
+#[derive(Debug, Clone)]
+pub(crate) struct ModeSettings {
+    pub full: MaskOptions,
+    pub partial: MaskOptions,
+    /// Mode `auto` applies to values classified as secrets
+    pub auto_mode: String,
+    /// Whether `auto` masks values that are neither secret nor benign
+    pub auto_mask_unknown: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            default_mode: "full".to_string(),
+            skip_comments: true,
+            dialect: None,
+            patterns: Vec::new(),
+            sources: Vec::new(),
+            credentials: Vec::new(),
+            modes: ModeSettings {
+                full: MaskOptions::default(),
+                partial: MaskOptions {
+                    mode: MaskMode::Partial,
+                    ..MaskOptions::default()
+                },
+                auto_mode: "full".to_string(),
+                auto_mask_unknown: true,
+            },
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from `path` or `$SHELTER_CONFIG`, else from the
+    /// user config directory if the file exists there
+    pub fn load(path: Option<&str>) -> Result<Config, Failure> {
+        let explicit = path
+            .map(PathBuf::from)
+            .or_else(|| env("SHELTER_CONFIG").map(PathBuf::from));
+        let path = match explicit {
+            Some(path) => path,
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+
+        let display = path.display().to_string();
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| Failure::Runtime(format!("{}: {}", display, e)))?;
+        Config::parse(&content, &display).map_err(Failure::Runtime)
+    }
+
+    /// Parse config file content; `name` picks the format and prefixes errors
+    pub fn parse(content: &str, name: &str) -> Result<Config, String> {
+        let dialect = match detect_dialect(Some(name), content) {
+            Dialect::Json => Dialect::Json,
+            _ => Dialect::Toml,
+        };
+        let parsed = parse_dialect(content, dialect, ParseOptions::default());
+
+        if let Some(d) = parsed.diagnostics.first() {
+            let line = crate::parse::offset_to_line(&parsed.line_starts, d.start);
+            return Err(format!("{}:{}: {}", name, line, d.message));
+        }
+
+        let mut config = Config::default();
+        let mut mask_char = None;
+
+        for pair in &parsed.pairs {
+            let value = pair.kv.value.as_ref();
+            let path = split_path(&pair.kv.key);
+            let segments: Vec<&str> = path.iter().map(String::as_str).collect();
+            let at = |message: String| format!("{}:{}: {}", name, pair.line_number, message);
+
+            match segments.as_slice() {
+                ["default_mode"] => config.default_mode = value.to_string(),
+                ["mask_char"] => mask_char = Some(parse_mask_char(value).map_err(at)?),
+                ["skip_comments"] => config.skip_comments = parse_bool(value).map_err(at)?,
+                ["dialect"] => {
+                    config.dialect = match value {
+                        "auto" => None,
+                        _ => Some(
+                            Dialect::from_name(value)
+                                .ok_or_else(|| at(format!("unknown dialect '{}'", value)))?,
+                        ),
+                    }
+                }
+                ["patterns", pattern] => config.patterns.push((pattern.to_string(), value.into())),
+                ["sources", pattern] => config.sources.push((pattern.to_string(), value.into())),
+                ["credentials", kind] => config.credentials.push((kind.to_string(), value.into())),
+                ["modes", mode, option] => {
+                    config.modes.set(mode, option, value).map_err(at)?;
+                }
+                _ => {}
+            }
+        }
+
+        // A global mask_char applies to both masking modes, as in the plugin
+        if let Some(c) = mask_char {
+            config.modes.full.mask_char = c;
+            config.modes.partial.mask_char = c;
+        }
+
+        Ok(config)
+    }
+}
+
+impl ModeSettings {
+    fn set(&mut self, mode: &str, option: &str, value: &str) -> Result<(), String> {
+        let unknown = || format!("unknown option '{}' for mode '{}'", option, mode);
+
+        match mode {
+            "full" => match option {
+                "mask_char" => self.full.mask_char = parse_mask_char(value)?,
+                "preserve_length" => {
+                    parse_bool(value)?;
+                }
+                "fixed_length" => {
+                    self.full.fixed_length = Some(parse_number(value)?).filter(|&n| n > 0)
+                }
+                _ => return Err(unknown()),
+            },
+            "partial" => match option {
+                "mask_char" => self.partial.mask_char = parse_mask_char(value)?,
+                "show_start" => self.partial.show_start = parse_number(value)?,
+                "show_end" => self.partial.show_end = parse_number(value)?,
+                "min_mask" => self.partial.min_mask = parse_number(value)?,
+                "fallback_mode" => {
+                    self.partial.fallback_mode = match value {
+                        "full" => MaskMode::Full,
+                        "none" => MaskMode::None,
+                        _ => return Err("fallback_mode must be full or none".to_string()),
+                    }
+                }
+                _ => return Err(unknown()),
+            },
+            "auto" => match option {
+                "mode" if value == "auto" => return Err("mode cannot be 'auto'".to_string()),
+                "mode" => self.auto_mode = value.to_string(),
+                "mask_unknown" => self.auto_mask_unknown = parse_bool(value)?,
+                _ => return Err(unknown()),
+            },
+            // `none` has no options; other names are custom Lua modes
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// A non-empty environment variable
+fn env(name: &str) -> Option<std::ffi::OsString> {
+    std::env::var_os(name).filter(|v| !v.is_empty())
+}
+
+/// `shelter/config.toml` in the user config directory
+fn default_path() -> Option<PathBuf> {
+    let config_dir = env("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env("APPDATA").map(PathBuf::from))?;
+    Some(config_dir.join("shelter").join("config.toml"))
+}
+
+/// Split a dotted key path into segments, unquoting `"..."` segments
+fn split_path(path: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut chars = path.chars();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => current.extend(chars.next()),
+            '.' if !quoted => segments.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    segments.push(current);
+    segments
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("expected true or false, not '{}'", value)),
+    }
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a non-negative number, not '{}'", value))
+}
+
+fn parse_mask_char(value: &str) -> Result<u8, String> {
+    match value.as_bytes() {
+        [c] => Ok(*c),
+        _ => Err("mask_char must be a single character".to_string()),
+    }
+}
@@ -0,0 +1,96 @@
+//! `shelter diff`: compare keys across files without showing values
+//!
+//! Prints one row per key and one column per file. A cell is `-` when the
+//! file lacks the key, otherwise a letter naming the value: files showing
+//! the same letter hold the same value.
+
+use std::io::{self, BufWriter, Write};
+
+use super::args::Args;
+use super::cat::broken_pipe_ok;
+use super::config::Config;
+use super::{read_input, Failure};
+use crate::compare::{compare, Cell, CellStatus};
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+pub(crate) fn run(args: Args) -> Result<(), Failure> {
+    if args.files.len() < 2 {
+        return Err(Failure::Usage("diff needs at least two files".to_string()));
+    }
+
+    let config = Config::load(args.config.as_deref())?;
+    let color = args.color.enabled();
+
+    let mut contents = Vec::with_capacity(args.files.len());
+    for file in &args.files {
+        let (content, source) = read_input(file, &args)?;
+        contents.push((source.unwrap_or(file).to_string(), content));
+    }
+    let files: Vec<(&str, &str)> = contents
+        .iter()
+        .map(|(path, content)| (path.as_str(), content.as_str()))
+        .collect();
+
+    let keys = compare(&files, args.dialect.or(config.dialect)).map_err(Failure::Runtime)?;
+    let rows: Vec<_> = keys
+        .iter()
+        .filter(|key| !args.changed || !key.is_uniform())
+        .collect();
+
+    let key_width = rows
+        .iter()
+        .map(|key| key.key.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("KEY".len());
+    let widths: Vec<usize> = files.iter().map(|(path, _)| path.chars().count()).collect();
+
+    let mut out = String::new();
+    out.push_str(&format!("{:<key_width$}", "KEY"));
+    for (path, _) in &files {
+        out.push_str(&format!("  {}", path));
+    }
+    out.push('\n');
+
+    for key in rows {
+        out.push_str(&format!("{:<key_width$}", key.key));
+        for (cell, &width) in key.cells.iter().zip(&widths) {
+            let label = format!("{:<width$}", label(cell));
+            match (color, cell.status) {
+                (false, _) => out.push_str(&format!("  {}", label)),
+                (true, status) => {
+                    let ansi = match status {
+                        CellStatus::Missing => RED,
+                        CellStatus::Same => GREEN,
+                        CellStatus::Different => YELLOW,
+                    };
+                    out.push_str(&format!("  {}{}{}", ansi, label, RESET));
+                }
+            }
+        }
+        // Padding of the last column is noise
+        let trimmed = out.trim_end_matches(' ').len();
+        out.truncate(trimmed);
+        out.push('\n');
+    }
+
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    if let Err(e) = writer.write_all(out.as_bytes()) {
+        return broken_pipe_ok(e);
+    }
+    writer.flush().or_else(broken_pipe_ok)
+}
+
+/// `-` for a missing key, else `a`, `b`, ... by value, then numbers
+fn label(cell: &Cell) -> String {
+    match cell.status {
+        CellStatus::Missing => "-".to_string(),
+        _ if cell.group < 26 => char::from(b'a' + cell.group as u8).to_string(),
+        _ => (cell.group + 1).to_string(),
+    }
+}
@@ -0,0 +1,39 @@
+//! `shelter example`: print a `.env.example` version of a file
+
+use std::io::{self, Write};
+
+use super::args::Args;
+use super::cat::broken_pipe_ok;
+use super::config::Config;
+use super::{read_input, Failure};
+use crate::dialect::detect_dialect;
+use crate::example::{generate_example, ExampleOptions, Placeholder};
+
+pub(crate) fn run(args: Args) -> Result<(), Failure> {
+    let [file] = args.files.as_slice() else {
+        return Err(Failure::Usage("example needs exactly one file".to_string()));
+    };
+
+    let config = Config::load(args.config.as_deref())?;
+    let (content, source) = read_input(file, &args)?;
+    let dialect = args
+        .dialect
+        .or(config.dialect)
+        .unwrap_or_else(|| detect_dialect(source, &content));
+
+    let options = ExampleOptions {
+        placeholder: if args.hints {
+            Placeholder::TypeHint
+        } else {
+            Placeholder::Empty
+        },
+        keep_benign: args.keep_benign,
+    };
+    let example = generate_example(&content, dialect, options)
+        .map_err(|e| Failure::Runtime(format!("{}: {}", source.unwrap_or(file), e)))?;
+
+    io::stdout()
+        .lock()
+        .write_all(example.as_bytes())
+        .or_else(broken_pipe_ok)
+}
@@ -0,0 +1,76 @@
+//! `shelter fmt`: format env files
+//!
+//! Prints the formatted file, rewrites files in place with `--write`, or
+//! with `--check` lists the files that are not formatted and exits with 1.
+
+use std::io::{self, Write};
+
+use super::args::Args;
+use super::cat::broken_pipe_ok;
+use super::config::Config;
+use super::{read_input, Failure};
+use crate::dialect::detect_dialect;
+use crate::format::{format, FormatOptions};
+
+pub(crate) fn run(args: Args) -> Result<(), Failure> {
+    if args.files.is_empty() {
+        return Err(Failure::Usage("fmt needs at least one file".to_string()));
+    }
+    if args.check && args.write {
+        return Err(Failure::Usage(
+            "--check and --write exclude each other".to_string(),
+        ));
+    }
+    if !args.check && !args.write && args.files.len() > 1 {
+        return Err(Failure::Usage(
+            "fmt prints one file; use --write or --check for several".to_string(),
+        ));
+    }
+    if args.write && args.files.iter().any(|f| f == "-") {
+        return Err(Failure::Usage("--write cannot write to stdin".to_string()));
+    }
+
+    let config = Config::load(args.config.as_deref())?;
+    let options = FormatOptions { sort: args.sort };
+    let mut unformatted = Vec::new();
+
+    for file in &args.files {
+        let (content, source) = read_input(file, &args)?;
+        let dialect = args
+            .dialect
+            .or(config.dialect)
+            .unwrap_or_else(|| detect_dialect(source, &content));
+        let formatted = format(&content, dialect, options)
+            .map_err(|e| Failure::Runtime(format!("{}: {}", source.unwrap_or(file), e)))?;
+
+        if args.write {
+            if formatted != content {
+                std::fs::write(file, &formatted)
+                    .map_err(|e| Failure::Runtime(format!("{}: {}", file, e)))?;
+            }
+        } else if args.check {
+            if formatted != content {
+                unformatted.push(source.unwrap_or(file).to_string());
+            }
+        } else {
+            return io::stdout()
+                .lock()
+                .write_all(formatted.as_bytes())
+                .or_else(broken_pipe_ok);
+        }
+    }
+
+    if unformatted.is_empty() {
+        return Ok(());
+    }
+    let mut stdout = io::stdout().lock();
+    for path in &unformatted {
+        if let Err(e) = writeln!(stdout, "{}", path) {
+            broken_pipe_ok(e)?;
+        }
+    }
+    Err(Failure::Runtime(match unformatted.len() {
+        1 => "1 file is not formatted".to_string(),
+        n => format!("{} files are not formatted", n),
+    }))
+}
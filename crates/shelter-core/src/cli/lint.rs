@@ -0,0 +1,118 @@
+//! `shelter lint`: check env files, for editors and pre-commit hooks
+//!
+//! Prints one `path:line:column: severity code name: message` line per
+//! finding and exits with 1 if any finding is an error or a warning. Files
+//! are also validated against their `@type`-style annotations and, with
+//! `--schema`, a `.env.schema` file.
+
+use std::io::{self, BufWriter, Write};
+
+use super::args::Args;
+use super::cat::broken_pipe_ok;
+use super::config::Config;
+use super::{read_input, Failure};
+use crate::dialect::detect_dialect;
+use crate::lint::{lint, Severity};
+use crate::parse::{line_starts, offset_to_line};
+use crate::schema::validate;
+
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+pub(crate) fn run(args: Args) -> Result<(), Failure> {
+    if args.files.is_empty() {
+        return Err(Failure::Usage("lint needs at least one file".to_string()));
+    }
+
+    let config = Config::load(args.config.as_deref())?;
+    let schema = match &args.schema {
+        Some(path) => Some(
+            std::fs::read_to_string(path)
+                .map_err(|e| Failure::Runtime(format!("{}: {}", path, e)))?,
+        ),
+        None => None,
+    };
+    let color = args.color.enabled();
+    let mut out = String::new();
+    let mut problems = 0;
+
+    for file in &args.files {
+        let (content, source) = read_input(file, &args)?;
+        let dialect = args
+            .dialect
+            .or(config.dialect)
+            .unwrap_or_else(|| detect_dialect(source, &content));
+        let line_starts = line_starts(&content);
+
+        // (start, severity, code, name, message)
+        let mut reports: Vec<(usize, Severity, &str, &str, String)> = lint(&content, dialect)
+            .into_iter()
+            .map(|f| {
+                let rule = f.rule;
+                (
+                    f.start,
+                    rule.severity(),
+                    rule.code(),
+                    rule.name(),
+                    f.message,
+                )
+            })
+            .collect();
+        let violations = validate(&content, dialect, schema.as_deref()).map_err(|e| {
+            Failure::Runtime(format!("{}: {}", args.schema.as_deref().unwrap_or(file), e))
+        })?;
+        for v in violations {
+            // `@required` in the file itself is a lint rule too
+            if !reports.iter().any(|r| r.0 == v.start && r.4 == v.message) {
+                reports.push((
+                    v.start,
+                    v.code.severity(),
+                    v.code.code(),
+                    v.code.name(),
+                    v.message,
+                ));
+            }
+        }
+        reports.sort_by_key(|r| (r.0, r.2));
+
+        for (start, severity, code, name, message) in reports {
+            let line = offset_to_line(&line_starts, start).max(1);
+            let column = start - line_starts[line - 1] + 1;
+            if severity < Severity::Info {
+                problems += 1;
+            }
+
+            let label = match (color, severity) {
+                (false, _) => severity.name().to_string(),
+                (true, Severity::Error) => format!("{}{}{}", RED, severity.name(), RESET),
+                (true, Severity::Warning) => format!("{}{}{}", YELLOW, severity.name(), RESET),
+                (true, Severity::Info) => format!("{}{}{}", CYAN, severity.name(), RESET),
+            };
+            out.push_str(&format!(
+                "{}:{}:{}: {} {} {}: {}\n",
+                source.unwrap_or(file),
+                line,
+                column,
+                label,
+                code,
+                name,
+                message
+            ));
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    if let Err(e) = writer.write_all(out.as_bytes()) {
+        broken_pipe_ok(e)?;
+    }
+    writer.flush().or_else(broken_pipe_ok)?;
+
+    match problems {
+        0 => Ok(()),
+        1 => Err(Failure::Runtime("1 problem".to_string())),
+        n => Err(Failure::Runtime(format!("{} problems", n))),
+    }
+}
@@ -0,0 +1,120 @@
+//! Mode resolution and masking for the CLI
+//!
+//! Mirrors the plugin: a credential mapping applies unless a key pattern
+//! names the key, then key patterns, source patterns and the default mode.
+//! Custom modes only exist in Lua, so the CLI masks their values fully.
+
+use std::collections::HashMap;
+
+use super::config::{Config, ModeSettings};
+use crate::detect::detect;
+use crate::mask::{mask_value, MaskOptions};
+use crate::parse::ParsedPair;
+use crate::pattern::PatternSet;
+use crate::score::{score_value, SecretClass};
+
+/// A masked value
+pub(crate) struct Masked {
+    /// Replacement for the unquoted value
+    pub text: Vec<u8>,
+    /// Character to fill other lines of a multi-line value with
+    pub mask_char: u8,
+}
+
+pub(crate) struct Masker {
+    patterns: PatternSet,
+    credentials: HashMap<String, String>,
+    modes: ModeSettings,
+    skip_comments: bool,
+}
+
+impl Masker {
+    pub fn new<'a>(config: &'a Config) -> Result<Self, String> {
+        let pairs = |rules: &'a [(String, String)]| {
+            rules
+                .iter()
+                .map(|(pattern, mode)| (pattern.as_str(), mode.as_str()))
+        };
+        let patterns = PatternSet::new(
+            pairs(&config.patterns),
+            pairs(&config.sources),
+            &config.default_mode,
+        )?;
+
+        Ok(Masker {
+            patterns,
+            credentials: config.credentials.iter().cloned().collect(),
+            modes: config.modes.clone(),
+            skip_comments: config.skip_comments,
+        })
+    }
+
+    /// Mask a pair read from `source`; `None` if its value is shown as is
+    ///
+    /// `is_reference` is set when the value is a lone `${VAR}` resolving to
+    /// another entry, which reveals nothing.
+    pub fn mask(
+        &self,
+        pair: &ParsedPair,
+        is_reference: bool,
+        source: Option<&str>,
+    ) -> Option<Masked> {
+        let kv = &pair.kv;
+        if (kv.is_comment && self.skip_comments) || is_reference {
+            return None;
+        }
+
+        let mode = self.mode_for(&kv.key, &kv.value, source);
+        self.apply(&mode, &kv.value)
+    }
+
+    /// Character full masks are made of
+    pub fn mask_char(&self) -> u8 {
+        self.modes.full.mask_char
+    }
+
+    fn mode_for(&self, key: &str, value: &str, source: Option<&str>) -> String {
+        let credential = detect(value)
+            .name()
+            .and_then(|name| self.credentials.get(name));
+        if let Some(mode) = credential {
+            if self.patterns.match_key(key).is_none() {
+                return mode.clone();
+            }
+        }
+
+        self.patterns
+            .resolve(key, source)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn apply(&self, mode: &str, value: &str) -> Option<Masked> {
+        let options = match mode {
+            "none" => return None,
+            "partial" => &self.modes.partial,
+            "auto" => {
+                let class = score_value(value).class;
+                let reveal = class == SecretClass::LikelyBenign
+                    || (class == SecretClass::Unknown && !self.modes.auto_mask_unknown);
+                if reveal {
+                    return None;
+                }
+                return match self.modes.auto_mode.as_str() {
+                    "auto" => self.masked(value, &self.modes.full),
+                    mode => self.apply(mode, value),
+                };
+            }
+            // `full`, and custom modes the CLI cannot run
+            _ => &self.modes.full,
+        };
+        self.masked(value, options)
+    }
+
+    fn masked(&self, value: &str, options: &MaskOptions) -> Option<Masked> {
+        mask_value(value.as_bytes(), options).map(|m| Masked {
+            text: m.mask,
+            mask_char: options.mask_char,
+        })
+    }
+}
@@ -0,0 +1,126 @@
+//! `shelter` command line interface
+//!
+//! Prints env and config files with their values masked by the same modes
+//! and pattern rules as the plugin, so terminal pickers (fzf, bat, lf) and
+//! shared tmux sessions don't show secrets, compares files key by key
+//! without printing values, writes `.env.example` files, and lints and
+//! formats env files. The `shelter` binary is a thin wrapper around [`run`].
+
+mod args;
+mod cat;
+mod config;
+mod diff;
+mod example;
+mod fmt;
+mod lint;
+mod masker;
+mod render;
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use args::Args;
+
+const USAGE: &str = "\
+shelter - show env and config files with secrets masked
+
+Usage:
+  shelter cat [options] <file>...   Print files with values masked (- reads stdin)
+  shelter diff [options] <file>...  Compare keys across files without showing values
+  shelter example [options] <file>  Print the file with values replaced, for .env.example
+  shelter lint [options] <file>...  Report problems; exits with 1 on errors and warnings
+  shelter fmt [options] <file>...   Print the file formatted, or format files in place
+
+Options:
+  -c, --config <file>   Config file (default: $SHELTER_CONFIG, then
+                        $XDG_CONFIG_HOME/shelter/config.toml)
+      --color <when>    Color output: auto, always or never (default: auto)
+      --dialect <name>  Parse files as this dialect instead of detecting it
+      --name <path>     File name for stdin, used for dialect and sources
+      --changed         diff: only list keys that are missing or differ
+      --hints           example: write type hints (<url>, <int>) instead of nothing
+      --keep-benign     example: keep values that are likely not secret
+      --sort <order>    fmt: none (default), alpha (within sections) or group
+                        (all keys, grouped by prefix)
+  -w, --write           fmt: rewrite the files instead of printing
+      --check           fmt: list files that are not formatted, exit with 1
+      --schema <file>   lint: also validate against this .env.schema file
+  -h, --help            Show this help
+  -V, --version         Show the version
+";
+
+/// An error and the exit code it maps to
+pub(crate) enum Failure {
+    /// Bad arguments or config (exit code 2)
+    Usage(String),
+    /// A file could not be read or written (exit code 1)
+    Runtime(String),
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Self {
+        Failure::Runtime(e.to_string())
+    }
+}
+
+/// Run the CLI with the arguments after the program name
+pub fn run(args: Vec<String>) -> ExitCode {
+    match dispatch(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Usage(message)) => {
+            eprintln!("shelter: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(Failure::Runtime(message)) => {
+            eprintln!("shelter: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn dispatch(args: Vec<String>) -> Result<(), Failure> {
+    let mut args = args.into_iter();
+    let command = args.next();
+    let rest: Vec<String> = args.collect();
+
+    let subcommand: fn(Args) -> Result<(), Failure> = match command.as_deref() {
+        Some("cat") => cat::run,
+        Some("diff") => diff::run,
+        Some("example") => example::run,
+        Some("lint") => lint::run,
+        Some("fmt") => fmt::run,
+        Some("-h" | "--help" | "help") => return print(USAGE),
+        Some("-V" | "--version") => {
+            return print(&format!("shelter {}\n", env!("CARGO_PKG_VERSION")))
+        }
+        Some(other) => return Err(Failure::Usage(format!("unknown command '{}'", other))),
+        None => return Err(Failure::Usage("missing command".to_string())),
+    };
+
+    // `-h` after a subcommand shows the usage too
+    let args = Args::parse(rest)?;
+    if args.help {
+        return print(USAGE);
+    }
+    subcommand(args)
+}
+
+/// Read a file operand (`-` is stdin) and the path it stands for
+fn read_input<'a>(file: &'a str, args: &'a Args) -> Result<(String, Option<&'a str>), Failure> {
+    if file == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| Failure::Runtime(format!("stdin: {}", e)))?;
+        return Ok((content, args.name.as_deref()));
+    }
+
+    let content =
+        std::fs::read_to_string(file).map_err(|e| Failure::Runtime(format!("{}: {}", file, e)))?;
+    Ok((content, Some(file)))
+}
+
+fn print(text: &str) -> Result<(), Failure> {
+    io::stdout().write_all(text.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,242 @@
+//! Masked, optionally colored rendering of a parsed file
+//!
+//! Every byte of the source gets a style (key, value, comment or plain) and
+//! masked values are swapped in over their unquoted span. Multi-line values
+//! are masked line by line so the output keeps the file's line layout.
+//! Text the parser rejected or skipped may hold values it could not find, so
+//! those ranges are masked whole: rendering never fails open.
+
+use korni::QuoteType;
+
+use super::masker::Masker;
+use crate::dialect::Dialect;
+use crate::interpolate::resolve;
+use crate::parse::ParsedContent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    Plain,
+    Key,
+    Value,
+    Masked,
+    Comment,
+}
+
+impl Style {
+    fn ansi(self) -> &'static str {
+        match self {
+            Style::Plain => "",
+            Style::Key => "\x1b[36m",
+            Style::Value => "\x1b[32m",
+            Style::Masked => "\x1b[33m",
+            Style::Comment => "\x1b[90m",
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+
+/// A masked byte range of the source and its replacement
+struct Replacement {
+    start: usize,
+    end: usize,
+    text: Vec<u8>,
+}
+
+/// Render `input` read as `dialect` with masked values, in color if `color`
+/// is set
+pub(crate) fn render(
+    input: &str,
+    parsed: &ParsedContent,
+    dialect: Dialect,
+    masker: &Masker,
+    source: Option<&str>,
+    color: bool,
+) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut styles = vec![Style::Plain; bytes.len()];
+    let mut replacements = Vec::new();
+
+    let mut paint = |start: usize, end: usize, style: Style| {
+        if let Some(range) = styles.get_mut(start..end) {
+            range.fill(style);
+        }
+    };
+
+    for comment in &parsed.comments {
+        paint(comment.start, comment.end, Style::Comment);
+    }
+
+    let resolutions = resolve(input, &parsed.pairs, dialect);
+    for (pair, resolution) in parsed.pairs.iter().zip(resolutions) {
+        let kv = &pair.kv;
+        let Some(span) = kv.value_span else {
+            continue;
+        };
+        let (start, end) = inner_span(input, span.start.offset, span.end.offset, kv.quote);
+
+        if !kv.is_comment {
+            if let Some(key) = kv.key_span {
+                paint(key.start.offset, key.end.offset, Style::Key);
+            }
+            paint(span.start.offset, span.end.offset, Style::Value);
+        }
+
+        let is_reference = resolution.is_some_and(|r| r.is_reference);
+        if let Some(masked) = masker.mask(pair, is_reference, source) {
+            mask_span(
+                input,
+                start,
+                end,
+                masked.text,
+                masked.mask_char,
+                &mut replacements,
+            );
+        }
+    }
+
+    // Unparsed ranges replace any value masks they overlap
+    let mut ranges: Vec<(usize, usize)> = parsed
+        .diagnostics
+        .iter()
+        .map(|d| (d.start, d.end))
+        .chain(parsed.skipped.iter().copied())
+        .map(|(start, end)| (start.min(bytes.len()), end.min(bytes.len())))
+        .collect();
+    ranges.sort_unstable();
+    let mut unparsed: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match unparsed.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ if start < end => unparsed.push((start, end)),
+            _ => {}
+        }
+    }
+    replacements.retain(|r| {
+        !unparsed
+            .iter()
+            .any(|&(start, end)| r.start < end && start < r.end)
+    });
+    for &(start, end) in &unparsed {
+        let mask = vec![masker.mask_char(); input[start..end].chars().count()];
+        mask_span(
+            input,
+            start,
+            end,
+            mask,
+            masker.mask_char(),
+            &mut replacements,
+        );
+    }
+
+    replacements.sort_by_key(|r| r.start);
+    emit(bytes, &styles, &replacements, color)
+}
+
+/// Span of a value without its quotes
+fn inner_span(input: &str, start: usize, end: usize, quote: QuoteType) -> (usize, usize) {
+    let quote = match quote {
+        QuoteType::None => return (start, end),
+        QuoteType::Single => "'",
+        QuoteType::Double => "\"",
+    };
+    let raw = &input[start..end];
+
+    // TOML and Python multi-line strings use three quotes
+    let triple = quote.repeat(3);
+    let width = if raw.len() >= 6 && raw.starts_with(&triple) && raw.ends_with(&triple) {
+        3
+    } else {
+        1
+    };
+
+    let open = if raw.starts_with(quote) { width } else { 0 };
+    let close = if raw.len() > open && raw.ends_with(quote) {
+        width
+    } else {
+        0
+    };
+    (start + open, end - close)
+}
+
+/// Replace a value span with its mask; spans covering several lines are
+/// filled with the mask character line by line, leaving indentation alone
+fn mask_span(
+    input: &str,
+    start: usize,
+    end: usize,
+    mask: Vec<u8>,
+    mask_char: u8,
+    out: &mut Vec<Replacement>,
+) {
+    let raw = &input[start..end];
+    if !raw.contains('\n') {
+        out.push(Replacement {
+            start,
+            end,
+            text: mask,
+        });
+        return;
+    }
+
+    let mut offset = start;
+    for (i, segment) in raw.split('\n').enumerate() {
+        let line = segment.strip_suffix('\r').unwrap_or(segment);
+        let indent = if i == 0 {
+            0
+        } else {
+            line.len() - line.trim_start_matches([' ', '\t']).len()
+        };
+        let chars = line[indent..].chars().count();
+        if chars > 0 {
+            out.push(Replacement {
+                start: offset + indent,
+                end: offset + line.len(),
+                text: vec![mask_char; chars],
+            });
+        }
+        offset += segment.len() + 1;
+    }
+}
+
+fn emit(bytes: &[u8], styles: &[Style], replacements: &[Replacement], color: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len() + bytes.len() / 4);
+    let mut current = Style::Plain;
+    let mut set_style = |out: &mut Vec<u8>, style: Style| {
+        if !color || style == current {
+            return;
+        }
+        if current != Style::Plain {
+            out.extend_from_slice(RESET.as_bytes());
+        }
+        out.extend_from_slice(style.ansi().as_bytes());
+        current = style;
+    };
+
+    let mut pos = 0;
+    let mut next = replacements.iter().peekable();
+    while pos < bytes.len() {
+        if let Some(r) = next.next_if(|r| r.start <= pos) {
+            // Overlapping replacements cannot happen, but never go backwards
+            if r.start == pos && r.end > pos {
+                set_style(&mut out, Style::Masked);
+                out.extend_from_slice(&r.text);
+                pos = r.end;
+            }
+            continue;
+        }
+
+        let byte = bytes[pos];
+        // Close colors at line ends so line-oriented previewers stay clean
+        let style = if byte == b'\n' || byte == b'\r' {
+            Style::Plain
+        } else {
+            styles[pos]
+        };
+        set_style(&mut out, style);
+        out.push(byte);
+        pos += 1;
+    }
+    set_style(&mut out, Style::Plain);
+    out
+}
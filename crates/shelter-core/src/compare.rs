@@ -0,0 +1,128 @@
+//! Multi-file comparison for shelter-core
+//!
+//! Builds a key × file matrix telling, for every key, which files define it
+//! and which of those definitions agree. Values are compared through keyed
+//! fingerprints, so only equality is ever reported, never the values.
+
+use std::collections::HashMap;
+
+use korni::ParseOptions;
+
+use crate::dialect::{detect_dialect, parse_dialect, Dialect};
+use crate::fingerprint::{fingerprint, random_key};
+
+/// How a file's definition of a key relates to the other files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CellStatus {
+    /// The file does not define the key
+    Missing,
+    /// Equal in every file that defines the key
+    Same,
+    /// At least one other file has a different value
+    Different,
+}
+
+/// One file's definition of a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Cell {
+    pub status: CellStatus,
+    /// 1-based line of the effective definition (0 if missing)
+    pub line_number: usize,
+    /// Equal values share a group; groups are numbered from 0 in file order
+    pub group: usize,
+}
+
+/// A row of the matrix
+#[derive(Debug, Clone)]
+pub(crate) struct ComparedKey {
+    pub key: String,
+    /// One cell per file, in input order
+    pub cells: Vec<Cell>,
+    /// Number of distinct values
+    pub groups: usize,
+}
+
+impl ComparedKey {
+    /// Whether every file defines the key with the same value
+    pub fn is_uniform(&self) -> bool {
+        self.groups == 1 && self.cells.iter().all(|c| c.status == CellStatus::Same)
+    }
+}
+
+/// Line number and value fingerprint of a file's effective definition
+type Definition = (usize, blake3::Hash);
+
+/// Compare `files` (path, content), read as `dialect` or as detected per
+/// file. Keys are listed in the order they first appear; within a file the
+/// last definition of a key is the effective one.
+pub(crate) fn compare(
+    files: &[(&str, &str)],
+    dialect: Option<Dialect>,
+) -> Result<Vec<ComparedKey>, String> {
+    // A fresh random key per comparison: fingerprints only need to agree
+    // within one call and mean nothing outside it
+    let key = random_key()?;
+
+    let mut rows: Vec<(String, Vec<Option<Definition>>)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for (file, (path, content)) in files.iter().enumerate() {
+        let dialect = dialect.unwrap_or_else(|| detect_dialect(Some(path), content));
+        let options = ParseOptions {
+            include_comments: false,
+            track_positions: true,
+        };
+        let parsed = parse_dialect(content, dialect, options);
+
+        for pair in parsed.pairs.iter().filter(|p| !p.kv.is_comment) {
+            let row = *index.entry(pair.kv.key.to_string()).or_insert_with(|| {
+                rows.push((pair.kv.key.to_string(), vec![None; files.len()]));
+                rows.len() - 1
+            });
+            let hash = fingerprint(pair.kv.value.as_bytes(), &key);
+            rows[row].1[file] = Some((pair.line_number, hash));
+        }
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|(key, definitions)| {
+            let (cells, groups) = cells(&definitions);
+            ComparedKey { key, cells, groups }
+        })
+        .collect())
+}
+
+/// Cells of one key and its number of distinct values
+fn cells(definitions: &[Option<Definition>]) -> (Vec<Cell>, usize) {
+    let mut groups: Vec<blake3::Hash> = Vec::new();
+    let mut cells: Vec<Cell> = definitions
+        .iter()
+        .map(|definition| match *definition {
+            None => Cell {
+                status: CellStatus::Missing,
+                line_number: 0,
+                group: 0,
+            },
+            Some((line_number, hash)) => {
+                // `Hash` equality is constant-time
+                let group = groups.iter().position(|g| *g == hash).unwrap_or_else(|| {
+                    groups.push(hash);
+                    groups.len() - 1
+                });
+                Cell {
+                    status: CellStatus::Same,
+                    line_number,
+                    group,
+                }
+            }
+        })
+        .collect();
+
+    if groups.len() > 1 {
+        for cell in cells.iter_mut().filter(|c| c.status == CellStatus::Same) {
+            cell.status = CellStatus::Different;
+        }
+    }
+    (cells, groups.len())
+}
@@ -0,0 +1,280 @@
+//! Known credential format detection for shelter-core
+//!
+//! Tags values that match the published format of a credential issuer.
+//! Checksums (GitHub, npm) and structure (JWT header, PEM armor, AWS key
+//! alphabet) are validated where possible so that look-alike values such as
+//! `ghp_example` or `AKIA-not-a-key` are not tagged.
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+
+const BASE62: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Credential type of a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CredentialKind {
+    None,
+    AwsAccessKey,
+    GithubToken,
+    GitlabToken,
+    NpmToken,
+    StripeKey,
+    SlackToken,
+    SlackWebhook,
+    Jwt,
+    GoogleApiKey,
+    SendgridKey,
+    PrivateKey,
+}
+
+impl CredentialKind {
+    /// Name used in the `credentials` config (`None` has none)
+    pub fn name(self) -> Option<&'static str> {
+        Some(match self {
+            CredentialKind::None => return None,
+            CredentialKind::AwsAccessKey => "aws_access_key",
+            CredentialKind::GithubToken => "github_token",
+            CredentialKind::GitlabToken => "gitlab_token",
+            CredentialKind::NpmToken => "npm_token",
+            CredentialKind::StripeKey => "stripe_key",
+            CredentialKind::SlackToken => "slack_token",
+            CredentialKind::SlackWebhook => "slack_webhook",
+            CredentialKind::Jwt => "jwt",
+            CredentialKind::GoogleApiKey => "google_api_key",
+            CredentialKind::SendgridKey => "sendgrid_key",
+            CredentialKind::PrivateKey => "private_key",
+        })
+    }
+}
+
+/// A format check and the kind it detects
+type Detector = (fn(&str) -> bool, CredentialKind);
+
+/// Prefix checks come first and reject most values within a few bytes
+const DETECTORS: [Detector; 11] = [
+    (is_aws_access_key, CredentialKind::AwsAccessKey),
+    (is_github_token, CredentialKind::GithubToken),
+    (is_gitlab_token, CredentialKind::GitlabToken),
+    (is_npm_token, CredentialKind::NpmToken),
+    (is_stripe_key, CredentialKind::StripeKey),
+    (is_slack_token, CredentialKind::SlackToken),
+    (is_slack_webhook, CredentialKind::SlackWebhook),
+    (is_google_api_key, CredentialKind::GoogleApiKey),
+    (is_sendgrid_key, CredentialKind::SendgridKey),
+    (is_jwt, CredentialKind::Jwt),
+    (is_private_key, CredentialKind::PrivateKey),
+];
+
+/// Detect the credential type of a value
+pub(crate) fn detect(value: &str) -> CredentialKind {
+    let value = value.trim();
+
+    DETECTORS
+        .iter()
+        .find(|(check, _)| check(value))
+        .map_or(CredentialKind::None, |&(_, kind)| kind)
+}
+
+#[inline]
+fn all_bytes(value: &str, allowed: impl Fn(u8) -> bool) -> bool {
+    value.bytes().all(allowed)
+}
+
+#[inline]
+fn is_base62(b: u8) -> bool {
+    b.is_ascii_alphanumeric()
+}
+
+#[inline]
+fn is_base64url(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+}
+
+/// Encode a CRC32 as 6 base62 digits, most significant first
+fn crc32_base62(data: &[u8]) -> [u8; 6] {
+    let mut n = crc32fast::hash(data);
+    let mut out = [b'0'; 6];
+    for slot in out.iter_mut().rev() {
+        *slot = BASE62[(n % 62) as usize];
+        n /= 62;
+    }
+    out
+}
+
+/// `<prefix><30 random base62><6 base62 CRC32 of the random part>`
+///
+/// Used by GitHub (`ghp_`, `gho_`, ...) and npm (`npm_`) tokens.
+fn has_crc32_checksum(body: &str) -> bool {
+    body.len() == 36
+        && all_bytes(body, is_base62)
+        && crc32_base62(&body.as_bytes()[..30]) == body.as_bytes()[30..]
+}
+
+/// Key id prefix (`AKIA` long-term, `ASIA` temporary, ...) followed by 16 base32 characters
+fn is_aws_access_key(value: &str) -> bool {
+    const PREFIXES: [&str; 5] = ["AKIA", "ASIA", "ABIA", "ACCA", "AGPA"];
+
+    value.len() == 20
+        && PREFIXES.iter().any(|p| value.starts_with(p))
+        && all_bytes(&value[4..], |b| {
+            b.is_ascii_uppercase() || (b'2'..=b'7').contains(&b)
+        })
+}
+
+fn is_github_token(value: &str) -> bool {
+    const PREFIXES: [&str; 5] = ["ghp_", "gho_", "ghu_", "ghs_", "ghr_"];
+
+    if let Some(prefix) = PREFIXES.iter().find(|p| value.starts_with(**p)) {
+        return has_crc32_checksum(&value[prefix.len()..]);
+    }
+
+    // Fine-grained tokens: github_pat_<22 base62>_<59 base62>, no public checksum
+    value
+        .strip_prefix("github_pat_")
+        .and_then(|rest| rest.split_once('_'))
+        .is_some_and(|(id, secret)| {
+            id.len() == 22
+                && secret.len() == 59
+                && all_bytes(id, is_base62)
+                && all_bytes(secret, is_base62)
+        })
+}
+
+fn is_gitlab_token(value: &str) -> bool {
+    value
+        .strip_prefix("glpat-")
+        .is_some_and(|rest| rest.len() >= 20 && all_bytes(rest, is_base64url))
+}
+
+fn is_npm_token(value: &str) -> bool {
+    value.strip_prefix("npm_").is_some_and(has_crc32_checksum)
+}
+
+/// `sk_live_...`, `rk_test_...`, `pk_live_...` and webhook secrets `whsec_...`
+fn is_stripe_key(value: &str) -> bool {
+    if let Some(rest) = value.strip_prefix("whsec_") {
+        return rest.len() >= 24 && all_bytes(rest, is_base62);
+    }
+
+    let rest = ["sk_", "rk_", "pk_"]
+        .iter()
+        .find_map(|p| value.strip_prefix(p))
+        .and_then(|rest| {
+            rest.strip_prefix("live_")
+                .or_else(|| rest.strip_prefix("test_"))
+        });
+
+    rest.is_some_and(|rest| (10..=247).contains(&rest.len()) && all_bytes(rest, is_base62))
+}
+
+/// `xoxb-`, `xoxp-`, `xoxa-`, `xoxr-`, `xoxs-` and `xapp-` tokens
+///
+/// Dash-separated segments: a numeric id first, the secret last.
+fn is_slack_token(value: &str) -> bool {
+    const PREFIXES: [&str; 6] = ["xoxb-", "xoxp-", "xoxa-", "xoxr-", "xoxs-", "xapp-"];
+
+    PREFIXES
+        .iter()
+        .find_map(|p| value.strip_prefix(p))
+        .is_some_and(|rest| {
+            let segments: Vec<&str> = rest.split('-').collect();
+            rest.len() >= 20
+                && segments.len() >= 3
+                && all_bytes(segments[0], |b| b.is_ascii_digit())
+                && segments
+                    .iter()
+                    .all(|s| !s.is_empty() && all_bytes(s, is_base62))
+        })
+}
+
+/// `https://hooks.slack.com/services/T.../B.../<secret>`
+fn is_slack_webhook(value: &str) -> bool {
+    value
+        .strip_prefix("https://hooks.slack.com/services/")
+        .is_some_and(|path| {
+            let parts: Vec<&str> = path.split('/').collect();
+            parts.len() == 3
+                && parts[0].starts_with('T')
+                && parts[1].starts_with('B')
+                && parts[2].len() >= 20
+                && parts.iter().all(|p| all_bytes(p, is_base62))
+        })
+}
+
+/// `AIza` followed by 35 URL-safe characters
+fn is_google_api_key(value: &str) -> bool {
+    value.len() == 39 && value.starts_with("AIza") && all_bytes(&value[4..], is_base64url)
+}
+
+/// `SG.<22>.<43>`
+fn is_sendgrid_key(value: &str) -> bool {
+    let mut parts = value.split('.');
+    matches!(
+        (parts.next(), parts.next(), parts.next(), parts.next()),
+        (Some("SG"), Some(id), Some(secret), None)
+            if id.len() == 22
+                && secret.len() == 43
+                && all_bytes(id, is_base64url)
+                && all_bytes(secret, is_base64url)
+    )
+}
+
+/// Three base64url segments whose header decodes to a JSON object with `alg`
+fn is_jwt(value: &str) -> bool {
+    if !value.starts_with("eyJ") {
+        return false;
+    }
+
+    let parts: Vec<&str> = value.split('.').collect();
+    if parts.len() != 3
+        || parts[..2].iter().any(|p| p.is_empty())
+        || !parts.iter().all(|p| all_bytes(p, is_base64url))
+    {
+        return false;
+    }
+
+    URL_SAFE_NO_PAD
+        .decode(parts[0])
+        .ok()
+        .and_then(|header| String::from_utf8(header).ok())
+        .is_some_and(|header| {
+            let header = header.trim();
+            header.starts_with('{') && header.ends_with('}') && header.contains("\"alg\"")
+        })
+}
+
+/// PEM armored private key with a matching END line and a base64 body
+fn is_private_key(value: &str) -> bool {
+    let Some(begin) = value.find("-----BEGIN ") else {
+        return false;
+    };
+    let after = &value[begin + "-----BEGIN ".len()..];
+    let Some(label_end) = after.find("-----") else {
+        return false;
+    };
+
+    let label = &after[..label_end];
+    if !label.ends_with("PRIVATE KEY") && !label.ends_with("PRIVATE KEY BLOCK") {
+        return false;
+    }
+
+    let footer = format!("-----END {}-----", label);
+    let Some(end) = after.find(&footer) else {
+        return false;
+    };
+
+    // Body: optional `Header: value` lines, then base64
+    let body: String = after[label_end + 5..end]
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.contains(':'))
+        .collect();
+
+    // PGP armor ends with a `=XXXX` CRC line
+    let body = match body.rfind('=') {
+        Some(pos) if label.contains("PGP") && body.len() - pos == 5 => &body[..pos],
+        _ => body.as_str(),
+    };
+
+    !body.is_empty() && STANDARD.decode(body).is_ok()
+}
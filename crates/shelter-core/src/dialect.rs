@@ -0,0 +1,1254 @@
+//! Dotenv dialects beyond EDF
+//!
+//! EDF files go through korni. Every other dialect is read by one of two
+//! scanners driven by a small rule table: a line scanner for
+//! docker-compose, systemd, python-dotenv and Node dotenv, and a shell word
+//! scanner for `export`-style snippets and direnv `.envrc` files. JSON, TOML
+//! INI and YAML config files have their own parsers and are selected the
+//! same way.
+//!
+//! All produce korni `KeyValuePair`s with the same span conventions as EDF
+//! (quoted value spans include the quotes), so entries, masks and inline
+//! comments line up no matter which dialect read the file.
+
+use std::borrow::Cow;
+
+use korni::{KeyValuePair, ParseOptions, Position, QuoteType, Span};
+
+use crate::ini::parse_ini;
+use crate::json::parse_json;
+use crate::parse::{
+    line_starts, offset_to_line, pair_lines, parse_content, Diagnostic, ParsedComment,
+    ParsedContent, ParsedPair, ValueEncoding,
+};
+use crate::toml::parse_toml;
+use crate::types::ShelterDiagnosticCode;
+use crate::yaml::parse_yaml;
+
+/// Dotenv dialect or config file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    Edf,
+    DockerCompose,
+    Systemd,
+    Direnv,
+    Shell,
+    PythonDotenv,
+    NodeDotenv,
+    Json,
+    Toml,
+    Ini,
+    Yaml,
+}
+
+impl Dialect {
+    const ALL: [Dialect; 11] = [
+        Dialect::Edf,
+        Dialect::DockerCompose,
+        Dialect::Systemd,
+        Dialect::Direnv,
+        Dialect::Shell,
+        Dialect::PythonDotenv,
+        Dialect::NodeDotenv,
+        Dialect::Json,
+        Dialect::Toml,
+        Dialect::Ini,
+        Dialect::Yaml,
+    ];
+
+    /// Name used in the config and on the command line (as in Lua)
+    pub fn name(self) -> &'static str {
+        match self {
+            Dialect::Edf => "edf",
+            Dialect::DockerCompose => "docker_compose",
+            Dialect::Systemd => "systemd",
+            Dialect::Direnv => "direnv",
+            Dialect::Shell => "shell",
+            Dialect::PythonDotenv => "python_dotenv",
+            Dialect::NodeDotenv => "node_dotenv",
+            Dialect::Json => "json",
+            Dialect::Toml => "toml",
+            Dialect::Ini => "ini",
+            Dialect::Yaml => "yaml",
+        }
+    }
+
+    /// Look up a dialect by name
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        Dialect::ALL.into_iter().find(|d| d.name() == name)
+    }
+
+    /// Whether this is a config format rather than a line-based env dialect
+    pub(crate) fn is_config(self) -> bool {
+        matches!(
+            self,
+            Dialect::Json | Dialect::Toml | Dialect::Ini | Dialect::Yaml
+        )
+    }
+
+    /// Whether loaders of this dialect expand `$VAR` references; elsewhere
+    /// a `$` is literal text (dotenv, systemd and config formats)
+    pub(crate) fn interpolates(self) -> bool {
+        matches!(
+            self,
+            Dialect::Edf
+                | Dialect::DockerCompose
+                | Dialect::Direnv
+                | Dialect::Shell
+                | Dialect::PythonDotenv
+        )
+    }
+
+    /// Escapes accepted inside double quotes (escaped byte, meaning); empty
+    /// for config formats, which have their own string syntax
+    pub(crate) fn double_quote_escapes(self) -> &'static [(u8, char)] {
+        match self {
+            Dialect::Edf => EDF_DOUBLE_ESCAPES,
+            Dialect::Shell | Dialect::Direnv => SHELL_DOUBLE_ESCAPES,
+            Dialect::DockerCompose => COMPOSE.double_escapes,
+            Dialect::Systemd => SYSTEMD.double_escapes,
+            Dialect::PythonDotenv => PYTHON.double_escapes,
+            Dialect::NodeDotenv => NODE.double_escapes,
+            Dialect::Json | Dialect::Toml | Dialect::Ini | Dialect::Yaml => &[],
+        }
+    }
+}
+
+/// Where an unquoted value may end in a `#` comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InlineComment {
+    /// `#` is always part of the value
+    Never,
+    /// `#` starts a comment when preceded by whitespace
+    AfterSpace,
+    /// `#` always starts a comment
+    Anywhere,
+}
+
+/// Syntax rules for the line scanner
+struct Rules {
+    /// Accept an `export ` prefix
+    export_prefix: bool,
+    /// Key bytes accepted besides `[A-Za-z0-9_]`
+    extra_key_chars: &'static [u8],
+    /// Allow whitespace around the separator
+    spaces_around_separator: bool,
+    /// Accept `KEY: value` as well as `KEY=value`
+    colon_separator: bool,
+    /// Bytes that start a full-line comment
+    comment_chars: &'static [u8],
+    inline_comment: InlineComment,
+    /// Escapes inside single quotes (escaped byte, replacement)
+    single_escapes: &'static [(u8, char)],
+    /// Escapes inside double quotes; unknown escapes are kept verbatim
+    double_escapes: &'static [(u8, char)],
+    /// Accept backtick quotes (reported as single quotes)
+    backtick_quotes: bool,
+    /// A backslash before the quote character keeps both in the value
+    /// instead of closing it (dotenv's `'a\'b'`)
+    escaped_quotes: bool,
+    /// A trailing backslash joins an unquoted value with the next line
+    line_continuation: bool,
+    /// Report lines that are not assignments
+    strict: bool,
+}
+
+const COMPOSE: Rules = Rules {
+    export_prefix: true,
+    extra_key_chars: b".-",
+    spaces_around_separator: true,
+    colon_separator: true,
+    comment_chars: b"#",
+    inline_comment: InlineComment::AfterSpace,
+    single_escapes: &[],
+    double_escapes: &[
+        (b'n', '\n'),
+        (b'r', '\r'),
+        (b't', '\t'),
+        (b'\\', '\\'),
+        (b'"', '"'),
+        (b'$', '$'),
+    ],
+    backtick_quotes: false,
+    escaped_quotes: false,
+    line_continuation: false,
+    strict: false,
+};
+
+const SYSTEMD: Rules = Rules {
+    export_prefix: false,
+    extra_key_chars: b"",
+    spaces_around_separator: true,
+    colon_separator: false,
+    comment_chars: b"#;",
+    inline_comment: InlineComment::Never,
+    single_escapes: &[],
+    double_escapes: &[(b'"', '"'), (b'\\', '\\'), (b'$', '$'), (b'`', '`')],
+    backtick_quotes: false,
+    escaped_quotes: false,
+    line_continuation: true,
+    strict: true,
+};
+
+const PYTHON: Rules = Rules {
+    export_prefix: true,
+    extra_key_chars: b".-",
+    spaces_around_separator: true,
+    colon_separator: false,
+    comment_chars: b"#",
+    inline_comment: InlineComment::AfterSpace,
+    single_escapes: &[(b'\\', '\\'), (b'\'', '\'')],
+    double_escapes: &[
+        (b'\\', '\\'),
+        (b'\'', '\''),
+        (b'"', '"'),
+        (b'a', '\x07'),
+        (b'b', '\x08'),
+        (b'f', '\x0c'),
+        (b'n', '\n'),
+        (b'r', '\r'),
+        (b't', '\t'),
+        (b'v', '\x0b'),
+    ],
+    backtick_quotes: false,
+    escaped_quotes: false,
+    line_continuation: false,
+    strict: false,
+};
+
+const NODE: Rules = Rules {
+    export_prefix: true,
+    extra_key_chars: b".-",
+    spaces_around_separator: true,
+    colon_separator: true,
+    comment_chars: b"#",
+    inline_comment: InlineComment::Anywhere,
+    single_escapes: &[],
+    double_escapes: &[(b'n', '\n'), (b'r', '\r')],
+    backtick_quotes: true,
+    escaped_quotes: true,
+    line_continuation: false,
+    strict: false,
+};
+
+/// Escapes korni accepts inside double quotes
+const EDF_DOUBLE_ESCAPES: &[(u8, char)] = &[
+    (b'n', '\n'),
+    (b'r', '\r'),
+    (b't', '\t'),
+    (b'\\', '\\'),
+    (b'"', '"'),
+    (b'$', '$'),
+];
+
+/// Escapes a POSIX shell accepts inside double quotes
+const SHELL_DOUBLE_ESCAPES: &[(u8, char)] = &[(b'"', '"'), (b'\\', '\\'), (b'$', '$'), (b'`', '`')];
+
+/// Parse content in the given dialect
+pub(crate) fn parse_dialect(
+    input: &str,
+    dialect: Dialect,
+    options: ParseOptions,
+) -> ParsedContent<'_> {
+    let rules = match dialect {
+        Dialect::Edf => return parse_content(input, options),
+        Dialect::Shell | Dialect::Direnv => None,
+        Dialect::DockerCompose => Some(&COMPOSE),
+        Dialect::Systemd => Some(&SYSTEMD),
+        Dialect::PythonDotenv => Some(&PYTHON),
+        Dialect::NodeDotenv => Some(&NODE),
+        Dialect::Json => return parse_json(input),
+        Dialect::Toml => return parse_toml(input),
+        Dialect::Ini => return parse_ini(input),
+        Dialect::Yaml => return parse_yaml(input),
+    };
+
+    let mut scanner = Scanner::new(input, options.include_comments);
+    match rules {
+        Some(rules) => scanner.scan_lines(rules),
+        None => scanner.scan_shell(),
+    }
+    scanner.finish()
+}
+
+// =============================================================================
+//  Detection
+// =============================================================================
+
+/// Detect the dialect from a file name, falling back to the content
+pub(crate) fn detect_dialect(source: Option<&str>, content: &str) -> Dialect {
+    source
+        .and_then(dialect_from_path)
+        .unwrap_or_else(|| dialect_from_content(content))
+}
+
+fn dialect_from_path(path: &str) -> Option<Dialect> {
+    let normalized = path.replace('\\', "/");
+    let basename = normalized.rsplit('/').next().unwrap_or(&normalized);
+    let lower = basename.to_ascii_lowercase();
+
+    const JSON_EXTENSIONS: [&str; 4] = [".json", ".jsonc", ".tfstate", ".tfstate.backup"];
+    if JSON_EXTENSIONS.iter().any(|e| lower.ends_with(e)) {
+        return Some(Dialect::Json);
+    }
+    if lower.ends_with(".toml") {
+        return Some(Dialect::Toml);
+    }
+    const INI_EXTENSIONS: [&str; 3] = [".ini", ".cfg", ".cnf"];
+    if INI_EXTENSIONS.iter().any(|e| lower.ends_with(e)) || lower == ".pypirc" {
+        return Some(Dialect::Ini);
+    }
+    if lower.ends_with(".yaml")
+        || lower.ends_with(".yml")
+        || lower == "kubeconfig"
+        || normalized.ends_with(".kube/config")
+    {
+        return Some(Dialect::Yaml);
+    }
+
+    if lower == ".envrc" || lower.ends_with(".envrc") {
+        return Some(Dialect::Direnv);
+    }
+
+    const SHELL_FILES: [&str; 7] = [
+        ".profile",
+        ".bashrc",
+        ".bash_profile",
+        ".zshrc",
+        ".zshenv",
+        ".zprofile",
+        ".kshrc",
+    ];
+    const SHELL_EXTENSIONS: [&str; 4] = [".sh", ".bash", ".zsh", ".ksh"];
+    if SHELL_FILES.contains(&lower.as_str()) || SHELL_EXTENSIONS.iter().any(|e| lower.ends_with(e))
+    {
+        return Some(Dialect::Shell);
+    }
+
+    if lower == ".flaskenv" {
+        return Some(Dialect::PythonDotenv);
+    }
+
+    if normalized.contains("/etc/default/")
+        || normalized.contains("/etc/sysconfig/")
+        || normalized.ends_with("/etc/environment")
+        || normalized.contains("/systemd/")
+    {
+        return Some(Dialect::Systemd);
+    }
+
+    None
+}
+
+/// Sniff the dialect from syntax EDF rejects or misreads
+fn dialect_from_content(content: &str) -> Dialect {
+    const MAX_LINES: usize = 200;
+
+    if let Some(format) = structured_from_content(content, MAX_LINES) {
+        return format;
+    }
+
+    let mut exports = 0;
+    let mut assignments = 0;
+    let mut shell = false;
+    let mut systemd = false;
+    let mut colon = false;
+    let mut spaced = false;
+    let mut lenient = false;
+    // Quote of a value that continues on the next lines
+    let mut open_quote: Option<char> = None;
+
+    for line in content.lines().take(MAX_LINES) {
+        if let Some(quote) = open_quote {
+            if line.contains(quote) {
+                open_quote = None;
+            }
+            continue;
+        }
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with(';') {
+            systemd = true;
+            continue;
+        }
+
+        let body = match line.strip_prefix("export") {
+            Some(rest) if rest.starts_with([' ', '\t']) => {
+                exports += 1;
+                rest.trim_start()
+            }
+            _ => line,
+        };
+
+        let key_len = body
+            .bytes()
+            .take_while(|&b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'))
+            .count();
+        let (key, rest) = body.split_at(key_len);
+
+        if key.is_empty() {
+            shell = true;
+            continue;
+        }
+
+        let after = rest.trim_start();
+        if let Some(raw) = after.strip_prefix('=') {
+            assignments += 1;
+            spaced |= rest.len() != after.len() || raw.starts_with([' ', '\t']);
+
+            let value = raw.trim();
+            if let Some(quote) = value.chars().next().filter(|c| matches!(c, '"' | '\'')) {
+                if !value[1..].contains(quote) {
+                    open_quote = Some(quote);
+                }
+            }
+
+            // Unquoted values with inner whitespace: lenient dotenv parsers keep them
+            let unquoted = !value.starts_with(['"', '\'']);
+            let cut = value.find(" #").unwrap_or(value.len());
+            lenient |= unquoted && value[..cut].trim_end().contains(' ');
+
+            // Several assignments or a command substitution on one line
+            shell |= value.contains("$(")
+                || (unquoted && value.split(' ').nth(1).is_some_and(|w| w.contains('=')));
+        } else if after.starts_with(": ") {
+            colon = true;
+        } else {
+            shell = true;
+        }
+    }
+
+    if shell || (exports > 0 && exports * 2 > assignments) {
+        Dialect::Shell
+    } else if systemd {
+        Dialect::Systemd
+    } else if colon {
+        Dialect::NodeDotenv
+    } else if spaced {
+        Dialect::PythonDotenv
+    } else if lenient {
+        Dialect::DockerCompose
+    } else {
+        Dialect::Edf
+    }
+}
+
+/// JSON documents start with `{` or `[` and YAML nests blocks under
+/// `key:` lines; `[section]` headers mean TOML, or INI when values are bare
+/// text, `key: value` or `;` comments appear
+fn structured_from_content(content: &str, max_lines: usize) -> Option<Dialect> {
+    let trimmed = content.trim_start_matches('\u{FEFF}').trim_start();
+    if trimmed.starts_with('{') {
+        return Some(Dialect::Json);
+    }
+    if let Some(rest) = trimmed.strip_prefix('[') {
+        let next = rest.trim_start().bytes().next();
+        if next.is_none_or(|b| matches!(b, b'{' | b'"' | b']' | b'-' | b'0'..=b'9')) {
+            return Some(Dialect::Json);
+        }
+    }
+
+    if is_yaml(trimmed, max_lines) {
+        return Some(Dialect::Yaml);
+    }
+
+    let mut sections = false;
+    let mut ini = false;
+    for line in content.lines().take(max_lines) {
+        let line = line.trim();
+        if line.starts_with(';') {
+            ini = true;
+        } else if line.starts_with('[') {
+            sections |= line.contains(']');
+        } else if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            let value = value.split(" #").next().unwrap_or(value);
+            let is_toml_scalar = value.starts_with(['"', '\'', '[', '{', '+', '-'])
+                || value.starts_with(|c: char| c.is_ascii_digit())
+                || matches!(value, "true" | "false" | "inf" | "nan");
+            ini |= !key.contains(':') && !is_toml_scalar;
+        } else if line.contains(": ") && !line.starts_with('#') {
+            ini = true;
+        }
+    }
+
+    sections.then_some(if ini { Dialect::Ini } else { Dialect::Toml })
+}
+
+/// YAML starts with `---` or `%YAML`, or nests a block under a `key:`
+fn is_yaml(content: &str, max_lines: usize) -> bool {
+    if content.starts_with("---") || content.starts_with("%YAML") {
+        return true;
+    }
+
+    let mut block_key = false;
+    for line in content.lines().take(max_lines) {
+        let body = line.trim_start();
+        if body.is_empty() || body.starts_with('#') {
+            continue;
+        }
+        if block_key && (body.len() < line.len() || body.starts_with("- ")) {
+            return true;
+        }
+
+        let body = body.split(" #").next().unwrap_or(body).trim_end();
+        block_key = body.strip_suffix(':').is_some_and(|key| {
+            !key.is_empty()
+                && key
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'))
+        });
+    }
+    false
+}
+
+// =============================================================================
+//  Scanner
+// =============================================================================
+
+/// Result of scanning one line assignment: the pair and the offset after
+/// its value, `None` for non-assignments, or the start and byte of an
+/// unclosed quote
+type LineScan<'a> = Result<Option<(KeyValuePair<'a>, usize)>, (usize, u8)>;
+
+/// A scanned value
+struct Value<'a> {
+    value: Cow<'a, str>,
+    quote: QuoteType,
+    /// Byte offset after the value (after the closing quote if quoted)
+    end: usize,
+}
+
+struct Scanner<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    include_comments: bool,
+    line_starts: Vec<usize>,
+    pairs: Vec<ParsedPair<'a>>,
+    comments: Vec<ParsedComment>,
+    diagnostics: Vec<Diagnostic>,
+    skipped: Vec<(usize, usize)>,
+}
+
+#[inline]
+fn is_blank(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+#[inline]
+fn is_eol(b: u8) -> bool {
+    b == b'\n' || b == b'\r'
+}
+
+impl<'a> Scanner<'a> {
+    fn new(input: &'a str, include_comments: bool) -> Self {
+        Scanner {
+            input,
+            bytes: input.as_bytes(),
+            include_comments,
+            line_starts: line_starts(input),
+            pairs: Vec::new(),
+            comments: Vec::new(),
+            diagnostics: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    fn finish(self) -> ParsedContent<'a> {
+        ParsedContent {
+            pairs: self.pairs,
+            comments: self.comments,
+            diagnostics: self.diagnostics,
+            skipped: self.skipped,
+            line_starts: self.line_starts,
+        }
+    }
+
+    #[inline]
+    fn skip_blanks(&self, mut pos: usize, limit: usize) -> usize {
+        while pos < limit && is_blank(self.bytes[pos]) {
+            pos += 1;
+        }
+        pos
+    }
+
+    /// End of the line containing `pos` (at `\n`, `\r` or EOF)
+    #[inline]
+    fn line_end(&self, pos: usize) -> usize {
+        self.bytes[pos..]
+            .iter()
+            .position(|&b| is_eol(b))
+            .map_or(self.bytes.len(), |i| pos + i)
+    }
+
+    /// Start of the next line after `pos`
+    #[inline]
+    fn next_line(&self, pos: usize) -> usize {
+        let end = self.line_end(pos);
+        if end < self.bytes.len()
+            && self.bytes[end] == b'\r'
+            && self.bytes.get(end + 1) == Some(&b'\n')
+        {
+            end + 2
+        } else {
+            (end + 1).min(self.bytes.len())
+        }
+    }
+
+    fn push_pair(&mut self, kv: KeyValuePair<'a>) {
+        let (line_number, value_end_line) = pair_lines(&self.line_starts, &kv);
+        self.pairs.push(ParsedPair {
+            kv: Box::new(kv),
+            line_number,
+            value_end_line,
+            inline_comment: None,
+            encoding: ValueEncoding::Plain,
+        });
+    }
+
+    /// Record a comment from `start` (the comment character) to the end of its line
+    fn push_comment(&mut self, start: usize, inline_of: Option<usize>) -> usize {
+        let end = self.line_end(start);
+        if let Some(i) = inline_of {
+            self.pairs[i].inline_comment = Some((start, end));
+        }
+        self.comments.push(ParsedComment {
+            start,
+            end,
+            line_number: offset_to_line(&self.line_starts, start),
+            is_inline: inline_of.is_some(),
+        });
+        end
+    }
+
+    fn unclosed_quote(&mut self, start: usize, quote: u8) {
+        let kind = match quote {
+            b'\'' => "single",
+            b'"' => "double",
+            _ => "backtick",
+        };
+        self.diagnostics.push(Diagnostic {
+            code: ShelterDiagnosticCode::UnclosedQuote,
+            message: format!("Unclosed {} quote", kind),
+            start,
+            end: self.input.len(),
+        });
+    }
+
+    /// Scan a key at `pos`, returning its end (equal to `pos` if there is none)
+    fn scan_key(&self, pos: usize, limit: usize, extra: &[u8]) -> usize {
+        if pos < limit && self.bytes[pos].is_ascii_digit() {
+            return pos;
+        }
+        let mut end = pos;
+        while end < limit {
+            let b = self.bytes[end];
+            if !(b.is_ascii_alphanumeric() || b == b'_' || extra.contains(&b)) {
+                break;
+            }
+            end += 1;
+        }
+        end
+    }
+
+    /// Consume an `export` keyword followed by whitespace
+    fn scan_export(&self, pos: usize, limit: usize) -> Option<(Span, usize)> {
+        let end = pos + 6;
+        (end < limit && &self.bytes[pos..end] == b"export" && is_blank(self.bytes[end]))
+            .then(|| (Span::from_offsets(pos, end), self.skip_blanks(end, limit)))
+    }
+
+    /// Scan a quoted value starting at the opening quote
+    ///
+    /// Escapes map to their replacement; unknown escapes are kept verbatim
+    /// but never close the quote. With `escaped_quotes`, a backslashed quote
+    /// is kept as written and only closes the value if no other quote does.
+    fn scan_quoted(
+        &self,
+        start: usize,
+        limit: usize,
+        escapes: &[(u8, char)],
+        escaped_quotes: bool,
+    ) -> Option<Value<'a>> {
+        let quote = self.bytes[start];
+        let quote_type = if quote == b'"' {
+            QuoteType::Double
+        } else {
+            QuoteType::Single
+        };
+        let content_start = start + 1;
+        let mut owned: Option<String> = None;
+        let mut chunk = content_start;
+        let mut pos = content_start;
+        // Offset of the last escaped quote, with the length of `owned` up to
+        // and including its backslash
+        let mut last_escaped: Option<(usize, usize)> = None;
+
+        while pos < limit {
+            let b = self.bytes[pos];
+            if b == quote {
+                let value = match owned {
+                    Some(mut s) => {
+                        s.push_str(&self.input[chunk..pos]);
+                        Cow::Owned(s)
+                    }
+                    None => Cow::Borrowed(&self.input[content_start..pos]),
+                };
+                return Some(Value {
+                    value,
+                    quote: quote_type,
+                    end: pos + 1,
+                });
+            }
+
+            if b == b'\\' && pos + 1 < limit && escaped_quotes && self.bytes[pos + 1] == quote {
+                // Kept verbatim, backslash included
+                let s = owned.get_or_insert_with(String::new);
+                s.push_str(&self.input[chunk..=pos]);
+                last_escaped = Some((pos + 1, s.len()));
+                s.push(quote as char);
+                pos += 2;
+                chunk = pos;
+                continue;
+            }
+
+            if b == b'\\' && pos + 1 < limit && !escapes.is_empty() {
+                let next = self.input[pos + 1..].chars().next().unwrap_or('\\');
+                let s = owned.get_or_insert_with(String::new);
+                s.push_str(&self.input[chunk..pos]);
+                match escapes.iter().find(|&&(e, _)| e as char == next) {
+                    Some(&(_, c)) => s.push(c),
+                    None => {
+                        s.push('\\');
+                        s.push(next);
+                    }
+                }
+                pos += 1 + next.len_utf8();
+                chunk = pos;
+                continue;
+            }
+
+            pos += 1;
+        }
+
+        // Without a closing quote, the last escaped one closes the value, as
+        // dotenv's pattern backtracks to it
+        let (end, len) = last_escaped?;
+        let mut value = owned?;
+        value.truncate(len);
+        Some(Value {
+            value: Cow::Owned(value),
+            quote: quote_type,
+            end: end + 1,
+        })
+    }
+
+    // =========================================================================
+    //  Line dialects
+    // =========================================================================
+
+    fn scan_lines(&mut self, rules: &Rules) {
+        let len = self.bytes.len();
+        let mut pos = 0;
+
+        // A leading UTF-8 BOM is not part of the first key
+        if self.input.starts_with('\u{FEFF}') {
+            pos = '\u{FEFF}'.len_utf8();
+        }
+
+        while pos < len {
+            pos = self.skip_blanks(pos, len);
+            if pos >= len {
+                break;
+            }
+
+            let b = self.bytes[pos];
+            if is_eol(b) {
+                pos += 1;
+                continue;
+            }
+
+            if rules.comment_chars.contains(&b) {
+                let end = self.push_comment(pos, None);
+                if self.include_comments {
+                    let body = self.skip_blanks(pos + 1, end);
+                    if let Ok(Some((kv, _))) = self.scan_line_pair(rules, body, end, true) {
+                        self.push_pair(kv);
+                    }
+                }
+                pos = self.next_line(end);
+                continue;
+            }
+
+            match self.scan_line_pair(rules, pos, len, false) {
+                Ok(Some((kv, end))) => {
+                    self.push_pair(kv);
+                    let index = self.pairs.len() - 1;
+
+                    // Trailing comment after the value; anything else is ignored
+                    let after = self.skip_blanks(end, len);
+                    pos = if after < len && self.bytes[after] == b'#' {
+                        let comment_end = self.push_comment(after, Some(index));
+                        self.next_line(comment_end)
+                    } else {
+                        if after < len && !is_eol(self.bytes[after]) {
+                            self.skipped.push((after, self.line_end(after)));
+                        }
+                        self.next_line(end)
+                    };
+                }
+                Ok(None) => {
+                    if rules.strict {
+                        let end = self.line_end(pos);
+                        self.diagnostics.push(Diagnostic {
+                            code: ShelterDiagnosticCode::Expected,
+                            message: "Expected KEY=VALUE".to_string(),
+                            start: pos,
+                            end,
+                        });
+                    } else {
+                        self.skipped.push((pos, self.line_end(pos)));
+                    }
+                    pos = self.next_line(pos);
+                }
+                Err((start, quote)) => {
+                    self.unclosed_quote(start, quote);
+                    pos = len;
+                }
+            }
+        }
+    }
+
+    /// Scan `KEY<sep>VALUE` at `pos`
+    ///
+    /// Returns `Ok(None)` for lines that are not assignments and
+    /// `Err((quote_start, quote))` for an unclosed quote. Pairs inside
+    /// comments are limited to the comment line and never report errors.
+    fn scan_line_pair(
+        &self,
+        rules: &Rules,
+        pos: usize,
+        limit: usize,
+        is_comment: bool,
+    ) -> LineScan<'a> {
+        let mut pos = pos;
+        let mut export_span = None;
+        if rules.export_prefix {
+            if let Some((span, next)) = self.scan_export(pos, limit) {
+                export_span = Some(span);
+                pos = next;
+            }
+        }
+
+        let key_start = pos;
+        let key_end = self.scan_key(pos, limit, rules.extra_key_chars);
+        if key_end == key_start {
+            return Ok(None);
+        }
+
+        let mut pos = key_end;
+        if rules.spaces_around_separator {
+            pos = self.skip_blanks(pos, limit);
+        }
+
+        let separator = pos;
+        match self.bytes.get(pos) {
+            Some(b'=') if pos < limit => pos += 1,
+            Some(b':')
+                if rules.colon_separator && pos + 1 < limit && is_blank(self.bytes[pos + 1]) =>
+            {
+                pos += 1
+            }
+            _ => return Ok(None),
+        }
+
+        if rules.spaces_around_separator || rules.colon_separator {
+            pos = self.skip_blanks(pos, limit);
+        }
+
+        let value_start = pos;
+        let first = self
+            .bytes
+            .get(value_start)
+            .copied()
+            .filter(|_| value_start < limit);
+        let quoted = match first {
+            Some(b'\'') => Some(rules.single_escapes),
+            Some(b'"') => Some(rules.double_escapes),
+            Some(b'`') if rules.backtick_quotes => Some(&[][..]),
+            _ => None,
+        };
+
+        let value = match quoted {
+            Some(escapes) => {
+                // Quoted values inside comments may not run past the comment line
+                match self.scan_quoted(value_start, limit, escapes, rules.escaped_quotes) {
+                    Some(v) => v,
+                    None if is_comment => return Ok(None),
+                    None => return Err((value_start, self.bytes[value_start])),
+                }
+            }
+            None => self.scan_unquoted_line(rules, value_start, limit),
+        };
+
+        let key = &self.input[key_start..key_end];
+        let mut kv = KeyValuePair::new(
+            key,
+            key_start,
+            value.value,
+            value_start,
+            value.end - value_start,
+            value.quote,
+            export_span.is_some(),
+            export_span,
+            is_comment,
+        );
+        // `KEY = value` and `KEY: value` do not put the separator right after the key
+        kv.equals_pos = Some(Position::from_offset(separator));
+
+        Ok(Some((kv, value.end)))
+    }
+
+    /// Unquoted value up to the end of the line, an inline comment or a
+    /// continuation, with trailing whitespace trimmed
+    fn scan_unquoted_line(&self, rules: &Rules, start: usize, limit: usize) -> Value<'a> {
+        let mut owned: Option<String> = None;
+        let mut segment_start = start;
+        let mut pos = start;
+
+        loop {
+            while pos < limit && !is_eol(self.bytes[pos]) {
+                let b = self.bytes[pos];
+                let starts_comment = b == b'#'
+                    && match rules.inline_comment {
+                        InlineComment::Never => false,
+                        InlineComment::Anywhere => true,
+                        InlineComment::AfterSpace => pos > start && is_blank(self.bytes[pos - 1]),
+                    };
+                if starts_comment {
+                    break;
+                }
+                pos += 1;
+            }
+
+            let mut end = pos;
+            while end > segment_start && is_blank(self.bytes[end - 1]) {
+                end -= 1;
+            }
+
+            let continues = rules.line_continuation
+                && end > segment_start
+                && self.bytes[end - 1] == b'\\'
+                && pos < limit
+                && is_eol(self.bytes[pos]);
+
+            if !continues {
+                let value = match owned {
+                    Some(mut s) => {
+                        s.push_str(&self.input[segment_start..end]);
+                        Cow::Owned(s)
+                    }
+                    None => Cow::Borrowed(&self.input[start..end]),
+                };
+                return Value {
+                    value,
+                    quote: QuoteType::None,
+                    end,
+                };
+            }
+
+            owned
+                .get_or_insert_with(String::new)
+                .push_str(&self.input[segment_start..end - 1]);
+            pos = self.next_line(pos);
+            segment_start = pos;
+        }
+    }
+
+    // =========================================================================
+    //  Shell dialects
+    // =========================================================================
+
+    fn scan_shell(&mut self) {
+        let len = self.bytes.len();
+        let mut pos = 0;
+        if self.input.starts_with('\u{FEFF}') {
+            pos = '\u{FEFF}'.len_utf8();
+        }
+
+        // Index of the last pair on the current line, for inline comments
+        let mut line_pair: Option<usize> = None;
+        // The previous statement was assignments with nothing after them yet
+        let mut prefix = false;
+
+        while pos < len {
+            pos = self.skip_blanks(pos, len);
+            if pos >= len {
+                break;
+            }
+
+            match self.bytes[pos] {
+                b'\n' | b'\r' => {
+                    line_pair = None;
+                    prefix = false;
+                    pos += 1;
+                }
+                b';' | b'&' | b'|' => {
+                    prefix = false;
+                    pos += 1;
+                }
+                b'#' => {
+                    prefix = false;
+                    let inline_of = line_pair.take();
+                    let end = self.push_comment(pos, inline_of);
+                    if self.include_comments && inline_of.is_none() {
+                        // Commented-out assignments; anything else is prose
+                        let body = self.skip_blanks(pos + 1, end);
+                        let _ = self.scan_shell_statement(body, end, true);
+                    }
+                    pos = end;
+                }
+                _ => {
+                    let before = self.pairs.len();
+                    match self.scan_shell_statement(pos, len, false) {
+                        Ok(Some(end)) => {
+                            prefix = true;
+                            pos = end;
+                        }
+                        Ok(None) => {
+                            let end = self.skip_command(pos, len);
+                            if prefix {
+                                // `A=1 cmd`: the assignments only reach the
+                                // command, whose name may well be a value
+                                // missing its quotes
+                                self.skip_words(pos, end);
+                            } else {
+                                self.skip_arguments(pos, end);
+                            }
+                            prefix = false;
+                            pos = end;
+                        }
+                        Err((start, quote)) => {
+                            self.unclosed_quote(start, quote);
+                            pos = len;
+                        }
+                    }
+                    if self.pairs.len() > before {
+                        line_pair = Some(self.pairs.len() - 1);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Scan `[export] NAME=word [NAME=word ...]` at `pos`
+    ///
+    /// Returns the position after the last assignment, or `Ok(None)` if
+    /// the statement is a command.
+    fn scan_shell_statement(
+        &mut self,
+        pos: usize,
+        limit: usize,
+        is_comment: bool,
+    ) -> Result<Option<usize>, (usize, u8)> {
+        let (export_span, mut pos) = match self.scan_export(pos, limit) {
+            Some((span, next)) => (Some(span), next),
+            None => (None, pos),
+        };
+
+        let mut found = false;
+        loop {
+            let key_start = pos;
+            let key_end = self.scan_key(pos, limit, b"");
+            let is_assignment =
+                key_end > key_start && key_end < limit && self.bytes[key_end] == b'=';
+
+            if !is_assignment {
+                // `export A B=1`: bare names are exported without a value
+                let bare = export_span.is_some()
+                    && key_end > key_start
+                    && (key_end >= limit
+                        || is_blank(self.bytes[key_end])
+                        || is_eol(self.bytes[key_end]));
+                if bare {
+                    pos = self.skip_blanks(key_end, limit);
+                    continue;
+                }
+                if found || export_span.is_some() {
+                    return Ok(Some(pos));
+                }
+                return Ok(None);
+            }
+
+            let value_start = key_end + 1;
+            let value = match self.scan_shell_word(value_start, limit) {
+                Ok(value) => value,
+                Err(_) if is_comment => return Ok(found.then_some(pos)),
+                Err(err) => return Err(err),
+            };
+
+            let kv = KeyValuePair::new(
+                &self.input[key_start..key_end],
+                key_start,
+                value.value,
+                value_start,
+                value.end - value_start,
+                value.quote,
+                export_span.is_some(),
+                export_span,
+                is_comment,
+            );
+            self.push_pair(kv);
+            found = true;
+
+            pos = self.skip_blanks(value.end, limit);
+        }
+    }
+
+    /// Scan one shell word: unquoted text, quoted strings and `$(...)` /
+    /// `${...}` / backtick substitutions, concatenated until unquoted
+    /// whitespace or an operator
+    fn scan_shell_word(&self, start: usize, limit: usize) -> Result<Value<'a>, (usize, u8)> {
+        let mut value = String::new();
+        let mut pos = start;
+        let mut segments = 0;
+        let mut single_quote = None;
+
+        while pos < limit {
+            let b = self.bytes[pos];
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' | b';' | b'&' | b'|' | b'<' | b'>' | b'(' | b')' => {
+                    break
+                }
+                b'\'' => {
+                    let end = self.bytes[pos + 1..limit]
+                        .iter()
+                        .position(|&c| c == b'\'')
+                        .map(|i| pos + 1 + i)
+                        .ok_or((pos, b'\''))?;
+                    value.push_str(&self.input[pos + 1..end]);
+                    single_quote = Some(QuoteType::Single);
+                    pos = end + 1;
+                }
+                b'"' => {
+                    let quoted = self
+                        .scan_quoted(pos, limit, SHELL_DOUBLE_ESCAPES, false)
+                        .ok_or((pos, b'"'))?;
+                    // A backslash-newline inside double quotes is a continuation
+                    value.push_str(&quoted.value.replace("\\\n", ""));
+                    single_quote = Some(QuoteType::Double);
+                    pos = quoted.end;
+                }
+                b'\\' if pos + 1 < limit => {
+                    let next = self.input[pos + 1..].chars().next().unwrap_or('\\');
+                    if next != '\n' {
+                        value.push(next);
+                    }
+                    pos += 1 + next.len_utf8();
+                }
+                b'$' | b'`' => {
+                    let end = self.scan_substitution(pos, limit).ok_or((pos, b))?;
+                    value.push_str(&self.input[pos..end]);
+                    pos = end;
+                }
+                _ => {
+                    let c = self.input[pos..].chars().next().unwrap_or(b as char);
+                    value.push(c);
+                    pos += c.len_utf8();
+                }
+            }
+            segments += 1;
+        }
+
+        // A single quoted segment is reported as quoted, like EDF
+        let quote = match (segments, single_quote) {
+            (1, Some(quote)) => quote,
+            _ => QuoteType::None,
+        };
+
+        Ok(Value {
+            value: Cow::Owned(value),
+            quote,
+            end: pos,
+        })
+    }
+
+    /// End of a `$(...)`, `${...}`, `$VAR` or backtick substitution at `pos`
+    fn scan_substitution(&self, pos: usize, limit: usize) -> Option<usize> {
+        let (open, close, body) = match (self.bytes[pos], self.bytes.get(pos + 1)) {
+            (b'`', _) => {
+                return self.bytes[pos + 1..limit]
+                    .iter()
+                    .position(|&c| c == b'`')
+                    .map(|i| pos + 2 + i);
+            }
+            (b'$', Some(b'(')) => (b'(', b')', pos + 2),
+            (b'$', Some(b'{')) => (b'{', b'}', pos + 2),
+            _ => return Some(pos + 1),
+        };
+
+        let mut depth = 1;
+        let mut i = body;
+        while i < limit {
+            match self.bytes[i] {
+                c if c == open => depth += 1,
+                c if c == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i + 1);
+                    }
+                }
+                b'\'' | b'"' => {
+                    let quote = self.bytes[i];
+                    i += self.bytes[i + 1..limit].iter().position(|&c| c == quote)? + 1;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Record the arguments of the command at `pos..end` as skipped; only the
+    /// command name is known not to hold a value
+    fn skip_arguments(&mut self, pos: usize, end: usize) {
+        let name_end = self.bytes[pos..end]
+            .iter()
+            .position(|&b| is_blank(b))
+            .map_or(end, |i| pos + i);
+        let start = self.skip_blanks(name_end, end);
+        self.skip_words(start, end);
+    }
+
+    /// Record the words at `start..end` as skipped
+    fn skip_words(&mut self, start: usize, end: usize) {
+        let end = start + self.input[start..end].trim_end().len();
+        if start < end {
+            self.skipped.push((start, end));
+        }
+    }
+
+    /// Skip a command up to the next statement separator, respecting quotes
+    fn skip_command(&self, pos: usize, limit: usize) -> usize {
+        let mut i = pos;
+        while i < limit {
+            match self.bytes[i] {
+                b'\n' | b'\r' | b';' => return i,
+                b'#' if i > pos && is_blank(self.bytes[i - 1]) => return i,
+                b'\\' => i += 1,
+                quote @ (b'\'' | b'"') => {
+                    match self.bytes[i + 1..limit].iter().position(|&c| c == quote) {
+                        Some(offset) => i += offset + 1,
+                        None => return self.line_end(i),
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        limit
+    }
+}
@@ -0,0 +1,626 @@
+//! Persistent document state for incremental re-parsing
+//!
+//! A `Document` owns the buffer text, its line table and the parsed units
+//! (pairs, comments and errors, each with a byte extent). Byte-range edits,
+//! as delivered by `nvim_buf_attach` `on_bytes`, shift the stored units and
+//! widen a dirty range. `update` then re-parses from the nearest line start
+//! that is not inside a unit and stops as soon as the new parse produces a
+//! top-level pair at the same offset as an untouched old one: from there on
+//! korni's state, and therefore its output, is identical.
+//!
+//! Other dialects have no resumable parser: their documents still track
+//! edits and report deltas, but `update` re-parses the whole text.
+
+use crate::dialect::{parse_dialect, Dialect};
+use crate::interpolate::{resolve, Resolution};
+use crate::parse::{
+    is_inline_comment, offset_to_line, pair_lines, Diagnostic, ParsedComment, ParsedPair,
+    ValueEncoding,
+};
+use korni::{Entry, Error, KeyValuePair, ParseOptions, Parser, Position, Span};
+use std::collections::{HashMap, VecDeque};
+
+/// A key-value pair tracked across edits
+pub(crate) struct DocPair {
+    /// Stable identifier, kept while the key survives edits
+    pub id: u64,
+    pub kv: KeyValuePair<'static>,
+    /// Byte span of a trailing `# comment` on the value's last line
+    pub inline_comment: Option<(usize, usize)>,
+    /// Encoding the value was decoded from
+    pub encoding: ValueEncoding,
+}
+
+impl DocPair {
+    fn shift(&mut self, delta: isize) {
+        shift_kv(&mut self.kv, delta);
+        if let Some((start, end)) = &mut self.inline_comment {
+            *start = start.wrapping_add_signed(delta);
+            *end = end.wrapping_add_signed(delta);
+        }
+    }
+}
+
+enum UnitKind {
+    Pair(DocPair),
+    Comment { is_inline: bool },
+    Error(Diagnostic),
+}
+
+/// A parsed unit and the byte range it covers
+struct Unit {
+    start: usize,
+    end: usize,
+    /// Touched by an edit since the last update; always re-parsed
+    stale: bool,
+    kind: UnitKind,
+}
+
+impl Unit {
+    /// Offset where a top-level (non-comment) pair starts, used for resync
+    fn resync_point(&self) -> Option<usize> {
+        match &self.kind {
+            UnitKind::Pair(p) if !p.kv.is_comment => Some(self.start),
+            _ => None,
+        }
+    }
+}
+
+/// How an entry differs from the previous update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChangeKind {
+    Added,
+    Changed,
+}
+
+/// Entries added, changed or removed since the last update
+pub(crate) struct Delta<'a> {
+    pub changes: Vec<(ChangeKind, &'a DocPair)>,
+    /// Resolved `${VAR}` references of each change, against every pair
+    pub resolutions: Vec<Option<Resolution>>,
+    pub removed: Vec<u64>,
+    /// Current line table, for resolving lines of the changed pairs
+    pub line_starts: &'a [usize],
+}
+
+/// Shift every position in a pair by `delta` bytes
+fn shift_kv(kv: &mut KeyValuePair<'static>, delta: isize) {
+    let shift_pos = |p: &mut Position| p.offset = p.offset.wrapping_add_signed(delta);
+    let shift_span = |s: &mut Span| {
+        shift_pos(&mut s.start);
+        shift_pos(&mut s.end);
+    };
+
+    kv.key_span.as_mut().map(shift_span);
+    kv.value_span.as_mut().map(shift_span);
+    kv.export_span.as_mut().map(shift_span);
+    kv.open_quote_pos.as_mut().map(shift_pos);
+    kv.close_quote_pos.as_mut().map(shift_pos);
+    kv.equals_pos.as_mut().map(shift_pos);
+}
+
+/// Whether two pairs would produce the same entry and mask
+///
+/// Positions are not compared: they shift with the text, like extmarks.
+fn same_content(a: &DocPair, b: &DocPair) -> bool {
+    let comment_len = |p: &DocPair| p.inline_comment.map(|(start, end)| end - start);
+
+    a.kv.key == b.kv.key
+        && a.kv.value == b.kv.value
+        && a.kv.quote == b.kv.quote
+        && a.kv.is_exported == b.kv.is_exported
+        && a.kv.is_comment == b.kv.is_comment
+        && comment_len(a) == comment_len(b)
+}
+
+pub(crate) struct Document {
+    dialect: Dialect,
+    text: String,
+    line_starts: Vec<usize>,
+    units: Vec<Unit>,
+    /// Range (in current coordinates) touched by edits since the last update
+    dirty: Option<(usize, usize)>,
+    /// Report every pair as added on the next update
+    pending_full: bool,
+    next_id: u64,
+}
+
+impl Document {
+    pub fn new(text: &str, dialect: Dialect) -> Self {
+        let mut doc = Document {
+            dialect,
+            text: String::new(),
+            line_starts: vec![0],
+            units: Vec::new(),
+            dirty: None,
+            pending_full: true,
+            next_id: 1,
+        };
+        doc.replace_all(text);
+        doc.update();
+        // The initial parse is reported by the first update call
+        doc.pending_full = true;
+        doc
+    }
+
+    /// Replace the whole text (e.g. on reload); the next update diffs against the old state
+    ///
+    /// Only the range between the common prefix and suffix of the old and
+    /// new text is edited, so unchanged regions are not re-parsed.
+    pub fn replace_all(&mut self, text: &str) {
+        let old = self.text.as_bytes();
+        let new = text.as_bytes();
+
+        let mut prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        while !text.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let mut suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !text.is_char_boundary(new.len() - suffix) {
+            suffix -= 1;
+        }
+
+        let old_len = old.len() - prefix - suffix;
+        let new_text = &text[prefix..text.len() - suffix];
+        self.edit(prefix, old_len, new_text)
+            .expect("common prefix and suffix end on char boundaries");
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn line_starts(&self) -> &[usize] {
+        &self.line_starts
+    }
+
+    /// Current parse diagnostics in document order
+    pub fn diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.units.iter().filter_map(|u| match &u.kind {
+            UnitKind::Error(d) => Some(d),
+            _ => None,
+        })
+    }
+
+    /// Current comments in document order
+    pub fn comments(&self) -> impl Iterator<Item = ParsedComment> + '_ {
+        self.units.iter().filter_map(|u| match u.kind {
+            UnitKind::Comment { is_inline } => Some(ParsedComment {
+                start: u.start,
+                end: u.end,
+                line_number: offset_to_line(&self.line_starts, u.start),
+                is_inline,
+            }),
+            _ => None,
+        })
+    }
+
+    /// Current pairs in document order
+    pub fn pairs(&self) -> impl Iterator<Item = &DocPair> {
+        self.units.iter().filter_map(|u| match &u.kind {
+            UnitKind::Pair(p) => Some(p),
+            _ => None,
+        })
+    }
+
+    /// Byte offset where the line containing `offset` starts
+    fn line_start_at(&self, offset: usize) -> usize {
+        self.line_starts[offset_to_line(&self.line_starts, offset).saturating_sub(1)]
+    }
+
+    /// Replace `old_len` bytes at `start` with `new_text`
+    pub fn edit(&mut self, start: usize, old_len: usize, new_text: &str) -> Result<(), String> {
+        let old_end = start
+            .checked_add(old_len)
+            .filter(|&end| end <= self.text.len())
+            .ok_or_else(|| format!("Edit range {}..+{} out of bounds", start, old_len))?;
+
+        if !self.text.is_char_boundary(start) || !self.text.is_char_boundary(old_end) {
+            return Err(format!(
+                "Edit range {}..{} splits a character",
+                start, old_end
+            ));
+        }
+
+        let new_len = new_text.len();
+        let new_end = start + new_len;
+        let delta = new_len as isize - old_len as isize;
+
+        self.text.replace_range(start..old_end, new_text);
+
+        // Line table: drop line starts inside the replaced range, shift the rest
+        let first = self.line_starts.partition_point(|&o| o <= start);
+        let last = self.line_starts.partition_point(|&o| o <= old_end);
+        for offset in &mut self.line_starts[last..] {
+            *offset = offset.wrapping_add_signed(delta);
+        }
+        let inserted = new_text
+            .bytes()
+            .enumerate()
+            .filter(|&(_, b)| b == b'\n')
+            .map(|(i, _)| start + i + 1);
+        self.line_starts.splice(first..last, inserted);
+
+        // Monotone position mapping keeps units sorted
+        let map_start = |pos: usize| match pos {
+            p if p <= start => p,
+            p if p >= old_end => p.wrapping_add_signed(delta),
+            _ => start,
+        };
+        let map_end = |pos: usize| match pos {
+            p if p <= start => p,
+            p if p >= old_end => p.wrapping_add_signed(delta),
+            _ => new_end,
+        };
+
+        for unit in &mut self.units {
+            // Touching counts: typing at either end of a value changes it
+            if unit.start <= old_end && unit.end >= start {
+                unit.stale = true;
+            } else if unit.start >= old_end {
+                if let UnitKind::Pair(p) = &mut unit.kind {
+                    p.shift(delta);
+                }
+            }
+            unit.start = map_start(unit.start);
+            unit.end = map_end(unit.end);
+            if let UnitKind::Error(d) = &mut unit.kind {
+                d.start = unit.start;
+                d.end = unit.end;
+            }
+        }
+
+        self.dirty = Some(match self.dirty {
+            Some((ds, de)) => (map_start(ds).min(start), map_end(de).max(new_end)),
+            None => (start, new_end),
+        });
+
+        Ok(())
+    }
+
+    /// Re-parse dirty regions and return what changed since the last update
+    pub fn update(&mut self) -> Delta<'_> {
+        let mut changes: Vec<(ChangeKind, usize)> = Vec::new();
+        let mut removed = Vec::new();
+
+        if let Some((dirty_start, dirty_end)) = self.dirty.take() {
+            let reparse_start = match self.dialect {
+                Dialect::Edf => self.reparse_start(dirty_start),
+                _ => 0,
+            };
+            let first = self.units.partition_point(|u| u.start < reparse_start);
+            let (mut new_units, resync) = match self.dialect {
+                Dialect::Edf => self.reparse(reparse_start, dirty_end, first),
+                dialect => (self.parse_all(dialect), None),
+            };
+            let last = resync.unwrap_or(self.units.len());
+
+            // Match replaced pairs to new ones by key, in document order
+            let mut old_pairs: HashMap<(String, bool), VecDeque<DocPair>> = HashMap::new();
+            for unit in self.units.drain(first..last) {
+                if let UnitKind::Pair(p) = unit.kind {
+                    old_pairs
+                        .entry((p.kv.key.to_string(), p.kv.is_comment))
+                        .or_default()
+                        .push_back(p);
+                }
+            }
+
+            for (i, unit) in new_units.iter_mut().enumerate() {
+                if let UnitKind::Pair(p) = &mut unit.kind {
+                    let old = old_pairs
+                        .get_mut(&(p.kv.key.to_string(), p.kv.is_comment))
+                        .and_then(VecDeque::pop_front);
+                    match old {
+                        Some(old) => {
+                            p.id = old.id;
+                            if !same_content(&old, p) {
+                                changes.push((ChangeKind::Changed, first + i));
+                            }
+                        }
+                        None => {
+                            p.id = self.next_id;
+                            self.next_id += 1;
+                            changes.push((ChangeKind::Added, first + i));
+                        }
+                    }
+                }
+            }
+
+            removed = old_pairs.into_values().flatten().map(|p| p.id).collect();
+            removed.sort_unstable();
+
+            self.units.splice(first..first, new_units);
+        }
+
+        if std::mem::take(&mut self.pending_full) {
+            changes = self
+                .units
+                .iter()
+                .enumerate()
+                .filter(|(_, u)| matches!(u.kind, UnitKind::Pair(_)))
+                .map(|(i, _)| (ChangeKind::Added, i))
+                .collect();
+            removed.clear();
+        }
+
+        let indices: Vec<usize> = changes.iter().map(|&(_, i)| i).collect();
+        let resolutions = self.resolve_units(&indices);
+        Delta {
+            changes: changes
+                .into_iter()
+                .filter_map(|(kind, i)| match &self.units[i].kind {
+                    UnitKind::Pair(p) => Some((kind, p)),
+                    _ => None,
+                })
+                .collect(),
+            resolutions,
+            removed,
+            line_starts: &self.line_starts,
+        }
+    }
+
+    /// Pairs starting on lines `first..=last` (1-based) and the resolved
+    /// references of each
+    pub fn pairs_in_lines(
+        &self,
+        first: usize,
+        last: usize,
+    ) -> (Vec<&DocPair>, Vec<Option<Resolution>>) {
+        let line_start = |line: usize| {
+            self.line_starts
+                .get(line.saturating_sub(1))
+                .copied()
+                .unwrap_or(usize::MAX)
+        };
+        let (start, end) = (line_start(first), line_start(last.saturating_add(1)));
+
+        let from = self.units.partition_point(|u| u.start < start);
+        let indices: Vec<usize> = (from..self.units.len())
+            .take_while(|&i| self.units[i].start < end)
+            .filter(|&i| matches!(self.units[i].kind, UnitKind::Pair(_)))
+            .collect();
+
+        let pairs = indices
+            .iter()
+            .filter_map(|&i| match &self.units[i].kind {
+                UnitKind::Pair(p) => Some(p),
+                _ => None,
+            })
+            .collect();
+        (pairs, self.resolve_units(&indices))
+    }
+
+    /// Resolve the values of the pair units at `indices` against every pair
+    ///
+    /// Without a `$` in any of those values there is nothing to resolve,
+    /// and the pairs are not collected.
+    fn resolve_units(&self, indices: &[usize]) -> Vec<Option<Resolution>> {
+        let has_reference = |&i: &usize| match &self.units[i].kind {
+            UnitKind::Pair(p) => p.kv.value.contains('$'),
+            _ => false,
+        };
+        if !indices.iter().any(has_reference) {
+            return vec![None; indices.len()];
+        }
+
+        let mut pair_units = Vec::new();
+        let pairs: Vec<ParsedPair> = self
+            .units
+            .iter()
+            .enumerate()
+            .filter_map(|(i, u)| match &u.kind {
+                UnitKind::Pair(p) => Some((i, p)),
+                _ => None,
+            })
+            .map(|(i, p)| {
+                pair_units.push(i);
+                let (line_number, value_end_line) = pair_lines(&self.line_starts, &p.kv);
+                ParsedPair {
+                    kv: Box::new(p.kv.clone()),
+                    line_number,
+                    value_end_line,
+                    inline_comment: p.inline_comment,
+                    encoding: p.encoding,
+                }
+            })
+            .collect();
+
+        let mut resolutions = resolve(&self.text, &pairs, self.dialect);
+        indices
+            .iter()
+            .map(|i| {
+                pair_units
+                    .binary_search(i)
+                    .ok()
+                    .and_then(|at| resolutions[at].take())
+            })
+            .collect()
+    }
+
+    /// Nearest line start at or before `dirty_start` that is not inside a unit
+    fn reparse_start(&self, dirty_start: usize) -> usize {
+        let mut start = self.line_start_at(dirty_start);
+        let mut idx = self.units.partition_point(|u| u.start < start);
+
+        // Units never overlap except pairs nested in a comment line, so only
+        // the units immediately before `start` can reach into it
+        while idx > 0 {
+            let unit = &self.units[idx - 1];
+            if !unit.stale && unit.end < start {
+                break;
+            }
+            if unit.start < start {
+                start = self.line_start_at(unit.start);
+            }
+            idx -= 1;
+        }
+
+        start
+    }
+
+    /// Parse from `reparse_start` until the output resynchronizes with the
+    /// old units (searched from index `first_old`)
+    ///
+    /// Returns the new units and the index of the first old unit to keep.
+    fn reparse(
+        &self,
+        reparse_start: usize,
+        dirty_end: usize,
+        first_old: usize,
+    ) -> (Vec<Unit>, Option<usize>) {
+        let slice = &self.text[reparse_start..];
+        let mut parser = Parser::with_options(slice, ParseOptions::full());
+        let mut units = Vec::new();
+
+        while let Some(entry) = parser.next_entry() {
+            // A BOM is only reported by a parse from the very beginning
+            if reparse_start > 0 && matches!(entry, Entry::Error(Error::InvalidBom { .. })) {
+                continue;
+            }
+
+            let mut unit = unit_from_entry(entry, slice, reparse_start);
+
+            // A comment directly after a top-level pair may trail its value
+            if let UnitKind::Comment { is_inline } = &mut unit.kind {
+                if let Some(Unit {
+                    kind: UnitKind::Pair(pair),
+                    ..
+                }) = units.last_mut()
+                {
+                    let (_, value_end_line) = pair_lines(&self.line_starts, &pair.kv);
+                    if !pair.kv.is_comment
+                        && is_inline_comment(&self.line_starts, value_end_line, unit.start)
+                    {
+                        *is_inline = true;
+                        pair.inline_comment = Some((unit.start, unit.end));
+                    }
+                }
+            }
+
+            if let Some(point) = unit.resync_point().filter(|&p| p >= dirty_end) {
+                if let Some(idx) = self.find_resync(point, first_old) {
+                    return (units, Some(idx));
+                }
+            }
+
+            units.push(unit);
+        }
+
+        (units, None)
+    }
+
+    /// Parse the whole text in a dialect other than EDF
+    fn parse_all(&self, dialect: Dialect) -> Vec<Unit> {
+        let parsed = parse_dialect(&self.text, dialect, ParseOptions::full());
+        let pairs = parsed.pairs.into_iter().map(|p| {
+            let (start, end) = pair_extent(&p.kv, 0);
+            Unit {
+                start,
+                end,
+                stale: false,
+                kind: UnitKind::Pair(DocPair {
+                    id: 0,
+                    kv: (*p.kv).into_owned(),
+                    inline_comment: p.inline_comment,
+                    encoding: p.encoding,
+                }),
+            }
+        });
+        let comments = parsed.comments.into_iter().map(|c| Unit {
+            start: c.start,
+            end: c.end,
+            stale: false,
+            kind: UnitKind::Comment {
+                is_inline: c.is_inline,
+            },
+        });
+        let errors = parsed.diagnostics.into_iter().map(|d| Unit {
+            start: d.start,
+            end: d.end,
+            stale: false,
+            kind: UnitKind::Error(d),
+        });
+
+        let mut units: Vec<Unit> = pairs.chain(comments).chain(errors).collect();
+        units.sort_by_key(|u| u.start);
+        units
+    }
+
+    /// Index of an untouched old top-level pair starting exactly at `point`
+    fn find_resync(&self, point: usize, first_old: usize) -> Option<usize> {
+        let from = first_old + self.units[first_old..].partition_point(|u| u.start < point);
+        self.units[from..]
+            .iter()
+            .take_while(|u| u.start == point)
+            .position(|u| !u.stale && u.resync_point() == Some(point))
+            .map(|i| from + i)
+    }
+}
+
+/// Convert a korni entry parsed from `slice` (starting at `base`) into an owned unit
+fn unit_from_entry(entry: Entry, slice: &str, base: usize) -> Unit {
+    match entry {
+        Entry::Pair(kv) => {
+            let mut kv = kv.into_owned();
+            shift_kv(&mut kv, base as isize);
+            let (start, end) = pair_extent(&kv, base);
+
+            Unit {
+                start,
+                end,
+                stale: false,
+                kind: UnitKind::Pair(DocPair {
+                    id: 0,
+                    kv,
+                    inline_comment: None,
+                    encoding: ValueEncoding::Plain,
+                }),
+            }
+        }
+        Entry::Comment(span) => Unit {
+            start: base + span.start.offset,
+            end: base + span.end.offset,
+            stale: false,
+            kind: UnitKind::Comment { is_inline: false },
+        },
+        Entry::Error(error) => {
+            // The diagnostic span is also the unit extent: unclosed quotes
+            // consume the rest of the input, other errors their line
+            let diagnostic = Diagnostic::from_korni(&error, slice).offset_by(base);
+            Unit {
+                start: diagnostic.start,
+                end: diagnostic.end,
+                stale: false,
+                kind: UnitKind::Error(diagnostic),
+            }
+        }
+    }
+}
+
+/// Byte range a pair covers, from `export` (or the key) to the end of the
+/// value; `fallback` is used for pairs without spans
+fn pair_extent(kv: &KeyValuePair, fallback: usize) -> (usize, usize) {
+    let start = kv
+        .export_span
+        .or(kv.key_span)
+        .map(|s| s.start.offset)
+        .unwrap_or(fallback);
+    let end = kv
+        .value_span
+        .or(kv.key_span)
+        .map(|s| s.end.offset)
+        .unwrap_or(start);
+    (start, end)
+}
@@ -0,0 +1,259 @@
+//! Environment dump detection for shelter-core
+//!
+//! Finds `KEY=VALUE` pairs by the structure of common environment dumps in
+//! any text, so values that are in no local env file can still be masked
+//! by key:
+//!
+//! - `printenv` / `env` output: runs of at least three `KEY=value` lines
+//! - `docker inspect` JSON: the strings of `"Env": ["KEY=value", ...]`
+//! - `kubectl describe`: the `KEY:  value` lines of `Environment:` sections
+//! - `/proc/<pid>/environ`: NUL-separated `KEY=value` records
+//!
+//! Pairs are entries like those of an env file, so the same pattern rules
+//! decide their masks.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use korni::QuoteType;
+use regex::Regex;
+
+use crate::json::string_at;
+use crate::parse::ParsedPair;
+use crate::structured::Collector;
+
+/// Consecutive `KEY=value` lines needed before lines count as `env` output
+const MIN_PRINTENV_RUN: usize = 3;
+
+/// Kind of dump a pair was found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DumpFormat {
+    Printenv,
+    DockerInspect,
+    Kubectl,
+    Environ,
+}
+
+/// Pairs found in a text, in document order
+pub(crate) struct Dump<'a> {
+    pub pairs: Vec<(DumpFormat, ParsedPair<'a>)>,
+    pub line_starts: Vec<usize>,
+}
+
+/// A pair before line numbers are resolved
+struct Found<'a> {
+    format: DumpFormat,
+    key: (usize, usize),
+    value: Cow<'a, str>,
+    value_span: (usize, usize),
+}
+
+/// Find the pairs of every environment dump in `input`
+///
+/// Empty values are skipped, as are pairs overlapping one found earlier in
+/// the text. Value spans never include quotes.
+pub(crate) fn find_dumps(input: &str) -> Dump<'_> {
+    let mut found = Vec::new();
+    find_environ(input, &mut found);
+    find_docker_env(input, &mut found);
+    find_kubectl_env(input, &mut found);
+    find_printenv(input, &mut found);
+    found.sort_by_key(|f| f.key.0);
+
+    let mut out = Collector::new(input);
+    let mut formats = Vec::new();
+    let mut last_end = 0;
+    for f in found {
+        if f.key.0 < last_end || f.value.is_empty() {
+            continue;
+        }
+        last_end = f.value_span.1;
+        let key = &input[f.key.0..f.key.1];
+        out.pair(key, f.key, f.value, f.value_span, QuoteType::None);
+        formats.push(f.format);
+    }
+
+    let parsed = out.finish();
+    Dump {
+        pairs: formats.into_iter().zip(parsed.pairs).collect(),
+        line_starts: parsed.line_starts,
+    }
+}
+
+/// Variable names as dumps print them: `[A-Za-z_][A-Za-z0-9_.-]*`
+fn is_name(key: &str) -> bool {
+    let mut bytes = key.bytes();
+    bytes
+        .next()
+        .is_some_and(|b| b.is_ascii_alphabetic() || b == b'_')
+        && bytes.all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'))
+}
+
+/// Lines of `input` with their start offsets, without line endings
+fn lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input.split('\n').scan(0, |start, line| {
+        let line_start = *start;
+        *start += line.len() + 1;
+        Some((line_start, line.strip_suffix('\r').unwrap_or(line)))
+    })
+}
+
+/// NUL-terminated records; the first may follow other output on its line,
+/// and an unterminated last one ends at the end of its line
+fn find_environ<'a>(input: &'a str, found: &mut Vec<Found<'a>>) {
+    if !input.contains('\0') {
+        return;
+    }
+
+    let mut record_start = 0;
+    for (i, record) in input.split('\0').enumerate() {
+        let start = record_start;
+        record_start += record.len() + 1;
+        let terminated = record_start <= input.len();
+
+        let key_start = match i {
+            0 => record.rfind('\n').map_or(0, |nl| nl + 1),
+            _ => 0,
+        };
+        let Some(eq) = record[key_start..].find('=').map(|eq| key_start + eq) else {
+            continue;
+        };
+        if !is_name(&record[key_start..eq]) {
+            continue;
+        }
+        let value_end = match terminated {
+            true => record.len(),
+            false => record[eq..]
+                .find(['\n', '\r'])
+                .map_or(record.len(), |end| eq + end),
+        };
+        found.push(Found {
+            format: DumpFormat::Environ,
+            key: (start + key_start, start + eq),
+            value: Cow::Borrowed(&record[eq + 1..value_end]),
+            value_span: (start + eq + 1, start + value_end),
+        });
+    }
+}
+
+fn docker_env_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r#""Env"\s*:\s*\["#).expect("docker env pattern is valid"))
+}
+
+/// `"KEY=value"` strings of `"Env": [...]` arrays; values are unescaped,
+/// spans cover them as written
+fn find_docker_env<'a>(input: &'a str, found: &mut Vec<Found<'a>>) {
+    let bytes = input.as_bytes();
+    let skip_whitespace = |mut pos: usize| {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        pos
+    };
+
+    for array in docker_env_regex().find_iter(input) {
+        let mut pos = skip_whitespace(array.end());
+        while bytes.get(pos) == Some(&b'"') {
+            let Ok((item, end)) = string_at(input, pos) else {
+                break;
+            };
+            // Names are never escaped, so the raw `=` ends the key
+            let key_start = pos + 1;
+            let key_end = input[key_start..end]
+                .find('=')
+                .map(|eq| key_start + eq)
+                .filter(|&eq| is_name(&input[key_start..eq]));
+            if let Some(key_end) = key_end {
+                let name_len = key_end - key_start + 1;
+                let value = match item {
+                    Cow::Borrowed(item) => Cow::Borrowed(&item[name_len..]),
+                    Cow::Owned(item) => Cow::Owned(item[name_len..].to_string()),
+                };
+                found.push(Found {
+                    format: DumpFormat::DockerInspect,
+                    key: (key_start, key_end),
+                    value,
+                    value_span: (key_end + 1, end - 1),
+                });
+            }
+
+            pos = skip_whitespace(end);
+            if bytes.get(pos) != Some(&b',') {
+                break;
+            }
+            pos = skip_whitespace(pos + 1);
+        }
+    }
+}
+
+/// `KEY:  value` lines indented under an `Environment:` header; values that
+/// point at a secret or field (`<set to the key ...>`, `(v1:...)`) are skipped
+fn find_kubectl_env<'a>(input: &'a str, found: &mut Vec<Found<'a>>) {
+    // Indentation of the header of the section being read
+    let mut section: Option<usize> = None;
+    for (line_start, line) in lines(input) {
+        let content = line.trim_start_matches([' ', '\t']);
+        let indent = line.len() - content.len();
+        if section.is_some_and(|header| indent <= header || content.is_empty()) {
+            section = None;
+        }
+        if content.trim_end() == "Environment:" {
+            section = Some(indent);
+            continue;
+        }
+        if section.is_none() {
+            continue;
+        }
+
+        let Some((key, rest)) = content.split_once(':') else {
+            continue;
+        };
+        let padded = rest.trim_start_matches([' ', '\t']);
+        let value = padded.trim_end();
+        if !is_name(key) || (!rest.is_empty() && !rest.starts_with([' ', '\t'])) {
+            continue;
+        }
+        if value.starts_with("<set to the key ")
+            || (value.starts_with("(v1:") && value.ends_with(')'))
+        {
+            continue;
+        }
+
+        let key_start = line_start + indent;
+        let value_start = key_start + key.len() + 1 + (rest.len() - padded.len());
+        found.push(Found {
+            format: DumpFormat::Kubectl,
+            key: (key_start, key_start + key.len()),
+            value: Cow::Borrowed(value),
+            value_span: (value_start, value_start + value.len()),
+        });
+    }
+}
+
+/// Runs of unindented `KEY=value` lines
+fn find_printenv<'a>(input: &'a str, found: &mut Vec<Found<'a>>) {
+    let mut run: Vec<Found<'a>> = Vec::new();
+    for (line_start, line) in lines(input) {
+        let pair = line
+            .find('=')
+            .filter(|&eq| is_name(&line[..eq]) && !line.contains('\0'));
+        match pair {
+            Some(eq) => run.push(Found {
+                format: DumpFormat::Printenv,
+                key: (line_start, line_start + eq),
+                value: Cow::Borrowed(&line[eq + 1..]),
+                value_span: (line_start + eq + 1, line_start + line.len()),
+            }),
+            None => {
+                if run.len() >= MIN_PRINTENV_RUN {
+                    found.append(&mut run);
+                }
+                run.clear();
+            }
+        }
+    }
+    if run.len() >= MIN_PRINTENV_RUN {
+        found.append(&mut run);
+    }
+}
@@ -0,0 +1,117 @@
+//! Duplicate key detection for shelter-core
+//!
+//! A key defined twice in one file leaves all but one definition dead.
+//!
+//! Every dotenv dialect keeps the last definition: Node and python-dotenv
+//! fill a map line by line, compose and direnv do the same in Go, systemd
+//! applies `EnvironmentFile=` assignments in order and shells simply run
+//! them. Their `override` options only decide between the file and the
+//! process environment, never between two lines of one file. JSON readers
+//! (`JSON.parse`, serde, Python) agree on the last as well.
+//!
+//! TOML forbids duplicate keys and strict YAML loaders (Kubernetes, docker
+//! compose) reject them. INI readers disagree: Python's configparser rejects
+//! them, PHP keeps the last and the Windows profile API the first. In those
+//! formats no definition is reliably effective, so every occurrence is a
+//! conflict. YAML keys only clash within one document of a `---` stream.
+
+use std::collections::HashMap;
+
+use crate::dialect::Dialect;
+
+/// Role of a definition whose key occurs more than once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DuplicateStatus {
+    /// The definition the loader ends up with
+    Effective,
+    /// Overridden by the effective definition
+    Shadowed,
+    /// The format rejects duplicate keys
+    Conflict,
+}
+
+/// A definition of a duplicated key
+#[derive(Debug, Clone)]
+pub(crate) struct Duplicate {
+    pub status: DuplicateStatus,
+    /// Indices of the other definitions of the key, in document order
+    pub others: Vec<usize>,
+}
+
+/// Find keys defined more than once
+///
+/// `keys` yields one item per pair of `input`: the key and its offset, or
+/// `None` for pairs that do not count as definitions (commented-out
+/// entries). Returns one item per pair, `None` for keys defined once.
+pub(crate) fn find_duplicates<'a>(
+    input: &str,
+    keys: impl Iterator<Item = Option<(&'a str, usize)>>,
+    dialect: Dialect,
+) -> Vec<Option<Duplicate>> {
+    let keys: Vec<Option<(&str, usize)>> = keys.collect();
+    let markers = match dialect {
+        Dialect::Yaml => document_markers(input),
+        _ => Vec::new(),
+    };
+
+    let mut groups: HashMap<(usize, &str), Vec<usize>> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        if let Some((key, offset)) = *key {
+            let document = markers.partition_point(|&m| m <= offset);
+            groups.entry((document, key)).or_default().push(i);
+        }
+    }
+
+    let rejects = rejects_duplicates(dialect);
+    let mut out = vec![None; keys.len()];
+    for indices in groups.into_values().filter(|g| g.len() > 1) {
+        let last = indices[indices.len() - 1];
+        for &i in &indices {
+            let status = match (rejects, i == last) {
+                (true, _) => DuplicateStatus::Conflict,
+                (false, true) => DuplicateStatus::Effective,
+                (false, false) => DuplicateStatus::Shadowed,
+            };
+            out[i] = Some(Duplicate {
+                status,
+                others: indices.iter().copied().filter(|&j| j != i).collect(),
+            });
+        }
+    }
+    out
+}
+
+/// Offsets of the `---` and `...` lines that separate the documents of a
+/// YAML stream; the markers end block scalars and quoted strings too
+fn document_markers(input: &str) -> Vec<usize> {
+    let mut markers = Vec::new();
+    let mut start = 0;
+    for line in input.split_inclusive('\n') {
+        if (line.starts_with("---") || line.starts_with("..."))
+            && matches!(
+                line.as_bytes().get(3),
+                None | Some(b' ' | b'\t' | b'\n' | b'\r')
+            )
+        {
+            markers.push(start);
+        }
+        start += line.len();
+    }
+    markers
+}
+
+/// Whether the format's loaders refuse duplicate keys or disagree on which
+/// definition they keep
+fn rejects_duplicates(dialect: Dialect) -> bool {
+    match dialect {
+        Dialect::Toml | Dialect::Yaml | Dialect::Ini => true,
+        Dialect::Edf
+        | Dialect::DockerCompose
+        | Dialect::Systemd
+        | Dialect::Direnv
+        | Dialect::Shell
+        | Dialect::PythonDotenv
+        | Dialect::NodeDotenv
+        | Dialect::Json => false,
+    }
+}
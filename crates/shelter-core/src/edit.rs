@@ -0,0 +1,346 @@
+//! Format-preserving edits for shelter-core
+//!
+//! Sets, unsets, renames and moves keys by splicing byte ranges of the
+//! parsed file, so comments, quoting, `export` prefixes, blank lines and
+//! line endings outside the edited entry come through unchanged. New values
+//! are quoted with [`quote_value`], keeping the entry's quote style when the
+//! dialect reads the value back through it.
+//!
+//! Env dialects support every edit. Config formats (JSON, TOML, INI, YAML)
+//! only support setting keys that already exist, since adding or removing
+//! lines there depends on the surrounding structure.
+
+use korni::{ParseOptions, QuoteType};
+
+use crate::dialect::{parse_dialect, Dialect};
+use crate::parse::{ParsedContent, ParsedPair};
+use crate::quote::quote_value;
+
+/// A single change to a file
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Edit<'a> {
+    /// Replace the value of the effective definition, or append the key
+    Set { key: &'a str, value: &'a str },
+    /// Remove every definition of the key
+    Unset { key: &'a str },
+    /// Rename every definition of the key
+    Rename { key: &'a str, to: &'a str },
+    /// Move the key's definitions, with the comments right above them,
+    /// before another key (or to the end of the file)
+    Move {
+        key: &'a str,
+        before: Option<&'a str>,
+    },
+}
+
+/// Apply `edits` in order, each to the result of the one before
+pub(crate) fn apply_edits(input: &str, dialect: Dialect, edits: &[Edit]) -> Result<String, String> {
+    let mut content = input.to_string();
+    for edit in edits {
+        content = apply_edit(&content, dialect, *edit)?;
+    }
+    Ok(content)
+}
+
+/// Apply one edit to `input` read as `dialect`
+pub(crate) fn apply_edit(input: &str, dialect: Dialect, edit: Edit) -> Result<String, String> {
+    let parsed = parse_dialect(input, dialect, ParseOptions::full());
+    let file = File {
+        input,
+        dialect,
+        parsed: &parsed,
+    };
+
+    if dialect.is_config() && !matches!(edit, Edit::Set { .. }) {
+        return Err(format!(
+            "Only setting existing keys is supported in {} files",
+            dialect.name()
+        ));
+    }
+
+    match edit {
+        Edit::Set { key, value } => file.set(key, value),
+        Edit::Unset { key } => file.unset(key),
+        Edit::Rename { key, to } => file.rename(key, to),
+        Edit::Move { key, before } => file.move_before(key, before),
+    }
+}
+
+struct File<'a> {
+    input: &'a str,
+    dialect: Dialect,
+    parsed: &'a ParsedContent<'a>,
+}
+
+impl<'a> File<'a> {
+    /// Active definitions of `key` in document order
+    fn definitions(&self, key: &str) -> Vec<&'a ParsedPair<'a>> {
+        self.parsed
+            .pairs
+            .iter()
+            .filter(|p| !p.kv.is_comment && p.kv.key == key)
+            .collect()
+    }
+
+    /// Active definitions of `key`, or an error if there are none
+    fn defined(&self, key: &str) -> Result<Vec<&'a ParsedPair<'a>>, String> {
+        let definitions = self.definitions(key);
+        if definitions.is_empty() {
+            return Err(format!("{} is not defined", key));
+        }
+        Ok(definitions)
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<String, String> {
+        // No file format can hold it, and C callers would lose the rest
+        if value.contains('\0') {
+            return Err(format!("The value of {} has a NUL byte", key));
+        }
+
+        let edited = match self.definitions(key).last() {
+            Some(pair) => {
+                let span = pair
+                    .kv
+                    .value_span
+                    .ok_or_else(|| format!("{} has no value to replace", key))?;
+                let quoted = quote_value(value, pair.kv.quote, self.dialect);
+                let start = self
+                    .block_indicator(span.start.offset)
+                    .unwrap_or(span.start.offset);
+                splice(self.input, &[(start, span.end.offset, &quoted)])
+            }
+            None if !self.dialect.is_config() => {
+                self.check_key(key)?;
+                let prefix = if self.all_exported() { "export " } else { "" };
+                let quoted = quote_value(value, QuoteType::None, self.dialect);
+                let line = format!("{}{}={}{}", prefix, key, quoted, newline(self.input));
+                append(self.input, &line)
+            }
+            None => return Err(format!("{} is not defined", key)),
+        };
+
+        // Config formats cannot hold every value (INI has no multi-line
+        // strings), so make sure the file reads back as intended
+        let reread = parse_dialect(&edited, self.dialect, ParseOptions::default());
+        let written = reread
+            .pairs
+            .iter()
+            .rfind(|p| p.kv.key == key)
+            .map(|p| p.kv.value.as_ref());
+        if written != Some(value) {
+            return Err(format!(
+                "{} cannot hold this value in {} files",
+                key,
+                self.dialect.name()
+            ));
+        }
+        Ok(edited)
+    }
+
+    fn unset(&self, key: &str) -> Result<String, String> {
+        let ranges = self
+            .defined(key)?
+            .into_iter()
+            .map(|pair| self.entry_lines(pair))
+            .collect::<Result<Vec<_>, _>>()?;
+        let removals: Vec<_> = ranges
+            .iter()
+            .map(|&(start, end)| (start, end, ""))
+            .collect();
+        Ok(splice(self.input, &removals))
+    }
+
+    fn rename(&self, key: &str, to: &str) -> Result<String, String> {
+        let definitions = self.defined(key)?;
+        if key == to {
+            return Ok(self.input.to_string());
+        }
+        self.check_key(to)?;
+        if !self.definitions(to).is_empty() {
+            return Err(format!("{} is already defined", to));
+        }
+
+        let renames = definitions
+            .iter()
+            .map(|pair| {
+                let span = pair
+                    .kv
+                    .key_span
+                    .ok_or_else(|| format!("{} has no key to rename", key))?;
+                Ok((span.start.offset, span.end.offset, to))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(splice(self.input, &renames))
+    }
+
+    fn move_before(&self, key: &str, before: Option<&str>) -> Result<String, String> {
+        let blocks = self
+            .defined(key)?
+            .into_iter()
+            .map(|pair| {
+                let (start, end) = self.entry_lines(pair)?;
+                Ok((self.comment_block_start(pair.line_number, start), end))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let mut moved = String::new();
+        for &(start, end) in &blocks {
+            moved.push_str(&self.input[start..end]);
+            if !moved.ends_with('\n') {
+                moved.push_str(newline(self.input));
+            }
+        }
+        let removals: Vec<_> = blocks
+            .iter()
+            .map(|&(start, end)| (start, end, ""))
+            .collect();
+
+        let Some(anchor) = before else {
+            return Ok(append(&splice(self.input, &removals), &moved));
+        };
+        if anchor == key {
+            return Err(format!("Cannot move {} before itself", key));
+        }
+
+        let target = self.defined(anchor)?[0];
+        let (target_start, _) = self.entry_lines(target)?;
+        let at = self.comment_block_start(target.line_number, target_start);
+
+        let mut edits = removals;
+        edits.push((at, at, &moved));
+        edits.sort_by_key(|&(start, end, _)| (start, end));
+        Ok(splice(self.input, &edits))
+    }
+
+    /// Offset of the `|` or `>` header of the YAML block scalar whose
+    /// content starts at `start`
+    ///
+    /// Block scalar spans only cover the content, so the header has to go
+    /// along with it when the value is replaced by a quoted one.
+    fn block_indicator(&self, start: usize) -> Option<usize> {
+        if self.dialect != Dialect::Yaml {
+            return None;
+        }
+        let before = self.input[..start].trim_end();
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let header = &before[line_start..];
+        let header = header
+            .find(" #")
+            .map_or(header, |i| &header[..i])
+            .trim_end();
+
+        let token_start = header.rfind([' ', '\t']).map_or(0, |i| i + 1);
+        let token = &header[token_start..];
+        let is_indicator = token.starts_with(['|', '>'])
+            && token[1..]
+                .bytes()
+                .all(|b| matches!(b, b'+' | b'-' | b'1'..=b'9'));
+        is_indicator.then_some(line_start + token_start)
+    }
+
+    /// Byte range of the whole lines holding `pair`, including the newline
+    fn entry_lines(&self, pair: &ParsedPair) -> Result<(usize, usize), String> {
+        let line_starts = &self.parsed.line_starts;
+        let first = pair.line_number;
+        let last = pair.value_end_line.max(first);
+
+        // Removing the lines would take the other entries with it
+        let shared = self.parsed.pairs.iter().any(|other| {
+            !std::ptr::eq(other, pair)
+                && !other.kv.is_comment
+                && other.line_number <= last
+                && other.value_end_line.max(other.line_number) >= first
+        });
+        if shared {
+            return Err(format!(
+                "{} shares its line with another entry",
+                pair.kv.key
+            ));
+        }
+
+        let start = line_starts[first - 1];
+        let end = line_starts.get(last).copied().unwrap_or(self.input.len());
+        Ok((start, end))
+    }
+
+    /// Start of the full-line comments directly above line `line_number`
+    /// (which starts at byte `start`)
+    fn comment_block_start(&self, line_number: usize, start: usize) -> usize {
+        let mut line = line_number;
+        while line > 1
+            && self
+                .parsed
+                .comments
+                .iter()
+                .any(|c| !c.is_inline && c.line_number == line - 1)
+        {
+            line -= 1;
+        }
+        if line == line_number {
+            start
+        } else {
+            self.parsed.line_starts[line - 1]
+        }
+    }
+
+    /// Whether every entry has an `export` prefix (as in `.envrc` files)
+    fn all_exported(&self) -> bool {
+        let mut pairs = self
+            .parsed
+            .pairs
+            .iter()
+            .filter(|p| !p.kv.is_comment)
+            .peekable();
+        pairs.peek().is_some()
+            && pairs.all(|p| {
+                let line_start = self.parsed.line_starts[p.line_number - 1];
+                self.input[line_start..].trim_start().starts_with("export ")
+            })
+    }
+
+    /// Make sure `key` reads back as a key in this dialect
+    fn check_key(&self, key: &str) -> Result<(), String> {
+        let line = format!("{}=x\n", key);
+        let parsed = parse_dialect(&line, self.dialect, ParseOptions::default());
+        let valid = parsed.diagnostics.is_empty()
+            && parsed.pairs.len() == 1
+            && parsed.pairs[0].kv.key == key;
+        if !valid {
+            return Err(format!("Invalid key: {:?}", key));
+        }
+        Ok(())
+    }
+}
+
+/// Replace byte ranges of `input`; ranges must be sorted and disjoint
+fn splice(input: &str, edits: &[(usize, usize, &str)]) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut pos = 0;
+    for &(start, end, text) in edits {
+        out.push_str(&input[pos..start]);
+        out.push_str(text);
+        pos = end;
+    }
+    out.push_str(&input[pos..]);
+    out
+}
+
+/// Append `text` as new lines at the end of `input`
+fn append(input: &str, text: &str) -> String {
+    let mut out = String::with_capacity(input.len() + text.len() + 2);
+    out.push_str(input);
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push_str(newline(input));
+    }
+    out.push_str(text);
+    out
+}
+
+/// The line ending used by `input`
+fn newline(input: &str) -> &'static str {
+    if input.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
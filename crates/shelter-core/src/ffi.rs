@@ -2,31 +2,206 @@
 //!
 //! These functions are exposed via the C ABI for LuaJIT FFI.
 
-use crate::types::{ShelterEntry, ShelterParseOptions, ShelterResult};
-use korni::Entry;
+use crate::compare::compare;
+use crate::dialect::{detect_dialect, parse_dialect};
+use crate::document::{ChangeKind, Document};
+use crate::dump::find_dumps;
+use crate::duplicates::find_duplicates;
+use crate::edit::{apply_edits, Edit};
+use crate::example::generate_example;
+use crate::fingerprint::{constant_time_eq, derive_key, fingerprint, machine_key, tag};
+use crate::format::format;
+use crate::interpolate::resolve;
+use crate::layers::resolve_layers;
+use crate::lint::lint;
+use crate::mask::{mask_value, MaskOptions};
+use crate::parse::{line_starts, pair_lines};
+use crate::pattern::PatternSet;
+use crate::scan::{scan_text, RuleSet};
+use crate::schema::validate;
+use crate::score::score_value;
+use crate::stream::{load_values, KnownValues, LoadOptions, StreamMasker};
+use crate::types::{
+    error_cstring, ShelterChangeKind, ShelterComment, ShelterCompareKey, ShelterCompareResult,
+    ShelterDiagnostic, ShelterDialect, ShelterDocument, ShelterDocumentDelta, ShelterDumpEntry,
+    ShelterDumpFormat, ShelterDumpResult, ShelterEdit, ShelterEditKind, ShelterEntry,
+    ShelterEntryChange, ShelterExampleOptions, ShelterFile, ShelterFormatOptions, ShelterLayerKey,
+    ShelterLayerResult, ShelterLayerScheme, ShelterLintFinding, ShelterLintResult, ShelterMask,
+    ShelterMaskOptions, ShelterMaskResult, ShelterModeConfig, ShelterParseOptions,
+    ShelterPatternRule, ShelterPatternSet, ShelterResult, ShelterScanFinding, ShelterScanResult,
+    ShelterSchemaResult, ShelterSchemaViolation, ShelterStreamChunk, ShelterStreamMasker,
+    ShelterStreamOptions, ShelterValueEncoding, ShelterValueScore,
+};
 use std::ffi::{c_char, CString};
+use std::ptr;
 use std::slice;
 
 /// Library version string
 const VERSION: &[u8] = b"0.1.0\0";
 
-// =============================================================================
-//  Parsing Functions
-// =============================================================================
+/// Convert a raw input pointer into a UTF-8 string slice
+///
+/// # Safety
+/// `input` must be null or point to `input_len` readable bytes
+unsafe fn input_str<'a>(input: *const c_char, input_len: usize) -> Result<&'a str, String> {
+    if input.is_null() {
+        return Err("Input is null".to_string());
+    }
+
+    let input_slice = slice::from_raw_parts(input as *const u8, input_len);
+    std::str::from_utf8(input_slice).map_err(|e| format!("Invalid UTF-8: {}", e))
+}
+
+/// Convert an optional raw string (null or empty means absent)
+///
+/// # Safety
+/// `input` must be null or point to `input_len` readable bytes
+unsafe fn optional_str<'a>(input: *const c_char, input_len: usize) -> Option<&'a str> {
+    if input.is_null() || input_len == 0 {
+        return None;
+    }
+    input_str(input, input_len).ok()
+}
+
+/// Convert a raw rule array into (pattern, mode) string pairs
+///
+/// # Safety
+/// `rules` must be null or point to `count` valid `ShelterPatternRule`s
+unsafe fn rule_pairs<'a>(
+    rules: *const ShelterPatternRule,
+    count: usize,
+) -> Result<Vec<(&'a str, &'a str)>, String> {
+    if rules.is_null() || count == 0 {
+        return Ok(Vec::new());
+    }
+
+    slice::from_raw_parts(rules, count)
+        .iter()
+        .map(|r| {
+            Ok((
+                input_str(r.pattern, r.pattern_len)?,
+                input_str(r.mode, r.mode_len)?,
+            ))
+        })
+        .collect()
+}
+
+/// Convert a raw file array into (path, content) string pairs
+///
+/// # Safety
+/// `files` must be null or point to `count` valid `ShelterFile`s
+unsafe fn file_pairs<'a>(
+    files: *const ShelterFile,
+    count: usize,
+) -> Result<Vec<(&'a str, &'a str)>, String> {
+    if files.is_null() || count == 0 {
+        return Ok(Vec::new());
+    }
+
+    slice::from_raw_parts(files, count)
+        .iter()
+        .map(|f| {
+            let path = optional_str(f.path, f.path_len).unwrap_or_default();
+            let content = match f.content_len {
+                0 => "",
+                len => input_str(f.content, len).map_err(|e| format!("{}: {}", path, e))?,
+            };
+            Ok((path, content))
+        })
+        .collect()
+}
+
+/// Convert a raw edit array into edits
+///
+/// # Safety
+/// `edits` must be null or point to `count` valid `ShelterEdit`s
+unsafe fn edit_list<'a>(edits: *const ShelterEdit, count: usize) -> Result<Vec<Edit<'a>>, String> {
+    if edits.is_null() || count == 0 {
+        return Ok(Vec::new());
+    }
+
+    slice::from_raw_parts(edits, count)
+        .iter()
+        .map(|e| {
+            let key = input_str(e.key, e.key_len)?;
+            let value = match e.value_len {
+                0 => "",
+                len => input_str(e.value, len)?,
+            };
+            Ok(match e.kind {
+                k if k == ShelterEditKind::Set as u8 => Edit::Set { key, value },
+                k if k == ShelterEditKind::Unset as u8 => Edit::Unset { key },
+                k if k == ShelterEditKind::Rename as u8 => Edit::Rename { key, to: value },
+                k if k == ShelterEditKind::Move as u8 => Edit::Move {
+                    key,
+                    before: (!value.is_empty()).then_some(value),
+                },
+                k => return Err(format!("Unknown edit kind: {}", k)),
+            })
+        })
+        .collect()
+}
+
+/// Convert a text result into an owned C string, or null with the error
+/// stored in `error_out` (if non-null)
+///
+/// Text holding a NUL byte cannot be returned whole, so it is an error too.
+///
+/// # Safety
+/// `error_out` must be null or a valid pointer
+unsafe fn text_result(text: Result<String, String>, error_out: *mut *mut c_char) -> *mut c_char {
+    let text = text.and_then(|text| {
+        CString::new(text).map_err(|e| format!("Output has a NUL byte at {}", e.nul_position()))
+    });
+
+    match text {
+        Ok(text) => text.into_raw(),
+        Err(e) => {
+            if !error_out.is_null() {
+                *error_out = error_cstring(&e);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a raw array together with every element's owned data
+///
+/// # Safety
+/// `items` must be null or a pointer produced by `into_raw_array` with `count` elements
+unsafe fn free_raw_array<T>(items: *mut T, count: usize, mut free_item: impl FnMut(T)) {
+    if items.is_null() || count == 0 {
+        return;
+    }
+
+    for item in Vec::from_raw_parts(items, count, count) {
+        free_item(item);
+    }
+}
 
-/// Binary search to find line number from byte offset
-/// Returns 1-based line number
+/// Free a C string allocated by `CString::into_raw`
+///
+/// # Safety
+/// `s` must be null or a pointer returned by `CString::into_raw`
 #[inline]
-fn offset_to_line_binary(line_starts: &[usize], offset: usize) -> usize {
-    match line_starts.binary_search(&offset) {
-        // Exact match: offset is at start of this line
-        Ok(line) => line + 1,
-        // Not found: offset is within the line before insert point
-        Err(line) => line,
+unsafe fn free_cstring(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
     }
 }
 
-/// Parse EDF content and return entries
+// =============================================================================
+//  Parsing Functions
+// =============================================================================
+
+/// Parse dotenv content and return entries
+///
+/// Content is read as EDF unless `options.dialect` selects another dialect;
+/// `Auto` sniffs the content. The dialect used is reported in the result.
+/// `${VAR}` references in values are resolved against the other entries,
+/// and entries whose key is defined more than once are linked to each other.
+/// With `options.fingerprint` set, each value gets a tag keyed with the
+/// machine secret, as from `shelter_fingerprint` without a key.
 ///
 /// # Safety
 /// - `input` must be a valid pointer to a UTF-8 string
@@ -38,111 +213,1417 @@ pub unsafe extern "C" fn shelter_parse(
     input_len: usize,
     options: ShelterParseOptions,
 ) -> *mut ShelterResult {
-    // Validate input
-    if input.is_null() {
-        return ShelterResult::err("Input is null");
+    let input_str = match input_str(input, input_len) {
+        Ok(s) => s,
+        Err(e) => return ShelterResult::err(&e),
+    };
+
+    let key = match options.fingerprint {
+        0 => None,
+        _ => match machine_key() {
+            Ok(key) => Some(key),
+            Err(e) => return ShelterResult::err(&format!("Fingerprint key: {}", e)),
+        },
+    };
+
+    let dialect = options.resolve_dialect(input_str);
+    let parsed = parse_dialect(input_str, dialect, korni::ParseOptions::from(options));
+
+    let definitions = parsed.pairs.iter().map(|p| definition(&p.kv));
+    let duplicates = find_duplicates(input_str, definitions, dialect);
+
+    let mut references = Vec::new();
+    let mut duplicate_links = Vec::new();
+    let entries = parsed
+        .pairs
+        .iter()
+        .zip(resolve(input_str, &parsed.pairs, dialect))
+        .zip(duplicates)
+        .map(|((pair, resolution), duplicate)| {
+            ShelterEntry::from_parsed(pair)
+                .with_resolution(resolution, &mut references)
+                .with_duplicate(duplicate, &mut duplicate_links)
+                .with_fingerprint(&pair.kv.value, key.as_ref())
+        })
+        .collect();
+
+    let comments = parsed
+        .comments
+        .iter()
+        .map(|c| ShelterComment::new(c, input_str))
+        .collect();
+
+    let diagnostics = parsed
+        .diagnostics
+        .iter()
+        .map(|d| ShelterDiagnostic::new(d, &parsed.line_starts))
+        .collect();
+
+    // Return entries and line_starts together - Lua gets pre-computed offsets
+    ShelterResult::ok(
+        entries,
+        comments,
+        diagnostics,
+        references,
+        duplicate_links,
+        parsed.line_starts,
+        dialect,
+    )
+}
+
+/// Free a parse result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_parse`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_result(result: *mut ShelterResult) {
+    if result.is_null() {
+        return;
     }
 
-    // Convert to Rust string
-    let input_slice = slice::from_raw_parts(input as *const u8, input_len);
-    let input_str = match std::str::from_utf8(input_slice) {
+    let result = Box::from_raw(result);
+
+    // Free entries and their key and value strings
+    free_raw_array(result.entries, result.count, |entry| {
+        free_cstring(entry.key);
+        free_cstring(entry.value);
+        free_cstring(entry.resolved);
+        free_cstring(entry.fingerprint);
+    });
+
+    // Free line_offsets array
+    free_raw_array(result.line_offsets, result.line_count, drop);
+
+    // Free comments and their text
+    free_raw_array(result.comments, result.comment_count, |c| {
+        free_cstring(c.text)
+    });
+
+    // Free diagnostics and their messages
+    free_raw_array(result.diagnostics, result.diagnostic_count, |d| {
+        free_cstring(d.message)
+    });
+
+    // Free references and duplicate links
+    free_raw_array(result.references, result.reference_count, drop);
+    free_raw_array(result.duplicates, result.duplicate_count, drop);
+
+    // Free error message if present
+    free_cstring(result.error);
+}
+
+/// Detect the dialect of a file from its path, falling back to its content
+///
+/// Returns a `ShelterDialect` value (never `Auto`). Invalid UTF-8 content
+/// is sniffed lossily; `source` may be null.
+///
+/// # Safety
+/// - `source` must be null or point to `source_len` readable bytes
+/// - `input` must be null or point to `input_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn shelter_detect_dialect(
+    source: *const c_char,
+    source_len: usize,
+    input: *const c_char,
+    input_len: usize,
+) -> u8 {
+    let source = (!source.is_null())
+        .then(|| String::from_utf8_lossy(slice::from_raw_parts(source as *const u8, source_len)));
+    let content = if input.is_null() {
+        Default::default()
+    } else {
+        String::from_utf8_lossy(slice::from_raw_parts(input as *const u8, input_len))
+    };
+
+    ShelterDialect::from(detect_dialect(source.as_deref(), &content)) as u8
+}
+
+// =============================================================================
+//  Masking Functions
+// =============================================================================
+
+/// Parse content in the requested dialect and generate ready-to-apply masks
+///
+/// Only values whose mask differs from the original value are returned.
+/// Values that are a single `${VAR}` reference hide nothing and are skipped.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string
+/// - `input_len` must be the exact length of the string
+/// - Caller must free the result using `shelter_free_mask_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_mask(
+    input: *const c_char,
+    input_len: usize,
+    options: ShelterMaskOptions,
+) -> *mut ShelterMaskResult {
+    let input_str = match input_str(input, input_len) {
         Ok(s) => s,
-        Err(e) => return ShelterResult::err(&format!("Invalid UTF-8: {}", e)),
+        Err(e) => return ShelterMaskResult::err(&e),
     };
 
-    // Parse using korni
-    let korni_opts = korni::ParseOptions::from(options);
-    let parsed_entries = korni::parse_with_options(input_str, korni_opts);
+    // Masking always needs comment pairs and spans
+    let dialect = ShelterDialect::resolve(options.dialect, input_str);
+    let parsed = parse_dialect(input_str, dialect, korni::ParseOptions::full());
+    let mask_opts = MaskOptions::from(options.mode);
+    let skip_comments = options.skip_comments != 0;
 
-    // Build line_starts array: indices where each line begins
-    // Pre-allocate with estimated capacity (avg line length ~30 chars)
-    let estimated_lines = input_len / 30 + 1;
-    let mut line_starts: Vec<usize> = Vec::with_capacity(estimated_lines);
-    line_starts.push(0); // Line 1 starts at offset 0
+    let masks = parsed
+        .pairs
+        .iter()
+        .zip(resolve(input_str, &parsed.pairs, dialect))
+        .filter(|(p, resolution)| {
+            let is_reference = resolution.as_ref().is_some_and(|r| r.is_reference);
+            !skips_mask(&p.kv, is_reference, skip_comments)
+        })
+        .filter_map(|(p, _)| {
+            mask_value(p.kv.value.as_bytes(), &mask_opts).map(|m| ShelterMask::new(p, m))
+        })
+        .collect();
 
-    for (i, b) in input_str.bytes().enumerate() {
-        if b == b'\n' {
-            line_starts.push(i + 1);
-        }
+    ShelterMaskResult::ok(masks, parsed.line_starts)
+}
+
+/// Key and offset of a pair that defines its key, for duplicate detection;
+/// commented-out entries define nothing
+fn definition<'a>(kv: &'a korni::KeyValuePair) -> Option<(&'a str, usize)> {
+    let offset = kv.key_span.or(kv.value_span).map_or(0, |s| s.start.offset);
+    (!kv.is_comment).then_some((kv.key.as_ref(), offset))
+}
+
+/// Whether masking leaves a value as is: values inside comments when
+/// `skip_comments` is set, and values that are a single `${VAR}` reference
+/// resolving to another entry
+fn skips_mask(kv: &korni::KeyValuePair, is_reference: bool, skip_comments: bool) -> bool {
+    (kv.is_comment && skip_comments) || is_reference
+}
+
+/// Free a mask result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_mask`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_mask_result(result: *mut ShelterMaskResult) {
+    if result.is_null() {
+        return;
+    }
+
+    let result = Box::from_raw(result);
+
+    free_raw_array(result.masks, result.count, |mask| free_cstring(mask.mask));
+    free_raw_array(result.line_offsets, result.line_count, drop);
+    free_cstring(result.error);
+}
+
+/// Mask a single value with a built-in mode
+///
+/// Returns the mask, or null when it would be identical to the value (e.g.
+/// `none` mode, or `partial` falling back to `none`). Works on raw bytes, so
+/// `value` need not be UTF-8.
+///
+/// # Safety
+/// - `value` must be null (only if `value_len` is 0) or point to `value_len` bytes
+/// - Caller must free the result using `shelter_free_string`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_mask_value(
+    value: *const c_char,
+    value_len: usize,
+    mode: ShelterModeConfig,
+) -> *mut c_char {
+    let value = match value_len {
+        0 => &[],
+        _ if value.is_null() => return ptr::null_mut(),
+        len => slice::from_raw_parts(value as *const u8, len),
+    };
+
+    match mask_value(value, &MaskOptions::from(mode)) {
+        Some(masked) => CString::new(masked.mask)
+            .expect("masks hold no NUL bytes")
+            .into_raw(),
+        None => ptr::null_mut(),
     }
+}
+
+// =============================================================================
+//  Layer Functions
+// =============================================================================
+
+/// Work out the effective keys of a set of env files loaded on top of each
+/// other, and which file and line each key comes from
+///
+/// `scheme` is a `ShelterLayerScheme`; files are recognised by name
+/// (`.env`, `.env.local`, `.env.<mode>`, `.env.<mode>.local`) and files the
+/// scheme does not load in `mode` are reported but ignored. `mode` defaults
+/// to `development` when null or empty. Compose loads every file in array
+/// order. Values are not returned, only their spans.
+///
+/// # Safety
+/// - `files` must be null or point to `file_count` valid `ShelterFile`s
+/// - `mode` must be null or point to `mode_len` readable bytes
+/// - Caller must free the result using `shelter_free_layer_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_resolve_layers(
+    files: *const ShelterFile,
+    file_count: usize,
+    scheme: u8,
+    mode: *const c_char,
+    mode_len: usize,
+) -> *mut ShelterLayerResult {
+    let Some(scheme) = ShelterLayerScheme::from_u8(scheme) else {
+        return ShelterLayerResult::err(&format!("Unknown layer scheme {}", scheme));
+    };
+    let files = match file_pairs(files, file_count) {
+        Ok(files) => files,
+        Err(e) => return ShelterLayerResult::err(&e),
+    };
+    let mode = optional_str(mode, mode_len).unwrap_or("development");
 
-    // Convert entries - pre-allocate based on parsed count
-    let mut entries = Vec::with_capacity(parsed_entries.len());
+    let layers = resolve_layers(&files, scheme, mode);
 
-    for entry in parsed_entries {
-        match entry {
-            Entry::Pair(kv) => {
-                let line_number = kv
-                    .key_span
-                    .map(|s| offset_to_line_binary(&line_starts, s.start.offset))
-                    .unwrap_or(0);
+    let mut shadowed = Vec::new();
+    let keys = layers
+        .keys
+        .into_iter()
+        .map(|key| ShelterLayerKey::new(key, &mut shadowed))
+        .collect();
+    let files = layers.files.into_iter().map(Into::into).collect();
 
-                // Calculate end line for multi-line values using binary search
-                let value_end_line = kv
-                    .value_span
-                    .map(|s| offset_to_line_binary(&line_starts, s.end.offset.saturating_sub(1)))
-                    .unwrap_or(line_number);
+    ShelterLayerResult::ok(keys, shadowed, files)
+}
 
-                entries.push(ShelterEntry::from_korni(&kv, line_number, value_end_line));
-            }
-            Entry::Comment(_) => {
-                // Skip comments for now, we only care about key-value pairs
-            }
-            Entry::Error(_) => {
-                // Silently skip parse errors - expected during editing
-            }
-        }
+/// Free a layer result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_resolve_layers`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_layer_result(result: *mut ShelterLayerResult) {
+    if result.is_null() {
+        return;
     }
 
-    // Return entries and line_starts together - Lua gets pre-computed offsets
-    ShelterResult::ok(entries, line_starts)
+    let result = Box::from_raw(result);
+
+    free_raw_array(result.keys, result.key_count, |key| free_cstring(key.key));
+    free_raw_array(result.shadowed, result.shadowed_count, drop);
+    free_raw_array(result.files, result.file_count, drop);
+    free_cstring(result.error);
 }
 
-/// Free a parse result
+/// Compare env files key by key
+///
+/// Returns a key × file matrix telling which files define each key and
+/// whether their values agree. Values are compared by keyed fingerprints
+/// that are only valid within one call; no value leaves the library.
 ///
 /// # Safety
-/// - `result` must be a valid pointer returned by `shelter_parse`
+/// - `files` must be null or point to `file_count` valid `ShelterFile`s
+/// - Caller must free the result using `shelter_free_compare_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_compare(
+    files: *const ShelterFile,
+    file_count: usize,
+) -> *mut ShelterCompareResult {
+    let files = match file_pairs(files, file_count) {
+        Ok(files) => files,
+        Err(e) => return ShelterCompareResult::err(&e),
+    };
+
+    let compared = match compare(&files, None) {
+        Ok(compared) => compared,
+        Err(e) => return ShelterCompareResult::err(&e),
+    };
+
+    let mut cells = Vec::new();
+    let keys = compared
+        .into_iter()
+        .map(|key| ShelterCompareKey::new(key, &mut cells))
+        .collect();
+
+    ShelterCompareResult::ok(keys, cells, files.len())
+}
+
+/// Free a comparison result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_compare`
 /// - Must not be called more than once on the same pointer
 #[no_mangle]
-pub unsafe extern "C" fn shelter_free_result(result: *mut ShelterResult) {
+pub unsafe extern "C" fn shelter_free_compare_result(result: *mut ShelterCompareResult) {
     if result.is_null() {
         return;
     }
 
     let result = Box::from_raw(result);
 
-    // Free entries
-    if !result.entries.is_null() && result.count > 0 {
-        let entries = Vec::from_raw_parts(result.entries, result.count, result.count);
-        for entry in entries {
-            // Free key and value strings
-            if !entry.key.is_null() {
-                drop(CString::from_raw(entry.key));
-            }
-            if !entry.value.is_null() {
-                drop(CString::from_raw(entry.value));
+    free_raw_array(result.keys, result.key_count, |key| free_cstring(key.key));
+    free_raw_array(result.cells, result.cell_count, drop);
+    free_cstring(result.error);
+}
+
+// =============================================================================
+//  Pattern Functions
+// =============================================================================
+
+/// Compile key and source rules into a pattern set
+///
+/// Returns null on failure and stores the error message in `error_out`
+/// (if non-null); free it with `shelter_free_string`.
+///
+/// # Safety
+/// - `patterns`/`sources` must be null or point to `*_count` valid rules
+/// - `default_mode` must point to `default_mode_len` bytes of UTF-8
+/// - `error_out` must be null or a valid pointer
+/// - Caller must free the set using `shelter_pattern_set_free`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_pattern_set_new(
+    patterns: *const ShelterPatternRule,
+    pattern_count: usize,
+    sources: *const ShelterPatternRule,
+    source_count: usize,
+    default_mode: *const c_char,
+    default_mode_len: usize,
+    error_out: *mut *mut c_char,
+) -> *mut ShelterPatternSet {
+    let build = || -> Result<PatternSet, String> {
+        let patterns = rule_pairs(patterns, pattern_count)?;
+        let sources = rule_pairs(sources, source_count)?;
+        let default_mode = input_str(default_mode, default_mode_len)?;
+        PatternSet::new(patterns, sources, default_mode)
+    };
+
+    match build() {
+        Ok(inner) => Box::into_raw(Box::new(ShelterPatternSet { inner })),
+        Err(e) => {
+            if !error_out.is_null() {
+                *error_out = error_cstring(&e);
             }
+            ptr::null_mut()
         }
     }
+}
 
-    // Free line_offsets array
-    if !result.line_offsets.is_null() && result.line_count > 0 {
-        drop(Vec::from_raw_parts(
-            result.line_offsets,
-            result.line_count,
-            result.line_count,
-        ));
+/// Resolve the mode for a key and optional source path
+///
+/// Key rules win over source rules, which win over the default mode.
+/// The returned string is owned by the set and valid until it is freed.
+///
+/// # Safety
+/// - `set` must be a valid pointer returned by `shelter_pattern_set_new`
+/// - `key` must point to `key_len` bytes; `source` may be null
+#[no_mangle]
+pub unsafe extern "C" fn shelter_pattern_set_resolve(
+    set: *const ShelterPatternSet,
+    key: *const c_char,
+    key_len: usize,
+    source: *const c_char,
+    source_len: usize,
+) -> *const c_char {
+    if set.is_null() {
+        return ptr::null();
     }
 
-    // Free error message if present
-    if !result.error.is_null() {
-        drop(CString::from_raw(result.error));
+    let set = &(*set).inner;
+    let key = optional_str(key, key_len).unwrap_or_default();
+    set.resolve(key, optional_str(source, source_len)).as_ptr()
+}
+
+/// Match a key against key rules only
+/// Returns null if no rule matches
+///
+/// # Safety
+/// Same as `shelter_pattern_set_resolve`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_pattern_set_match_key(
+    set: *const ShelterPatternSet,
+    key: *const c_char,
+    key_len: usize,
+) -> *const c_char {
+    if set.is_null() {
+        return ptr::null();
+    }
+
+    let key = optional_str(key, key_len).unwrap_or_default();
+    (*set)
+        .inner
+        .match_key(key)
+        .map_or(ptr::null(), |mode| mode.as_ptr())
+}
+
+/// Match a source path against source rules only
+/// Returns null if no rule matches
+///
+/// # Safety
+/// Same as `shelter_pattern_set_resolve`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_pattern_set_match_source(
+    set: *const ShelterPatternSet,
+    source: *const c_char,
+    source_len: usize,
+) -> *const c_char {
+    if set.is_null() {
+        return ptr::null();
+    }
+
+    optional_str(source, source_len)
+        .and_then(|source| (*set).inner.match_source(source))
+        .map_or(ptr::null(), |mode| mode.as_ptr())
+}
+
+/// Free a pattern set
+///
+/// # Safety
+/// - `set` must be a valid pointer returned by `shelter_pattern_set_new`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_pattern_set_free(set: *mut ShelterPatternSet) {
+    if !set.is_null() {
+        drop(Box::from_raw(set));
     }
 }
 
 // =============================================================================
-//  Utility Functions
+//  Scoring Functions
+// =============================================================================
+
+/// Score a value: Shannon entropy, character classes, length and a
+/// likely-secret / likely-benign / unknown classification, plus the known
+/// credential format if the value matches one
+///
+/// Invalid UTF-8 is scored lossily; a null value scores as an empty one.
+///
+/// # Safety
+/// `value` must be null or point to `value_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn shelter_score_value(
+    value: *const c_char,
+    value_len: usize,
+) -> ShelterValueScore {
+    if value.is_null() {
+        return score_value("").into();
+    }
+
+    let bytes = slice::from_raw_parts(value as *const u8, value_len);
+    score_value(&String::from_utf8_lossy(bytes)).into()
+}
+
+// =============================================================================
+//  Fingerprint Functions
+// =============================================================================
+
+/// Compute a short keyed fingerprint of a value
+///
+/// Returns a 4-digit hex tag (e.g. `a3f9`) of a BLAKE3 keyed hash. Without
+/// `key` the hash is keyed with a random per-machine secret, created in the
+/// user data directory on first use, so tags cannot be reversed by anyone
+/// who only sees them. A non-empty `key` is a shared secret that makes tags
+/// agree across machines. Compare tags with `shelter_fingerprint_equal`.
+///
+/// Returns null on failure and stores the error message in `error_out`
+/// (if non-null). Free the tag and the error with `shelter_free_string`.
+///
+/// # Safety
+/// - `value` must point to `value_len` readable bytes
+/// - `key` must be null or point to `key_len` readable bytes
+/// - `error_out` must be null or a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_fingerprint(
+    value: *const c_char,
+    value_len: usize,
+    key: *const c_char,
+    key_len: usize,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let compute = || -> Result<String, String> {
+        if value.is_null() {
+            return Err("Value is null".to_string());
+        }
+        let value = slice::from_raw_parts(value as *const u8, value_len);
+        let key = if key.is_null() || key_len == 0 {
+            machine_key()?
+        } else {
+            derive_key(slice::from_raw_parts(key as *const u8, key_len))
+        };
+        Ok(tag(&fingerprint(value, &key)))
+    };
+
+    match compute() {
+        Ok(tag) => CString::new(tag).unwrap_or_default().into_raw(),
+        Err(e) => {
+            if !error_out.is_null() {
+                *error_out = error_cstring(&e);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Compare two fingerprints in constant time
+///
+/// Returns 1 if they are equal, 0 otherwise (or if either is null).
+///
+/// # Safety
+/// - `a` and `b` must be null or point to `a_len` / `b_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn shelter_fingerprint_equal(
+    a: *const c_char,
+    a_len: usize,
+    b: *const c_char,
+    b_len: usize,
+) -> u8 {
+    if a.is_null() || b.is_null() {
+        return 0;
+    }
+    let a = slice::from_raw_parts(a as *const u8, a_len);
+    let b = slice::from_raw_parts(b as *const u8, b_len);
+    constant_time_eq(a, b) as u8
+}
+
 // =============================================================================
+//  Document Functions
+// =============================================================================
+
+/// Create a persistent document for incremental re-parsing
+///
+/// `options.dialect` is resolved once, from the initial content for `Auto`.
+/// The first `shelter_document_update` reports every entry as added.
+/// Returns null on failure and stores the error message in `error_out`
+/// (if non-null); free it with `shelter_free_string`.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `error_out` must be null or a valid pointer
+/// - Caller must free the document using `shelter_document_free`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_document_new(
+    input: *const c_char,
+    input_len: usize,
+    options: ShelterMaskOptions,
+    error_out: *mut *mut c_char,
+) -> *mut ShelterDocument {
+    match input_str(input, input_len) {
+        Ok(text) => Box::into_raw(Box::new(ShelterDocument {
+            inner: Document::new(text, ShelterDialect::resolve(options.dialect, text)),
+            mask: MaskOptions::from(options.mode),
+            skip_comments: options.skip_comments != 0,
+        })),
+        Err(e) => {
+            if !error_out.is_null() {
+                *error_out = error_cstring(&e);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Apply a byte-range edit, as reported by `nvim_buf_attach` `on_bytes`
+///
+/// Replaces `old_len` bytes at `start_byte` with `new_text`. Parsing is
+/// deferred until `shelter_document_update`, so several edits can be batched.
+/// Returns 1 on success, 0 if the range is out of bounds, splits a UTF-8
+/// character or `new_text` is not UTF-8 (the document is left unchanged;
+/// callers should fall back to `shelter_document_reset`).
+///
+/// # Safety
+/// - `doc` must be a valid pointer returned by `shelter_document_new`
+/// - `new_text` must be null (only if `new_len` is 0) or point to `new_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn shelter_document_edit(
+    doc: *mut ShelterDocument,
+    start_byte: usize,
+    old_len: usize,
+    new_text: *const c_char,
+    new_len: usize,
+) -> u8 {
+    if doc.is_null() {
+        return 0;
+    }
+
+    let new_text = if new_len == 0 {
+        ""
+    } else {
+        match input_str(new_text, new_len) {
+            Ok(s) => s,
+            Err(_) => return 0,
+        }
+    };
+
+    (*doc).inner.edit(start_byte, old_len, new_text).is_ok() as u8
+}
+
+/// Replace the whole document text
+///
+/// The next update reports the differences against the previous content.
+/// Returns 1 on success, 0 if `input` is null or not UTF-8.
+///
+/// # Safety
+/// - `doc` must be a valid pointer returned by `shelter_document_new`
+/// - `input` must point to `input_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn shelter_document_reset(
+    doc: *mut ShelterDocument,
+    input: *const c_char,
+    input_len: usize,
+) -> u8 {
+    if doc.is_null() {
+        return 0;
+    }
+
+    match input_str(input, input_len) {
+        Ok(text) => {
+            (*doc).inner.replace_all(text);
+            1
+        }
+        Err(_) => 0,
+    }
+}
+
+/// Re-parse edited regions and return entries added, changed or removed
+/// since the previous update
+///
+/// Entries outside the edited regions are not reported; their byte offsets
+/// shift with the text, like extmarks do.
+///
+/// # Safety
+/// - `doc` must be a valid pointer returned by `shelter_document_new`
+/// - Caller must free the result using `shelter_free_document_delta`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_document_update(
+    doc: *mut ShelterDocument,
+) -> *mut ShelterDocumentDelta {
+    if doc.is_null() {
+        return ShelterDocumentDelta::err("Document is null");
+    }
+
+    let ShelterDocument {
+        inner,
+        mask,
+        skip_comments,
+    } = &mut *doc;
+
+    let delta = inner.update();
+    let line_starts = delta.line_starts;
+
+    let changes = delta
+        .changes
+        .into_iter()
+        .zip(delta.resolutions)
+        .map(|((kind, pair), resolution)| {
+            let kv = &pair.kv;
+            let (line_number, value_end_line) = pair_lines(line_starts, kv);
+            let mut references = Vec::new();
+            let mut entry = ShelterEntry::from_korni(kv, line_number, value_end_line)
+                .with_inline_comment(pair.inline_comment)
+                .with_resolution(resolution, &mut references);
+            // A delta has no reference list; the resolved value is enough
+            entry.reference_count = 0;
+            entry.encoding = ShelterValueEncoding::from(pair.encoding) as u8;
+            let masked = if skips_mask(kv, entry.is_reference != 0, *skip_comments) {
+                None
+            } else {
+                mask_value(kv.value.as_bytes(), mask)
+            };
+
+            let value_start_col = entry
+                .value_start
+                .saturating_sub(line_starts[line_number.saturating_sub(1)]);
+            let value_end_col = entry
+                .value_end
+                .saturating_sub(line_starts[value_end_line.saturating_sub(1)]);
+            let kind = match kind {
+                ChangeKind::Added => ShelterChangeKind::Added,
+                ChangeKind::Changed => ShelterChangeKind::Changed,
+            };
+
+            let (mask_len, masked_start, masked_end, mask) = match masked {
+                Some(m) => (
+                    m.mask.len(),
+                    m.masked_start,
+                    m.masked_end,
+                    CString::new(m.mask)
+                        .expect("masks hold no NUL bytes")
+                        .into_raw(),
+                ),
+                None => (0, 0, 0, ptr::null_mut()),
+            };
+
+            ShelterEntryChange {
+                entry,
+                mask,
+                mask_len,
+                masked_start,
+                masked_end,
+                value_start_col,
+                value_end_col,
+                id: pair.id,
+                kind: kind as u8,
+            }
+        })
+        .collect();
+
+    ShelterDocumentDelta::ok(changes, delta.removed)
+}
+
+/// Snapshot the current entries, comments and diagnostics as of the last update
+///
+/// Useful to (re)build all extmarks, e.g. after the buffer is re-displayed.
+///
+/// # Safety
+/// - `doc` must be a valid pointer returned by `shelter_document_new`
+/// - Caller must free the result using `shelter_free_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_document_entries(
+    doc: *const ShelterDocument,
+) -> *mut ShelterResult {
+    if doc.is_null() {
+        return ShelterResult::err("Document is null");
+    }
+
+    let doc = &(*doc).inner;
+    let line_starts = doc.line_starts();
+
+    let definitions = doc.pairs().map(|p| definition(&p.kv));
+    let duplicates = find_duplicates(doc.text(), definitions, doc.dialect());
+
+    let mut duplicate_links = Vec::new();
+    let entries = doc
+        .pairs()
+        .zip(duplicates)
+        .map(|(p, duplicate)| {
+            let (line_number, value_end_line) = pair_lines(line_starts, &p.kv);
+            let mut entry = ShelterEntry::from_korni(&p.kv, line_number, value_end_line)
+                .with_inline_comment(p.inline_comment)
+                .with_duplicate(duplicate, &mut duplicate_links);
+            entry.encoding = ShelterValueEncoding::from(p.encoding) as u8;
+            entry
+        })
+        .collect();
+
+    let comments = doc
+        .comments()
+        .map(|c| ShelterComment::new(&c, doc.text()))
+        .collect();
+
+    let diagnostics = doc
+        .diagnostics()
+        .map(|d| ShelterDiagnostic::new(d, line_starts))
+        .collect();
+
+    ShelterResult::ok(
+        entries,
+        comments,
+        diagnostics,
+        Vec::new(),
+        duplicate_links,
+        line_starts.to_vec(),
+        doc.dialect(),
+    )
+}
+
+/// Snapshot the entries starting on lines `first_line..=last_line`
+/// (1-based) as of the last update
+///
+/// Lets a caller re-mask only the lines it redraws. `${VAR}` references are
+/// resolved against the whole document; duplicate links, comments and
+/// diagnostics are not included. The line table covers the whole document.
+///
+/// # Safety
+/// - `doc` must be a valid pointer returned by `shelter_document_new`
+/// - Caller must free the result using `shelter_free_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_document_entries_in_lines(
+    doc: *const ShelterDocument,
+    first_line: usize,
+    last_line: usize,
+) -> *mut ShelterResult {
+    if doc.is_null() {
+        return ShelterResult::err("Document is null");
+    }
+
+    let doc = &(*doc).inner;
+    let line_starts = doc.line_starts();
+    let (pairs, resolutions) = doc.pairs_in_lines(first_line, last_line);
+
+    let mut references = Vec::new();
+    let entries = pairs
+        .into_iter()
+        .zip(resolutions)
+        .map(|(p, resolution)| {
+            let (line_number, value_end_line) = pair_lines(line_starts, &p.kv);
+            let mut entry = ShelterEntry::from_korni(&p.kv, line_number, value_end_line)
+                .with_inline_comment(p.inline_comment)
+                .with_resolution(resolution, &mut references);
+            entry.encoding = ShelterValueEncoding::from(p.encoding) as u8;
+            entry
+        })
+        .collect();
+
+    ShelterResult::ok(
+        entries,
+        Vec::new(),
+        Vec::new(),
+        references,
+        Vec::new(),
+        line_starts.to_vec(),
+        doc.dialect(),
+    )
+}
+
+/// Free a document delta
+///
+/// # Safety
+/// - `delta` must be a valid pointer returned by `shelter_document_update`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_document_delta(delta: *mut ShelterDocumentDelta) {
+    if delta.is_null() {
+        return;
+    }
+
+    let delta = Box::from_raw(delta);
+
+    free_raw_array(delta.changes, delta.change_count, |change| {
+        free_cstring(change.entry.key);
+        free_cstring(change.entry.value);
+        free_cstring(change.entry.resolved);
+        free_cstring(change.entry.fingerprint);
+        free_cstring(change.mask);
+    });
+    free_raw_array(delta.removed, delta.removed_count, drop);
+    free_cstring(delta.error);
+}
+
+/// Free a document
+///
+/// # Safety
+/// - `doc` must be a valid pointer returned by `shelter_document_new`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_document_free(doc: *mut ShelterDocument) {
+    if !doc.is_null() {
+        drop(Box::from_raw(doc));
+    }
+}
+
+// =============================================================================
+//  Lint Functions
+// =============================================================================
+
+/// Lint a file
+///
+/// Findings name a stable rule (`ShelterLintRule`) and carry a severity
+/// numbered like `vim.diagnostic.severity`. Key, quoting and `export` rules
+/// only apply to env dialects; parse errors, empty `@required` values and a
+/// missing final newline are reported for every format.
+///
+/// `dialect` is a `ShelterDialect` value; `Auto` detects it from the content.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - Caller must free the result using `shelter_free_lint_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_lint(
+    input: *const c_char,
+    input_len: usize,
+    dialect: u8,
+) -> *mut ShelterLintResult {
+    let input_str = match input_str(input, input_len) {
+        Ok(s) => s,
+        Err(e) => return ShelterLintResult::err(&e),
+    };
+
+    let dialect = ShelterDialect::resolve(dialect, input_str);
+    let line_starts = line_starts(input_str);
+    let findings = lint(input_str, dialect)
+        .iter()
+        .map(|finding| ShelterLintFinding::new(finding, &line_starts))
+        .collect();
+
+    ShelterLintResult::ok(findings)
+}
+
+/// Free a lint result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_lint`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_lint_result(result: *mut ShelterLintResult) {
+    if result.is_null() {
+        return;
+    }
+
+    let result = Box::from_raw(result);
+
+    free_raw_array(result.findings, result.count, |finding| {
+        free_cstring(finding.message)
+    });
+    free_cstring(result.error);
+}
+
+// =============================================================================
+//  Schema Functions
+// =============================================================================
+
+/// Validate a file against a schema
+///
+/// Rules come from `# @type`, `@required`, `@optional`, `@default` and
+/// `@pattern` annotations in `schema` (a `.env.schema` file, may be null)
+/// and in `input`, whose annotations override the schema key by key. Empty
+/// values count as unset. With a schema, keys it does not declare are
+/// reported as warnings.
+///
+/// `dialect` is a `ShelterDialect` value for `input`; `Auto` detects it from
+/// the content. The schema is always read as EDF. Invalid rules (an unknown
+/// type, a bad pattern or a default that breaks its own rules) are reported
+/// in `error`.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `schema` must be null or a valid pointer to a UTF-8 string of
+///   `schema_len` bytes
+/// - Caller must free the result using `shelter_free_schema_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_validate(
+    input: *const c_char,
+    input_len: usize,
+    schema: *const c_char,
+    schema_len: usize,
+    dialect: u8,
+) -> *mut ShelterSchemaResult {
+    let input_str = match input_str(input, input_len) {
+        Ok(s) => s,
+        Err(e) => return ShelterSchemaResult::err(&e),
+    };
+    let schema = optional_str(schema, schema_len);
+
+    let dialect = ShelterDialect::resolve(dialect, input_str);
+    let line_starts = line_starts(input_str);
+    match validate(input_str, dialect, schema) {
+        Ok(violations) => ShelterSchemaResult::ok(
+            violations
+                .iter()
+                .map(|violation| ShelterSchemaViolation::new(violation, &line_starts))
+                .collect(),
+        ),
+        Err(e) => ShelterSchemaResult::err(&e),
+    }
+}
+
+/// Free a schema result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_validate`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_schema_result(result: *mut ShelterSchemaResult) {
+    if result.is_null() {
+        return;
+    }
+
+    let result = Box::from_raw(result);
+
+    free_raw_array(result.violations, result.count, |violation| {
+        free_cstring(violation.key);
+        free_cstring(violation.message);
+    });
+    free_cstring(result.error);
+}
+
+// =============================================================================
+//  Scan Functions
+// =============================================================================
+
+/// Find secrets in any text
+///
+/// Detects known credential formats (the `ShelterCredentialKind` formats),
+/// passwords in URLs and values assigned to secret-sounding names such as
+/// `password` or `api_key`. Findings never overlap; the most confident rule
+/// wins. The result carries line offsets like `ShelterResult`, and findings
+/// use the span fields of `ShelterEntry`, so they can be masked the same way.
+///
+/// `ruleset` enables rules by bit: bit `n` is the `ShelterScanRule` with
+/// value `n`; pass `0xFFFFFFFF` for all of them.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - Caller must free the result using `shelter_free_scan_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_scan_text(
+    input: *const c_char,
+    input_len: usize,
+    ruleset: u32,
+) -> *mut ShelterScanResult {
+    let input_str = match input_str(input, input_len) {
+        Ok(s) => s,
+        Err(e) => return ShelterScanResult::err(&e),
+    };
+
+    let line_starts = line_starts(input_str);
+    let findings = scan_text(input_str, RuleSet(ruleset))
+        .iter()
+        .map(|finding| ShelterScanFinding::new(finding, input_str, &line_starts))
+        .collect();
+
+    ShelterScanResult::ok(findings, line_starts)
+}
+
+/// Free a scan result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_scan_text`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_scan_result(result: *mut ShelterScanResult) {
+    if result.is_null() {
+        return;
+    }
+
+    let result = Box::from_raw(result);
+
+    free_raw_array(result.findings, result.count, |finding| {
+        free_cstring(finding.key);
+        free_cstring(finding.value);
+    });
+    free_raw_array(result.line_offsets, result.line_count, drop);
+    free_cstring(result.error);
+}
+
+// =============================================================================
+//  Stream Functions
+// =============================================================================
+
+/// Create a stream masker loaded with the values of the given env files
+///
+/// Loads every active value, the expansion of values with references and
+/// each line of multi-line values, skipping values the options exclude.
+/// The values are kept only inside the masker and are zeroized when it is
+/// freed. Returns null on failure and stores the error message in
+/// `error_out` (if non-null); free it with `shelter_free_string`.
+///
+/// # Safety
+/// - `files` must be null (only if `file_count` is 0) or point to `file_count` valid `ShelterFile`s
+/// - `error_out` must be null or a valid pointer
+/// - Caller must free the masker using `shelter_stream_masker_free`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_stream_masker_new(
+    files: *const ShelterFile,
+    file_count: usize,
+    options: ShelterStreamOptions,
+    error_out: *mut *mut c_char,
+) -> *mut ShelterStreamMasker {
+    match file_pairs(files, file_count) {
+        Ok(files) => {
+            let values = load_values(&files, LoadOptions::from(options));
+            Box::into_raw(Box::new(ShelterStreamMasker {
+                inner: StreamMasker::new(KnownValues::new(values), options.mask_char),
+            }))
+        }
+        Err(e) => {
+            if !error_out.is_null() {
+                *error_out = error_cstring(&e);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Number of distinct values a stream masker is loaded with
+///
+/// # Safety
+/// - `masker` must be a valid pointer returned by `shelter_stream_masker_new`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_stream_masker_value_count(
+    masker: *const ShelterStreamMasker,
+) -> usize {
+    if masker.is_null() {
+        return 0;
+    }
+    (*masker).inner.values().len()
+}
+
+/// Feed the next chunk of a stream
+///
+/// Chunks are raw bytes and need not be UTF-8 or split at line or character
+/// boundaries; a value split across chunks is still found. The output holds
+/// the redacted bytes that no later chunk can turn into a value; the rest
+/// is held back, so concatenating every output (and the output of
+/// `shelter_stream_masker_finish`) gives the redacted stream.
+///
+/// # Safety
+/// - `masker` must be a valid pointer returned by `shelter_stream_masker_new`
+/// - `chunk` must be null (only if `chunk_len` is 0) or point to `chunk_len` bytes
+/// - Caller must free the result using `shelter_free_stream_chunk`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_stream_masker_feed(
+    masker: *mut ShelterStreamMasker,
+    chunk: *const c_char,
+    chunk_len: usize,
+) -> *mut ShelterStreamChunk {
+    if masker.is_null() {
+        return ptr::null_mut();
+    }
+
+    let chunk = match chunk.is_null() || chunk_len == 0 {
+        true => &[][..],
+        false => slice::from_raw_parts(chunk as *const u8, chunk_len),
+    };
+    ShelterStreamChunk::new((*masker).inner.feed(chunk))
+}
+
+/// End the current stream, returning the bytes held back so far
+///
+/// The next chunk starts a new stream, with spans counted from 0 again.
+///
+/// # Safety
+/// - `masker` must be a valid pointer returned by `shelter_stream_masker_new`
+/// - Caller must free the result using `shelter_free_stream_chunk`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_stream_masker_finish(
+    masker: *mut ShelterStreamMasker,
+) -> *mut ShelterStreamChunk {
+    if masker.is_null() {
+        return ptr::null_mut();
+    }
+    ShelterStreamChunk::new((*masker).inner.finish())
+}
+
+/// Redact a whole text at once
+///
+/// Ends any stream in progress (discarding its held-back bytes) and masks
+/// `input` as a complete stream; the output has the same length as `input`.
+///
+/// # Safety
+/// - `masker` must be a valid pointer returned by `shelter_stream_masker_new`
+/// - `input` must be null (only if `input_len` is 0) or point to `input_len` bytes
+/// - Caller must free the result using `shelter_free_stream_chunk`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_stream_masker_redact(
+    masker: *mut ShelterStreamMasker,
+    input: *const c_char,
+    input_len: usize,
+) -> *mut ShelterStreamChunk {
+    if masker.is_null() {
+        return ptr::null_mut();
+    }
+
+    let input = match input.is_null() || input_len == 0 {
+        true => &[][..],
+        false => slice::from_raw_parts(input as *const u8, input_len),
+    };
+    ShelterStreamChunk::new((*masker).inner.redact(input))
+}
+
+/// Free a stream chunk
+///
+/// # Safety
+/// - `chunk` must be a valid pointer returned by a `shelter_stream_masker_*` function
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_stream_chunk(chunk: *mut ShelterStreamChunk) {
+    if chunk.is_null() {
+        return;
+    }
+
+    let chunk = Box::from_raw(chunk);
+    free_raw_array(chunk.output, chunk.output_len, drop);
+    free_raw_array(chunk.spans, chunk.span_count, drop);
+}
+
+/// Free a stream masker, zeroizing its values and held-back bytes
+///
+/// # Safety
+/// - `masker` must be a valid pointer returned by `shelter_stream_masker_new`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_stream_masker_free(masker: *mut ShelterStreamMasker) {
+    if !masker.is_null() {
+        drop(Box::from_raw(masker));
+    }
+}
+
+// =============================================================================
+//  Dump Functions
+// =============================================================================
+
+/// Find `KEY=VALUE` pairs of environment dumps in any text
+///
+/// Recognizes `printenv`/`env` output (three or more `KEY=value` lines in a
+/// row), the `"Env"` arrays of `docker inspect` JSON, the `Environment:`
+/// sections of `kubectl describe` and NUL-separated `/proc/<pid>/environ`
+/// data. Entries have the fields of `shelter_parse` entries, so the same
+/// pattern rules and masking apply; values are unescaped and their spans
+/// never include quotes.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - Caller must free the result using `shelter_free_dump_result`
+#[no_mangle]
+pub unsafe extern "C" fn shelter_find_env_dumps(
+    input: *const c_char,
+    input_len: usize,
+) -> *mut ShelterDumpResult {
+    let input_str = match input_str(input, input_len) {
+        Ok(s) => s,
+        Err(e) => return ShelterDumpResult::err(&e),
+    };
+
+    let dump = find_dumps(input_str);
+    let entries = dump
+        .pairs
+        .iter()
+        .map(|(format, pair)| ShelterDumpEntry {
+            entry: ShelterEntry::from_parsed(pair),
+            format: ShelterDumpFormat::from(*format) as u8,
+        })
+        .collect();
+
+    ShelterDumpResult::ok(entries, dump.line_starts)
+}
+
+/// Free a dump result
+///
+/// # Safety
+/// - `result` must be a valid pointer returned by `shelter_find_env_dumps`
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_dump_result(result: *mut ShelterDumpResult) {
+    if result.is_null() {
+        return;
+    }
+
+    let result = Box::from_raw(result);
+
+    free_raw_array(result.entries, result.count, |dump_entry| {
+        free_cstring(dump_entry.entry.key);
+        free_cstring(dump_entry.entry.value);
+        free_cstring(dump_entry.entry.resolved);
+        free_cstring(dump_entry.entry.fingerprint);
+    });
+    free_raw_array(result.line_offsets, result.line_count, drop);
+    free_cstring(result.error);
+}
+
+// =============================================================================
+//  Transform Functions
+// =============================================================================
+
+/// Generate a `.env.example` version of a file
+///
+/// Only value spans are rewritten; keys, comments, blank lines, `export`
+/// prefixes and ordering are kept byte for byte. A value becomes the
+/// placeholder of an `# @example <value>` comment on its line or right
+/// above it, else an empty string or type hint. Likely benign values are
+/// kept if `options.keep_benign` is set, and pure `${VAR}` references are
+/// always kept. New values are quoted so the dialect reads them back as is.
+///
+/// Returns null on failure (parse errors, text the dialect skips) and stores
+/// the error message in `error_out` (if non-null). Free the result and the
+/// error with `shelter_free_string`.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `error_out` must be null or a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_generate_example(
+    input: *const c_char,
+    input_len: usize,
+    options: ShelterExampleOptions,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let example = input_str(input, input_len).and_then(|input_str| {
+        let dialect = ShelterDialect::resolve(options.dialect, input_str);
+        generate_example(input_str, dialect, options.into())
+    });
+
+    text_result(example, error_out)
+}
+
+/// Apply edits to a file, leaving every byte outside the edited entries as is
+///
+/// Edits run in order, each on the result of the one before. Setting a key
+/// replaces the value of its last definition (or appends `KEY=value`),
+/// keeping the entry's quote style when the value can be read back through
+/// it and re-quoting it otherwise. Unsetting removes the lines of every
+/// definition; moving takes the comment lines right above each definition
+/// along. Config formats (JSON, TOML, INI, YAML) only support setting keys
+/// that are already defined.
+///
+/// `dialect` is a `ShelterDialect` value; `Auto` detects it from the content.
+///
+/// Returns null on failure (an unknown key, an invalid new key, a value the
+/// format cannot hold) and stores the error message in `error_out` (if
+/// non-null). Free the result and the error with `shelter_free_string`.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `edits` must be null or point to `edit_count` valid `ShelterEdit`s
+/// - `error_out` must be null or a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_edit(
+    input: *const c_char,
+    input_len: usize,
+    dialect: u8,
+    edits: *const ShelterEdit,
+    edit_count: usize,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let edited = input_str(input, input_len).and_then(|input_str| {
+        let edits = edit_list(edits, edit_count)?;
+        let dialect = ShelterDialect::resolve(dialect, input_str);
+        apply_edits(input_str, dialect, &edits)
+    });
+
+    text_result(edited, error_out)
+}
+
+/// Format an env file
+///
+/// Entries are rebuilt as `KEY=value` with the least quoting that reads
+/// back the same (values with `$` keep their text), comment lines are
+/// trimmed and runs of blank lines collapsed. With `options.sort`, keys are
+/// sorted and the comment lines directly above each key move with it. The
+/// output holds the same values as the input and formats to itself.
+///
+/// Returns null on failure (parse errors, config formats) and stores the
+/// error message in `error_out` (if non-null). Free the result and the
+/// error with `shelter_free_string`.
+///
+/// # Safety
+/// - `input` must be a valid pointer to a UTF-8 string of `input_len` bytes
+/// - `error_out` must be null or a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_format(
+    input: *const c_char,
+    input_len: usize,
+    options: ShelterFormatOptions,
+    error_out: *mut *mut c_char,
+) -> *mut c_char {
+    let formatted = input_str(input, input_len).and_then(|input_str| {
+        let dialect = ShelterDialect::resolve(options.dialect, input_str);
+        format(input_str, dialect, options.into())
+    });
+
+    text_result(formatted, error_out)
+}
+
+// =============================================================================
+//  Utility Functions
+// =============================================================================
+
+/// Free a string returned by `shelter_fingerprint`,
+/// `shelter_generate_example`, `shelter_edit`, `shelter_format` or through
+/// an error out-parameter
+///
+/// # Safety
+/// - `s` must be null or a string allocated by shelter-core
+/// - Must not be called more than once on the same pointer
+#[no_mangle]
+pub unsafe extern "C" fn shelter_free_string(s: *mut c_char) {
+    free_cstring(s);
+}
 
 /// Get library version string
 ///
@@ -0,0 +1,136 @@
+//! Keyed value fingerprints for shelter-core
+//!
+//! A fingerprint is a BLAKE3 keyed hash of a value, shown as a short hex tag
+//! next to a masked value so two values can be told apart or confirmed equal
+//! without revealing them. The key is a random per-machine secret kept in
+//! the user data directory, so tags cannot be brute-forced offline by anyone
+//! who only sees them. A shared secret can be passed instead, to make tags
+//! agree across machines.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Length of a fingerprint key in bytes
+pub(crate) const KEY_LEN: usize = 32;
+
+/// Length of a tag in hex digits
+pub(crate) const TAG_LEN: usize = 4;
+
+/// Context string for deriving keys from shared secrets
+const DERIVE_CONTEXT: &str = "shelter.nvim 2025 value fingerprint key";
+
+/// File name of the machine secret in the data directory
+const KEY_FILE: &str = "fingerprint.key";
+
+static MACHINE_KEY: OnceLock<[u8; KEY_LEN]> = OnceLock::new();
+
+/// Keyed hash of `value`
+pub(crate) fn fingerprint(value: &[u8], key: &[u8; KEY_LEN]) -> blake3::Hash {
+    blake3::keyed_hash(key, value)
+}
+
+/// Short hex tag of a fingerprint, e.g. `a3f9`
+pub(crate) fn tag(hash: &blake3::Hash) -> String {
+    hash.to_hex()[..TAG_LEN].to_string()
+}
+
+/// Compare two byte strings in time independent of their content
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+/// Key derived from a caller-supplied secret of any length
+pub(crate) fn derive_key(secret: &[u8]) -> [u8; KEY_LEN] {
+    blake3::derive_key(DERIVE_CONTEXT, secret)
+}
+
+/// A fresh random key, for fingerprints that only need to agree within
+/// one call
+pub(crate) fn random_key() -> Result<[u8; KEY_LEN], String> {
+    let mut key = [0u8; KEY_LEN];
+    getrandom::getrandom(&mut key).map_err(|e| format!("No random source: {}", e))?;
+    Ok(key)
+}
+
+/// The per-machine secret, created on first use
+pub(crate) fn machine_key() -> Result<[u8; KEY_LEN], String> {
+    if let Some(key) = MACHINE_KEY.get() {
+        return Ok(*key);
+    }
+    let dir = data_dir().ok_or("No user data directory (set XDG_DATA_HOME)")?;
+    let key = load_or_create(&dir.join(KEY_FILE))?;
+    Ok(*MACHINE_KEY.get_or_init(|| key))
+}
+
+/// A non-empty environment variable
+fn env(name: &str) -> Option<std::ffi::OsString> {
+    std::env::var_os(name).filter(|v| !v.is_empty())
+}
+
+/// `shelter` in the user data directory
+fn data_dir() -> Option<PathBuf> {
+    let data_dir = env("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+        .or_else(|| env("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(data_dir.join("shelter"))
+}
+
+fn load_or_create(path: &PathBuf) -> Result<[u8; KEY_LEN], String> {
+    let display = path.display();
+    match fs::read_to_string(path) {
+        Ok(content) => return parse_key(&content).ok_or(format!("{}: not a key", display)),
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(format!("{}: {}", display, e))
+        }
+        Err(_) => {}
+    }
+
+    let key = random_key()?;
+    let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+
+    // Write a private temporary file and link it into place, so that a
+    // concurrent first use never sees a partial key or replaces one in use
+    let dir = path.parent().unwrap_or(path);
+    let temp = dir.join(format!("{}.{}", KEY_FILE, std::process::id()));
+    let write = || -> std::io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&temp)?;
+        file.write_all(hex.as_bytes())?;
+        file.sync_all()
+    };
+    let linked = write().and_then(|()| fs::hard_link(&temp, path));
+    let _ = fs::remove_file(&temp);
+
+    match linked {
+        Ok(()) => Ok(key),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", display, e))?;
+            parse_key(&content).ok_or(format!("{}: not a key", display))
+        }
+        Err(e) => Err(format!("{}: {}", display, e)),
+    }
+}
+
+/// Decode a key stored as hex
+fn parse_key(content: &str) -> Option<[u8; KEY_LEN]> {
+    let hex = content.trim().as_bytes();
+    if hex.len() != KEY_LEN * 2 {
+        return None;
+    }
+    let mut key = [0u8; KEY_LEN];
+    for (byte, pair) in key.iter_mut().zip(hex.chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(key)
+}
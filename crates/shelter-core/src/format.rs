@@ -0,0 +1,355 @@
+//! Env file formatter for shelter-core
+//!
+//! Rebuilds every entry as `KEY=value` (keeping `export` prefixes and inline
+//! comments), requotes values with the least quoting that reads back the
+//! same, trims stray whitespace and collapses runs of blank lines. Values
+//! with `$` keep their text as written, since requoting would change
+//! whether they are expanded.
+//!
+//! Keys can be sorted alphabetically within each blank-line separated
+//! section, or across the whole file and grouped by prefix (`DB_*`,
+//! `AWS_*`). The comment lines directly above a key move with it. The result
+//! is parsed again and must hold the same values, and formatting it again
+//! changes nothing. Files with text the dialect skips (stray lines, shell
+//! command words) are refused rather than losing that text.
+
+use korni::{ParseOptions, QuoteType};
+
+use crate::dialect::{parse_dialect, Dialect};
+use crate::parse::{offset_to_line, ParsedContent, ParsedPair};
+use crate::quote::quote_value;
+
+/// How to order keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Sort {
+    /// Keep the file's order
+    None,
+    /// Sort keys within each section separated by blank lines or comments
+    Alphabetical,
+    /// Sort all keys and separate groups sharing a prefix with blank lines
+    Grouped,
+}
+
+/// Options for formatting
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FormatOptions {
+    pub sort: Sort,
+}
+
+/// A line (or, for multi-line values, lines) of the formatted file
+#[derive(Debug, Clone)]
+enum Line {
+    Blank,
+    /// A comment or other line that is not an entry, trimmed
+    Comment(String),
+    Entry {
+        key: String,
+        text: String,
+    },
+}
+
+/// An entry with the comment lines directly above it, or a line that
+/// stays where it is
+#[derive(Debug)]
+enum Unit {
+    Item {
+        comments: Vec<String>,
+        key: String,
+        text: String,
+    },
+    Blank,
+    Comment(String),
+}
+
+/// Format `input` read as `dialect`
+pub(crate) fn format(
+    input: &str,
+    dialect: Dialect,
+    options: FormatOptions,
+) -> Result<String, String> {
+    if dialect.is_config() {
+        return Err(format!(
+            "Formatting {} files is not supported",
+            dialect.name()
+        ));
+    }
+
+    let parsed = parse_dialect(input, dialect, ParseOptions::full());
+    if let Some(diagnostic) = parsed.diagnostics.first() {
+        return Err(format!(
+            "Line {}: {}",
+            offset_to_line(&parsed.line_starts, diagnostic.start).max(1),
+            diagnostic.message
+        ));
+    }
+    // Text the dialect skips has no place in the rebuilt file and would be lost
+    if let Some(&(start, _)) = parsed.skipped.first() {
+        return Err(format!(
+            "Line {}: Expected KEY=VALUE",
+            offset_to_line(&parsed.line_starts, start).max(1)
+        ));
+    }
+
+    let units = units(lines(input, &parsed, dialect));
+    let units = match options.sort {
+        Sort::None => units,
+        Sort::Alphabetical => sort_sections(units),
+        Sort::Grouped => sort_grouped(units),
+    };
+    let newline = if input.contains("\r\n") { "\r\n" } else { "\n" };
+    let formatted = render(&units, newline);
+
+    // Never hand back a file that reads differently
+    if values(&parsed) != values(&parse_dialect(&formatted, dialect, ParseOptions::default())) {
+        return Err("Formatting would change values".to_string());
+    }
+    Ok(formatted)
+}
+
+/// Classify the lines of a parsed file, rebuilding every entry
+fn lines(input: &str, parsed: &ParsedContent, dialect: Dialect) -> Vec<Line> {
+    let line_starts = &parsed.line_starts;
+    let line_text = |line: usize| {
+        let start = line_starts[line - 1];
+        let end = line_starts.get(line).copied().unwrap_or(input.len());
+        input[start..end].trim_end_matches(['\n', '\r'])
+    };
+    let other_lines = |lines: &mut Vec<Line>, from: usize, to: usize| {
+        for line in from..to {
+            let text = line_text(line).trim();
+            lines.push(match text.is_empty() {
+                true => Line::Blank,
+                false => Line::Comment(text.to_string()),
+            });
+        }
+    };
+
+    let mut lines = Vec::new();
+    // Next line not yet classified
+    let mut cursor = 1;
+    for pair in parsed.pairs.iter().filter(|p| !p.kv.is_comment) {
+        let first_on_line = pair.line_number >= cursor;
+        if first_on_line {
+            other_lines(&mut lines, cursor, pair.line_number);
+        }
+        lines.push(Line::Entry {
+            key: pair.kv.key.to_string(),
+            text: entry_text(input, parsed, pair, first_on_line, dialect),
+        });
+        cursor = cursor.max(pair.value_end_line.max(pair.line_number) + 1);
+    }
+    // The line after a final newline is empty, not a line of its own
+    let last = match input.ends_with('\n') {
+        true => line_starts.len(),
+        false => line_starts.len() + 1,
+    };
+    other_lines(&mut lines, cursor, last);
+    lines
+}
+
+/// `[export ]KEY=value[ # comment]` for a pair
+fn entry_text(
+    input: &str,
+    parsed: &ParsedContent,
+    pair: &ParsedPair,
+    first_on_line: bool,
+    dialect: Dialect,
+) -> String {
+    let kv = &pair.kv;
+    let key_start = kv.key_span.map_or(0, |s| s.start.offset);
+    let line_start = parsed.line_starts[pair.line_number.max(1) - 1];
+    let exported = first_on_line
+        && input
+            .get(line_start..key_start)
+            .is_some_and(|prefix| prefix.trim() == "export");
+
+    let value = match kv.value_span {
+        // Requoting could turn expansion on or off
+        Some(span) if kv.value.contains('$') => {
+            input[span.start.offset..span.end.offset].to_string()
+        }
+        _ => {
+            let preferred = match kv.value.contains(['\n', '\r', '\t']) {
+                true => QuoteType::Double,
+                false => QuoteType::None,
+            };
+            quote_value(&kv.value, preferred, dialect)
+        }
+    };
+
+    let mut text = format!(
+        "{}{}={}",
+        if exported { "export " } else { "" },
+        kv.key,
+        value
+    );
+    if let Some((start, end)) = pair.inline_comment {
+        text.push(' ');
+        text.push_str(input[start..end].trim_end());
+    }
+    text
+}
+
+/// Attach each comment block to the entry right below it
+fn units(lines: Vec<Line>) -> Vec<Unit> {
+    let mut units = Vec::new();
+    let mut comments = Vec::new();
+    for line in lines {
+        match line {
+            Line::Comment(text) => comments.push(text),
+            Line::Entry { key, text } => units.push(Unit::Item {
+                comments: std::mem::take(&mut comments),
+                key,
+                text,
+            }),
+            Line::Blank => {
+                units.extend(comments.drain(..).map(Unit::Comment));
+                units.push(Unit::Blank);
+            }
+        }
+    }
+    units.extend(comments.into_iter().map(Unit::Comment));
+    units
+}
+
+fn key_of(unit: &Unit) -> &str {
+    match unit {
+        Unit::Item { key, .. } => key,
+        _ => "",
+    }
+}
+
+/// Sort each run of entries between blank lines and standalone comments
+fn sort_sections(mut units: Vec<Unit>) -> Vec<Unit> {
+    let mut start = 0;
+    while start < units.len() {
+        let len = units[start..]
+            .iter()
+            .take_while(|u| matches!(u, Unit::Item { .. }))
+            .count();
+        // Stable, so the last of duplicate keys stays the effective one
+        units[start..start + len].sort_by(|a, b| key_of(a).cmp(key_of(b)));
+        start += len.max(1);
+    }
+    units
+}
+
+/// Sort all entries and group them by prefix; comments before the first
+/// entry and after the last one stay in place, other standalone comments
+/// join the entry below them
+fn sort_grouped(units: Vec<Unit>) -> Vec<Unit> {
+    let first = units.iter().position(|u| matches!(u, Unit::Item { .. }));
+    let last = units.iter().rposition(|u| matches!(u, Unit::Item { .. }));
+    let (Some(first), Some(last)) = (first, last) else {
+        return units;
+    };
+
+    let mut units = units.into_iter();
+    let head: Vec<Unit> = units.by_ref().take(first).collect();
+    let mut items = Vec::new();
+    let mut comments = Vec::new();
+    for unit in units.by_ref().take(last - first + 1) {
+        match unit {
+            Unit::Comment(text) => comments.push(text),
+            Unit::Blank => {}
+            Unit::Item {
+                comments: own,
+                key,
+                text,
+            } => {
+                comments.extend(own);
+                items.push(Unit::Item {
+                    comments: std::mem::take(&mut comments),
+                    key,
+                    text,
+                });
+            }
+        }
+    }
+    let tail: Vec<Unit> = units.collect();
+    items.sort_by(|a, b| key_of(a).cmp(key_of(b)));
+
+    // Keys that share their prefix with no other key form one group
+    let labels: Vec<&str> = items
+        .iter()
+        .map(|item| {
+            let prefix = prefix(key_of(item));
+            let shared = items
+                .iter()
+                .filter(|other| self::prefix(key_of(other)) == prefix)
+                .count()
+                > 1;
+            if shared {
+                prefix
+            } else {
+                ""
+            }
+        })
+        .collect();
+    let boundaries: Vec<bool> = (0..items.len())
+        .map(|i| i > 0 && labels[i] != labels[i - 1])
+        .collect();
+
+    let mut out = head;
+    out.push(Unit::Blank);
+    for (item, boundary) in items.into_iter().zip(boundaries) {
+        if boundary {
+            out.push(Unit::Blank);
+        }
+        out.push(item);
+    }
+    out.push(Unit::Blank);
+    out.extend(tail);
+    out
+}
+
+/// The group prefix of a key: up to and including its first `_`
+fn prefix(key: &str) -> &str {
+    match key.char_indices().skip(1).find(|&(_, c)| c == '_') {
+        Some((i, _)) => &key[..=i],
+        None => key,
+    }
+}
+
+/// Join units into lines, with at most one blank line in a row and none at
+/// the start or end
+fn render(units: &[Unit], newline: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut blank = false;
+    for unit in units {
+        if matches!(unit, Unit::Blank) {
+            blank = !lines.is_empty();
+            continue;
+        }
+        if blank {
+            lines.push("");
+            blank = false;
+        }
+        match unit {
+            Unit::Item { comments, text, .. } => {
+                lines.extend(comments.iter().map(String::as_str));
+                lines.push(text);
+            }
+            Unit::Comment(text) => lines.push(text),
+            Unit::Blank => {}
+        }
+    }
+
+    let mut out = lines.join(newline);
+    if !out.is_empty() {
+        out.push_str(newline);
+    }
+    out
+}
+
+/// Active keys and values, ordered by key and then by definition
+fn values(parsed: &ParsedContent) -> Vec<(String, String)> {
+    let mut values: Vec<(String, String)> = parsed
+        .pairs
+        .iter()
+        .filter(|p| !p.kv.is_comment)
+        .map(|p| (p.kv.key.to_string(), p.kv.value.to_string()))
+        .collect();
+    values.sort_by(|a, b| a.0.cmp(&b.0));
+    values
+}
@@ -0,0 +1,158 @@
+//! INI parser for shelter-core
+//!
+//! `[section]` headers prefix the keys below them (`database.password`);
+//! dots in section names nest the path. Values follow `=` or `:`, comments
+//! start with `;` or `#` at the start of a line or after whitespace, and a
+//! value wrapped in matching quotes is unquoted with its span kept whole.
+
+use std::borrow::Cow;
+
+use korni::QuoteType;
+
+use crate::parse::ParsedContent;
+use crate::structured::{push_key, Collector};
+use crate::types::ShelterDiagnosticCode;
+
+#[inline]
+fn is_blank(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+/// Parse an INI document
+pub(crate) fn parse_ini(input: &str) -> ParsedContent<'_> {
+    let mut out = Collector::new(input);
+    let bytes = input.as_bytes();
+    let mut section = String::new();
+    let mut pos = 0;
+
+    if input.starts_with('\u{FEFF}') {
+        pos = '\u{FEFF}'.len_utf8();
+    }
+
+    while pos < bytes.len() {
+        let line_end = out.line_end(pos);
+        let start = pos
+            + bytes[pos..line_end]
+                .iter()
+                .take_while(|&&b| is_blank(b))
+                .count();
+        let next = next_line(bytes, line_end);
+
+        match bytes.get(start).filter(|_| start < line_end) {
+            None => {}
+            Some(b';' | b'#') => out.comment(start, line_end),
+            Some(b'[') => match input[start..line_end].find(']') {
+                Some(close) => {
+                    section.clear();
+                    for segment in input[start + 1..start + close].split('.') {
+                        push_key(&mut section, segment.trim());
+                    }
+                    trailing_comment(&mut out, start + close + 1, line_end);
+                }
+                None => out.line_error(ShelterDiagnosticCode::Expected, "Expected ']'", start),
+            },
+            Some(_) => pair(&mut out, &section, start, line_end),
+        }
+
+        pos = next;
+    }
+
+    out.finish()
+}
+
+/// Start of the line after the one ending at `line_end`
+fn next_line(bytes: &[u8], line_end: usize) -> usize {
+    match bytes.get(line_end) {
+        Some(b'\r') if bytes.get(line_end + 1) == Some(&b'\n') => line_end + 2,
+        Some(_) => line_end + 1,
+        None => line_end,
+    }
+}
+
+/// Offset of a `;`/`#` comment preceded by whitespace in `start..end`
+fn find_comment(bytes: &[u8], start: usize, end: usize) -> Option<usize> {
+    (start..end)
+        .find(|&i| matches!(bytes[i], b';' | b'#') && (i == start || is_blank(bytes[i - 1])))
+}
+
+/// Record a comment after a header or quoted value; other trailing text is ignored
+fn trailing_comment(out: &mut Collector, start: usize, end: usize) {
+    let bytes = out.input.as_bytes();
+    if let Some(comment) = find_comment(bytes, start, end) {
+        out.comment(comment, end);
+    }
+}
+
+/// `key = value` or `key: value` on the line `start..end`
+fn pair(out: &mut Collector, section: &str, start: usize, end: usize) {
+    let input = out.input;
+    let bytes = input.as_bytes();
+
+    let Some(separator) = (start..end).find(|&i| matches!(bytes[i], b'=' | b':')) else {
+        out.line_error(
+            ShelterDiagnosticCode::Expected,
+            "Expected '=' or ':'",
+            start,
+        );
+        return;
+    };
+
+    let key = input[start..separator].trim_end();
+    if key.is_empty() {
+        out.line_error(
+            ShelterDiagnosticCode::InvalidKey,
+            "Invalid key: empty",
+            start,
+        );
+        return;
+    }
+    let key_span = (start, start + key.len());
+
+    let mut path = section.to_string();
+    push_key(&mut path, key);
+
+    let value_start = separator
+        + 1
+        + bytes[separator + 1..end]
+            .iter()
+            .take_while(|&&b| is_blank(b))
+            .count();
+
+    // Quoted: the closing quote must be followed by whitespace, a comment or the end
+    if let Some(&quote @ (b'"' | b'\'')) = bytes.get(value_start).filter(|_| value_start < end) {
+        let close = (value_start + 1..end).find(|&i| {
+            bytes[i] == quote && {
+                let rest = input[i + 1..end].trim_start();
+                rest.is_empty() || rest.starts_with([';', '#'])
+            }
+        });
+
+        if let Some(close) = close {
+            let quote_type = if quote == b'"' {
+                QuoteType::Double
+            } else {
+                QuoteType::Single
+            };
+            let value = Cow::Borrowed(&input[value_start + 1..close]);
+            out.pair(&path, key_span, value, (value_start, close + 1), quote_type);
+            trailing_comment(out, close + 1, end);
+            return;
+        }
+    }
+
+    let comment = find_comment(bytes, value_start, end);
+    let value_end = comment.unwrap_or(end);
+    let value = input[value_start..value_end].trim_end();
+    let value_end = value_start + value.len();
+
+    out.pair(
+        &path,
+        key_span,
+        Cow::Borrowed(value),
+        (value_start, value_end),
+        QuoteType::None,
+    );
+    if let Some(comment) = comment {
+        out.comment(comment, end);
+    }
+}
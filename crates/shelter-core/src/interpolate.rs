@@ -0,0 +1,507 @@
+//! Variable interpolation for shelter-core
+//!
+//! Finds `$VAR`, `${VAR}` and `${VAR:-default}`-style references in values
+//! and resolves them against the other entries in the file. A reference
+//! resolves to the closest earlier definition of its name, or to the last
+//! one when the name is only defined later, so `PATH=${PATH}:/bin` is a
+//! cycle only when nothing else defines `PATH`.
+//!
+//! References are found in the value as written, so `\$` escapes in double
+//! quotes are honoured; single-quoted values are literal. Unquoted values
+//! whose text differs from the value (folded YAML, for instance) are left
+//! alone since their offsets cannot be mapped back.
+
+use std::collections::HashMap;
+
+use korni::QuoteType;
+
+use crate::dialect::Dialect;
+use crate::parse::ParsedPair;
+
+/// Nesting limit for references resolved inside default or alternate words
+const MAX_DEPTH: usize = 256;
+
+/// What a reference does when its variable is unset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReferenceKind {
+    /// `$VAR` or `${VAR}`
+    Plain,
+    /// `${VAR:-default}` or `${VAR-default}`
+    Default,
+    /// `${VAR:?error}` or `${VAR?error}`
+    Required,
+    /// `${VAR:+alternate}` or `${VAR+alternate}`
+    Alternate,
+}
+
+/// Outcome of resolving a reference, ordered by severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ResolveStatus {
+    /// The variable is defined in the file
+    Resolved,
+    /// The variable is unset and the default was used
+    Defaulted,
+    /// The variable is unset (or a `:?` check failed)
+    Unresolved,
+    /// The variable's value depends on itself
+    Cycle,
+}
+
+/// A reference inside a value
+#[derive(Debug, Clone)]
+pub(crate) struct Reference {
+    /// Byte range of the whole reference (`${...}` or `$NAME`)
+    pub start: usize,
+    pub end: usize,
+    /// Byte range of the variable name
+    pub name_start: usize,
+    pub name_end: usize,
+    pub kind: ReferenceKind,
+    pub status: ResolveStatus,
+    /// Index of the pair the name resolved to
+    pub target: Option<usize>,
+}
+
+/// Resolved value of a pair that contains references
+#[derive(Debug, Clone)]
+pub(crate) struct Resolution {
+    pub value: String,
+    /// Top-level references in source order (references nested in a
+    /// default are resolved but not reported)
+    pub references: Vec<Reference>,
+    /// Most severe status of the references
+    pub status: ResolveStatus,
+    /// The value is a single reference to an entry of the file and nothing
+    /// else, so it reveals nothing
+    pub is_reference: bool,
+}
+
+/// Resolve the references of every pair; `None` for pairs without any, and
+/// for every pair of a dialect that does not expand references
+pub(crate) fn resolve(
+    input: &str,
+    pairs: &[ParsedPair],
+    dialect: Dialect,
+) -> Vec<Option<Resolution>> {
+    if !dialect.interpolates() {
+        return vec![None; pairs.len()];
+    }
+    let segments: Vec<Option<Vec<Segment>>> =
+        pairs.iter().map(|pair| scan_pair(input, pair)).collect();
+
+    let mut names: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, pair) in pairs.iter().enumerate() {
+        if !pair.kv.is_comment {
+            names.entry(pair.kv.key.as_ref()).or_default().push(i);
+        }
+    }
+
+    let mut resolver = Resolver {
+        input,
+        pairs,
+        scanned: &segments,
+        names,
+        state: vec![State::Pending; pairs.len()],
+        references: vec![Vec::new(); pairs.len()],
+        depth: 0,
+    };
+    for i in 0..pairs.len() {
+        resolver.warm(i);
+    }
+
+    (0..pairs.len())
+        .map(|i| {
+            let segments = segments[i].as_ref()?;
+            let (value, status) = resolver.pair(i);
+            let is_reference = is_pure(segments) && status == ResolveStatus::Resolved;
+            Some(Resolution {
+                value,
+                references: std::mem::take(&mut resolver.references[i]),
+                status,
+                is_reference,
+            })
+        })
+        .collect()
+}
+
+/// A lone `$VAR` or `${VAR}`; a default or alternate word is literal text
+/// that may itself be a secret, so those never count
+fn is_pure(segments: &[Segment]) -> bool {
+    matches!(segments, [Segment::Reference(r)] if r.kind == ReferenceKind::Plain)
+}
+
+// =============================================================================
+//  Scanning
+// =============================================================================
+
+/// A run of literal text or a reference
+#[derive(Debug)]
+enum Segment {
+    Text(String),
+    Reference(Box<RawReference>),
+}
+
+/// A reference as written, before resolution
+#[derive(Debug)]
+struct RawReference {
+    start: usize,
+    end: usize,
+    name_start: usize,
+    name_end: usize,
+    kind: ReferenceKind,
+    /// `:-` rather than `-`: an empty value counts as unset
+    colon: bool,
+    /// Default, error or alternate text
+    word: Vec<Segment>,
+}
+
+/// Segments of a pair's value, or `None` when it has no references
+fn scan_pair(input: &str, pair: &ParsedPair) -> Option<Vec<Segment>> {
+    let kv = &pair.kv;
+    let span = kv.value_span?;
+    let (start, end) = (span.start.offset, span.end.offset);
+    let raw = input.get(start..end)?;
+    if !raw.contains('$') {
+        return None;
+    }
+
+    let (start, end, escapes) = match kv.quote {
+        QuoteType::Single => return None,
+        QuoteType::Double if raw.len() >= 2 && raw.ends_with('"') => (start + 1, end - 1, true),
+        QuoteType::Double => (start + 1, end, true),
+        QuoteType::None if raw == kv.value => (start, end, false),
+        QuoteType::None => return None,
+    };
+
+    let mut scanner = Scanner {
+        input,
+        pos: start,
+        end,
+        escapes,
+    };
+    let segments = scanner.segments(false);
+    segments
+        .iter()
+        .any(|s| matches!(s, Segment::Reference(_)))
+        .then_some(segments)
+}
+
+#[inline]
+fn is_name_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_'
+}
+
+#[inline]
+fn is_name_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+struct Scanner<'a> {
+    input: &'a str,
+    pos: usize,
+    end: usize,
+    /// Decode double-quote escapes (`\n`, `\$`, ...)
+    escapes: bool,
+}
+
+impl Scanner<'_> {
+    #[inline]
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        let pos = self.pos + offset;
+        (pos < self.end).then(|| self.input.as_bytes()[pos])
+    }
+
+    /// Scan up to the end, or up to an unmatched `}` inside a reference word
+    fn segments(&mut self, in_word: bool) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+
+        while let Some(b) = self.peek_at(0) {
+            match b {
+                b'}' if in_word => break,
+                b'\\' if self.escapes && self.peek_at(1).is_some() => {
+                    let c = self.input[self.pos + 1..].chars().next().unwrap_or('\\');
+                    match c {
+                        'n' => text.push('\n'),
+                        'r' => text.push('\r'),
+                        't' => text.push('\t'),
+                        '\\' | '"' | '$' => text.push(c),
+                        _ => {
+                            text.push('\\');
+                            text.push(c);
+                        }
+                    }
+                    self.pos += 1 + c.len_utf8();
+                }
+                b'$' => match self.reference() {
+                    Some(reference) => {
+                        if !text.is_empty() {
+                            segments.push(Segment::Text(std::mem::take(&mut text)));
+                        }
+                        segments.push(Segment::Reference(Box::new(reference)));
+                    }
+                    None => {
+                        text.push('$');
+                        self.pos += 1;
+                    }
+                },
+                _ => {
+                    let c = self.input[self.pos..].chars().next().unwrap_or('\u{FFFD}');
+                    text.push(c);
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        segments
+    }
+
+    /// Parse the reference at `$`, leaving `pos` unchanged if there is none
+    fn reference(&mut self) -> Option<RawReference> {
+        let start = self.pos;
+        let braced = self.peek_at(1) == Some(b'{');
+        let name_start = start + if braced { 2 } else { 1 };
+
+        self.pos = name_start;
+        if !self.peek_at(0).is_some_and(is_name_start) {
+            self.pos = start;
+            return None;
+        }
+        while self.peek_at(0).is_some_and(is_name_char) {
+            self.pos += 1;
+        }
+        let name_end = self.pos;
+
+        let mut reference = RawReference {
+            start,
+            end: name_end,
+            name_start,
+            name_end,
+            kind: ReferenceKind::Plain,
+            colon: false,
+            word: Vec::new(),
+        };
+        if !braced {
+            return Some(reference);
+        }
+
+        if self.peek_at(0) == Some(b':') {
+            reference.colon = true;
+            self.pos += 1;
+        }
+        let operator = self.peek_at(0);
+        reference.kind = match operator {
+            Some(b'}') if !reference.colon => ReferenceKind::Plain,
+            Some(b'-') => ReferenceKind::Default,
+            Some(b'?') => ReferenceKind::Required,
+            Some(b'+') => ReferenceKind::Alternate,
+            _ => {
+                self.pos = start;
+                return None;
+            }
+        };
+
+        if reference.kind != ReferenceKind::Plain {
+            self.pos += 1;
+            reference.word = self.segments(true);
+        }
+        if self.peek_at(0) != Some(b'}') {
+            self.pos = start;
+            return None;
+        }
+        self.pos += 1;
+        reference.end = self.pos;
+        Some(reference)
+    }
+}
+
+// =============================================================================
+//  Resolution
+// =============================================================================
+
+#[derive(Debug, Clone)]
+enum State {
+    Pending,
+    Visiting,
+    Done(String, ResolveStatus),
+}
+
+struct Resolver<'a, 'p> {
+    input: &'a str,
+    pairs: &'p [ParsedPair<'a>],
+    scanned: &'p [Option<Vec<Segment>>],
+    names: HashMap<&'p str, Vec<usize>>,
+    state: Vec<State>,
+    references: Vec<Vec<Reference>>,
+    /// Nesting of `pair` calls
+    depth: usize,
+}
+
+impl<'a, 'p> Resolver<'a, 'p> {
+    /// Resolve pair `root` after every pair its references depend on, deepest
+    /// first, with an explicit stack
+    ///
+    /// Pairs on the stack are `Visiting`, as they are while `pair` recurses,
+    /// so cycles are found the same way; but by the time a pair is expanded
+    /// its targets are done, and a long chain of forward references
+    /// (`A0=${A1}`, `A1=${A2}`, ...) never recurses.
+    fn warm(&mut self, root: usize) {
+        let scanned = self.scanned;
+        if scanned[root].is_none() || !matches!(self.state[root], State::Pending) {
+            return;
+        }
+
+        self.state[root] = State::Visiting;
+        let mut stack = vec![(root, self.targets(root), 0)];
+        while let Some((i, targets, next)) = stack.last_mut() {
+            let i = *i;
+            match targets.get(*next).copied() {
+                Some(j) => {
+                    *next += 1;
+                    if scanned[j].is_some() && matches!(self.state[j], State::Pending) {
+                        self.state[j] = State::Visiting;
+                        let targets = self.targets(j);
+                        stack.push((j, targets, 0));
+                    }
+                }
+                None => {
+                    stack.pop();
+                    if let Some(segments) = &scanned[i] {
+                        let (value, status) = self.segments(i, segments, true);
+                        self.state[i] = State::Done(value, status);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Targets of the top-level references of pair `i`, which are looked up
+    /// whatever the reference kind
+    fn targets(&self, i: usize) -> Vec<usize> {
+        let Some(segments) = &self.scanned[i] else {
+            return Vec::new();
+        };
+        segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Reference(raw) => self.target(i, raw),
+                Segment::Text(_) => None,
+            })
+            .collect()
+    }
+
+    /// The pair a reference from pair `i` resolves to
+    fn target(&self, i: usize, raw: &RawReference) -> Option<usize> {
+        let name = &self.input[raw.name_start..raw.name_end];
+        self.names.get(name).and_then(|defs| {
+            defs.iter()
+                .rev()
+                .find(|&&j| j < i)
+                .or_else(|| defs.last())
+                .copied()
+        })
+    }
+
+    /// Resolved value and status of pair `i`
+    fn pair(&mut self, i: usize) -> (String, ResolveStatus) {
+        let scanned = self.scanned;
+        let Some(segments) = &scanned[i] else {
+            return (self.pairs[i].kv.value.to_string(), ResolveStatus::Resolved);
+        };
+
+        match &self.state[i] {
+            State::Done(value, status) => return (value.clone(), *status),
+            State::Visiting => return (String::new(), ResolveStatus::Cycle),
+            State::Pending => {}
+        }
+
+        // Only references nested in default or alternate words still
+        // recurse after `warm`; give up on absurdly deep nesting
+        if self.depth >= MAX_DEPTH {
+            return (String::new(), ResolveStatus::Unresolved);
+        }
+
+        self.depth += 1;
+        self.state[i] = State::Visiting;
+        let (value, status) = self.segments(i, segments, true);
+        self.state[i] = State::Done(value.clone(), status);
+        self.depth -= 1;
+        (value, status)
+    }
+
+    /// Expand `segments` of pair `i`, recording references when `top_level`
+    fn segments(
+        &mut self,
+        i: usize,
+        segments: &'p [Segment],
+        top_level: bool,
+    ) -> (String, ResolveStatus) {
+        let mut value = String::new();
+        let mut status = ResolveStatus::Resolved;
+
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => value.push_str(text),
+                Segment::Reference(raw) => {
+                    let (text, target, reference_status) = self.reference(i, raw);
+                    value.push_str(&text);
+                    status = status.max(reference_status);
+                    if top_level {
+                        self.references[i].push(Reference {
+                            start: raw.start,
+                            end: raw.end,
+                            name_start: raw.name_start,
+                            name_end: raw.name_end,
+                            kind: raw.kind,
+                            status: reference_status,
+                            target,
+                        });
+                    }
+                }
+            }
+        }
+
+        (value, status)
+    }
+
+    /// Expand one reference from pair `i`
+    fn reference(
+        &mut self,
+        i: usize,
+        raw: &'p RawReference,
+    ) -> (String, Option<usize>, ResolveStatus) {
+        let target = self.target(i, raw);
+        let lookup = target.map(|j| self.pair(j));
+        if let Some((_, ResolveStatus::Cycle)) = lookup {
+            return (String::new(), target, ResolveStatus::Cycle);
+        }
+
+        let (value, set) = match lookup {
+            Some((value, _)) => {
+                let set = !(raw.colon && value.is_empty());
+                (value, set)
+            }
+            None => (String::new(), false),
+        };
+
+        match raw.kind {
+            ReferenceKind::Plain if target.is_some() => (value, target, ResolveStatus::Resolved),
+            ReferenceKind::Plain => (value, target, ResolveStatus::Unresolved),
+            ReferenceKind::Default if set => (value, target, ResolveStatus::Resolved),
+            ReferenceKind::Default => {
+                let (word, status) = self.segments(i, &raw.word, false);
+                (word, target, status.max(ResolveStatus::Defaulted))
+            }
+            ReferenceKind::Required if set => (value, target, ResolveStatus::Resolved),
+            ReferenceKind::Required => (String::new(), target, ResolveStatus::Unresolved),
+            ReferenceKind::Alternate if set => {
+                let (word, status) = self.segments(i, &raw.word, false);
+                (word, target, status)
+            }
+            ReferenceKind::Alternate => (String::new(), target, ResolveStatus::Resolved),
+        }
+    }
+}
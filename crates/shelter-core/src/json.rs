@@ -0,0 +1,324 @@
+//! JSON parser for shelter-core
+//!
+//! Emits one entry per scalar keyed by its dotted path. Comments and
+//! trailing commas are accepted, as in `appsettings.json` and other JSONC
+//! files; `//` comments are reported, `/* */` blocks are skipped.
+
+use std::borrow::Cow;
+
+use korni::QuoteType;
+
+use crate::parse::ParsedContent;
+use crate::structured::{decode_hex, push_index, push_key, Collector};
+use crate::types::ShelterDiagnosticCode;
+
+/// Nesting limit; deeper documents are reported instead of overflowing the stack
+const MAX_DEPTH: usize = 256;
+
+/// A syntax error: code, message and byte range
+type SyntaxError = (ShelterDiagnosticCode, &'static str, usize, usize);
+
+/// Parse a JSON document
+pub(crate) fn parse_json(input: &str) -> ParsedContent<'_> {
+    let mut parser = Parser {
+        out: Collector::new(input),
+        bytes: input.as_bytes(),
+        pos: 0,
+        path: String::new(),
+    };
+
+    if input.starts_with('\u{FEFF}') {
+        parser.pos = '\u{FEFF}'.len_utf8();
+    }
+
+    let result = parser.skip_trivia().and_then(|()| {
+        if parser.pos < parser.bytes.len() {
+            parser.value((parser.pos, parser.pos), 0)?;
+            parser.skip_trivia()?;
+        }
+        if parser.pos < parser.bytes.len() {
+            return Err(parser.unexpected("Expected end of input"));
+        }
+        Ok(())
+    });
+
+    if let Err((code, message, start, end)) = result {
+        parser.out.error(code, message, start, end);
+    }
+
+    parser.out.finish()
+}
+
+struct Parser<'a> {
+    out: Collector<'a>,
+    bytes: &'a [u8],
+    pos: usize,
+    /// Path of the value being parsed
+    path: String,
+}
+
+impl<'a> Parser<'a> {
+    fn unexpected(&self, message: &'static str) -> SyntaxError {
+        let end = self.out.line_end(self.pos);
+        (ShelterDiagnosticCode::Expected, message, self.pos, end)
+    }
+
+    /// Skip whitespace and comments
+    fn skip_trivia(&mut self) -> Result<(), SyntaxError> {
+        while self.pos < self.bytes.len() {
+            match self.bytes[self.pos] {
+                b' ' | b'\t' | b'\n' | b'\r' => self.pos += 1,
+                b'/' if self.bytes.get(self.pos + 1) == Some(&b'/') => {
+                    let end = self.out.line_end(self.pos);
+                    self.out.comment(self.pos, end);
+                    self.pos = end;
+                }
+                b'/' if self.bytes.get(self.pos + 1) == Some(&b'*') => {
+                    let body = &self.out.input[self.pos + 2..];
+                    match body.find("*/") {
+                        Some(i) => self.pos += 2 + i + 2,
+                        None => {
+                            return Err((
+                                ShelterDiagnosticCode::Generic,
+                                "Unclosed block comment",
+                                self.pos,
+                                self.bytes.len(),
+                            ))
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, key_span: (usize, usize), depth: usize) -> Result<(), SyntaxError> {
+        if depth > MAX_DEPTH {
+            return Err(self.unexpected("Nesting too deep"));
+        }
+
+        let start = self.pos;
+        match self.bytes.get(start) {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => {
+                let value = self.string()?;
+                self.out.pair(
+                    &self.path,
+                    key_span,
+                    value,
+                    (start, self.pos),
+                    QuoteType::Double,
+                );
+                Ok(())
+            }
+            Some(b'-' | b'0'..=b'9' | b't' | b'f' | b'n') => {
+                let end = start
+                    + self.bytes[start..]
+                        .iter()
+                        .position(|&b| {
+                            !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.'))
+                        })
+                        .unwrap_or(self.bytes.len() - start);
+                let literal = &self.out.input[start..end];
+
+                let valid = matches!(literal, "true" | "false" | "null")
+                    || literal.parse::<f64>().is_ok_and(|n| n.is_finite());
+                if !valid {
+                    return Err(self.unexpected("Expected a value"));
+                }
+
+                self.pos = end;
+                self.out.pair(
+                    &self.path,
+                    key_span,
+                    Cow::Borrowed(literal),
+                    (start, end),
+                    QuoteType::None,
+                );
+                Ok(())
+            }
+            _ => Err(self.unexpected("Expected a value")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<(), SyntaxError> {
+        self.pos += 1;
+        loop {
+            self.skip_trivia()?;
+            match self.bytes.get(self.pos) {
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b'"') => {}
+                _ => return Err(self.unexpected("Expected '\"' or '}'")),
+            }
+
+            let key_start = self.pos;
+            let key = self.string()?;
+            let key_span = (key_start + 1, self.pos - 1);
+
+            self.skip_trivia()?;
+            if self.bytes.get(self.pos) != Some(&b':') {
+                return Err(self.unexpected("Expected ':'"));
+            }
+            self.pos += 1;
+            self.skip_trivia()?;
+
+            let len = self.path.len();
+            push_key(&mut self.path, &key);
+            self.value(key_span, depth + 1)?;
+            self.path.truncate(len);
+
+            if !self.separator(b'}')? {
+                return Err(self.unexpected("Expected ',' or '}'"));
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<(), SyntaxError> {
+        self.pos += 1;
+        let mut index = 0;
+        loop {
+            self.skip_trivia()?;
+            if self.bytes.get(self.pos) == Some(&b']') {
+                self.pos += 1;
+                return Ok(());
+            }
+
+            let len = self.path.len();
+            push_index(&mut self.path, index);
+            self.value((self.pos, self.pos), depth + 1)?;
+            self.path.truncate(len);
+            index += 1;
+
+            if !self.separator(b']')? {
+                return Err(self.unexpected("Expected ',' or ']'"));
+            }
+        }
+    }
+
+    /// Consume a `,` or peek the closing bracket; false on anything else
+    fn separator(&mut self, close: u8) -> Result<bool, SyntaxError> {
+        self.skip_trivia()?;
+        match self.bytes.get(self.pos) {
+            Some(b',') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            Some(&b) => Ok(b == close),
+            None => Ok(false),
+        }
+    }
+
+    /// Parse a string at the opening quote, leaving `pos` after the closing one
+    fn string(&mut self) -> Result<Cow<'a, str>, SyntaxError> {
+        let (value, end) = string_at(self.out.input, self.pos)?;
+        self.pos = end;
+        Ok(value)
+    }
+}
+
+/// Decode the string whose opening quote is at `open`; returns the value and
+/// the offset after the closing quote
+pub(crate) fn string_at(input: &str, open: usize) -> Result<(Cow<'_, str>, usize), SyntaxError> {
+    let bytes = input.as_bytes();
+    let content_start = open + 1;
+    let mut owned: Option<String> = None;
+    let mut chunk = content_start;
+    let mut pos = content_start;
+
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => {
+                let value = match owned {
+                    Some(mut s) => {
+                        s.push_str(&input[chunk..pos]);
+                        Cow::Owned(s)
+                    }
+                    None => Cow::Borrowed(&input[content_start..pos]),
+                };
+                return Ok((value, pos + 1));
+            }
+            b'\\' => {
+                let s = owned.get_or_insert_with(String::new);
+                s.push_str(&input[chunk..pos]);
+                let escape = bytes.get(pos + 1).copied();
+                let width = match escape {
+                    Some(b'u') => {
+                        let (c, width) = unicode_escape(input, pos).ok_or((
+                            ShelterDiagnosticCode::Generic,
+                            "Invalid unicode escape",
+                            pos,
+                            (pos + 6).min(input.len()),
+                        ))?;
+                        s.push(c);
+                        width
+                    }
+                    Some(b) => {
+                        s.push(match b {
+                            b'b' => '\x08',
+                            b'f' => '\x0c',
+                            b'n' => '\n',
+                            b'r' => '\r',
+                            b't' => '\t',
+                            b'"' | b'\\' | b'/' => b as char,
+                            _ => {
+                                return Err((
+                                    ShelterDiagnosticCode::Generic,
+                                    "Invalid escape",
+                                    pos,
+                                    pos + 2,
+                                ))
+                            }
+                        });
+                        2
+                    }
+                    None => break,
+                };
+                pos += width;
+                chunk = pos;
+            }
+            b'\n' | b'\r' => {
+                return Err((
+                    ShelterDiagnosticCode::UnclosedQuote,
+                    "Unclosed double quote",
+                    open,
+                    pos,
+                ))
+            }
+            _ => pos += 1,
+        }
+    }
+
+    Err((
+        ShelterDiagnosticCode::UnclosedQuote,
+        "Unclosed double quote",
+        open,
+        bytes.len(),
+    ))
+}
+
+/// Decode `\uXXXX` at `pos`, joining surrogate pairs; returns the char and bytes consumed
+fn unicode_escape(input: &str, pos: usize) -> Option<(char, usize)> {
+    let unit = |at: usize| {
+        input
+            .get(at + 2..at + 6)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+    };
+
+    let high = unit(pos)?;
+    if !(0xD800..0xDC00).contains(&high) {
+        return decode_hex(&input[pos + 2..pos + 6]).map(|c| (c, 6));
+    }
+
+    // High surrogate: must be followed by `\u` and a low surrogate
+    if input.get(pos + 6..pos + 8) != Some("\\u") {
+        return None;
+    }
+    let low = unit(pos + 6).filter(|low| (0xDC00..0xE000).contains(low))?;
+    char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).map(|c| (c, 12))
+}
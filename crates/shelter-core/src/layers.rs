@@ -0,0 +1,199 @@
+//! Layered environment resolution for shelter-core
+//!
+//! Frameworks load several env files on top of each other: `.env`, then
+//! `.env.local`, `.env.<mode>` and `.env.<mode>.local` in an order that
+//! depends on the framework. Given the files that exist, this works out
+//! which ones a scheme loads, in what order, and which file each key's
+//! effective value comes from. Values are never copied out, only their
+//! locations.
+
+use std::collections::HashMap;
+
+use korni::ParseOptions;
+
+use crate::dialect::{detect_dialect, parse_dialect, Dialect};
+
+/// Which framework's loading rules to apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scheme {
+    /// `.env` < `.env.local` < `.env.<mode>` < `.env.<mode>.local`
+    Vite,
+    /// `.env` < `.env.<mode>` < `.env.local` < `.env.<mode>.local`;
+    /// `.env.local` is skipped in the `test` mode
+    Next,
+    /// dotenv-rails; same order and `test` exception as Next.js
+    Rails,
+    /// docker-compose `env_file:` lists; later files win
+    Compose,
+}
+
+/// Where a key is defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Occurrence {
+    /// Index of the file in the input list
+    pub file: usize,
+    /// 1-based line number of the key
+    pub line_number: usize,
+    pub key_start: usize,
+    pub key_end: usize,
+    pub value_start: usize,
+    pub value_end: usize,
+}
+
+/// A key of the effective environment
+#[derive(Debug, Clone)]
+pub(crate) struct LayeredKey {
+    pub key: String,
+    /// The definition that wins
+    pub origin: Occurrence,
+    /// Definitions it overrides, highest precedence first
+    pub shadowed: Vec<Occurrence>,
+}
+
+/// How a scheme treats one input file
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FileLayer {
+    /// Position in load order (0 loads first and has the lowest
+    /// precedence); `None` if the scheme does not load the file
+    pub precedence: Option<usize>,
+    pub dialect: Dialect,
+    /// Number of (non-comment) entries in the file
+    pub entry_count: usize,
+    /// Number of parse errors in the file
+    pub diagnostic_count: usize,
+}
+
+/// Effective keys of a file set
+pub(crate) struct Layers {
+    /// One per input file, in input order
+    pub files: Vec<FileLayer>,
+    /// Keys in the order they are first defined, in load order
+    pub keys: Vec<LayeredKey>,
+}
+
+/// Resolve the effective keys of `files` (path, content) under `scheme`
+///
+/// `mode` is the Vite mode, `NODE_ENV` or `RAILS_ENV`; it is ignored by
+/// compose, which loads every file in list order.
+pub(crate) fn resolve_layers(files: &[(&str, &str)], scheme: Scheme, mode: &str) -> Layers {
+    let ranks: Vec<Option<u8>> = files
+        .iter()
+        .map(|(path, _)| layer_rank(basename(path), scheme, mode))
+        .collect();
+
+    // Stable sort: files of the same rank load in list order
+    let mut order: Vec<usize> = (0..files.len()).filter(|&i| ranks[i].is_some()).collect();
+    order.sort_by_key(|&i| ranks[i]);
+
+    let mut layers: Vec<FileLayer> = files
+        .iter()
+        .map(|(path, content)| FileLayer {
+            precedence: None,
+            dialect: detect_dialect(Some(path), content),
+            entry_count: 0,
+            diagnostic_count: 0,
+        })
+        .collect();
+
+    let mut keys: Vec<LayeredKey> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for (precedence, &file) in order.iter().enumerate() {
+        let layer = &mut layers[file];
+        layer.precedence = Some(precedence);
+
+        let content = files[file].1;
+        let options = ParseOptions {
+            include_comments: false,
+            track_positions: true,
+        };
+        let parsed = parse_dialect(content, layer.dialect, options);
+        layer.diagnostic_count = parsed.diagnostics.len();
+
+        for pair in parsed.pairs.iter().filter(|p| !p.kv.is_comment) {
+            layer.entry_count += 1;
+
+            let span =
+                |s: Option<korni::Span>| s.map_or((0, 0), |s| (s.start.offset, s.end.offset));
+            let (key_start, key_end) = span(pair.kv.key_span);
+            let (value_start, value_end) = span(pair.kv.value_span);
+            let occurrence = Occurrence {
+                file,
+                line_number: pair.line_number,
+                key_start,
+                key_end,
+                value_start,
+                value_end,
+            };
+
+            match index.get(pair.kv.key.as_ref()) {
+                Some(&i) => {
+                    let key = &mut keys[i];
+                    key.shadowed.push(key.origin);
+                    key.origin = occurrence;
+                }
+                None => {
+                    index.insert(pair.kv.key.to_string(), keys.len());
+                    keys.push(LayeredKey {
+                        key: pair.kv.key.to_string(),
+                        origin: occurrence,
+                        shadowed: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    for key in &mut keys {
+        key.shadowed.reverse();
+    }
+
+    Layers {
+        files: layers,
+        keys,
+    }
+}
+
+fn basename(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Load rank of a file under a scheme (higher loads later and wins), or
+/// `None` if the scheme does not load it
+fn layer_rank(name: &str, scheme: Scheme, mode: &str) -> Option<u8> {
+    if scheme == Scheme::Compose {
+        return Some(0);
+    }
+
+    let rest = name.strip_prefix(".env")?;
+    let layer = match rest {
+        "" => Layer::Base,
+        ".local" => Layer::Local,
+        _ => {
+            let rest = rest.strip_prefix('.')?;
+            match rest.strip_suffix(".local") {
+                Some(m) if m == mode => Layer::ModeLocal,
+                None if rest == mode => Layer::Mode,
+                _ => return None,
+            }
+        }
+    };
+
+    match (scheme, layer) {
+        (_, Layer::Base) => Some(0),
+        (Scheme::Vite, Layer::Local) => Some(1),
+        (Scheme::Vite, Layer::Mode) => Some(2),
+        (_, Layer::Mode) => Some(1),
+        (_, Layer::Local) => (mode != "test").then_some(2),
+        (_, Layer::ModeLocal) => Some(3),
+    }
+}
+
+/// Role of a file in a mode-based scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Base,
+    Local,
+    Mode,
+    ModeLocal,
+}
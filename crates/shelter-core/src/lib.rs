@@ -1,9 +1,38 @@
 //! shelter-core: Native Rust core for shelter.nvim
 //!
-//! Provides EDF-compliant dotenv parsing via C FFI for LuaJIT.
+//! Provides EDF-compliant dotenv parsing via C FFI for LuaJIT, and the
+//! `shelter` command line tool built on the same parser.
 
+mod annotation;
+pub mod cli;
+mod compare;
+mod detect;
+mod dialect;
+mod document;
+mod dump;
+mod duplicates;
+mod edit;
+mod example;
 mod ffi;
+mod fingerprint;
+mod format;
+mod ini;
+mod interpolate;
+mod json;
+mod layers;
+mod lint;
+mod mask;
+mod parse;
+mod pattern;
+mod quote;
+mod scan;
+mod schema;
+mod score;
+mod stream;
+mod structured;
+mod toml;
 mod types;
+mod yaml;
 
 pub use ffi::*;
 pub use types::*;
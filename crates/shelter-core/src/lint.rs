@@ -0,0 +1,342 @@
+//! Env file linter for shelter-core
+//!
+//! Checks a parsed file for entries that parse but read differently across
+//! loaders or break conventions. Every finding names a rule by a stable code
+//! (`SH001`) and name (`lowercase-key`), so editors and pre-commit hooks can
+//! filter on them. Key, quoting and `export` rules only apply to env
+//! dialects; config formats get parse errors, required values and the final
+//! newline check.
+
+use korni::{ParseOptions, QuoteType};
+
+use crate::annotation::Annotations;
+use crate::dialect::{parse_dialect, Dialect};
+use crate::parse::{ParsedContent, ParsedPair};
+use crate::quote::quote_value;
+
+/// Annotation marking a value that must not be empty
+const REQUIRED: &str = "@required";
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+}
+
+/// A lint rule; codes and names never change once released
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Rule {
+    /// The file does not parse
+    ParseError,
+    /// A key has lowercase letters
+    LowercaseKey,
+    /// A key has characters shells do not accept in names
+    NonPortableKey,
+    /// Whitespace around the `=`
+    SpacesAroundSeparator,
+    /// An unquoted value contains `#` or whitespace
+    UnquotedSpecialValue,
+    /// A value uses other quotes than the rest of the file
+    InconsistentQuotes,
+    /// The file does not end with a newline
+    MissingFinalNewline,
+    /// Some entries have an `export` prefix and some do not
+    MixedExport,
+    /// A value marked `@required` is empty
+    EmptyRequiredValue,
+}
+
+impl Rule {
+    /// Stable code, e.g. `SH001`
+    pub fn code(self) -> &'static str {
+        match self {
+            Rule::ParseError => "SH000",
+            Rule::LowercaseKey => "SH001",
+            Rule::NonPortableKey => "SH002",
+            Rule::SpacesAroundSeparator => "SH003",
+            Rule::UnquotedSpecialValue => "SH004",
+            Rule::InconsistentQuotes => "SH005",
+            Rule::MissingFinalNewline => "SH006",
+            Rule::MixedExport => "SH007",
+            Rule::EmptyRequiredValue => "SH008",
+        }
+    }
+
+    /// Stable name, e.g. `lowercase-key`
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::ParseError => "parse-error",
+            Rule::LowercaseKey => "lowercase-key",
+            Rule::NonPortableKey => "non-portable-key",
+            Rule::SpacesAroundSeparator => "spaces-around-separator",
+            Rule::UnquotedSpecialValue => "unquoted-special-value",
+            Rule::InconsistentQuotes => "inconsistent-quotes",
+            Rule::MissingFinalNewline => "missing-final-newline",
+            Rule::MixedExport => "mixed-export",
+            Rule::EmptyRequiredValue => "empty-required-value",
+        }
+    }
+
+    pub fn severity(self) -> Severity {
+        match self {
+            Rule::ParseError | Rule::EmptyRequiredValue => Severity::Error,
+            Rule::InconsistentQuotes => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+/// A rule violation and the byte range it covers
+#[derive(Debug, Clone)]
+pub(crate) struct Finding {
+    pub rule: Rule,
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Lint `input` read as `dialect`; findings are in document order
+pub(crate) fn lint(input: &str, dialect: Dialect) -> Vec<Finding> {
+    let parsed = parse_dialect(input, dialect, ParseOptions::full());
+    let pairs: Vec<&ParsedPair> = parsed.pairs.iter().filter(|p| !p.kv.is_comment).collect();
+    let mut findings = Vec::new();
+
+    for diagnostic in &parsed.diagnostics {
+        findings.push(Finding {
+            rule: Rule::ParseError,
+            message: diagnostic.message.clone(),
+            start: diagnostic.start,
+            end: diagnostic.end,
+        });
+    }
+
+    if !dialect.is_config() {
+        for pair in &pairs {
+            check_key(pair, &mut findings);
+            check_separator(input, pair, &mut findings);
+            check_unquoted(pair, &mut findings);
+        }
+        check_quotes(&pairs, dialect, &mut findings);
+        check_export(input, &parsed, &pairs, &mut findings);
+    }
+
+    let annotations = Annotations::new(input, &parsed);
+    for pair in &pairs {
+        if pair.kv.value.is_empty() && annotations.find(pair, REQUIRED).is_some() {
+            let (start, end) = key_range(pair);
+            findings.push(Finding {
+                rule: Rule::EmptyRequiredValue,
+                message: format!("{} is required but empty", pair.kv.key),
+                start,
+                end,
+            });
+        }
+    }
+
+    if !input.is_empty() && !input.ends_with('\n') {
+        findings.push(Finding {
+            rule: Rule::MissingFinalNewline,
+            message: "No newline at end of file".to_string(),
+            start: input.len(),
+            end: input.len(),
+        });
+    }
+
+    findings.sort_by_key(|f| (f.start, f.rule.code()));
+    findings
+}
+
+/// Byte range of a pair's key
+fn key_range(pair: &ParsedPair) -> (usize, usize) {
+    pair.kv
+        .key_span
+        .map(|s| (s.start.offset, s.end.offset))
+        .unwrap_or((0, 0))
+}
+
+fn check_key(pair: &ParsedPair, findings: &mut Vec<Finding>) {
+    let key = pair.kv.key.as_ref();
+    let (start, end) = key_range(pair);
+
+    if key.bytes().any(|b| b.is_ascii_lowercase()) {
+        findings.push(Finding {
+            rule: Rule::LowercaseKey,
+            message: format!("{} has lowercase letters", key),
+            start,
+            end,
+        });
+    }
+
+    let portable = key.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+        && !key.starts_with(|c: char| c.is_ascii_digit());
+    if !portable {
+        findings.push(Finding {
+            rule: Rule::NonPortableKey,
+            message: format!(
+                "{} is not a valid shell variable name (use A-Z, 0-9 and _)",
+                key
+            ),
+            start,
+            end,
+        });
+    }
+}
+
+fn check_separator(input: &str, pair: &ParsedPair, findings: &mut Vec<Finding>) {
+    let (_, key_end) = key_range(pair);
+    let bytes = input.as_bytes();
+    let is_space = |b: u8| b == b' ' || b == b'\t';
+
+    let mut pos = key_end;
+    while pos < bytes.len() && is_space(bytes[pos]) {
+        pos += 1;
+    }
+    if pos >= bytes.len() || !matches!(bytes[pos], b'=' | b':') {
+        return;
+    }
+    let separator = pos;
+    pos += 1;
+    // `KEY: value` needs the space; only flag space before the colon there
+    if bytes[separator] == b'=' {
+        let value_start = pos;
+        while pos < bytes.len() && is_space(bytes[pos]) {
+            pos += 1;
+        }
+        // Space before a comment that follows an empty value is fine
+        if pos >= bytes.len() || matches!(bytes[pos], b'#' | b'\n' | b'\r') {
+            pos = value_start;
+        }
+    }
+
+    if pos - key_end > 1 {
+        findings.push(Finding {
+            rule: Rule::SpacesAroundSeparator,
+            message: "Whitespace around the separator".to_string(),
+            start: key_end,
+            end: pos,
+        });
+    }
+}
+
+fn check_unquoted(pair: &ParsedPair, findings: &mut Vec<Finding>) {
+    let kv = &pair.kv;
+    if kv.quote != QuoteType::None || !kv.value.contains(|c: char| c == '#' || c.is_whitespace()) {
+        return;
+    }
+    let Some(span) = kv.value_span else {
+        return;
+    };
+    findings.push(Finding {
+        rule: Rule::UnquotedSpecialValue,
+        message: "Unquoted value with `#` or whitespace; loaders disagree on it, quote it"
+            .to_string(),
+        start: span.start.offset,
+        end: span.end.offset,
+    });
+}
+
+/// Flag quoted values that use the less common quotes in the file, unless
+/// the common quotes cannot hold them
+fn check_quotes(pairs: &[&ParsedPair], dialect: Dialect, findings: &mut Vec<Finding>) {
+    let quoted: Vec<&&ParsedPair> = pairs
+        .iter()
+        .filter(|p| p.kv.quote != QuoteType::None)
+        .collect();
+    let Some(first) = quoted.first() else {
+        return;
+    };
+    let singles = quoted
+        .iter()
+        .filter(|p| p.kv.quote == QuoteType::Single)
+        .count();
+    let doubles = quoted.len() - singles;
+    let common = match singles.cmp(&doubles) {
+        std::cmp::Ordering::Greater => QuoteType::Single,
+        std::cmp::Ordering::Less => QuoteType::Double,
+        std::cmp::Ordering::Equal => first.kv.quote,
+    };
+    let (common_char, common_name) = match common {
+        QuoteType::Single => ('\'', "single"),
+        _ => ('"', "double"),
+    };
+
+    for pair in quoted {
+        let kv = &pair.kv;
+        if kv.quote == common {
+            continue;
+        }
+        let rewritten = quote_value(&kv.value, common, dialect);
+        let (Some(span), true) = (kv.value_span, rewritten.starts_with(common_char)) else {
+            continue;
+        };
+        findings.push(Finding {
+            rule: Rule::InconsistentQuotes,
+            message: format!("Other values in this file use {} quotes", common_name),
+            start: span.start.offset,
+            end: span.end.offset,
+        });
+    }
+}
+
+/// Flag entries whose `export` prefix differs from most entries
+fn check_export(
+    input: &str,
+    parsed: &ParsedContent,
+    pairs: &[&ParsedPair],
+    findings: &mut Vec<Finding>,
+) {
+    // (line start, key start, exported)
+    let prefixes: Vec<(usize, usize, bool)> = pairs
+        .iter()
+        .map(|p| {
+            let line_start = parsed.line_starts[p.line_number.max(1) - 1];
+            let (key_start, _) = key_range(p);
+            let prefix = input.get(line_start..key_start).unwrap_or("").trim();
+            (line_start, key_start, prefix == "export")
+        })
+        .collect();
+
+    let exported = prefixes.iter().filter(|p| p.2).count();
+    if exported == 0 || exported == prefixes.len() {
+        return;
+    }
+    let common = match (exported * 2).cmp(&prefixes.len()) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Equal => prefixes[0].2,
+    };
+
+    for (pair, &(line_start, key_start, is_exported)) in pairs.iter().zip(&prefixes) {
+        if is_exported == common {
+            continue;
+        }
+        let (start, end, message) = if is_exported {
+            (
+                line_start,
+                key_start,
+                "`export` prefix, unlike most entries",
+            )
+        } else {
+            let (start, end) = key_range(pair);
+            (start, end, "No `export` prefix, unlike most entries")
+        };
+        findings.push(Finding {
+            rule: Rule::MixedExport,
+            message: message.to_string(),
+            start,
+            end,
+        });
+    }
+}
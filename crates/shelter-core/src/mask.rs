@@ -0,0 +1,117 @@
+//! Native masking engine for shelter-core
+//!
+//! The built-in `full` and `partial` modes (`modes/builtin/*.lua`) delegate
+//! here. Masks are built on raw bytes, like Lua strings, so byte offsets line
+//! up with the Lua side.
+
+/// Built-in masking mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MaskMode {
+    Full,
+    Partial,
+    None,
+}
+
+/// Options for the built-in modes, mirroring the Lua mode schemas
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MaskOptions {
+    pub mode: MaskMode,
+    pub mask_char: u8,
+    /// Fixed output length for `full` mode (overrides value length)
+    pub fixed_length: Option<usize>,
+    pub show_start: usize,
+    pub show_end: usize,
+    pub min_mask: usize,
+    /// Mode used by `partial` when the value is too short (`Full` or `None`)
+    pub fallback_mode: MaskMode,
+}
+
+impl Default for MaskOptions {
+    fn default() -> Self {
+        Self {
+            mode: MaskMode::Full,
+            mask_char: b'*',
+            fixed_length: None,
+            show_start: 3,
+            show_end: 3,
+            min_mask: 3,
+            fallback_mode: MaskMode::Full,
+        }
+    }
+}
+
+/// A masked value
+///
+/// `masked_start..masked_end` is the masked byte range of the original value;
+/// the bytes before and after it are revealed verbatim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MaskedValue {
+    pub mask: Vec<u8>,
+    pub masked_start: usize,
+    pub masked_end: usize,
+}
+
+#[inline]
+fn full_mask(value: &[u8], mask_char: u8, length: usize) -> MaskedValue {
+    MaskedValue {
+        mask: vec![mask_char; length],
+        masked_start: 0,
+        masked_end: value.len(),
+    }
+}
+
+/// Apply a mode to a value
+///
+/// Returns `None` when the mask would be identical to the value (e.g. `none`
+/// mode), matching the Lua engine which skips those entries. NUL bytes, from
+/// the value or a NUL mask character, are masked with the mask character
+/// (`*` if that is NUL).
+pub(crate) fn mask_value(value: &[u8], opts: &MaskOptions) -> Option<MaskedValue> {
+    let masked = match opts.mode {
+        MaskMode::None => return None,
+        MaskMode::Full => {
+            let length = opts.fixed_length.unwrap_or(value.len());
+            full_mask(value, opts.mask_char, length)
+        }
+        MaskMode::Partial => {
+            let value_len = value.len();
+            let min_length = opts.show_start + opts.show_end + opts.min_mask;
+
+            if value_len < min_length {
+                // Use fallback mode for short values
+                if opts.fallback_mode == MaskMode::None {
+                    return None;
+                }
+                full_mask(value, opts.mask_char, value_len)
+            } else {
+                let masked_end = value_len - opts.show_end;
+                let mut mask = Vec::with_capacity(value_len);
+                mask.extend_from_slice(&value[..opts.show_start]);
+                mask.resize(masked_end, opts.mask_char);
+                mask.extend_from_slice(&value[masked_end..]);
+
+                MaskedValue {
+                    mask,
+                    masked_start: opts.show_start,
+                    masked_end,
+                }
+            }
+        }
+    };
+
+    if masked.mask == value {
+        return None;
+    }
+
+    // Masks cross the FFI as C strings, which end at the first NUL
+    let mut masked = masked;
+    let replacement = match opts.mask_char {
+        0 => b'*',
+        c => c,
+    };
+    for b in masked.mask.iter_mut().filter(|b| **b == 0) {
+        *b = replacement;
+    }
+
+    Some(masked)
+}
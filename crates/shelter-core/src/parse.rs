@@ -0,0 +1,259 @@
+//! Shared parsing helpers for shelter-core
+//!
+//! Wraps korni and resolves byte offsets to line numbers so every FFI entry
+//! point sees the same entries and line table.
+
+use crate::types::ShelterDiagnosticCode;
+use korni::{Entry, Error, KeyValuePair, ParseOptions};
+
+/// How a value is encoded in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueEncoding {
+    /// The value is the text as written (after unquoting)
+    Plain,
+    /// The value was decoded from base64 (Kubernetes Secret `data`)
+    Base64,
+}
+
+/// A key-value pair together with its resolved line numbers
+pub(crate) struct ParsedPair<'a> {
+    pub kv: Box<KeyValuePair<'a>>,
+    /// 1-based line number where key starts
+    pub line_number: usize,
+    /// 1-based line number where value ends
+    pub value_end_line: usize,
+    /// Byte span of a trailing `# comment` on the value's last line
+    pub inline_comment: Option<(usize, usize)>,
+    /// Encoding the value was decoded from
+    pub encoding: ValueEncoding,
+}
+
+/// A comment and its location
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ParsedComment {
+    /// Byte offset of the `#`
+    pub start: usize,
+    /// Byte offset of the end of the line (excluding the newline)
+    pub end: usize,
+    /// 1-based line number
+    pub line_number: usize,
+    /// Whether the comment trails a value on the same line
+    pub is_inline: bool,
+}
+
+/// A parse error and the byte range it affects
+#[derive(Debug, Clone)]
+pub(crate) struct Diagnostic {
+    pub code: ShelterDiagnosticCode,
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Diagnostic {
+    /// Build a diagnostic from a korni error found while parsing `input`
+    ///
+    /// Unclosed quotes cover the rest of the input (korni consumes it all);
+    /// every other error covers the rest of its line.
+    pub fn from_korni(error: &Error, input: &str) -> Self {
+        let start = error.offset().min(input.len());
+        let line_end = input[start..]
+            .find(['\n', '\r'])
+            .map_or(input.len(), |i| start + i);
+
+        let (code, message, end) = match error {
+            Error::UnclosedQuote { quote_type, .. } => (
+                ShelterDiagnosticCode::UnclosedQuote,
+                format!("Unclosed {} quote", quote_type),
+                input.len(),
+            ),
+            Error::InvalidKey { reason, .. } => (
+                ShelterDiagnosticCode::InvalidKey,
+                format!("Invalid key: {}", reason),
+                line_end,
+            ),
+            Error::ForbiddenWhitespace { location, .. } => (
+                ShelterDiagnosticCode::ForbiddenWhitespace,
+                format!("Whitespace not allowed {}", location),
+                line_end,
+            ),
+            Error::DoubleEquals { .. } => (
+                ShelterDiagnosticCode::DoubleEquals,
+                "Double equals sign, quote the value: KEY=\"=val\"".to_string(),
+                line_end,
+            ),
+            Error::InvalidBom { .. } => (
+                ShelterDiagnosticCode::InvalidBom,
+                "BOM found at invalid position".to_string(),
+                (start + '\u{FEFF}'.len_utf8()).min(input.len()),
+            ),
+            Error::Expected { expected, .. } => (
+                ShelterDiagnosticCode::Expected,
+                format!("Expected {}", expected),
+                line_end,
+            ),
+            Error::InvalidUtf8 { reason, .. } => (
+                ShelterDiagnosticCode::InvalidUtf8,
+                format!("Invalid UTF-8: {}", reason),
+                line_end,
+            ),
+            Error::Generic { message, .. } => {
+                (ShelterDiagnosticCode::Generic, message.clone(), line_end)
+            }
+            Error::Io(message) => (ShelterDiagnosticCode::Io, message.clone(), start),
+        };
+
+        Diagnostic {
+            code,
+            message,
+            start,
+            end,
+        }
+    }
+
+    /// Shift the span by `base` bytes (for errors found in a slice)
+    pub fn offset_by(mut self, base: usize) -> Self {
+        self.start += base;
+        self.end += base;
+        self
+    }
+}
+
+/// Parsed content: pairs in document order plus the line start table
+pub(crate) struct ParsedContent<'a> {
+    pub pairs: Vec<ParsedPair<'a>>,
+    pub comments: Vec<ParsedComment>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Byte ranges a lenient dialect ignored without a diagnostic
+    pub skipped: Vec<(usize, usize)>,
+    pub line_starts: Vec<usize>,
+}
+
+/// Build line_starts array: indices where each line begins
+pub(crate) fn line_starts(input: &str) -> Vec<usize> {
+    // Pre-allocate with estimated capacity (avg line length ~30 chars)
+    let estimated_lines = input.len() / 30 + 1;
+    let mut line_starts: Vec<usize> = Vec::with_capacity(estimated_lines);
+    line_starts.push(0); // Line 1 starts at offset 0
+
+    for (i, b) in input.bytes().enumerate() {
+        if b == b'\n' {
+            line_starts.push(i + 1);
+        }
+    }
+
+    line_starts
+}
+
+/// Binary search to find line number from byte offset
+/// Returns 1-based line number
+#[inline]
+pub(crate) fn offset_to_line(line_starts: &[usize], offset: usize) -> usize {
+    match line_starts.binary_search(&offset) {
+        // Exact match: offset is at start of this line
+        Ok(line) => line + 1,
+        // Not found: offset is within the line before insert point
+        Err(line) => line,
+    }
+}
+
+/// Resolve (line_number, value_end_line) for a pair
+pub(crate) fn pair_lines(line_starts: &[usize], kv: &KeyValuePair) -> (usize, usize) {
+    let line_number = kv
+        .key_span
+        .map(|s| offset_to_line(line_starts, s.start.offset))
+        .unwrap_or(0);
+
+    // Calculate end line for multi-line values using binary search
+    let value_end_line = kv
+        .value_span
+        .map(|s| offset_to_line(line_starts, s.end.offset.saturating_sub(1)))
+        .unwrap_or(line_number);
+
+    (line_number, value_end_line)
+}
+
+/// Whether a comment starting at `comment_start` trails a pair whose value
+/// ends on `value_end_line` (the comment must directly follow the pair)
+#[inline]
+pub(crate) fn is_inline_comment(
+    line_starts: &[usize],
+    value_end_line: usize,
+    comment_start: usize,
+) -> bool {
+    offset_to_line(line_starts, comment_start) == value_end_line
+}
+
+/// Parse content with korni and resolve line numbers for every pair
+pub(crate) fn parse_content(input: &str, options: ParseOptions) -> ParsedContent<'_> {
+    let parsed_entries = korni::parse_with_options(input, options);
+    let line_starts = line_starts(input);
+
+    // Convert entries - pre-allocate based on parsed count
+    let mut pairs = Vec::with_capacity(parsed_entries.len());
+    let mut comments = Vec::new();
+    let mut diagnostics = Vec::new();
+    // Index of the top-level pair emitted right before the current entry
+    let mut last_pair: Option<usize> = None;
+
+    for entry in parsed_entries {
+        let previous_pair = last_pair.take();
+
+        match entry {
+            Entry::Pair(kv) => {
+                let (line_number, value_end_line) = pair_lines(&line_starts, &kv);
+                if !kv.is_comment {
+                    last_pair = Some(pairs.len());
+                }
+                pairs.push(ParsedPair {
+                    kv,
+                    line_number,
+                    value_end_line,
+                    inline_comment: None,
+                    encoding: ValueEncoding::Plain,
+                });
+            }
+            Entry::Comment(span) => {
+                let (start, end) = (span.start.offset, span.end.offset);
+                let inline_of = previous_pair
+                    .filter(|&i| is_inline_comment(&line_starts, pairs[i].value_end_line, start));
+                if let Some(i) = inline_of {
+                    pairs[i].inline_comment = Some((start, end));
+                }
+
+                comments.push(ParsedComment {
+                    start,
+                    end,
+                    line_number: offset_to_line(&line_starts, start),
+                    is_inline: inline_of.is_some(),
+                });
+            }
+            Entry::Error(error) => {
+                // Errors are expected while editing; report them, don't fail
+                diagnostics.push(Diagnostic::from_korni(&error, input));
+            }
+        }
+    }
+
+    // korni ignores whatever follows a closing quote on its line
+    let skipped = pairs
+        .iter()
+        .filter_map(|p| {
+            let end = p.kv.value_span?.end.offset;
+            let rest = &input[end..];
+            let line = &rest[..rest.find(['\n', '\r']).unwrap_or(rest.len())];
+            let text = line.trim_start_matches([' ', '\t']);
+            let start = end + line.len() - text.len();
+            let text = text.trim_end();
+            (!text.is_empty() && !text.starts_with('#')).then(|| (start, start + text.len()))
+        })
+        .collect();
+
+    ParsedContent {
+        pairs,
+        comments,
+        diagnostics,
+        skipped,
+        line_starts,
+    }
+}
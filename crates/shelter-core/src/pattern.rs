@@ -0,0 +1,423 @@
+//! Compiled key/source pattern matching for shelter-core
+//!
+//! Rules map a glob (or regex) to a mode name. Supported syntax:
+//! - `*` any run of characters except `/`, `**` any run including `/`
+//! - `?` any single character
+//! - `[A-Z_]`, `[!0-9]` / `[^0-9]` character classes
+//! - `{KEY,TOKEN}` brace alternation (nestable)
+//! - `\x` escapes a special character
+//! - a leading `!` negates the rule (it matches when the pattern does not)
+//!   and ranks it after every positive rule
+//! - a `re:` prefix makes the rest of the rule a regular expression; like
+//!   a glob it must match the whole text
+//!
+//! Rules are ranked once at build time by specificity (same scoring as the
+//! Lua pattern cache: length minus 10 per `*`), ties broken by pattern text,
+//! so resolution never depends on config table iteration order. A negated
+//! rule matches nearly every key, so its pattern says nothing about how
+//! specific it is: negated rules only apply when no positive rule matches.
+
+use regex::Regex;
+use std::cmp::Reverse;
+use std::ffi::{CStr, CString};
+
+/// Prefix marking a regex rule
+const REGEX_PREFIX: &str = "re:";
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Literal(char),
+    /// `*` - any run of characters except `/`
+    Star,
+    /// `**` - any run of characters
+    Globstar,
+    /// `?` - exactly one character
+    One,
+    Class {
+        negated: bool,
+        items: Vec<ClassItem>,
+    },
+}
+
+impl Token {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Token::Literal(l) => *l == c,
+            Token::One => true,
+            Token::Class { negated, items } => {
+                let found = items.iter().any(|item| match item {
+                    ClassItem::Char(ch) => *ch == c,
+                    ClassItem::Range(lo, hi) => (*lo..=*hi).contains(&c),
+                });
+                found != *negated
+            }
+            Token::Star | Token::Globstar => true,
+        }
+    }
+}
+
+/// A glob compiled into brace-expanded token sequences
+#[derive(Debug, Clone)]
+struct Glob {
+    alternatives: Vec<Vec<Token>>,
+}
+
+impl Glob {
+    fn compile(pattern: &str) -> Self {
+        Glob {
+            alternatives: expand_braces(pattern)
+                .iter()
+                .map(|alt| tokenize(alt))
+                .collect(),
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        self.alternatives
+            .iter()
+            .any(|tokens| match_tokens(tokens, &chars))
+    }
+}
+
+/// Expand `{a,b}` alternations into plain patterns
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut search_from = 0;
+
+    while let Some((open, close, commas)) = find_brace_group(&chars, search_from) {
+        if commas.is_empty() {
+            // `{x}` without a comma is literal, keep looking after it
+            search_from = open + 1;
+            continue;
+        }
+
+        let prefix: String = chars[..open].iter().collect();
+        let suffix: String = chars[close + 1..].iter().collect();
+
+        let mut bounds = vec![open];
+        bounds.extend(&commas);
+        bounds.push(close);
+
+        return bounds
+            .windows(2)
+            .flat_map(|w| {
+                let alt: String = chars[w[0] + 1..w[1]].iter().collect();
+                expand_braces(&format!("{}{}{}", prefix, alt, suffix))
+            })
+            .collect();
+    }
+
+    vec![pattern.to_string()]
+}
+
+/// Find the next top-level brace group at or after `from`
+/// Returns (open index, close index, top-level comma indices)
+fn find_brace_group(chars: &[char], from: usize) -> Option<(usize, usize, Vec<usize>)> {
+    let mut i = from;
+    let mut in_class = false;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '{' if !in_class => {
+                let mut depth = 0;
+                let mut commas = Vec::new();
+                let mut j = i + 1;
+                while j < chars.len() {
+                    match chars[j] {
+                        '\\' => j += 1,
+                        '{' => depth += 1,
+                        '}' if depth == 0 => return Some((i, j, commas)),
+                        '}' => depth -= 1,
+                        ',' if depth == 0 => commas.push(j),
+                        _ => {}
+                    }
+                    j += 1;
+                }
+                // Unclosed brace is literal
+                return None;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Tokenize a brace-free glob
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::with_capacity(chars.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                tokens.push(Token::Literal(chars[i + 1]));
+                i += 2;
+                continue;
+            }
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                tokens.push(Token::Globstar);
+                i += 2;
+                continue;
+            }
+            '*' => tokens.push(Token::Star),
+            '?' => tokens.push(Token::One),
+            '[' => {
+                if let Some((class, next)) = parse_class(&chars, i) {
+                    tokens.push(class);
+                    i = next;
+                    continue;
+                }
+                // Unclosed class is literal
+                tokens.push(Token::Literal('['));
+            }
+            c => tokens.push(Token::Literal(c)),
+        }
+        i += 1;
+    }
+
+    tokens
+}
+
+/// Parse a character class starting at `[`
+/// Returns the token and the index after the closing `]`
+fn parse_class(chars: &[char], open: usize) -> Option<(Token, usize)> {
+    let mut i = open + 1;
+    let negated = matches!(chars.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut items = Vec::new();
+    let first = i;
+
+    while i < chars.len() {
+        let c = chars[i];
+        // `]` right after the opening bracket is a literal member
+        if c == ']' && i > first {
+            return Some((Token::Class { negated, items }, i + 1));
+        }
+
+        if chars.get(i + 1) == Some(&'-') && i + 2 < chars.len() && chars[i + 2] != ']' {
+            items.push(ClassItem::Range(c, chars[i + 2]));
+            i += 3;
+        } else {
+            items.push(ClassItem::Char(c));
+            i += 1;
+        }
+    }
+
+    None
+}
+
+/// Match tokens against text with dynamic programming (no exponential backtracking)
+fn match_tokens(tokens: &[Token], text: &[char]) -> bool {
+    // reachable[j] = tokens[..i] can match text[..j]
+    let mut reachable = vec![false; text.len() + 1];
+    reachable[0] = true;
+
+    for token in tokens {
+        let mut next = vec![false; text.len() + 1];
+        match token {
+            Token::Star | Token::Globstar => {
+                let crosses_slash = matches!(token, Token::Globstar);
+                let mut carry = false;
+                for j in 0..=text.len() {
+                    if j > 0 && !crosses_slash && text[j - 1] == '/' {
+                        carry = false;
+                    }
+                    carry |= reachable[j];
+                    next[j] = carry;
+                }
+            }
+            _ => {
+                for j in 0..text.len() {
+                    if reachable[j] && token.matches(text[j]) {
+                        next[j + 1] = true;
+                    }
+                }
+            }
+        }
+        reachable = next;
+    }
+
+    reachable[text.len()]
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Glob(Glob),
+    Regex(Regex),
+}
+
+/// A single compiled rule
+#[derive(Debug)]
+struct Rule {
+    pattern: String,
+    matcher: Matcher,
+    negated: bool,
+    /// Whether source rules match the whole path instead of the basename
+    match_path: bool,
+    specificity: i64,
+    /// Index into `PatternSet::modes`
+    mode: usize,
+}
+
+impl Rule {
+    fn compile(pattern: &str, mode: usize) -> Result<Self, String> {
+        let (negated, body) = match pattern.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+
+        let (matcher, specificity) = match body.strip_prefix(REGEX_PREFIX) {
+            Some(re) => {
+                // Anchored like globs, so `re:KEY` does not match `MY_KEY_X`
+                let regex = Regex::new(&format!("^(?:{})$", re))
+                    .map_err(|e| format!("Invalid regex in pattern '{}': {}", pattern, e))?;
+                (Matcher::Regex(regex), re.len() as i64)
+            }
+            None => {
+                let stars = body.matches('*').count() as i64;
+                (
+                    Matcher::Glob(Glob::compile(body)),
+                    body.len() as i64 - stars * 10,
+                )
+            }
+        };
+
+        Ok(Rule {
+            pattern: pattern.to_string(),
+            matcher,
+            negated,
+            match_path: body.contains('/'),
+            specificity,
+            mode,
+        })
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        let matched = match &self.matcher {
+            Matcher::Glob(glob) => glob.is_match(text),
+            Matcher::Regex(regex) => regex.is_match(text),
+        };
+        matched != self.negated
+    }
+}
+
+/// Intern a mode name, returning its index
+fn intern(modes: &mut Vec<CString>, mode: &str) -> Result<usize, String> {
+    if let Some(idx) = modes.iter().position(|m| m.as_bytes() == mode.as_bytes()) {
+        return Ok(idx);
+    }
+
+    let mode = CString::new(mode).map_err(|_| format!("Invalid mode name '{}'", mode))?;
+    modes.push(mode);
+    Ok(modes.len() - 1)
+}
+
+/// Compile and rank a rule list (positive rules first, then most specific
+/// first, ties by pattern text)
+fn compile_rules<'a>(
+    rules: impl IntoIterator<Item = (&'a str, &'a str)>,
+    modes: &mut Vec<CString>,
+) -> Result<Vec<Rule>, String> {
+    let mut compiled = rules
+        .into_iter()
+        .map(|(pattern, mode)| Rule::compile(pattern, intern(modes, mode)?))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    compiled.sort_by(|a, b| {
+        (a.negated, Reverse(a.specificity), &a.pattern).cmp(&(
+            b.negated,
+            Reverse(b.specificity),
+            &b.pattern,
+        ))
+    });
+
+    Ok(compiled)
+}
+
+/// Basename of a path, accepting both `/` and `\` separators
+fn basename(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Key and source rules compiled from the `patterns` and `sources` config
+///
+/// Mode names are returned as C strings owned by the set, so FFI callers can
+/// use them without copying.
+#[derive(Debug)]
+pub(crate) struct PatternSet {
+    key_rules: Vec<Rule>,
+    source_rules: Vec<Rule>,
+    default_mode: usize,
+    modes: Vec<CString>,
+}
+
+impl PatternSet {
+    /// Build a pattern set from (pattern, mode) pairs
+    pub fn new<'a>(
+        patterns: impl IntoIterator<Item = (&'a str, &'a str)>,
+        sources: impl IntoIterator<Item = (&'a str, &'a str)>,
+        default_mode: &str,
+    ) -> Result<Self, String> {
+        let mut modes = Vec::new();
+        let default_mode = intern(&mut modes, default_mode)?;
+        let key_rules = compile_rules(patterns, &mut modes)?;
+        let source_rules = compile_rules(sources, &mut modes)?;
+
+        Ok(PatternSet {
+            key_rules,
+            source_rules,
+            default_mode,
+            modes,
+        })
+    }
+
+    /// Mode of the most specific key rule matching `key`
+    pub fn match_key(&self, key: &str) -> Option<&CStr> {
+        self.key_rules
+            .iter()
+            .find(|rule| rule.is_match(key))
+            .map(|rule| self.modes[rule.mode].as_c_str())
+    }
+
+    /// Mode of the most specific source rule matching `source`
+    ///
+    /// Rules containing `/` match the whole path, others the basename.
+    pub fn match_source(&self, source: &str) -> Option<&CStr> {
+        let normalized = source.replace('\\', "/");
+        let name = basename(source);
+
+        self.source_rules
+            .iter()
+            .find(|rule| {
+                if rule.match_path {
+                    rule.is_match(&normalized)
+                } else {
+                    rule.is_match(name)
+                }
+            })
+            .map(|rule| self.modes[rule.mode].as_c_str())
+    }
+
+    /// Resolve the mode for a key: key rules, then source rules, then default
+    pub fn resolve(&self, key: &str, source: Option<&str>) -> &CStr {
+        self.match_key(key)
+            .or_else(|| source.and_then(|s| self.match_source(s)))
+            .unwrap_or(&self.modes[self.default_mode])
+    }
+}
@@ -0,0 +1,120 @@
+//! Writing values back into files
+//!
+//! Picks the quoting for a value so that the file's own dialect reads it
+//! back unchanged. Env dialects try the preferred style first, then bare,
+//! single, double and backtick quotes, and keep the first candidate that
+//! parses back to the same value; `$` is never left where it would be
+//! expanded, and `#` and whitespace are never left unquoted. Config formats
+//! get JSON-style double-quoted strings, which TOML and YAML read the same
+//! way.
+
+use korni::{ParseOptions, QuoteType};
+
+use crate::dialect::{parse_dialect, Dialect};
+
+/// Quote `value` for `dialect`, keeping `preferred` quotes if they work
+pub(crate) fn quote_value(value: &str, preferred: QuoteType, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::Json | Dialect::Toml | Dialect::Yaml => return json_string(value),
+        // INI readers take the rest of the line as is
+        Dialect::Ini => return value.to_string(),
+        _ => {}
+    }
+
+    let order = match preferred {
+        QuoteType::Single => [Style::Single, Style::Bare, Style::Double, Style::Backtick],
+        QuoteType::Double => [Style::Double, Style::Bare, Style::Single, Style::Backtick],
+        QuoteType::None => [Style::Bare, Style::Single, Style::Double, Style::Backtick],
+    };
+
+    order
+        .iter()
+        .filter_map(|style| style.quote(value, dialect))
+        .find(|quoted| reads_back(quoted, value, dialect))
+        .unwrap_or_else(|| {
+            Style::Double
+                .quote(value, dialect)
+                .unwrap_or_else(|| json_string(value))
+        })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Style {
+    Bare,
+    Single,
+    Double,
+    Backtick,
+}
+
+impl Style {
+    /// Candidate text, or `None` if the style cannot hold the value
+    fn quote(self, value: &str, dialect: Dialect) -> Option<String> {
+        let escapes = dialect.double_quote_escapes();
+        let escapes_dollar = escapes.iter().any(|&(_, c)| c == '$');
+
+        match self {
+            // Unquoted and double-quoted values are expanded by shells and
+            // by shelter's own `${VAR}` resolution
+            Style::Bare if value.contains('$') => None,
+            // Loaders disagree on unquoted `#` and whitespace
+            Style::Bare if value.contains(|c: char| c == '#' || c.is_whitespace()) => None,
+            Style::Bare => Some(value.to_string()),
+            Style::Single => Some(format!("'{}'", value)),
+            Style::Double if value.contains('$') && !escapes_dollar => None,
+            Style::Double => {
+                let mut out = String::with_capacity(value.len() + 2);
+                out.push('"');
+                for c in value.chars() {
+                    let escape = escapes
+                        .iter()
+                        .find(|&&(_, meaning)| meaning == c && SPECIAL.contains(&c));
+                    match escape {
+                        Some(&(byte, _)) => {
+                            out.push('\\');
+                            out.push(byte as char);
+                        }
+                        None => out.push(c),
+                    }
+                }
+                out.push('"');
+                Some(out)
+            }
+            Style::Backtick if dialect == Dialect::NodeDotenv => Some(format!("`{}`", value)),
+            Style::Backtick => None,
+        }
+    }
+}
+
+/// Characters written as escapes when the dialect has one for them
+const SPECIAL: [char; 7] = ['\\', '"', '$', '`', '\n', '\r', '\t'];
+
+/// Whether `KEY=<quoted>` reads back as `value` in `dialect`
+fn reads_back(quoted: &str, value: &str, dialect: Dialect) -> bool {
+    let line = format!("KEY={}\n", quoted);
+    let parsed = parse_dialect(&line, dialect, ParseOptions::default());
+    parsed.diagnostics.is_empty()
+        && parsed.pairs.len() == 1
+        && parsed.pairs[0].kv.key == "KEY"
+        && parsed.pairs[0].kv.value == value
+}
+
+/// A JSON string literal
+pub(crate) fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\u{7f}' => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
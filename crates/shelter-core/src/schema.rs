@@ -0,0 +1,447 @@
+//! Schema validation for shelter-core
+//!
+//! Rules are declared with annotations, either in a `.env.schema` file that
+//! lists every key the app reads or right in the env file:
+//!
+//! ```text
+//! # @type url
+//! # @required
+//! DATABASE_URL=
+//!
+//! # @type enum(debug, info, warn, error)
+//! # @default info
+//! LOG_LEVEL=
+//!
+//! # @type string
+//! # @pattern sk_(live|test)_[A-Za-z0-9]+
+//! STRIPE_KEY=
+//! ```
+//!
+//! Annotations in the env file override the schema file key by key. Empty
+//! values count as unset. With a schema file, keys it does not declare are
+//! reported too.
+
+use base64::Engine;
+use korni::ParseOptions;
+use regex::Regex;
+
+use crate::annotation::Annotations;
+use crate::dialect::{parse_dialect, Dialect};
+use crate::example::is_email;
+use crate::json::parse_json;
+use crate::lint::Severity;
+use crate::parse::{offset_to_line, ParsedContent, ParsedPair};
+
+/// Type of a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ValueType {
+    String,
+    Int,
+    Bool,
+    Url,
+    Email,
+    Enum(Vec<String>),
+    Duration,
+    Port,
+    Base64,
+    Json,
+}
+
+impl ValueType {
+    /// Parse a type annotation like `port` or `enum(a, b)`
+    fn parse(spec: &str) -> Option<Self> {
+        if let Some(variants) = spec
+            .strip_prefix("enum(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let variants: Vec<String> = variants
+                .split(',')
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect();
+            return (!variants.is_empty()).then_some(ValueType::Enum(variants));
+        }
+        Some(match spec {
+            "string" => ValueType::String,
+            "int" => ValueType::Int,
+            "bool" => ValueType::Bool,
+            "url" => ValueType::Url,
+            "email" => ValueType::Email,
+            "duration" => ValueType::Duration,
+            "port" => ValueType::Port,
+            "base64" => ValueType::Base64,
+            "json" => ValueType::Json,
+            _ => return None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ValueType::String => "string",
+            ValueType::Int => "int",
+            ValueType::Bool => "bool",
+            ValueType::Url => "url",
+            ValueType::Email => "email",
+            ValueType::Enum(_) => "enum",
+            ValueType::Duration => "duration",
+            ValueType::Port => "port",
+            ValueType::Base64 => "base64",
+            ValueType::Json => "json",
+        }
+    }
+
+    /// Whether `value` is of this type
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            ValueType::String => true,
+            ValueType::Int => value.parse::<i64>().is_ok(),
+            ValueType::Bool => matches!(
+                value.to_ascii_lowercase().as_str(),
+                "true" | "false" | "yes" | "no" | "on" | "off" | "1" | "0"
+            ),
+            ValueType::Url => is_url(value),
+            ValueType::Email => is_email(value),
+            ValueType::Enum(variants) => variants.iter().any(|v| v == value),
+            ValueType::Duration => is_duration(value),
+            ValueType::Port => value.parse::<u16>().is_ok_and(|port| port > 0),
+            ValueType::Base64 => is_base64(value),
+            ValueType::Json => !value.trim().is_empty() && parse_json(value).diagnostics.is_empty(),
+        }
+    }
+}
+
+/// `scheme://rest` without whitespace
+fn is_url(value: &str) -> bool {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return false;
+    };
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'.' | b'-'))
+        && !rest.is_empty()
+        && !value.contains(char::is_whitespace)
+}
+
+/// Go-style durations: `250ms`, `30s`, `1h30m`, `1.5d` (or `0`)
+fn is_duration(value: &str) -> bool {
+    const UNITS: [&str; 9] = ["ns", "us", "µs", "ms", "s", "m", "h", "d", "w"];
+
+    if value == "0" {
+        return true;
+    }
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let number = &rest[..number_len];
+        if number.is_empty() || number.parse::<f64>().is_err() {
+            return false;
+        }
+        rest = &rest[number_len..];
+        // Longest unit first, so `ms` is not read as `m`
+        let Some(unit) = UNITS
+            .iter()
+            .filter(|unit| rest.starts_with(*unit))
+            .max_by_key(|unit| unit.len())
+        else {
+            return false;
+        };
+        rest = &rest[unit.len()..];
+    }
+    true
+}
+
+fn is_base64(value: &str) -> bool {
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+
+    !value.is_empty()
+        && [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD]
+            .iter()
+            .any(|engine| engine.decode(value).is_ok())
+}
+
+/// Rules declared for one key
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyRule {
+    pub value_type: Option<ValueType>,
+    pub required: Option<bool>,
+    pub default: Option<String>,
+    /// The pattern as written and its whole-value regex
+    pub pattern: Option<(String, Regex)>,
+}
+
+impl KeyRule {
+    /// Rules from the annotations of `pair`; `location` names it in errors
+    fn from_annotations(
+        annotations: &Annotations,
+        pair: &ParsedPair,
+        location: &str,
+    ) -> Result<Self, String> {
+        let error = |message: String| format!("{}: {}", location, message);
+        let mut rule = KeyRule::default();
+
+        if let Some(spec) = annotations.find(pair, "@type") {
+            rule.value_type = Some(
+                ValueType::parse(spec).ok_or_else(|| error(format!("Unknown type '{}'", spec)))?,
+            );
+        }
+        if annotations.find(pair, "@required").is_some() {
+            rule.required = Some(true);
+        } else if annotations.find(pair, "@optional").is_some() {
+            rule.required = Some(false);
+        }
+        rule.default = annotations.find(pair, "@default").map(str::to_string);
+        if let Some(pattern) = annotations.find(pair, "@pattern") {
+            let anchored = format!("^(?:{})$", pattern);
+            let regex = Regex::new(&anchored)
+                .map_err(|e| error(format!("Invalid pattern '{}': {}", pattern, e)))?;
+            rule.pattern = Some((pattern.to_string(), regex));
+        }
+
+        if let Some(default) = &rule.default {
+            if let Some(problem) = rule.check(default) {
+                return Err(error(format!("Default '{}' {}", default, problem.1)));
+            }
+        }
+        Ok(rule)
+    }
+
+    /// Fields set in `other` replace those of `self`
+    fn merge(&mut self, other: KeyRule) {
+        if other.value_type.is_some() {
+            self.value_type = other.value_type;
+        }
+        if other.required.is_some() {
+            self.required = other.required;
+        }
+        if other.default.is_some() {
+            self.default = other.default;
+        }
+        if other.pattern.is_some() {
+            self.pattern = other.pattern;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.value_type.is_none()
+            && self.required.is_none()
+            && self.default.is_none()
+            && self.pattern.is_none()
+    }
+
+    /// The violation of a non-empty value, if any
+    fn check(&self, value: &str) -> Option<(Code, String)> {
+        match &self.value_type {
+            Some(ValueType::Enum(variants)) if !variants.iter().any(|v| v == value) => {
+                return Some((
+                    Code::NotInEnum,
+                    format!("is not one of {}", variants.join(", ")),
+                ))
+            }
+            Some(value_type) if !value_type.accepts(value) => {
+                return Some((
+                    Code::InvalidType,
+                    format!("is not a valid {}", value_type.name()),
+                ))
+            }
+            _ => {}
+        }
+        match &self.pattern {
+            Some((source, regex)) if !regex.is_match(value) => {
+                Some((Code::PatternMismatch, format!("does not match {}", source)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Kind of schema violation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Code {
+    /// A required key is missing or empty
+    Required,
+    /// A value does not parse as its type
+    InvalidType,
+    /// A value is not one of the enum variants
+    NotInEnum,
+    /// A value does not match its pattern
+    PatternMismatch,
+    /// A key the schema file does not declare
+    UndeclaredKey,
+}
+
+impl Code {
+    /// Stable code, numbered after the lint rules
+    pub fn code(self) -> &'static str {
+        match self {
+            Code::Required => "SH101",
+            Code::InvalidType => "SH102",
+            Code::NotInEnum => "SH103",
+            Code::PatternMismatch => "SH104",
+            Code::UndeclaredKey => "SH105",
+        }
+    }
+
+    /// Stable name
+    pub fn name(self) -> &'static str {
+        match self {
+            Code::Required => "required",
+            Code::InvalidType => "invalid-type",
+            Code::NotInEnum => "not-in-enum",
+            Code::PatternMismatch => "pattern-mismatch",
+            Code::UndeclaredKey => "undeclared-key",
+        }
+    }
+
+    pub fn severity(self) -> Severity {
+        match self {
+            Code::UndeclaredKey => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// A value that breaks the schema, and the byte range to flag
+#[derive(Debug, Clone)]
+pub(crate) struct Violation {
+    pub code: Code,
+    pub key: String,
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Rules by key, in declaration order
+#[derive(Debug, Default)]
+struct Schema {
+    rules: Vec<(String, KeyRule)>,
+}
+
+impl Schema {
+    /// Add the annotated rules of every key in a parsed file
+    fn declare(
+        &mut self,
+        input: &str,
+        parsed: &ParsedContent,
+        location: &str,
+        all_keys: bool,
+    ) -> Result<(), String> {
+        let annotations = Annotations::new(input, parsed);
+        for pair in parsed.pairs.iter().filter(|p| !p.kv.is_comment) {
+            let line = offset_to_line(
+                &parsed.line_starts,
+                pair.kv.key_span.map_or(0, |s| s.start.offset),
+            );
+            let rule =
+                KeyRule::from_annotations(&annotations, pair, &format!("{}{}", location, line))?;
+            if !all_keys && rule.is_empty() {
+                continue;
+            }
+            match self.rules.iter_mut().find(|(key, _)| key == &pair.kv.key) {
+                Some((_, existing)) => existing.merge(rule),
+                None => self.rules.push((pair.kv.key.to_string(), rule)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validate `input` read as `dialect` against the annotations in it and
+/// an optional `.env.schema` file
+pub(crate) fn validate(
+    input: &str,
+    dialect: Dialect,
+    schema_file: Option<&str>,
+) -> Result<Vec<Violation>, String> {
+    let mut schema = Schema::default();
+    if let Some(schema_file) = schema_file {
+        let parsed = parse_dialect(schema_file, Dialect::Edf, ParseOptions::full());
+        if let Some(diagnostic) = parsed.diagnostics.first() {
+            return Err(format!(
+                "Schema line {}: {}",
+                offset_to_line(&parsed.line_starts, diagnostic.start).max(1),
+                diagnostic.message
+            ));
+        }
+        schema.declare(schema_file, &parsed, "Schema line ", true)?;
+    }
+    let declared = schema.rules.len();
+
+    let parsed = parse_dialect(input, dialect, ParseOptions::full());
+    schema.declare(input, &parsed, "Line ", false)?;
+    let pairs: Vec<&ParsedPair> = parsed.pairs.iter().filter(|p| !p.kv.is_comment).collect();
+
+    let mut violations = Vec::new();
+    for (key, rule) in &schema.rules {
+        // The effective definition
+        let Some(pair) = pairs.iter().rev().find(|p| p.kv.key == key.as_str()) else {
+            if rule.required == Some(true) && rule.default.is_none() {
+                violations.push(Violation {
+                    code: Code::Required,
+                    key: key.clone(),
+                    message: format!("{} is required but missing", key),
+                    start: 0,
+                    end: 0,
+                });
+            }
+            continue;
+        };
+
+        let value = pair.kv.value.as_ref();
+        let (key_start, key_end) = pair
+            .kv
+            .key_span
+            .map_or((0, 0), |s| (s.start.offset, s.end.offset));
+        if value.is_empty() {
+            if rule.required == Some(true) {
+                violations.push(Violation {
+                    code: Code::Required,
+                    key: key.clone(),
+                    message: format!("{} is required but empty", key),
+                    start: key_start,
+                    end: key_end,
+                });
+            }
+            continue;
+        }
+
+        if let Some((code, problem)) = rule.check(value) {
+            let (start, end) = pair
+                .kv
+                .value_span
+                .map_or((key_start, key_end), |s| (s.start.offset, s.end.offset));
+            violations.push(Violation {
+                code,
+                key: key.clone(),
+                message: format!("{} {}", key, problem),
+                start,
+                end,
+            });
+        }
+    }
+
+    if schema_file.is_some() {
+        let declared = &schema.rules[..declared];
+        for pair in &pairs {
+            if declared.iter().any(|(key, _)| key == &pair.kv.key) {
+                continue;
+            }
+            let (start, end) = pair
+                .kv
+                .key_span
+                .map_or((0, 0), |s| (s.start.offset, s.end.offset));
+            violations.push(Violation {
+                code: Code::UndeclaredKey,
+                key: pair.kv.key.to_string(),
+                message: format!("{} is not declared in the schema", pair.kv.key),
+                start,
+                end,
+            });
+        }
+    }
+
+    violations.sort_by_key(|v| (v.start, v.code.code()));
+    Ok(violations)
+}
@@ -0,0 +1,312 @@
+//! Heuristic secret scoring for shelter-core
+//!
+//! Classifies a value as likely-secret, likely-benign or unknown from its
+//! Shannon entropy, character-class mix, length and a few well-known shapes
+//! (booleans, numbers, hosts, paths, URLs, PEM blocks). The goal is to keep
+//! configuration such as `true`, `3000` or `localhost` readable while still
+//! masking anything that looks like a credential.
+
+use crate::detect::{detect, CredentialKind};
+
+/// Value contains ASCII lowercase letters
+pub(crate) const CLASS_LOWER: u8 = 1;
+/// Value contains ASCII uppercase letters
+pub(crate) const CLASS_UPPER: u8 = 2;
+/// Value contains ASCII digits
+pub(crate) const CLASS_DIGIT: u8 = 4;
+/// Value contains anything else (punctuation, whitespace, non-ASCII)
+pub(crate) const CLASS_SYMBOL: u8 = 8;
+
+/// Minimum length for a value to be scored as a secret on entropy alone
+const SECRET_MIN_LEN: usize = 16;
+/// Entropy (bits per byte) above which a long mixed-class value is a secret
+const SECRET_ENTROPY: f64 = 3.5;
+/// Entropy above which a long value is a secret regardless of its classes
+const SECRET_ENTROPY_STRONG: f64 = 4.0;
+/// Entropy below which a value is too repetitive to be a secret
+const BENIGN_ENTROPY: f64 = 3.0;
+
+/// Common non-secret configuration words (compared case-insensitively)
+const BENIGN_WORDS: &[&str] = &[
+    "true",
+    "false",
+    "yes",
+    "no",
+    "on",
+    "off",
+    "null",
+    "none",
+    "nil",
+    "enabled",
+    "disabled",
+    "debug",
+    "info",
+    "warn",
+    "warning",
+    "error",
+    "trace",
+    "development",
+    "production",
+    "staging",
+    "test",
+    "localhost",
+];
+
+/// Classification of a scored value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SecretClass {
+    Unknown,
+    LikelySecret,
+    LikelyBenign,
+}
+
+/// Score of a single value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ValueScore {
+    /// Shannon entropy in bits per byte
+    pub entropy: f64,
+    /// Length in characters
+    pub length: usize,
+    /// Bitmask of `CLASS_*` flags
+    pub char_classes: u8,
+    pub class: SecretClass,
+    /// Known credential format, if any (always classified as a secret)
+    pub credential: CredentialKind,
+}
+
+/// Shannon entropy of `bytes` in bits per byte (0.0 for empty input)
+pub(crate) fn shannon_entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; 256];
+    for &b in bytes {
+        counts[b as usize] += 1;
+    }
+
+    let len = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Bitmask of the character classes present in `value`
+pub(crate) fn char_classes(value: &str) -> u8 {
+    value.bytes().fold(0, |classes, b| {
+        classes
+            | match b {
+                b'a'..=b'z' => CLASS_LOWER,
+                b'A'..=b'Z' => CLASS_UPPER,
+                b'0'..=b'9' => CLASS_DIGIT,
+                _ => CLASS_SYMBOL,
+            }
+    })
+}
+
+/// Score and classify a value
+pub(crate) fn score_value(value: &str) -> ValueScore {
+    let entropy = shannon_entropy(value.as_bytes());
+    let char_classes = char_classes(value);
+    let credential = detect(value);
+
+    let class = if credential == CredentialKind::None {
+        classify(value, entropy, char_classes)
+    } else {
+        SecretClass::LikelySecret
+    };
+
+    ValueScore {
+        entropy,
+        length: value.chars().count(),
+        char_classes,
+        class,
+        credential,
+    }
+}
+
+fn classify(value: &str, entropy: f64, classes: u8) -> SecretClass {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return SecretClass::LikelyBenign;
+    }
+
+    if trimmed.contains("-----BEGIN ") {
+        return SecretClass::LikelySecret;
+    }
+
+    if let Some((_, rest)) = trimmed.split_once("://") {
+        return classify_url(rest);
+    }
+
+    if BENIGN_WORDS.iter().any(|w| trimmed.eq_ignore_ascii_case(w))
+        || is_number(trimmed)
+        || is_path(trimmed)
+        || is_email(trimmed)
+        || is_host(trimmed)
+    {
+        return SecretClass::LikelyBenign;
+    }
+
+    let len = trimmed.len();
+
+    if len >= 32 && trimmed.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return SecretClass::LikelySecret;
+    }
+
+    // Repetitive single-case words such as `my-app` are no token, but may
+    // still be a dictionary password like `changeme`, so they stay unknown
+    let mixed_case = classes & (CLASS_LOWER | CLASS_UPPER) == CLASS_LOWER | CLASS_UPPER;
+    if entropy < BENIGN_ENTROPY && !mixed_case && !is_token(trimmed) {
+        return SecretClass::Unknown;
+    }
+
+    // Prose, or a passphrase: several words and no long token inside
+    if trimmed.contains(' ') && trimmed.split_whitespace().all(|w| w.len() < SECRET_MIN_LEN) {
+        return SecretClass::Unknown;
+    }
+
+    if is_secret_token(trimmed, entropy, classes) {
+        return SecretClass::LikelySecret;
+    }
+
+    SecretClass::Unknown
+}
+
+/// A URL is a secret when it embeds credentials or a token-like segment
+fn classify_url(rest: &str) -> SecretClass {
+    if has_password(rest) {
+        return SecretClass::LikelySecret;
+    }
+
+    let has_token = rest
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .any(|segment| {
+            segment.len() >= 20
+                && is_secret_token(
+                    segment,
+                    shannon_entropy(segment.as_bytes()),
+                    char_classes(segment),
+                )
+        });
+
+    if has_token {
+        SecretClass::LikelySecret
+    } else {
+        SecretClass::LikelyBenign
+    }
+}
+
+/// Whether the part of a URL after `://` embeds a password (`user:pass@`)
+pub(crate) fn has_password(rest: &str) -> bool {
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    authority
+        .rsplit_once('@')
+        .is_some_and(|(userinfo, _)| userinfo.contains(':'))
+}
+
+#[inline]
+fn is_secret_token(value: &str, entropy: f64, classes: u8) -> bool {
+    let len = value.len();
+    (len >= SECRET_MIN_LEN && entropy >= SECRET_ENTROPY && classes.count_ones() >= 2)
+        || (len >= 24 && entropy >= SECRET_ENTROPY_STRONG)
+}
+
+/// Mixed letters and digits without separators, e.g. `hunter2` or `a1b2c3`
+#[inline]
+fn is_token(value: &str) -> bool {
+    let classes = char_classes(value);
+    classes & CLASS_SYMBOL == 0 && classes & CLASS_DIGIT != 0 && classes != CLASS_DIGIT
+}
+
+/// Integers and decimals; very long digit runs are left to the entropy rules
+fn is_number(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+
+    !int.is_empty()
+        && int.len() <= 15
+        && int.bytes().all(|b| b.is_ascii_digit())
+        && frac.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_path(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    let unix = value.starts_with('/') || value.starts_with("./") || value.starts_with("../");
+    let home = value.starts_with("~/");
+    let windows = bytes.len() > 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+
+    (unix || home || windows)
+        && !value.contains(char::is_whitespace)
+        && !value.split(['/', '\\']).any(is_generated)
+}
+
+fn is_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !local.contains([':', ' ', '/'])
+                && domain.contains('.')
+                && is_hostname(domain)
+        }
+        None => false,
+    }
+}
+
+/// Hostname or IP address with an optional port, e.g. `db.internal:5432`
+fn is_host(value: &str) -> bool {
+    let host = match value.rsplit_once(':') {
+        Some((host, port)) => {
+            if port.is_empty() || port.len() > 5 || !port.bytes().all(|b| b.is_ascii_digit()) {
+                return false;
+            }
+            host
+        }
+        None => value,
+    };
+
+    (host.contains('.') || host.eq_ignore_ascii_case("localhost")) && is_hostname(host)
+}
+
+fn is_hostname(host: &str) -> bool {
+    !host.is_empty()
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+                && !is_generated(label)
+        })
+}
+
+/// A path segment or host label that looks generated rather than named:
+/// a token on its own, or letters and digits alternating like `a9f3k2`
+///
+/// Keeps base64 starting with `/` and dotted random strings from passing
+/// as paths and hosts.
+fn is_generated(segment: &str) -> bool {
+    let alternations = segment
+        .as_bytes()
+        .windows(2)
+        .filter(|pair| {
+            pair.iter().all(u8::is_ascii_alphanumeric)
+                && pair[0].is_ascii_digit() != pair[1].is_ascii_digit()
+        })
+        .count();
+
+    alternations >= 3
+        || is_secret_token(
+            segment,
+            shannon_entropy(segment.as_bytes()),
+            char_classes(segment),
+        )
+}
@@ -0,0 +1,380 @@
+//! Known-value stream masker for shelter-core
+//!
+//! Masks the values of the workspace's env files wherever they show up in
+//! other output (terminal buffers, test logs, `docker compose up`), whatever
+//! key or format they are printed under. Every value is loaded into an
+//! Aho-Corasick automaton, so a chunk is scanned in one pass no matter how
+//! many values there are.
+//!
+//! Output arrives in chunks, and a value can be split across two of them.
+//! The automaton state carries over between chunks, and the bytes that could
+//! still be the start of a value are held back until the next chunk decides
+//! them, so the redacted output never leaks a prefix.
+//!
+//! The automaton is the only copy of the values shelter keeps, and its
+//! tables are zeroized when it is dropped, as is the held-back output.
+
+use std::collections::VecDeque;
+
+use korni::ParseOptions;
+use zeroize::Zeroize;
+
+use crate::dialect::{detect_dialect, parse_dialect};
+use crate::interpolate::resolve;
+use crate::score::{score_value, SecretClass};
+
+/// Which values to load
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LoadOptions {
+    /// Shorter values are skipped, since they match too much ordinary text
+    pub min_length: usize,
+    /// Skip values that score as likely benign (`true`, `3000`, `localhost`)
+    pub skip_benign: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            min_length: 6,
+            skip_benign: true,
+        }
+    }
+}
+
+/// Values of `files` (path and content) worth masking: every active value,
+/// its expansion if it has references, and each line of multi-line values
+pub(crate) fn load_values(files: &[(&str, &str)], options: LoadOptions) -> Vec<String> {
+    let mut values = Vec::new();
+    for &(path, content) in files {
+        let dialect = detect_dialect(Some(path), content);
+        let parsed = parse_dialect(content, dialect, ParseOptions::full());
+        let resolved = resolve(content, &parsed.pairs, dialect);
+        for (pair, resolution) in parsed.pairs.iter().zip(resolved) {
+            if pair.kv.is_comment {
+                continue;
+            }
+            let mut candidates = vec![pair.kv.value.to_string()];
+            if let Some(mut resolution) = resolution {
+                candidates.push(std::mem::take(&mut resolution.value));
+            }
+            for mut value in candidates {
+                if value.contains('\n') {
+                    values.extend(
+                        value
+                            .lines()
+                            .map(str::trim)
+                            .filter(|line| keep(line, options))
+                            .map(str::to_string),
+                    );
+                }
+                if keep(&value, options) {
+                    values.push(value);
+                } else {
+                    value.zeroize();
+                }
+            }
+        }
+    }
+    values
+}
+
+fn keep(value: &str, options: LoadOptions) -> bool {
+    value.len() >= options.min_length
+        && !(options.skip_benign && score_value(value).class == SecretClass::LikelyBenign)
+}
+
+/// Aho-Corasick automaton over a set of values
+///
+/// Transitions are stored sparsely: the edges of state `s` are
+/// `labels[edge_start[s]..edge_start[s + 1]]` (sorted) and the matching
+/// `targets`. State 0 is the root.
+pub(crate) struct KnownValues {
+    edge_start: Vec<u32>,
+    labels: Vec<u8>,
+    targets: Vec<u32>,
+    /// Failure link: the state of the longest proper suffix that is a prefix
+    fail: Vec<u32>,
+    /// Length of the longest value ending in each state (0 if none)
+    match_len: Vec<u32>,
+    /// Length of the prefix each state stands for
+    depth: Vec<u32>,
+    count: usize,
+}
+
+impl KnownValues {
+    /// Build the automaton, zeroizing `values` afterwards
+    ///
+    /// Every buffer is sized up front, so growing one never leaves a stale
+    /// copy of value bytes behind.
+    pub fn new(mut values: Vec<String>) -> Self {
+        values.sort_unstable();
+        let total: usize = values.iter().map(String::len).sum();
+        let longest = values.iter().map(String::len).max().unwrap_or(0);
+
+        // With sorted values, each value shares the path of the one before
+        // up to their common prefix, and every state's children are created
+        // in byte order. `parents` holds (parent, byte) of each new state.
+        let mut parents: Vec<(u32, u8)> = Vec::with_capacity(total);
+        let mut match_len = Vec::with_capacity(total + 1);
+        let mut depth = Vec::with_capacity(total + 1);
+        match_len.push(0u32);
+        depth.push(0u32);
+        let mut path: Vec<u32> = Vec::with_capacity(longest + 1);
+        path.push(0);
+        let mut count = 0;
+        let mut previous: &[u8] = &[];
+        for value in &values {
+            let bytes = value.as_bytes();
+            if bytes.is_empty() || bytes == previous {
+                continue;
+            }
+            let common = previous
+                .iter()
+                .zip(bytes)
+                .take_while(|(a, b)| a == b)
+                .count();
+            path.truncate(common + 1);
+            for &b in &bytes[common..] {
+                let state = parents.len() as u32 + 1;
+                parents.push((path[path.len() - 1], b));
+                match_len.push(0);
+                depth.push(path.len() as u32);
+                path.push(state);
+            }
+            match_len[path[path.len() - 1] as usize] = bytes.len() as u32;
+            count += 1;
+            previous = bytes;
+        }
+        path.zeroize();
+        values.zeroize();
+
+        // Edges grouped by parent; children keep their creation (byte) order
+        let states = parents.len() + 1;
+        let mut edge_start = vec![0u32; states + 1];
+        for &(parent, _) in &parents {
+            edge_start[parent as usize + 1] += 1;
+        }
+        for state in 0..states {
+            edge_start[state + 1] += edge_start[state];
+        }
+        let mut cursor = edge_start.clone();
+        let mut labels = vec![0u8; parents.len()];
+        let mut targets = vec![0u32; parents.len()];
+        for (i, &(parent, b)) in parents.iter().enumerate() {
+            let slot = cursor[parent as usize] as usize;
+            labels[slot] = b;
+            targets[slot] = i as u32 + 1;
+            cursor[parent as usize] += 1;
+        }
+        parents.zeroize();
+        cursor.zeroize();
+
+        let mut automaton = KnownValues {
+            edge_start,
+            labels,
+            targets,
+            fail: vec![0; states],
+            match_len,
+            depth,
+            count,
+        };
+
+        // Failure links in breadth-first order, so shallower states are done
+        let mut queue: VecDeque<u32> = VecDeque::with_capacity(states);
+        queue.push_back(0);
+        while let Some(state) = queue.pop_front() {
+            let start = automaton.edge_start[state as usize] as usize;
+            let end = automaton.edge_start[state as usize + 1] as usize;
+            for edge in start..end {
+                let (b, next) = (automaton.labels[edge], automaton.targets[edge]);
+                let link = match state {
+                    0 => 0,
+                    _ => automaton.next(automaton.fail[state as usize], b),
+                };
+                automaton.fail[next as usize] = link;
+                automaton.match_len[next as usize] =
+                    automaton.match_len[next as usize].max(automaton.match_len[link as usize]);
+                queue.push_back(next);
+            }
+        }
+        queue.clear();
+        automaton
+    }
+
+    /// Number of distinct values loaded
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    fn goto(&self, state: u32, b: u8) -> Option<u32> {
+        let start = self.edge_start[state as usize] as usize;
+        let end = self.edge_start[state as usize + 1] as usize;
+        self.labels[start..end]
+            .binary_search(&b)
+            .ok()
+            .map(|i| self.targets[start + i])
+    }
+
+    /// The state after reading `b` in `state`
+    fn next(&self, mut state: u32, b: u8) -> u32 {
+        loop {
+            if let Some(target) = self.goto(state, b) {
+                return target;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.fail[state as usize];
+        }
+    }
+}
+
+impl Drop for KnownValues {
+    fn drop(&mut self) {
+        self.edge_start.zeroize();
+        self.labels.zeroize();
+        self.targets.zeroize();
+        self.fail.zeroize();
+        self.match_len.zeroize();
+        self.depth.zeroize();
+        self.count = 0;
+    }
+}
+
+/// What a chunk produced
+#[derive(Debug, Default)]
+pub(crate) struct Output {
+    /// Redacted bytes that are safe to show; held-back bytes follow later
+    pub text: Vec<u8>,
+    /// Byte ranges of the stream (counted from its start) that hold values,
+    /// merged and in order; a range can start in an earlier chunk
+    pub spans: Vec<(usize, usize)>,
+}
+
+/// Masks known values in a stream fed in chunks
+pub(crate) struct StreamMasker {
+    values: KnownValues,
+    mask_char: u8,
+    state: u32,
+    /// Bytes read so far
+    consumed: usize,
+    /// Bytes read but not yet emitted, starting at `consumed - pending.len()`
+    pending: Vec<u8>,
+    /// Ranges to mask that reach into `pending`
+    masked: Vec<(usize, usize)>,
+}
+
+impl StreamMasker {
+    pub fn new(values: KnownValues, mask_char: u8) -> Self {
+        StreamMasker {
+            values,
+            mask_char,
+            state: 0,
+            consumed: 0,
+            pending: Vec::new(),
+            masked: Vec::new(),
+        }
+    }
+
+    pub fn values(&self) -> &KnownValues {
+        &self.values
+    }
+
+    /// Read a chunk; emits everything a later chunk cannot turn into a value
+    pub fn feed(&mut self, chunk: &[u8]) -> Output {
+        let mut spans: Vec<(usize, usize)> = Vec::new();
+        for (i, &b) in chunk.iter().enumerate() {
+            self.state = self.values.next(self.state, b);
+            let len = self.values.match_len[self.state as usize] as usize;
+            if len > 0 {
+                let end = self.consumed + i + 1;
+                add_span(&mut spans, (end - len, end));
+            }
+        }
+        self.consumed += chunk.len();
+        self.pending.extend_from_slice(chunk);
+        for &span in &spans {
+            add_span(&mut self.masked, span);
+        }
+
+        // A value completed later starts within the current prefix, but the
+        // part of it already masked is safe to emit. The prefix can reach
+        // back into bytes emitted earlier (masked as part of another value),
+        // so never go below what is still pending.
+        let emitted = self.consumed - self.pending.len();
+        let mut until =
+            (self.consumed - self.values.depth[self.state as usize] as usize).max(emitted);
+        if let Some(&(start, end)) = self.masked.last() {
+            if start <= until && until < end {
+                until = end;
+            }
+        }
+        Output {
+            text: self.emit(until),
+            spans,
+        }
+    }
+
+    /// End the stream, emitting the held-back bytes; the next chunk starts
+    /// a new stream at offset 0
+    pub fn finish(&mut self) -> Output {
+        let text = self.emit(self.consumed);
+        self.state = 0;
+        self.consumed = 0;
+        self.masked.clear();
+        Output {
+            text,
+            spans: Vec::new(),
+        }
+    }
+
+    /// Redact a whole text at once, as a stream of one chunk
+    pub fn redact(&mut self, text: &[u8]) -> Output {
+        self.finish();
+        let mut output = self.feed(text);
+        output.text.extend(self.finish().text);
+        output
+    }
+
+    /// Emit pending bytes up to stream offset `until`, masked
+    fn emit(&mut self, until: usize) -> Vec<u8> {
+        let base = self.consumed - self.pending.len();
+        let count = until - base;
+        let mut text = self.pending[..count].to_vec();
+        for &(start, end) in &self.masked {
+            let start = start.max(base) - base;
+            let end = end.min(until).saturating_sub(base);
+            if start < end {
+                text[start..end].fill(self.mask_char);
+            }
+        }
+        self.masked.retain(|&(_, end)| end > until);
+
+        // Shift the held-back bytes down and wipe what they leave behind
+        let remaining = self.pending.len() - count;
+        self.pending.copy_within(count.., 0);
+        self.pending[remaining..].zeroize();
+        self.pending.truncate(remaining);
+        text
+    }
+}
+
+impl Drop for StreamMasker {
+    fn drop(&mut self) {
+        self.pending.zeroize();
+    }
+}
+
+/// Add a range to sorted, merged ranges; a range can start before the ones
+/// added last (`abcd` completes after `bc` does)
+fn add_span(spans: &mut Vec<(usize, usize)>, (mut start, mut end): (usize, usize)) {
+    while let Some(&(last_start, last_end)) = spans.last() {
+        if last_end < start {
+            break;
+        }
+        start = start.min(last_start);
+        end = end.max(last_end);
+        spans.pop();
+    }
+    spans.push((start, end));
+}
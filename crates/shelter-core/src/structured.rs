@@ -0,0 +1,178 @@
+//! Shared helpers for structured config formats
+//!
+//! JSON, TOML and INI files have no flat keys: every scalar becomes an entry
+//! keyed by its dotted path (`database.password`, `servers[0].host`), with a
+//! value span covering the scalar as written, quotes included, like EDF.
+
+use std::borrow::Cow;
+
+use korni::{KeyValuePair, Position, QuoteType, Span};
+
+use crate::parse::{
+    is_inline_comment, line_starts, offset_to_line, pair_lines, Diagnostic, ParsedComment,
+    ParsedContent, ParsedPair, ValueEncoding,
+};
+use crate::types::ShelterDiagnosticCode;
+
+/// Append a key segment to a dotted path
+///
+/// Segments that would be ambiguous in a path (dots, brackets, quotes,
+/// whitespace) are written in double quotes.
+pub(crate) fn push_key(path: &mut String, key: &str) {
+    if !path.is_empty() {
+        path.push('.');
+    }
+
+    let bare = !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'$' | b'@'));
+    if bare {
+        path.push_str(key);
+        return;
+    }
+
+    path.push('"');
+    for c in key.chars() {
+        if c == '"' || c == '\\' {
+            path.push('\\');
+        }
+        path.push(c);
+    }
+    path.push('"');
+}
+
+/// Append an array index to a path
+pub(crate) fn push_index(path: &mut String, index: usize) {
+    path.push('[');
+    path.push_str(&index.to_string());
+    path.push(']');
+}
+
+/// Decode a `\uXXXX` (or `\UXXXXXXXX`) escape body
+pub(crate) fn decode_hex(hex: &str) -> Option<char> {
+    if hex.is_empty() || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+}
+
+/// Collects entries, comments and diagnostics for a structured document
+pub(crate) struct Collector<'a> {
+    pub input: &'a str,
+    line_starts: Vec<usize>,
+    pub pairs: Vec<ParsedPair<'a>>,
+    comments: Vec<ParsedComment>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Collector<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Collector {
+            input,
+            line_starts: line_starts(input),
+            pairs: Vec::new(),
+            comments: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Add a scalar at `path`
+    ///
+    /// `key_span` is the last key segment as written (empty for array
+    /// items), `value_span` the scalar including any quotes.
+    pub fn pair(
+        &mut self,
+        path: &str,
+        key_span: (usize, usize),
+        value: Cow<'a, str>,
+        value_span: (usize, usize),
+        quote: QuoteType,
+    ) {
+        let (value_start, value_end) = value_span;
+        let quoted = quote != QuoteType::None;
+
+        let kv = KeyValuePair {
+            key: Cow::Owned(path.to_string()),
+            key_span: Some(Span::from_offsets(key_span.0, key_span.1)),
+            value,
+            value_span: Some(Span::from_offsets(value_start, value_end)),
+            quote,
+            open_quote_pos: quoted.then(|| Position::from_offset(value_start)),
+            close_quote_pos: quoted.then(|| Position::from_offset(value_end - 1)),
+            equals_pos: None,
+            is_exported: false,
+            export_span: None,
+            is_comment: false,
+        };
+
+        let (_, value_end_line) = pair_lines(&self.line_starts, &kv);
+        // Array items have no key of their own; report the value's line
+        let line_number = offset_to_line(&self.line_starts, key_span.0.min(value_start));
+        self.pairs.push(ParsedPair {
+            kv: Box::new(kv),
+            line_number,
+            value_end_line,
+            inline_comment: None,
+            encoding: ValueEncoding::Plain,
+        });
+    }
+
+    /// Add a comment, attaching it to the last pair if it trails its value
+    pub fn comment(&mut self, start: usize, end: usize) {
+        let inline_of = self.pairs.last_mut().filter(|p| {
+            p.inline_comment.is_none()
+                && p.kv.value_span.is_some_and(|s| s.end.offset <= start)
+                && is_inline_comment(&self.line_starts, p.value_end_line, start)
+        });
+
+        let is_inline = match inline_of {
+            Some(pair) => {
+                pair.inline_comment = Some((start, end));
+                true
+            }
+            None => false,
+        };
+
+        self.comments.push(ParsedComment {
+            start,
+            end,
+            line_number: offset_to_line(&self.line_starts, start),
+            is_inline,
+        });
+    }
+
+    /// Report a syntax error
+    pub fn error(&mut self, code: ShelterDiagnosticCode, message: &str, start: usize, end: usize) {
+        self.diagnostics.push(Diagnostic {
+            code,
+            message: message.to_string(),
+            start: start.min(self.input.len()),
+            end: end.clamp(start, self.input.len()),
+        });
+    }
+
+    /// Report an error covering the rest of the line at `start`
+    pub fn line_error(&mut self, code: ShelterDiagnosticCode, message: &str, start: usize) {
+        let end = self.line_end(start);
+        self.error(code, message, start, end);
+    }
+
+    /// End of the line containing `pos` (at `\n`, `\r` or EOF)
+    pub fn line_end(&self, pos: usize) -> usize {
+        let pos = pos.min(self.input.len());
+        self.input[pos..]
+            .find(['\n', '\r'])
+            .map_or(self.input.len(), |i| pos + i)
+    }
+
+    pub fn finish(self) -> ParsedContent<'a> {
+        ParsedContent {
+            pairs: self.pairs,
+            comments: self.comments,
+            diagnostics: self.diagnostics,
+            skipped: Vec::new(),
+            line_starts: self.line_starts,
+        }
+    }
+}
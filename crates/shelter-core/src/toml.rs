@@ -0,0 +1,493 @@
+//! TOML parser for shelter-core
+//!
+//! Emits one entry per scalar keyed by its dotted path. Tables and dotted
+//! keys nest the path, arrays and arrays of tables add an index
+//! (`servers[0].host`). Dates and numbers are reported as written; a
+//! syntax error skips the rest of its line.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use korni::QuoteType;
+
+use crate::parse::ParsedContent;
+use crate::structured::{decode_hex, push_index, push_key, Collector};
+use crate::types::ShelterDiagnosticCode;
+
+/// Nesting limit for inline tables and arrays
+const MAX_DEPTH: usize = 256;
+
+/// A syntax error: code, message and byte range
+type SyntaxError = (ShelterDiagnosticCode, &'static str, usize, usize);
+
+/// A key segment: decoded text and its span as written (without quotes)
+type KeySegment<'a> = (Cow<'a, str>, (usize, usize));
+
+/// Parse a TOML document
+pub(crate) fn parse_toml(input: &str) -> ParsedContent<'_> {
+    let mut parser = Parser {
+        out: Collector::new(input),
+        bytes: input.as_bytes(),
+        pos: 0,
+        table: String::new(),
+        array_tables: HashMap::new(),
+    };
+
+    if input.starts_with('\u{FEFF}') {
+        parser.pos = '\u{FEFF}'.len_utf8();
+    }
+
+    while parser.pos < parser.bytes.len() {
+        match parser.line() {
+            Ok(()) => {}
+            Err((code, message, start, end)) => {
+                parser.out.error(code, message, start, end);
+                if code == ShelterDiagnosticCode::UnclosedQuote {
+                    break;
+                }
+                parser.pos = parser.out.line_end(parser.pos.max(start));
+            }
+        }
+    }
+
+    parser.out.finish()
+}
+
+struct Parser<'a> {
+    out: Collector<'a>,
+    bytes: &'a [u8],
+    pos: usize,
+    /// Path of the current `[table]`, array indices included
+    table: String,
+    /// Number of `[[array]]` tables seen per resolved path
+    array_tables: HashMap<String, usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn unexpected(&self, message: &'static str) -> SyntaxError {
+        let end = self.out.line_end(self.pos);
+        (ShelterDiagnosticCode::Expected, message, self.pos, end)
+    }
+
+    fn skip_blanks(&mut self) {
+        while self.pos < self.bytes.len() && matches!(self.bytes[self.pos], b' ' | b'\t') {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip blanks, newlines and comments
+    fn skip_trivia(&mut self) {
+        while self.pos < self.bytes.len() {
+            match self.bytes[self.pos] {
+                b' ' | b'\t' | b'\n' | b'\r' => self.pos += 1,
+                b'#' => self.comment(),
+                _ => break,
+            }
+        }
+    }
+
+    fn comment(&mut self) {
+        let end = self.out.line_end(self.pos);
+        self.out.comment(self.pos, end);
+        self.pos = end;
+    }
+
+    /// Parse one statement: blank, comment, table header or key/value
+    fn line(&mut self) -> Result<(), SyntaxError> {
+        self.skip_trivia();
+        match self.bytes.get(self.pos) {
+            None => return Ok(()),
+            Some(b'[') => self.header()?,
+            Some(_) => {
+                let (path, key_span) = self.key(&self.table.clone())?;
+                self.skip_blanks();
+                if self.bytes.get(self.pos) != Some(&b'=') {
+                    return Err(self.unexpected("Expected '='"));
+                }
+                self.pos += 1;
+                self.skip_blanks();
+                self.value(&path, key_span, 0)?;
+            }
+        }
+
+        // Only a comment may follow on the same line
+        self.skip_blanks();
+        match self.bytes.get(self.pos) {
+            None | Some(b'\n' | b'\r') => Ok(()),
+            Some(b'#') => {
+                self.comment();
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected("Expected end of line")),
+        }
+    }
+
+    /// `[table]` or `[[array.of.tables]]`
+    fn header(&mut self) -> Result<(), SyntaxError> {
+        let is_array = self.bytes.get(self.pos + 1) == Some(&b'[');
+        self.pos += if is_array { 2 } else { 1 };
+        self.skip_blanks();
+
+        let segments = self.key_segments()?;
+        self.skip_blanks();
+        let close: &[u8] = if is_array { b"]]" } else { b"]" };
+        if !self.bytes[self.pos..].starts_with(close) {
+            return Err(self.unexpected("Expected ']'"));
+        }
+        self.pos += close.len();
+
+        // Parents that are arrays of tables refer to their last element
+        let mut table = String::new();
+        let last = segments.len() - 1;
+        for (i, (segment, _)) in segments.iter().enumerate() {
+            push_key(&mut table, segment);
+            if i == last && is_array {
+                let count = self.array_tables.entry(table.clone()).or_insert(0);
+                push_index(&mut table, *count);
+                *count += 1;
+            } else if let Some(&count) = self.array_tables.get(&table) {
+                push_index(&mut table, count - 1);
+            }
+        }
+
+        self.table = table;
+        Ok(())
+    }
+
+    /// Dotted key relative to `base`: the full path and the last segment's span
+    fn key(&mut self, base: &str) -> Result<(String, (usize, usize)), SyntaxError> {
+        let segments = self.key_segments()?;
+        let mut path = base.to_string();
+        for (segment, _) in &segments {
+            push_key(&mut path, segment);
+        }
+        let span = segments
+            .last()
+            .map_or((self.pos, self.pos), |(_, span)| *span);
+        Ok((path, span))
+    }
+
+    fn key_segments(&mut self) -> Result<Vec<KeySegment<'a>>, SyntaxError> {
+        let mut segments = Vec::new();
+        loop {
+            let start = self.pos;
+            let segment = match self.bytes.get(start) {
+                Some(b'"') => {
+                    let key = self.basic_string()?;
+                    (key, (start + 1, self.pos - 1))
+                }
+                Some(b'\'') => {
+                    let key = self.literal_string()?;
+                    (key, (start + 1, self.pos - 1))
+                }
+                _ => {
+                    while self.pos < self.bytes.len()
+                        && (self.bytes[self.pos].is_ascii_alphanumeric()
+                            || matches!(self.bytes[self.pos], b'_' | b'-'))
+                    {
+                        self.pos += 1;
+                    }
+                    if self.pos == start {
+                        return Err(self.unexpected("Expected a key"));
+                    }
+                    let key = &self.out.input[start..self.pos];
+                    (Cow::Borrowed(key), (start, self.pos))
+                }
+            };
+            segments.push(segment);
+
+            self.skip_blanks();
+            if self.bytes.get(self.pos) != Some(&b'.') {
+                return Ok(segments);
+            }
+            self.pos += 1;
+            self.skip_blanks();
+        }
+    }
+
+    fn value(
+        &mut self,
+        path: &str,
+        key_span: (usize, usize),
+        depth: usize,
+    ) -> Result<(), SyntaxError> {
+        if depth > MAX_DEPTH {
+            return Err(self.unexpected("Nesting too deep"));
+        }
+
+        let start = self.pos;
+        let (value, quote) = match self.bytes.get(start) {
+            Some(b'{') => return self.inline_table(path, depth),
+            Some(b'[') => return self.array(path, depth),
+            Some(b'"') if self.bytes[start..].starts_with(b"\"\"\"") => {
+                (self.multiline_basic_string()?, QuoteType::Double)
+            }
+            Some(b'\'') if self.bytes[start..].starts_with(b"'''") => {
+                (self.multiline_literal_string()?, QuoteType::Single)
+            }
+            Some(b'"') => (self.basic_string()?, QuoteType::Double),
+            Some(b'\'') => (self.literal_string()?, QuoteType::Single),
+            Some(_) => (self.bare_value()?, QuoteType::None),
+            None => return Err(self.unexpected("Expected a value")),
+        };
+
+        self.out
+            .pair(path, key_span, value, (start, self.pos), quote);
+        Ok(())
+    }
+
+    /// Numbers, booleans and dates, as written
+    fn bare_value(&mut self) -> Result<Cow<'a, str>, SyntaxError> {
+        let start = self.pos;
+        let mut end = start;
+        while end < self.bytes.len() {
+            match self.bytes[end] {
+                b',' | b']' | b'}' | b'#' | b'\n' | b'\r' => break,
+                // A space is only part of a `date time` value
+                b' ' | b'\t'
+                    if !(self.bytes[start..end].contains(&b'-')
+                        && self.bytes.get(end + 1).is_some_and(u8::is_ascii_digit)) =>
+                {
+                    break
+                }
+                _ => end += 1,
+            }
+        }
+
+        let literal = self.out.input[start..end].trim_end();
+        let valid = !literal.is_empty()
+            && literal.bytes().all(|b| {
+                b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.' | b'_' | b':' | b' ')
+            });
+        if !valid {
+            return Err(self.unexpected("Expected a value"));
+        }
+
+        self.pos = start + literal.len();
+        Ok(Cow::Borrowed(literal))
+    }
+
+    fn inline_table(&mut self, path: &str, depth: usize) -> Result<(), SyntaxError> {
+        self.pos += 1;
+        self.skip_blanks();
+        if self.bytes.get(self.pos) == Some(&b'}') {
+            self.pos += 1;
+            return Ok(());
+        }
+
+        loop {
+            self.skip_blanks();
+            let (key_path, key_span) = self.key(path)?;
+            self.skip_blanks();
+            if self.bytes.get(self.pos) != Some(&b'=') {
+                return Err(self.unexpected("Expected '='"));
+            }
+            self.pos += 1;
+            self.skip_blanks();
+            self.value(&key_path, key_span, depth + 1)?;
+
+            self.skip_blanks();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.unexpected("Expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self, path: &str, depth: usize) -> Result<(), SyntaxError> {
+        self.pos += 1;
+        let mut index = 0;
+        let mut item = String::with_capacity(path.len() + 4);
+
+        loop {
+            self.skip_trivia();
+            if self.bytes.get(self.pos) == Some(&b']') {
+                self.pos += 1;
+                return Ok(());
+            }
+
+            item.clear();
+            item.push_str(path);
+            push_index(&mut item, index);
+            self.value(&item, (self.pos, self.pos), depth + 1)?;
+            index += 1;
+
+            self.skip_trivia();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {}
+                _ => return Err(self.unexpected("Expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn unclosed(&self, start: usize, message: &'static str) -> SyntaxError {
+        (
+            ShelterDiagnosticCode::UnclosedQuote,
+            message,
+            start,
+            self.bytes.len(),
+        )
+    }
+
+    /// `'...'`: no escapes, single line
+    fn literal_string(&mut self) -> Result<Cow<'a, str>, SyntaxError> {
+        let start = self.pos;
+        let content = start + 1;
+        let end = self.bytes[content..]
+            .iter()
+            .position(|&b| b == b'\'' || b == b'\n')
+            .map(|i| content + i)
+            .filter(|&i| self.bytes[i] == b'\'')
+            .ok_or_else(|| self.unclosed(start, "Unclosed single quote"))?;
+
+        self.pos = end + 1;
+        Ok(Cow::Borrowed(&self.out.input[content..end]))
+    }
+
+    /// `'''...'''`: no escapes, a newline right after the opening quotes is trimmed
+    fn multiline_literal_string(&mut self) -> Result<Cow<'a, str>, SyntaxError> {
+        let start = self.pos;
+        let content = start + 3;
+        let close = self.out.input[content..]
+            .find("'''")
+            .map(|i| content + i)
+            .ok_or_else(|| self.unclosed(start, "Unclosed single quote"))?;
+
+        // Up to two quotes may directly precede the closing delimiter
+        let mut end = close;
+        while self.bytes.get(end + 3) == Some(&b'\'') && end < close + 2 {
+            end += 1;
+        }
+        self.pos = end + 3;
+
+        let text = &self.out.input[content..end];
+        Ok(Cow::Borrowed(trim_leading_newline(text)))
+    }
+
+    /// `"..."`: escapes, single line
+    fn basic_string(&mut self) -> Result<Cow<'a, str>, SyntaxError> {
+        let start = self.pos;
+        self.escaped(start, start + 1, false)
+    }
+
+    /// `"""..."""`: escapes, line-ending backslashes, leading newline trimmed
+    fn multiline_basic_string(&mut self) -> Result<Cow<'a, str>, SyntaxError> {
+        let start = self.pos;
+        let mut content = start + 3;
+        if self.bytes[content..].starts_with(b"\r\n") {
+            content += 2;
+        } else if self.bytes.get(content) == Some(&b'\n') {
+            content += 1;
+        }
+        self.escaped(start, content, true)
+    }
+
+    /// Decode a basic string from `content` up to its closing delimiter
+    fn escaped(
+        &mut self,
+        start: usize,
+        content: usize,
+        multiline: bool,
+    ) -> Result<Cow<'a, str>, SyntaxError> {
+        let input = self.out.input;
+        let mut owned: Option<String> = None;
+        let mut chunk = content;
+        let mut pos = content;
+
+        while pos < self.bytes.len() {
+            match self.bytes[pos] {
+                b'"' if !multiline || self.bytes[pos..].starts_with(b"\"\"\"") => {
+                    // Up to two quotes may directly precede the closing delimiter
+                    let mut end = pos;
+                    if multiline {
+                        while self.bytes.get(end + 3) == Some(&b'"') && end < pos + 2 {
+                            end += 1;
+                        }
+                    }
+                    self.pos = end + if multiline { 3 } else { 1 };
+                    return Ok(match owned {
+                        Some(mut s) => {
+                            s.push_str(&input[chunk..end]);
+                            Cow::Owned(s)
+                        }
+                        None => Cow::Borrowed(&input[content..end]),
+                    });
+                }
+                b'\\' => {
+                    let s = owned.get_or_insert_with(String::new);
+                    s.push_str(&input[chunk..pos]);
+
+                    let escape = self.bytes.get(pos + 1).copied();
+                    pos += match escape {
+                        Some(b'u') | Some(b'U') => {
+                            let digits = if escape == Some(b'u') { 4 } else { 8 };
+                            let c = input
+                                .get(pos + 2..pos + 2 + digits)
+                                .and_then(decode_hex)
+                                .ok_or((
+                                    ShelterDiagnosticCode::Generic,
+                                    "Invalid unicode escape",
+                                    pos,
+                                    (pos + 2 + digits).min(input.len()),
+                                ))?;
+                            s.push(c);
+                            2 + digits
+                        }
+                        // Line-ending backslash: trim the newline and following whitespace
+                        Some(b' ' | b'\t' | b'\n' | b'\r') if multiline => {
+                            let rest = &input[pos + 1..];
+                            let trimmed = rest.trim_start_matches([' ', '\t']);
+                            if !trimmed.starts_with(['\n', '\r']) {
+                                return Err((
+                                    ShelterDiagnosticCode::Generic,
+                                    "Invalid escape",
+                                    pos,
+                                    pos + 2,
+                                ));
+                            }
+                            let skipped = rest.len() - rest.trim_start().len();
+                            1 + skipped
+                        }
+                        Some(b) => {
+                            s.push(match b {
+                                b'b' => '\x08',
+                                b't' => '\t',
+                                b'n' => '\n',
+                                b'f' => '\x0c',
+                                b'r' => '\r',
+                                b'e' => '\x1b',
+                                b'"' | b'\\' => b as char,
+                                _ => {
+                                    return Err((
+                                        ShelterDiagnosticCode::Generic,
+                                        "Invalid escape",
+                                        pos,
+                                        pos + 2,
+                                    ))
+                                }
+                            });
+                            2
+                        }
+                        None => break,
+                    };
+                    chunk = pos;
+                }
+                b'\n' if !multiline => break,
+                _ => pos += 1,
+            }
+        }
+
+        Err(self.unclosed(start, "Unclosed double quote"))
+    }
+}
+
+fn trim_leading_newline(text: &str) -> &str {
+    text.strip_prefix("\r\n")
+        .or_else(|| text.strip_prefix('\n'))
+        .unwrap_or(text)
+}
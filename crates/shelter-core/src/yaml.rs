@@ -0,0 +1,1181 @@
+//! YAML front end for shelter-core
+//!
+//! Reads the block and flow YAML found in docker-compose files, GitHub
+//! Actions workflows, Helm values and kubeconfigs, and reports the scalars
+//! that carry secrets, keyed by dotted path (`services.web.environment.TOKEN`,
+//! `users[0].user.token`). A scalar carries a secret when it sits under an
+//! env-style block (`environment`, `env`, `secrets`, `credentials`), when
+//! its key names a secret (`password`, `password2`, `client-key-data`, ...),
+//! or when the value matches a known credential format or is a URL with a
+//! password.
+//!
+//! Compose `environment` and `args` lists (`- KEY=value`) are split so the
+//! entry is keyed by the variable and its span covers only the value. Block
+//! scalars (`|`, `>`) span from their first to their last content line.
+//!
+//! In Kubernetes `Secret` documents every `data` entry is reported with its
+//! base64 decoded, so masks and peeks reflect the real secret while the span
+//! still covers the encoded text. `stringData` entries are plain text.
+
+use std::borrow::Cow;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use korni::QuoteType;
+
+use crate::detect::{detect, CredentialKind};
+use crate::parse::{ParsedContent, ValueEncoding};
+use crate::score::has_password;
+use crate::structured::{decode_hex, push_index, push_key, Collector};
+use crate::types::ShelterDiagnosticCode;
+
+/// Nesting limit for block and flow collections
+const MAX_DEPTH: usize = 256;
+
+/// Keys whose whole subtree is treated as secret-bearing
+const SECRET_PARENTS: [&str; 5] = ["environment", "env", "secrets", "credentials", "stringData"];
+
+/// Words that mark a key as naming a secret (plurals included)
+const SECRET_WORDS: [&str; 11] = [
+    "password",
+    "passwd",
+    "pwd",
+    "passphrase",
+    "secret",
+    "token",
+    "apikey",
+    "key",
+    "credential",
+    "auth",
+    "dsn",
+];
+
+/// A syntax error: code, message and byte range
+type SyntaxError = (ShelterDiagnosticCode, &'static str, usize, usize);
+
+/// A decoded scalar and its span as written
+struct Scalar<'a> {
+    value: Cow<'a, str>,
+    start: usize,
+    end: usize,
+    quote: QuoteType,
+}
+
+/// Where a node appears
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Place {
+    /// Document root
+    Root,
+    /// Value of a mapping key
+    Value,
+    /// Sequence entry
+    Entry,
+    /// Entry of a compose `environment`/`args` list
+    EnvEntry,
+}
+
+/// Parse a YAML stream
+pub(crate) fn parse_yaml(input: &str) -> ParsedContent<'_> {
+    let mut parser = Parser {
+        out: Collector::new(input),
+        bytes: input.as_bytes(),
+        pos: 0,
+        depth: 0,
+        secret: false,
+        data_pairs: Vec::new(),
+    };
+
+    if input.starts_with('\u{FEFF}') {
+        parser.pos = '\u{FEFF}'.len_utf8();
+    }
+
+    while parser.pos < parser.bytes.len() {
+        let result = parser.document();
+        parser.finish_document();
+        if let Err((code, message, start, end)) = result {
+            parser.out.error(code, message, start, end);
+            if code == ShelterDiagnosticCode::UnclosedQuote {
+                break;
+            }
+            parser.skip_document();
+        }
+    }
+
+    parser.out.finish()
+}
+
+/// Split a path into key segments, dropping array indices and key quotes
+fn path_segments(path: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = path.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => current.extend(chars.next()),
+            '.' if !quoted => segments.push(std::mem::take(&mut current)),
+            '[' if !quoted => {
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    segments.push(current);
+    segments.retain(|s| !s.is_empty());
+    segments
+}
+
+/// Whether a key names a secret: `password`, `client-key-data`, `apiToken`, ...
+fn is_secret_name(key: &str) -> bool {
+    // Split on separators and lower-to-upper case changes
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in key.chars() {
+        if !c.is_ascii_alphanumeric() {
+            words.push(std::mem::take(&mut word));
+            prev_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && prev_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        word.push(c.to_ascii_lowercase());
+    }
+    words.push(word);
+
+    words.iter().any(|w| {
+        // `password2`, `tokens`
+        let w = w.trim_end_matches(|c: char| c.is_ascii_digit());
+        let w = w.strip_suffix('s').unwrap_or(w);
+        SECRET_WORDS.contains(&w)
+            || ["password", "secret", "token", "apikey"]
+                .iter()
+                .any(|suffix| w.ends_with(suffix))
+    })
+}
+
+/// Whether the scalar at `path` carries a secret
+fn is_secret_bearing(path: &str, value: &str) -> bool {
+    let segments = path_segments(path);
+    if let Some((last, parents)) = segments.split_last() {
+        let under_secret_parent = parents
+            .iter()
+            .any(|p| SECRET_PARENTS.iter().any(|s| s.eq_ignore_ascii_case(p)));
+        // `env: [{name: X, value: Y}]` - the name is not the secret
+        if under_secret_parent && last != "name" {
+            return true;
+        }
+        if is_secret_name(last) {
+            return true;
+        }
+    }
+    detect(value) != CredentialKind::None
+        || value
+            .split_once("://")
+            .is_some_and(|(_, rest)| has_password(rest))
+}
+
+struct Parser<'a> {
+    out: Collector<'a>,
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+    /// The current document has `kind: Secret`
+    secret: bool,
+    /// Indices of the current document's `data` pairs, and whether each
+    /// would be reported outside a Secret
+    data_pairs: Vec<(usize, bool)>,
+}
+
+impl<'a> Parser<'a> {
+    // =========================================================================
+    //  Positions
+    // =========================================================================
+
+    fn unexpected(&self, message: &'static str) -> SyntaxError {
+        let end = self.out.line_end(self.pos);
+        (ShelterDiagnosticCode::Expected, message, self.pos, end)
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    #[inline]
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.bytes[..pos]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1)
+    }
+
+    fn column(&self) -> usize {
+        self.pos - self.line_start(self.pos)
+    }
+
+    /// Whether `b` ends a token (whitespace, line end or end of input)
+    #[inline]
+    fn is_break(b: Option<u8>) -> bool {
+        matches!(b, None | Some(b' ' | b'\t' | b'\n' | b'\r'))
+    }
+
+    fn at_document_marker(&self) -> bool {
+        self.column() == 0
+            && (self.bytes[self.pos..].starts_with(b"---")
+                || self.bytes[self.pos..].starts_with(b"..."))
+            && Self::is_break(self.peek_at(3))
+    }
+
+    fn at_sequence_entry(&self) -> bool {
+        self.peek() == Some(b'-') && Self::is_break(self.peek_at(1))
+    }
+
+    /// At the end of the line's content (a comment may follow)
+    fn at_line_end(&self) -> bool {
+        match self.peek() {
+            None | Some(b'\n' | b'\r') => true,
+            Some(b'#') => self.pos == 0 || matches!(self.bytes[self.pos - 1], b' ' | b'\t' | b'\n'),
+            _ => false,
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn comment(&mut self) {
+        let end = self.out.line_end(self.pos);
+        self.out.comment(self.pos, end);
+        self.pos = end;
+    }
+
+    /// Skip blank lines, comments and directives up to the next content
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some(b'#') => self.comment(),
+                Some(b'\n' | b'\r') => self.pos += 1,
+                Some(b'%') if self.column() == 0 => self.pos = self.out.line_end(self.pos),
+                _ => return,
+            }
+        }
+    }
+
+    /// Skip the rest of the current document after an error
+    fn skip_document(&mut self) {
+        while self.pos < self.bytes.len() {
+            self.pos = self.out.line_end(self.pos);
+            while matches!(self.peek(), Some(b'\n' | b'\r')) {
+                self.pos += 1;
+            }
+            if self.pos < self.bytes.len() && self.at_document_marker() {
+                return;
+            }
+        }
+    }
+
+    /// Require the rest of the line to be blank or a comment
+    fn end_of_line(&mut self) -> Result<(), SyntaxError> {
+        self.skip_spaces();
+        if !self.at_line_end() {
+            return Err(self.unexpected("Expected end of line"));
+        }
+        if self.peek() == Some(b'#') {
+            self.comment();
+        }
+        Ok(())
+    }
+
+    /// Skip `&anchor` and `!tag` node properties
+    fn skip_properties(&mut self) {
+        while matches!(self.peek(), Some(b'&' | b'!')) {
+            while !Self::is_break(self.peek()) {
+                self.pos += 1;
+            }
+            self.skip_spaces();
+        }
+    }
+
+    // =========================================================================
+    //  Documents and block collections
+    // =========================================================================
+
+    fn document(&mut self) -> Result<(), SyntaxError> {
+        self.skip_trivia();
+        if self.pos >= self.bytes.len() {
+            return Ok(());
+        }
+
+        if self.at_document_marker() {
+            let is_end = self.bytes[self.pos] == b'.';
+            self.pos += 3;
+            if is_end {
+                return self.end_of_line();
+            }
+        }
+
+        self.node("", (self.pos, self.pos), -1, Place::Root, false)?;
+
+        self.skip_trivia();
+        if self.pos < self.bytes.len() && !self.at_document_marker() {
+            return Err(self.unexpected("Unexpected content"));
+        }
+        Ok(())
+    }
+
+    /// Parse the node at `pos`, owned by a parent at column `parent`
+    ///
+    /// `env_list` marks the value of a compose `environment`/`args` key.
+    fn node(
+        &mut self,
+        path: &str,
+        key_span: (usize, usize),
+        parent: isize,
+        place: Place,
+        env_list: bool,
+    ) -> Result<(), SyntaxError> {
+        if self.depth > MAX_DEPTH {
+            return Err(self.unexpected("Nesting too deep"));
+        }
+
+        self.skip_spaces();
+        self.skip_properties();
+
+        if self.at_line_end() {
+            if self.peek() == Some(b'#') {
+                self.comment();
+            }
+            self.skip_trivia();
+            if self.pos >= self.bytes.len() || self.at_document_marker() {
+                return Ok(());
+            }
+
+            // A sequence may sit at its key's column
+            let column = self.column() as isize;
+            let sequence = self.at_sequence_entry();
+            let nested = column > parent || (column == parent && sequence && place == Place::Value);
+            if !nested {
+                // Empty (null) value; the parent continues at this line
+                return Ok(());
+            }
+
+            self.depth += 1;
+            let result = if sequence {
+                self.block_sequence(path, column, env_list)
+            } else if self.at_mapping_key() {
+                self.block_mapping(path, column)
+            } else {
+                // A scalar or flow collection below its key, e.g. `key:\n  |+`
+                self.node(path, key_span, parent, place, env_list)
+            };
+            self.depth -= 1;
+            return result;
+        }
+
+        // Compact collections start on the line of their parent
+        if place != Place::Value {
+            let column = self.column() as isize;
+            let compact = if self.at_sequence_entry() {
+                Some(true)
+            } else if self.at_mapping_key() {
+                Some(false)
+            } else {
+                None
+            };
+            if let Some(sequence) = compact {
+                self.depth += 1;
+                let result = if sequence {
+                    self.block_sequence(path, column, false)
+                } else {
+                    self.block_mapping(path, column)
+                };
+                self.depth -= 1;
+                return result;
+            }
+        }
+
+        let scalar = match self.peek() {
+            Some(b'|' | b'>') => self.block_scalar(parent)?,
+            Some(b'[' | b'{') => {
+                self.depth += 1;
+                let result = self.flow_collection(path);
+                self.depth -= 1;
+                result?;
+                return self.end_of_line();
+            }
+            Some(b'*') => {
+                // Aliases repeat a node reported where it was anchored
+                while !Self::is_break(self.peek()) {
+                    self.pos += 1;
+                }
+                return self.end_of_line();
+            }
+            Some(b'"' | b'\'') => {
+                let scalar = self.quoted()?;
+                self.end_of_line()?;
+                scalar
+            }
+            _ => self.plain(parent),
+        };
+
+        self.emit(path, key_span, scalar, place);
+        Ok(())
+    }
+
+    fn block_mapping(&mut self, path: &str, indent: isize) -> Result<(), SyntaxError> {
+        loop {
+            self.skip_trivia();
+            if self.pos >= self.bytes.len() || self.at_document_marker() {
+                return Ok(());
+            }
+
+            let column = self.column() as isize;
+            if column < indent || (column == indent && self.at_sequence_entry()) {
+                return Ok(());
+            }
+            if column > indent {
+                return Err(self.unexpected("Unexpected indentation"));
+            }
+            if self.peek() == Some(b'?') && Self::is_break(self.peek_at(1)) {
+                return Err(self.unexpected("Complex keys are not supported"));
+            }
+
+            let (key, key_span) = self.mapping_key()?;
+            let mut child = path.to_string();
+            push_key(&mut child, &key);
+            let env_list = key == "environment" || key == "args";
+            self.node(&child, key_span, indent, Place::Value, env_list)?;
+        }
+    }
+
+    fn block_sequence(
+        &mut self,
+        path: &str,
+        indent: isize,
+        env_list: bool,
+    ) -> Result<(), SyntaxError> {
+        let place = if env_list {
+            Place::EnvEntry
+        } else {
+            Place::Entry
+        };
+        let mut index = 0;
+
+        loop {
+            self.skip_trivia();
+            if self.pos >= self.bytes.len() || self.at_document_marker() {
+                return Ok(());
+            }
+
+            let column = self.column() as isize;
+            if column < indent || (column == indent && !self.at_sequence_entry()) {
+                return Ok(());
+            }
+            if column > indent {
+                return Err(self.unexpected("Unexpected indentation"));
+            }
+
+            self.pos += 1;
+            let mut item = path.to_string();
+            push_index(&mut item, index);
+            self.node(&item, (self.pos, self.pos), indent, place, false)?;
+            index += 1;
+        }
+    }
+
+    /// Whether a `key:` starts at `pos` on this line
+    fn at_mapping_key(&self) -> bool {
+        let line_end = self.out.line_end(self.pos);
+        let mut i = self.pos;
+
+        match self.bytes.get(i) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                i += 1;
+                while i < line_end {
+                    if self.bytes[i] == b'\\' && quote == b'"' {
+                        i += 2;
+                        continue;
+                    }
+                    if self.bytes[i] == quote {
+                        if quote == b'\'' && self.bytes.get(i + 1) == Some(&b'\'') {
+                            i += 2;
+                            continue;
+                        }
+                        break;
+                    }
+                    i += 1;
+                }
+                if i >= line_end {
+                    return false;
+                }
+                i += 1;
+                while i < line_end && matches!(self.bytes[i], b' ' | b'\t') {
+                    i += 1;
+                }
+                self.bytes.get(i) == Some(&b':')
+                    && (i + 1 >= line_end || matches!(self.bytes[i + 1], b' ' | b'\t'))
+            }
+            Some(b'[' | b'{' | b'|' | b'>' | b'*' | b'&' | b'!' | b'%' | b'@' | b'`' | b'#')
+            | None => false,
+            Some(_) => {
+                while i < line_end {
+                    match self.bytes[i] {
+                        b':' if i + 1 >= line_end || matches!(self.bytes[i + 1], b' ' | b'\t') => {
+                            return true
+                        }
+                        b'#' if matches!(self.bytes[i - 1], b' ' | b'\t') => return false,
+                        _ => i += 1,
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    /// Consume `key:`, returning the key and its span as written (without quotes)
+    fn mapping_key(&mut self) -> Result<(Cow<'a, str>, (usize, usize)), SyntaxError> {
+        if !self.at_mapping_key() {
+            return Err(self.unexpected("Expected a mapping key"));
+        }
+
+        let start = self.pos;
+        let (key, span) = match self.peek() {
+            Some(b'"' | b'\'') => {
+                let scalar = self.quoted()?;
+                (scalar.value, (start + 1, self.pos - 1))
+            }
+            _ => {
+                while !(self.bytes[self.pos] == b':' && Self::is_break(self.peek_at(1))) {
+                    self.pos += 1;
+                }
+                let key = self.out.input[start..self.pos].trim_end();
+                (Cow::Borrowed(key), (start, start + key.len()))
+            }
+        };
+
+        self.skip_spaces();
+        self.pos += 1; // ':'
+        Ok((key, span))
+    }
+
+    // =========================================================================
+    //  Scalars
+    // =========================================================================
+
+    /// Plain scalar, folded over more-indented continuation lines
+    fn plain(&mut self, parent: isize) -> Scalar<'a> {
+        let input = self.out.input;
+        let start = self.pos;
+        let mut end = self.plain_line_end(start);
+        let mut owned: Option<String> = None;
+        let mut scan = end;
+
+        loop {
+            // A comment ends the scalar
+            let line_end = self.out.line_end(scan);
+            if input[scan..line_end].trim_start().starts_with('#') {
+                break;
+            }
+
+            // Find the next non-blank line
+            let mut next = line_end;
+            let mut blank_lines = 0;
+            let content = loop {
+                if next >= self.bytes.len() {
+                    break None;
+                }
+                next += if input[next..].starts_with("\r\n") {
+                    2
+                } else {
+                    1
+                };
+                let line_end = self.out.line_end(next);
+                let text = &input[next..line_end];
+                let trimmed = text.trim_start_matches([' ', '\t']);
+                if trimmed.is_empty() {
+                    blank_lines += 1;
+                    next = line_end;
+                    continue;
+                }
+                let indent = (text.len() - trimmed.len()) as isize;
+                let is_marker =
+                    indent == 0 && (trimmed.starts_with("---") || trimmed.starts_with("..."));
+                // `key: value` more indented than its parent is an error, not text
+                let is_pair = trimmed.contains(": ") || trimmed.trim_end().ends_with(':');
+                if indent <= parent || trimmed.starts_with('#') || is_marker || is_pair {
+                    break None;
+                }
+                break Some(line_end - trimmed.len());
+            };
+
+            let Some(content_start) = content else {
+                break;
+            };
+
+            let line_end = self.plain_line_end(content_start);
+            let s = owned.get_or_insert_with(|| input[start..end].to_string());
+            if blank_lines == 0 {
+                s.push(' ');
+            } else {
+                s.extend(std::iter::repeat_n('\n', blank_lines));
+            }
+            s.push_str(&input[content_start..line_end]);
+            end = line_end;
+            scan = line_end;
+        }
+
+        self.pos = end;
+        Scalar {
+            value: owned.map_or(Cow::Borrowed(&input[start..end]), Cow::Owned),
+            start,
+            end,
+            quote: QuoteType::None,
+        }
+    }
+
+    /// End of a plain scalar's text on the line starting at `pos`
+    fn plain_line_end(&self, pos: usize) -> usize {
+        let line_end = self.out.line_end(pos);
+        let mut end = (pos..line_end)
+            .find(|&i| {
+                self.bytes[i] == b'#' && i > pos && matches!(self.bytes[i - 1], b' ' | b'\t')
+            })
+            .unwrap_or(line_end);
+        while end > pos && matches!(self.bytes[end - 1], b' ' | b'\t') {
+            end -= 1;
+        }
+        end
+    }
+
+    /// Single or double quoted scalar, possibly spanning lines
+    fn quoted(&mut self) -> Result<Scalar<'a>, SyntaxError> {
+        let input = self.out.input;
+        let start = self.pos;
+        let quote = self.bytes[start];
+        let mut value = String::new();
+        let mut pos = start + 1;
+        let mut simple = true;
+
+        loop {
+            let Some(&b) = self.bytes.get(pos) else {
+                return Err((
+                    ShelterDiagnosticCode::UnclosedQuote,
+                    if quote == b'"' {
+                        "Unclosed double quote"
+                    } else {
+                        "Unclosed single quote"
+                    },
+                    start,
+                    self.bytes.len(),
+                ));
+            };
+
+            match b {
+                b'\'' if quote == b'\'' && self.bytes.get(pos + 1) == Some(&b'\'') => {
+                    simple = false;
+                    value.push('\'');
+                    pos += 2;
+                }
+                _ if b == quote => break,
+                b'\\' if quote == b'"' => {
+                    simple = false;
+                    pos = self.escape(pos, &mut value)?;
+                }
+                b'\n' | b'\r' => {
+                    // Line folding: one break is a space, blank lines are newlines
+                    simple = false;
+                    let trimmed = value.trim_end_matches([' ', '\t']).len();
+                    value.truncate(trimmed);
+                    let mut breaks = 0;
+                    while let Some(&c) = self.bytes.get(pos) {
+                        match c {
+                            b'\n' => {
+                                breaks += 1;
+                                pos += 1;
+                            }
+                            b' ' | b'\t' | b'\r' => pos += 1,
+                            _ => break,
+                        }
+                    }
+                    if breaks <= 1 {
+                        value.push(' ');
+                    } else {
+                        value.extend(std::iter::repeat_n('\n', breaks - 1));
+                    }
+                }
+                _ => {
+                    let c = input[pos..].chars().next().unwrap_or('\u{FFFD}');
+                    value.push(c);
+                    pos += c.len_utf8();
+                }
+            }
+        }
+
+        self.pos = pos + 1;
+        let value = if simple {
+            Cow::Borrowed(&input[start + 1..pos])
+        } else {
+            Cow::Owned(value)
+        };
+
+        Ok(Scalar {
+            value,
+            start,
+            end: self.pos,
+            quote: if quote == b'"' {
+                QuoteType::Double
+            } else {
+                QuoteType::Single
+            },
+        })
+    }
+
+    /// Decode the double-quoted escape at `pos`, returning the position after it
+    fn escape(&self, pos: usize, value: &mut String) -> Result<usize, SyntaxError> {
+        let input = self.out.input;
+        let invalid = (
+            ShelterDiagnosticCode::Generic,
+            "Invalid escape",
+            pos,
+            (pos + 2).min(input.len()),
+        );
+        let Some(&b) = self.bytes.get(pos + 1) else {
+            return Err(invalid);
+        };
+
+        let hex_len = match b {
+            b'x' => 2,
+            b'u' => 4,
+            b'U' => 8,
+            _ => 0,
+        };
+        if hex_len > 0 {
+            let c = input
+                .get(pos + 2..pos + 2 + hex_len)
+                .and_then(decode_hex)
+                .ok_or(invalid)?;
+            value.push(c);
+            return Ok(pos + 2 + hex_len);
+        }
+
+        // Escaped line break: join the lines without a space
+        if matches!(b, b'\n' | b'\r') {
+            let mut next = pos + 1;
+            while matches!(self.bytes.get(next), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+                next += 1;
+            }
+            return Ok(next);
+        }
+
+        value.push(match b {
+            b'0' => '\0',
+            b'a' => '\x07',
+            b'b' => '\x08',
+            b't' | b'\t' => '\t',
+            b'n' => '\n',
+            b'v' => '\x0b',
+            b'f' => '\x0c',
+            b'r' => '\r',
+            b'e' => '\x1b',
+            b' ' => ' ',
+            b'"' => '"',
+            b'/' => '/',
+            b'\\' => '\\',
+            b'N' => '\u{85}',
+            b'_' => '\u{a0}',
+            b'L' => '\u{2028}',
+            b'P' => '\u{2029}',
+            _ => return Err(invalid),
+        });
+        Ok(pos + 2)
+    }
+
+    /// `|` literal or `>` folded block scalar
+    ///
+    /// The span runs from the first content character to the end of the
+    /// last non-blank content line.
+    fn block_scalar(&mut self, parent: isize) -> Result<Scalar<'a>, SyntaxError> {
+        let input = self.out.input;
+        let folded = self.bytes[self.pos] == b'>';
+        self.pos += 1;
+
+        let mut chomp = b' ';
+        let mut explicit = None;
+        for _ in 0..2 {
+            match self.peek() {
+                Some(c @ (b'+' | b'-')) => chomp = c,
+                Some(c @ b'1'..=b'9') => explicit = Some((c - b'0') as usize),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        self.end_of_line()?;
+        let header_end = self.pos;
+
+        // Content lines: blank, or indented at least as much as the first one
+        let base = parent.max(0) as usize;
+        let mut indent = explicit.map(|n| base + n);
+        let mut lines: Vec<(usize, usize)> = Vec::new();
+        let mut pos = header_end;
+        while pos < self.bytes.len() {
+            pos += if input[pos..].starts_with("\r\n") {
+                2
+            } else {
+                1
+            };
+            let line_end = self.out.line_end(pos);
+            let text = &input[pos..line_end];
+            let spaces = text.len() - text.trim_start_matches(' ').len();
+
+            if text.trim().is_empty() {
+                lines.push((line_end.min(pos + indent.unwrap_or(spaces)), line_end));
+                pos = line_end;
+                continue;
+            }
+
+            let required = *indent.get_or_insert(spaces);
+            if spaces < required || (spaces as isize) <= parent {
+                break;
+            }
+            lines.push((pos + required, line_end));
+            pos = line_end;
+        }
+
+        // Trailing blank lines only matter for `+` chomping
+        let last = lines
+            .iter()
+            .rposition(|&(s, e)| !input[s..e].trim().is_empty());
+        let Some(last) = last else {
+            self.pos = header_end;
+            return Ok(Scalar {
+                value: Cow::Borrowed(""),
+                start: header_end,
+                end: header_end,
+                quote: QuoteType::None,
+            });
+        };
+        let trailing = lines.len() - 1 - last;
+        let content = &lines[..=last];
+        let first = content
+            .iter()
+            .position(|&(s, e)| !input[s..e].trim().is_empty())
+            .unwrap_or(0);
+
+        let mut value = String::new();
+        let mut previous_more_indented: Option<bool> = None;
+        let mut blanks = 0;
+        for &(s, e) in content {
+            let text = &input[s..e];
+            if text.trim().is_empty() {
+                blanks += 1;
+                continue;
+            }
+            let more_indented = text.starts_with([' ', '\t']);
+            match previous_more_indented {
+                None => value.extend(std::iter::repeat_n('\n', blanks)),
+                Some(previous) if folded && !previous && !more_indented => {
+                    if blanks == 0 {
+                        value.push(' ');
+                    } else {
+                        value.extend(std::iter::repeat_n('\n', blanks));
+                    }
+                }
+                Some(_) => value.extend(std::iter::repeat_n('\n', blanks + 1)),
+            }
+            value.push_str(text);
+            previous_more_indented = Some(more_indented);
+            blanks = 0;
+        }
+
+        let ends_with_break = content[last].1 < self.bytes.len();
+        match chomp {
+            b'-' => {}
+            b'+' => value.extend(std::iter::repeat_n(
+                '\n',
+                usize::from(ends_with_break) + trailing,
+            )),
+            _ if ends_with_break => value.push('\n'),
+            _ => {}
+        }
+
+        let (start, end) = (lines[first].0, content[last].1);
+        self.pos = end;
+        Ok(Scalar {
+            value: Cow::Owned(value),
+            start,
+            end,
+            quote: QuoteType::None,
+        })
+    }
+
+    // =========================================================================
+    //  Flow collections
+    // =========================================================================
+
+    /// Skip whitespace, line breaks and comments inside a flow collection
+    fn skip_flow_trivia(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                Some(b'#') if self.at_line_end() => self.comment(),
+                _ => return,
+            }
+        }
+    }
+
+    /// `[a, b]` or `{k: v}`, possibly nested and spanning lines
+    fn flow_collection(&mut self, path: &str) -> Result<(), SyntaxError> {
+        if self.depth > MAX_DEPTH {
+            return Err(self.unexpected("Nesting too deep"));
+        }
+
+        let is_mapping = self.bytes[self.pos] == b'{';
+        let close = if is_mapping { b'}' } else { b']' };
+        self.pos += 1;
+        let mut index = 0;
+
+        loop {
+            self.skip_flow_trivia();
+            match self.peek() {
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                None => {
+                    return Err((
+                        ShelterDiagnosticCode::Expected,
+                        "Unclosed flow collection",
+                        self.pos,
+                        self.pos,
+                    ))
+                }
+                _ => {}
+            }
+
+            let mut child = path.to_string();
+            let mut key_span = (self.pos, self.pos);
+            if is_mapping {
+                let start = self.pos;
+                let key = match self.peek() {
+                    Some(b'"' | b'\'') => {
+                        let scalar = self.quoted()?;
+                        key_span = (start + 1, self.pos - 1);
+                        scalar.value
+                    }
+                    _ => {
+                        let scalar = self.flow_plain();
+                        key_span = (scalar.start, scalar.end);
+                        scalar.value
+                    }
+                };
+                push_key(&mut child, &key);
+                self.skip_flow_trivia();
+                if self.peek() != Some(b':') {
+                    return Err(self.unexpected("Expected ':'"));
+                }
+                self.pos += 1;
+                self.skip_flow_trivia();
+            } else {
+                push_index(&mut child, index);
+            }
+
+            self.skip_properties();
+            match self.peek() {
+                Some(b'[' | b'{') => {
+                    self.depth += 1;
+                    let result = self.flow_collection(&child);
+                    self.depth -= 1;
+                    result?;
+                }
+                Some(b'"' | b'\'') => {
+                    let scalar = self.quoted()?;
+                    self.emit(&child, key_span, scalar, Place::Value);
+                }
+                Some(b'*') => {
+                    while !matches!(self.peek(), None | Some(b',' | b']' | b'}' | b' ' | b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                Some(c) if c == b',' || c == close => {}
+                _ => {
+                    let scalar = self.flow_plain();
+                    self.emit(&child, key_span, scalar, Place::Value);
+                }
+            }
+            index += 1;
+
+            self.skip_flow_trivia();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(c) if c == close => {}
+                _ => return Err(self.unexpected("Expected ',' or closing bracket")),
+            }
+        }
+    }
+
+    /// Plain scalar inside a flow collection (single line)
+    fn flow_plain(&mut self) -> Scalar<'a> {
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            let ends = match b {
+                b',' | b'[' | b']' | b'{' | b'}' | b'\n' | b'\r' => true,
+                b':' => {
+                    Self::is_break(self.peek_at(1))
+                        || matches!(self.peek_at(1), Some(b',' | b']' | b'}'))
+                }
+                b'#' => matches!(self.bytes[self.pos - 1], b' ' | b'\t'),
+                _ => false,
+            };
+            if ends {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        let text = self.out.input[start..self.pos].trim_end();
+        Scalar {
+            value: Cow::Borrowed(text),
+            start,
+            end: start + text.len(),
+            quote: QuoteType::None,
+        }
+    }
+
+    // =========================================================================
+    //  Entries
+    // =========================================================================
+
+    fn emit(&mut self, path: &str, key_span: (usize, usize), scalar: Scalar<'a>, place: Place) {
+        if place == Place::EnvEntry {
+            self.emit_env_entry(path, scalar);
+            return;
+        }
+
+        if path == "kind" {
+            self.secret |= scalar.value == "Secret";
+        }
+
+        // `kind` may follow `data`, so keep every `data` entry until the document ends
+        let bearing = is_secret_bearing(path, &scalar.value);
+        let data = path.starts_with("data.");
+        if data {
+            self.data_pairs.push((self.out.pairs.len(), bearing));
+        }
+
+        if bearing || data {
+            self.out.pair(
+                path,
+                key_span,
+                scalar.value,
+                (scalar.start, scalar.end),
+                scalar.quote,
+            );
+        }
+    }
+
+    /// Decode the `data` entries of a Secret, or drop the ones kept only in
+    /// case the document was one
+    fn finish_document(&mut self) {
+        let data_pairs = std::mem::take(&mut self.data_pairs);
+        if !std::mem::take(&mut self.secret) {
+            for &(index, bearing) in data_pairs.iter().rev() {
+                if !bearing {
+                    self.out.pairs.remove(index);
+                }
+            }
+            return;
+        }
+
+        for (index, _) in data_pairs {
+            let pair = &mut self.out.pairs[index];
+            let encoded: String = pair
+                .kv
+                .value
+                .chars()
+                .filter(|c| !c.is_ascii_whitespace())
+                .collect();
+
+            match STANDARD.decode(encoded) {
+                // Binary data stays encoded; there is no text to show
+                Ok(bytes) => {
+                    if let Ok(text) = String::from_utf8(bytes) {
+                        if !text.contains('\0') {
+                            pair.kv.value = Cow::Owned(text);
+                            pair.encoding = ValueEncoding::Base64;
+                        }
+                    }
+                }
+                Err(_) => {
+                    let span = pair
+                        .kv
+                        .value_span
+                        .map_or((0, 0), |s| (s.start.offset, s.end.offset));
+                    self.out.error(
+                        ShelterDiagnosticCode::Generic,
+                        "Invalid base64 in Secret data",
+                        span.0,
+                        span.1,
+                    );
+                }
+            }
+        }
+    }
+
+    /// `- KEY=value` in a compose `environment`/`args` list
+    ///
+    /// Keyed `<list>.KEY`; the span covers only the value when it is
+    /// written verbatim, otherwise the whole item. `- KEY` alone passes the
+    /// variable through from the host and has no value to mask.
+    fn emit_env_entry(&mut self, path: &str, scalar: Scalar<'a>) {
+        let Some(eq) = scalar.value.find('=') else {
+            return;
+        };
+
+        let list = &path[..path.rfind('[').unwrap_or(path.len())];
+        let name = &scalar.value[..eq];
+        let mut key = list.to_string();
+        push_key(&mut key, name.trim());
+
+        let content_start = scalar.start + usize::from(scalar.quote != QuoteType::None);
+        let content_end = scalar.end - usize::from(scalar.quote != QuoteType::None);
+        let verbatim =
+            self.out.input.get(content_start..content_end) == Some(scalar.value.as_ref());
+
+        let value: Cow<'a, str> = match scalar.value {
+            Cow::Borrowed(s) => Cow::Borrowed(&s[eq + 1..]),
+            Cow::Owned(s) => Cow::Owned(s[eq + 1..].to_string()),
+        };
+
+        if verbatim {
+            let key_span = (content_start, content_start + eq);
+            let value_span = (content_start + eq + 1, content_end);
+            self.out
+                .pair(&key, key_span, value, value_span, QuoteType::None);
+        } else {
+            let span = (scalar.start, scalar.end);
+            self.out.pair(
+                &key,
+                (scalar.start, scalar.start),
+                value,
+                span,
+                QuoteType::None,
+            );
+        }
+    }
+}
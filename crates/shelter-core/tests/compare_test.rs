@@ -0,0 +1,131 @@
+//! Integration tests for shelter-core file comparison
+//!
+//! Verifies the key × file matrix: missing keys, value groups and that
+//! comparisons go by value across dialects.
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+use ShelterCellStatus as S;
+
+/// Key, group count and (status, group, line) per file
+type Row = (String, usize, Vec<(S, usize, usize)>);
+
+fn compare(files: &[(&str, &str)]) -> Vec<Row> {
+    let raw: Vec<ShelterFile> = files
+        .iter()
+        .map(|(path, content)| ShelterFile {
+            path: path.as_ptr() as *const c_char,
+            path_len: path.len(),
+            content: content.as_ptr() as *const c_char,
+            content_len: content.len(),
+        })
+        .collect();
+
+    unsafe {
+        let result = shelter_compare(raw.as_ptr(), raw.len());
+        let r = &*result;
+        assert!(r.error.is_null());
+        assert_eq!(r.file_count, files.len());
+        assert_eq!(r.cell_count, r.key_count * r.file_count);
+
+        let rows = (0..r.key_count)
+            .map(|k| {
+                let key = &*r.keys.add(k);
+                let cells: Vec<_> = (0..r.file_count)
+                    .map(|f| {
+                        let cell = &*r.cells.add(k * r.file_count + f);
+                        let status = match cell.status {
+                            0 => S::Missing,
+                            1 => S::Same,
+                            _ => S::Different,
+                        };
+                        (status, cell.group, cell.line_number)
+                    })
+                    .collect();
+                let missing = cells.iter().filter(|c| c.0 == S::Missing).count();
+                assert_eq!(key.missing_count, missing);
+                (
+                    CStr::from_ptr(key.key).to_string_lossy().into_owned(),
+                    key.group_count,
+                    cells,
+                )
+            })
+            .collect();
+
+        shelter_free_compare_result(result);
+        rows
+    }
+}
+
+#[test]
+fn test_compare_matrix() {
+    let rows = compare(&[
+        (".env", "A=1\nB=2\nC=3\n"),
+        (".env.staging", "A=1\nB=x\n"),
+        (".env.production", "B=x\nA=1\nD=4\n"),
+    ]);
+
+    let keys: Vec<&str> = rows.iter().map(|r| r.0.as_str()).collect();
+    assert_eq!(keys, vec!["A", "B", "C", "D"]);
+
+    assert_eq!(rows[0].1, 1);
+    assert_eq!(
+        rows[0].2,
+        vec![(S::Same, 0, 1), (S::Same, 0, 1), (S::Same, 0, 2)]
+    );
+    assert_eq!(rows[1].1, 2);
+    assert_eq!(
+        rows[1].2,
+        vec![
+            (S::Different, 0, 2),
+            (S::Different, 1, 2),
+            (S::Different, 1, 1)
+        ]
+    );
+    assert_eq!(rows[2].2[1].0, S::Missing);
+    assert_eq!(rows[2].2[0], (S::Same, 0, 3));
+    assert_eq!(rows[3].2[2], (S::Same, 0, 3));
+}
+
+#[test]
+fn test_compare_uses_effective_values() {
+    // The last definition wins; commented-out keys are not definitions
+    let rows = compare(&[(".env", "A=old\n# B=1\nA=new\n"), (".env.local", "A=new\n")]);
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].2, vec![(S::Same, 0, 3), (S::Same, 0, 1)]);
+}
+
+#[test]
+fn test_compare_goes_by_value_across_dialects() {
+    let rows = compare(&[
+        (".env", "DB_PASSWORD='hunter2'\n"),
+        ("config.json", "{\"DB_PASSWORD\": \"hunter2\"}"),
+        ("other.env", "DB_PASSWORD=hunter3\n"),
+    ]);
+
+    assert_eq!(rows[0].1, 2);
+    let groups: Vec<usize> = rows[0].2.iter().map(|c| c.1).collect();
+    assert_eq!(groups, vec![0, 0, 1]);
+}
+
+#[test]
+fn test_compare_empty_and_invalid_input() {
+    assert!(compare(&[]).is_empty());
+
+    unsafe {
+        let content = [b'A', b'=', 0xff];
+        let file = ShelterFile {
+            path: ".env".as_ptr() as *const c_char,
+            path_len: 4,
+            content: content.as_ptr() as *const c_char,
+            content_len: content.len(),
+        };
+        let result = shelter_compare(&file, 1);
+        let error = CStr::from_ptr((*result).error).to_string_lossy();
+        assert!(error.starts_with(".env: Invalid UTF-8"));
+        shelter_free_compare_result(result);
+    }
+}
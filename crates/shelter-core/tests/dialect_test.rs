@@ -0,0 +1,311 @@
+//! Integration tests for shelter-core dialects
+//!
+//! Verifies that every dialect yields EDF-compatible entry spans, quoting
+//! and comments, and that dialects are detected from file names and content.
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+use ShelterDialect as D;
+
+/// Key, value, the source text of the value span, quote type and comment flag
+#[derive(Debug, PartialEq)]
+struct Entry {
+    key: String,
+    value: String,
+    raw: String,
+    quote_type: u8,
+    is_comment: bool,
+    inline_comment: Option<String>,
+}
+
+struct Parsed {
+    entries: Vec<Entry>,
+    diagnostics: Vec<u8>,
+    dialect: u8,
+}
+
+fn parse(input: &str, dialect: D) -> Parsed {
+    let options = ShelterParseOptions {
+        dialect: dialect as u8,
+        ..Default::default()
+    };
+
+    unsafe {
+        let result = shelter_parse(input.as_ptr() as *const c_char, input.len(), options);
+        let r = &*result;
+        assert!(r.error.is_null());
+
+        let entries = (0..r.count)
+            .map(|i| {
+                let e = &*r.entries.add(i);
+                Entry {
+                    key: CStr::from_ptr(e.key).to_string_lossy().into_owned(),
+                    value: CStr::from_ptr(e.value).to_string_lossy().into_owned(),
+                    raw: input[e.value_start..e.value_end].to_string(),
+                    quote_type: e.quote_type,
+                    is_comment: e.is_comment != 0,
+                    inline_comment: (e.has_inline_comment != 0)
+                        .then(|| input[e.inline_comment_start..e.inline_comment_end].to_string()),
+                }
+            })
+            .collect();
+        let diagnostics = (0..r.diagnostic_count)
+            .map(|i| (*r.diagnostics.add(i)).code)
+            .collect();
+        let dialect = r.dialect;
+
+        shelter_free_result(result);
+        Parsed {
+            entries,
+            diagnostics,
+            dialect,
+        }
+    }
+}
+
+/// (key, value) of non-comment entries
+fn pairs(input: &str, dialect: D) -> Vec<(String, String)> {
+    parse(input, dialect)
+        .entries
+        .into_iter()
+        .filter(|e| !e.is_comment)
+        .map(|e| (e.key, e.value))
+        .collect()
+}
+
+fn kv(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn detect(source: Option<&str>, content: &str) -> u8 {
+    let (source_ptr, source_len) = source.map_or((std::ptr::null(), 0), |s| (s.as_ptr(), s.len()));
+    unsafe {
+        shelter_detect_dialect(
+            source_ptr as *const c_char,
+            source_len,
+            content.as_ptr() as *const c_char,
+            content.len(),
+        )
+    }
+}
+
+// =============================================================================
+// Span Tests
+// =============================================================================
+
+#[test]
+fn test_spans_match_edf_for_common_syntax() {
+    let input = "A=plain\nB=\"double\"\nC='single'\n";
+    let edf = parse(input, D::Edf).entries;
+
+    for dialect in [
+        D::DockerCompose,
+        D::Systemd,
+        D::Direnv,
+        D::Shell,
+        D::PythonDotenv,
+        D::NodeDotenv,
+    ] {
+        assert_eq!(parse(input, dialect).entries, edf, "{:?}", dialect);
+    }
+}
+
+#[test]
+fn test_quoted_value_span_includes_quotes() {
+    let entries = parse("KEY = \"secret\"  # note\n", D::PythonDotenv).entries;
+
+    assert_eq!(entries[0].raw, "\"secret\"");
+    assert_eq!(entries[0].value, "secret");
+    assert_eq!(entries[0].quote_type, 2);
+    assert_eq!(entries[0].inline_comment.as_deref(), Some("# note"));
+}
+
+#[test]
+fn test_result_reports_dialect() {
+    assert_eq!(parse("A=1", D::Edf).dialect, D::Edf as u8);
+    assert_eq!(parse("A=1", D::Systemd).dialect, D::Systemd as u8);
+    assert_eq!(
+        parse("export A=1\nexport B=2\n", D::Auto).dialect,
+        D::Shell as u8
+    );
+}
+
+// =============================================================================
+// Dialect Tests
+// =============================================================================
+
+#[test]
+fn test_docker_compose_keeps_unquoted_spaces() {
+    let input = "GREETING=hello world # comment\nURL=http://x/#anchor\nlog.level: debug\n";
+    assert_eq!(
+        pairs(input, D::DockerCompose),
+        kv(&[
+            ("GREETING", "hello world"),
+            ("URL", "http://x/#anchor"),
+            ("log.level", "debug"),
+        ])
+    );
+}
+
+#[test]
+fn test_systemd_rules() {
+    let input = "; comment\nOPTS=-a -b # not a comment\nLONG=one \\\n  two\nnot an assignment\n";
+    let parsed = parse(input, D::Systemd);
+
+    let values: Vec<_> = parsed
+        .entries
+        .iter()
+        .map(|e| (e.key.as_str(), e.value.as_str()))
+        .collect();
+    assert_eq!(
+        values,
+        vec![("OPTS", "-a -b # not a comment"), ("LONG", "one   two")]
+    );
+    // The continuation is part of the value span
+    assert_eq!(parsed.entries[1].raw, "one \\\n  two");
+    assert_eq!(
+        parsed.diagnostics,
+        vec![ShelterDiagnosticCode::Expected as u8]
+    );
+}
+
+#[test]
+fn test_python_dotenv_escapes() {
+    let input = "A='it\\'s'\nB=\"tab\\there\"\nexport C = bare value  # note\n";
+    assert_eq!(
+        pairs(input, D::PythonDotenv),
+        kv(&[("A", "it's"), ("B", "tab\there"), ("C", "bare value")])
+    );
+}
+
+#[test]
+fn test_node_dotenv_rules() {
+    let input = "A=`back'tick`\nB: colon\nC=value#comment\nD=\"line\\nbreak\"\n";
+    let entries = parse(input, D::NodeDotenv).entries;
+
+    let values: Vec<_> = entries
+        .iter()
+        .map(|e| (e.key.as_str(), e.value.as_str()))
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            ("A", "back'tick"),
+            ("B", "colon"),
+            ("C", "value"),
+            ("D", "line\nbreak")
+        ]
+    );
+    // Backticks are reported as single quotes so masks skip them
+    assert_eq!(entries[0].quote_type, 1);
+    assert_eq!(entries[0].raw, "`back'tick`");
+
+    // Like dotenv, an escaped quote stays in the value with its backslash
+    // rather than closing it early
+    assert_eq!(
+        pairs(
+            "A='x\\'y'\nB=\"a\\\"b\\n\"\nC=`c\\`d`\nD='e\\'\nE=\"f\\\"\n",
+            D::NodeDotenv
+        ),
+        kv(&[
+            ("A", "x\\'y"),
+            ("B", "a\\\"b\n"),
+            ("C", "c\\`d"),
+            // Without a later closing quote, the escaped one closes the value
+            ("D", "e\\"),
+            ("E", "f\\"),
+        ])
+    );
+}
+
+#[test]
+fn test_shell_assignments_and_commands() {
+    let input = "#!/bin/sh\nexport A=1 B=\"two words\"\nsource ./other.sh; C=$(cat 'x y') D=${HOME}/bin\necho \"E=nope\"\nexport PATH\n";
+    assert_eq!(
+        pairs(input, D::Shell),
+        kv(&[
+            ("A", "1"),
+            ("B", "two words"),
+            ("C", "$(cat 'x y')"),
+            ("D", "${HOME}/bin"),
+        ])
+    );
+}
+
+#[test]
+fn test_shell_word_spans() {
+    let input = "A=\"quoted\" B=pre\"mixed\"post # trailing\n";
+    let entries = parse(input, D::Direnv).entries;
+
+    assert_eq!(entries[0].raw, "\"quoted\"");
+    assert_eq!(entries[0].quote_type, 2);
+    // Concatenated words are unquoted as a whole
+    assert_eq!(entries[1].raw, "pre\"mixed\"post");
+    assert_eq!(entries[1].value, "premixedpost");
+    assert_eq!(entries[1].quote_type, 0);
+    assert_eq!(entries[1].inline_comment.as_deref(), Some("# trailing"));
+}
+
+#[test]
+fn test_commented_out_pairs() {
+    let entries = parse("# OLD_KEY=\"value\"\n# Just prose\nA=1\n", D::Shell).entries;
+
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0].is_comment);
+    assert_eq!(entries[0].key, "OLD_KEY");
+    assert_eq!(entries[0].raw, "\"value\"");
+
+    let entries = parse("# OLD: value\n", D::NodeDotenv).entries;
+    assert!(entries[0].is_comment);
+}
+
+#[test]
+fn test_unclosed_quote_reports_diagnostic() {
+    for dialect in [D::DockerCompose, D::Shell] {
+        let parsed = parse("A=1\nB=\"open\nC=3", dialect);
+        assert_eq!(parsed.entries.len(), 1, "{:?}", dialect);
+        assert_eq!(
+            parsed.diagnostics,
+            vec![ShelterDiagnosticCode::UnclosedQuote as u8]
+        );
+    }
+}
+
+// =============================================================================
+// Detection Tests
+// =============================================================================
+
+#[test]
+fn test_detect_from_filename() {
+    assert_eq!(detect(Some("project/.envrc"), ""), D::Direnv as u8);
+    assert_eq!(detect(Some("scripts/env.sh"), ""), D::Shell as u8);
+    assert_eq!(detect(Some("/home/u/.bashrc"), ""), D::Shell as u8);
+    assert_eq!(detect(Some(".flaskenv"), ""), D::PythonDotenv as u8);
+    assert_eq!(detect(Some("/etc/default/nginx"), ""), D::Systemd as u8);
+    // Plain .env files fall back to the content
+    assert_eq!(detect(Some(".env"), "A=1\n"), D::Edf as u8);
+}
+
+#[test]
+fn test_detect_from_content() {
+    assert_eq!(detect(None, "A=1\nB=\"two\"\n"), D::Edf as u8);
+    assert_eq!(detect(None, "export A=1\nexport B=2\n"), D::Shell as u8);
+    assert_eq!(detect(None, "A=$(whoami)\n"), D::Shell as u8);
+    assert_eq!(detect(None, "; note\nA=1\n"), D::Systemd as u8);
+    assert_eq!(detect(None, "A: 1\n"), D::NodeDotenv as u8);
+    assert_eq!(detect(None, "A = 1\n"), D::PythonDotenv as u8);
+    assert_eq!(detect(None, "A=hello world\n"), D::DockerCompose as u8);
+    // Continuation lines of a multi-line value are not shell syntax
+    assert_eq!(
+        detect(
+            None,
+            "KEY=\"-----BEGIN KEY-----\nabc def\n-----END KEY-----\"\n"
+        ),
+        D::Edf as u8
+    );
+}
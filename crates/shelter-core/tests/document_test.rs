@@ -0,0 +1,725 @@
+//! Integration tests for shelter-core persistent documents
+//!
+//! Verifies that incremental updates after byte-range edits report the
+//! right deltas and always agree with a full `shelter_parse`.
+
+use std::collections::HashSet;
+use std::ffi::{c_char, CStr};
+use std::fs;
+use std::path::Path;
+
+use shelter_core::*;
+
+/// Entry fields compared between a document snapshot and a full parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EntrySnapshot {
+    key: String,
+    value: String,
+    key_start: usize,
+    key_end: usize,
+    value_start: usize,
+    value_end: usize,
+    line_number: usize,
+    value_end_line: usize,
+    quote_type: u8,
+    is_exported: bool,
+    is_comment: bool,
+    inline_comment: Option<(usize, usize)>,
+    encoding: u8,
+}
+
+unsafe fn snapshot(entry: &ShelterEntry) -> EntrySnapshot {
+    EntrySnapshot {
+        key: CStr::from_ptr(entry.key).to_string_lossy().into_owned(),
+        value: CStr::from_ptr(entry.value).to_string_lossy().into_owned(),
+        key_start: entry.key_start,
+        key_end: entry.key_end,
+        value_start: entry.value_start,
+        value_end: entry.value_end,
+        line_number: entry.line_number,
+        value_end_line: entry.value_end_line,
+        quote_type: entry.quote_type,
+        is_exported: entry.is_exported != 0,
+        is_comment: entry.is_comment != 0,
+        inline_comment: (entry.has_inline_comment != 0)
+            .then_some((entry.inline_comment_start, entry.inline_comment_end)),
+        encoding: entry.encoding,
+    }
+}
+
+/// Diagnostic fields compared between a document snapshot and a full parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DiagnosticSnapshot {
+    message: String,
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+    end_line: usize,
+    end_column: usize,
+    code: u8,
+}
+
+/// (text, start, end, line_number, is_inline) of a comment
+type CommentSnapshot = (String, usize, usize, usize, bool);
+
+/// Entries, comments, diagnostics and line offsets of a parse result
+type Snapshot = (
+    Vec<EntrySnapshot>,
+    Vec<CommentSnapshot>,
+    Vec<DiagnosticSnapshot>,
+    Vec<usize>,
+);
+
+/// Extract everything from a parse result, then free it
+unsafe fn read_result(result: *mut ShelterResult) -> Snapshot {
+    assert!(!result.is_null());
+    let result_ref = &*result;
+    assert!(result_ref.error.is_null(), "unexpected error");
+
+    let entries = (0..result_ref.count)
+        .map(|i| snapshot(&*result_ref.entries.add(i)))
+        .collect();
+    let comments = (0..result_ref.comment_count)
+        .map(|i| {
+            let comment = &*result_ref.comments.add(i);
+            (
+                CStr::from_ptr(comment.text).to_string_lossy().into_owned(),
+                comment.start,
+                comment.end,
+                comment.line_number,
+                comment.is_inline != 0,
+            )
+        })
+        .collect();
+    let diagnostics = (0..result_ref.diagnostic_count)
+        .map(|i| {
+            let diag = &*result_ref.diagnostics.add(i);
+            DiagnosticSnapshot {
+                message: CStr::from_ptr(diag.message).to_string_lossy().into_owned(),
+                start: diag.start,
+                end: diag.end,
+                line: diag.line,
+                column: diag.column,
+                end_line: diag.end_line,
+                end_column: diag.end_column,
+                code: diag.code,
+            }
+        })
+        .collect();
+    let line_offsets = (0..result_ref.line_count)
+        .map(|i| *result_ref.line_offsets.add(i))
+        .collect();
+
+    shelter_free_result(result);
+    (entries, comments, diagnostics, line_offsets)
+}
+
+fn full_parse(content: &str, dialect: u8) -> Snapshot {
+    unsafe {
+        read_result(shelter_parse(
+            content.as_ptr() as *const c_char,
+            content.len(),
+            ShelterParseOptions {
+                dialect,
+                ..Default::default()
+            },
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Change {
+    id: u64,
+    kind: u8,
+    entry: EntrySnapshot,
+    mask: Option<String>,
+    resolved: Option<String>,
+    masked_start: usize,
+    masked_end: usize,
+    value_start_col: usize,
+    value_end_col: usize,
+}
+
+#[derive(Debug, Default)]
+struct Delta {
+    changes: Vec<Change>,
+    removed: Vec<u64>,
+}
+
+impl Delta {
+    fn keys(&self, kind: ShelterChangeKind) -> Vec<&str> {
+        self.changes
+            .iter()
+            .filter(|c| c.kind == kind as u8)
+            .map(|c| c.entry.key.as_str())
+            .collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Owned document that frees itself on drop, tracking the text for comparison
+struct Document {
+    doc: *mut ShelterDocument,
+    text: String,
+    dialect: u8,
+}
+
+impl Drop for Document {
+    fn drop(&mut self) {
+        unsafe { shelter_document_free(self.doc) };
+    }
+}
+
+impl Document {
+    fn new(content: &str) -> Self {
+        Self::with_options(content, ShelterMaskOptions::default())
+    }
+
+    fn with_options(content: &str, options: ShelterMaskOptions) -> Self {
+        let doc = unsafe {
+            shelter_document_new(
+                content.as_ptr() as *const c_char,
+                content.len(),
+                options,
+                std::ptr::null_mut(),
+            )
+        };
+        assert!(!doc.is_null());
+        Document {
+            doc,
+            text: content.to_string(),
+            dialect: options.dialect,
+        }
+    }
+
+    fn edit(&mut self, start: usize, old_len: usize, new_text: &str) {
+        let ok = unsafe {
+            shelter_document_edit(
+                self.doc,
+                start,
+                old_len,
+                new_text.as_ptr() as *const c_char,
+                new_text.len(),
+            )
+        };
+        assert_eq!(ok, 1, "edit {}..+{} rejected", start, old_len);
+        self.text.replace_range(start..start + old_len, new_text);
+    }
+
+    /// Replace the first occurrence of `from` with `to`
+    fn replace(&mut self, from: &str, to: &str) {
+        let start = self.text.find(from).expect("text to replace");
+        self.edit(start, from.len(), to);
+    }
+
+    fn update(&mut self) -> Delta {
+        unsafe {
+            let delta = shelter_document_update(self.doc);
+            assert!(!delta.is_null());
+            let delta_ref = &*delta;
+            assert!(delta_ref.error.is_null());
+
+            let changes = (0..delta_ref.change_count)
+                .map(|i| {
+                    let change = &*delta_ref.changes.add(i);
+                    Change {
+                        id: change.id,
+                        kind: change.kind,
+                        entry: snapshot(&change.entry),
+                        mask: (!change.mask.is_null())
+                            .then(|| CStr::from_ptr(change.mask).to_string_lossy().into_owned()),
+                        resolved: (!change.entry.resolved.is_null()).then(|| {
+                            CStr::from_ptr(change.entry.resolved)
+                                .to_string_lossy()
+                                .into_owned()
+                        }),
+                        masked_start: change.masked_start,
+                        masked_end: change.masked_end,
+                        value_start_col: change.value_start_col,
+                        value_end_col: change.value_end_col,
+                    }
+                })
+                .collect();
+            let removed = (0..delta_ref.removed_count)
+                .map(|i| *delta_ref.removed.add(i))
+                .collect();
+
+            shelter_free_document_delta(delta);
+            Delta { changes, removed }
+        }
+    }
+
+    fn entries(&self) -> Snapshot {
+        unsafe { read_result(shelter_document_entries(self.doc)) }
+    }
+
+    fn entries_in_lines(&self, first: usize, last: usize) -> Vec<EntrySnapshot> {
+        unsafe { read_result(shelter_document_entries_in_lines(self.doc, first, last)).0 }
+    }
+
+    /// Assert that the document agrees with a full parse of its text,
+    /// comments and diagnostics included
+    fn assert_consistent(&self) {
+        assert_eq!(
+            self.entries(),
+            full_parse(&self.text, self.dialect),
+            "document diverged from full parse of {:?}",
+            self.text
+        );
+    }
+}
+
+// =============================================================================
+// Delta Tests
+// =============================================================================
+
+#[test]
+fn test_first_update_reports_all_entries() {
+    let mut doc = Document::new("A=1\nB=2\n# C=3\n");
+
+    let delta = doc.update();
+    assert_eq!(delta.keys(ShelterChangeKind::Added), vec!["A", "B", "C"]);
+    assert!(delta.removed.is_empty());
+
+    let ids: HashSet<u64> = delta.changes.iter().map(|c| c.id).collect();
+    assert_eq!(ids.len(), 3, "ids must be unique");
+
+    assert!(doc.update().is_empty(), "no edits, no delta");
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_value_edit_reports_changed_entry_only() {
+    let mut doc = Document::new("A=one\nB=two\nC=three\n");
+    let initial = doc.update();
+    let b_id = initial.changes[1].id;
+
+    doc.replace("two", "TWO!");
+    let delta = doc.update();
+
+    assert_eq!(delta.changes.len(), 1);
+    let change = &delta.changes[0];
+    assert_eq!(change.kind, ShelterChangeKind::Changed as u8);
+    assert_eq!(change.id, b_id, "id is stable across edits");
+    assert_eq!(change.entry.value, "TWO!");
+    assert_eq!(change.entry.line_number, 2);
+    assert_eq!(change.mask.as_deref(), Some("****"));
+    assert!(delta.removed.is_empty());
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_whitespace_edit_reports_nothing() {
+    let mut doc = Document::new("A=1\n\nB=2\n");
+    doc.update();
+
+    doc.edit(4, 0, "   \n");
+    assert!(doc.update().is_empty());
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_inserted_line_is_added() {
+    let mut doc = Document::new("A=1\nC=3\n");
+    doc.update();
+
+    doc.edit(4, 0, "B=2\n");
+    let delta = doc.update();
+
+    assert_eq!(delta.keys(ShelterChangeKind::Added), vec!["B"]);
+    assert_eq!(delta.changes[0].entry.line_number, 2);
+    assert!(delta.removed.is_empty());
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_deleted_line_is_removed() {
+    let mut doc = Document::new("A=1\nB=2\nC=3\n");
+    let initial = doc.update();
+    let b_id = initial.changes[1].id;
+
+    doc.replace("B=2\n", "");
+    let delta = doc.update();
+
+    assert!(delta.changes.is_empty());
+    assert_eq!(delta.removed, vec![b_id]);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_renamed_key_is_removed_and_added() {
+    let mut doc = Document::new("A=1\nB=2\n");
+    let initial = doc.update();
+    let a_id = initial.changes[0].id;
+
+    doc.replace("A=", "AA=");
+    let delta = doc.update();
+
+    assert_eq!(delta.keys(ShelterChangeKind::Added), vec!["AA"]);
+    assert_eq!(delta.removed, vec![a_id]);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_commenting_out_a_line() {
+    let mut doc = Document::new("A=1\nB=secret\nC=3\n");
+    let initial = doc.update();
+    let b_id = initial.changes[1].id;
+
+    doc.edit(4, 0, "# ");
+    let delta = doc.update();
+
+    assert_eq!(delta.keys(ShelterChangeKind::Added), vec!["B"]);
+    assert!(delta.changes[0].entry.is_comment);
+    // Comment values are skipped by default
+    assert_eq!(delta.changes[0].mask, None);
+    assert_eq!(delta.removed, vec![b_id]);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_unclosed_quote_swallows_following_entries() {
+    let mut doc = Document::new("A=1\nB=2\nC=3\nD=4\n");
+    let initial = doc.update();
+    let ids: Vec<u64> = initial.changes.iter().map(|c| c.id).collect();
+
+    // Opening a quote on B consumes the rest of the file
+    doc.replace("B=2", "B=\"2");
+    let delta = doc.update();
+    assert!(delta.changes.is_empty());
+    assert_eq!(delta.removed, ids[1..].to_vec());
+    doc.assert_consistent();
+
+    // Closing it on C's line turns B into a multi-line value
+    doc.replace("C=3", "C=3\"");
+    let delta = doc.update();
+    assert_eq!(delta.keys(ShelterChangeKind::Added), vec!["B", "D"]);
+    assert_eq!(delta.changes[0].entry.value, "2\nC=3");
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_inline_comment_edit() {
+    let mut doc = Document::new("A=1 # note\nB=2\n");
+    let initial = doc.update();
+    assert!(initial.changes[0].entry.inline_comment.is_some());
+
+    doc.replace("note", "longer note");
+    let delta = doc.update();
+    assert_eq!(delta.keys(ShelterChangeKind::Changed), vec!["A"]);
+    doc.assert_consistent();
+
+    doc.replace(" # longer note", "");
+    let delta = doc.update();
+    assert_eq!(delta.changes[0].entry.inline_comment, None);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_multiline_value_edit() {
+    let mut doc = Document::new("A=1\nCERT=\"line1\nline2\nline3\"\nB=2\n");
+    let initial = doc.update();
+    let cert_id = initial.changes[1].id;
+
+    doc.replace("line2", "LINE-2");
+    let delta = doc.update();
+
+    assert_eq!(delta.changes.len(), 1);
+    let change = &delta.changes[0];
+    assert_eq!(change.id, cert_id);
+    assert_eq!(change.entry.value, "line1\nLINE-2\nline3");
+    assert_eq!(change.entry.line_number, 2);
+    assert_eq!(change.entry.value_end_line, 4);
+    assert_eq!(change.value_start_col, 5);
+    assert_eq!(change.value_end_col, 6);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_batched_edits_before_update() {
+    let mut doc = Document::new("A=1\nB=2\nC=3\nD=4\nE=5\n");
+    doc.update();
+
+    doc.replace("A=1", "A=10");
+    doc.replace("E=5", "E=50");
+    doc.replace("C=3\n", "");
+    let delta = doc.update();
+
+    assert_eq!(delta.keys(ShelterChangeKind::Changed), vec!["A", "E"]);
+    assert_eq!(delta.removed.len(), 1);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_masks_follow_options() {
+    let options = ShelterMaskOptions {
+        mode: ShelterModeConfig {
+            mode: ShelterMaskMode::Partial as u8,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut doc = Document::with_options("TOKEN=abcdefghij\n", options);
+
+    let delta = doc.update();
+    let change = &delta.changes[0];
+    assert_eq!(change.mask.as_deref(), Some("abc****hij"));
+    assert_eq!((change.masked_start, change.masked_end), (3, 7));
+}
+
+#[test]
+fn test_references_are_resolved_and_pure_ones_not_masked() {
+    let mut doc = Document::new("DB_HOST=db\nHOST=${DB_HOST}\nURL=http://${DB_HOST}/\n");
+
+    let delta = doc.update();
+    assert_eq!(delta.changes[1].mask, None);
+    assert_eq!(delta.changes[1].resolved.as_deref(), Some("db"));
+    assert_eq!(
+        delta.changes[2].mask.as_deref(),
+        Some("*".repeat(18).as_str())
+    );
+
+    doc.replace("/\n", "/app\n");
+    let delta = doc.update();
+    assert_eq!(delta.keys(ShelterChangeKind::Changed), vec!["URL"]);
+    assert_eq!(delta.changes[0].resolved.as_deref(), Some("http://db/app"));
+}
+
+#[test]
+fn test_documents_follow_dialect() {
+    let options = ShelterMaskOptions {
+        dialect: ShelterDialect::NodeDotenv as u8,
+        ..Default::default()
+    };
+    // EDF rejects the `KEY: value` form Node accepts
+    let mut doc = Document::with_options("A: one\nB=two\n", options);
+    let initial = doc.update();
+    assert_eq!(initial.keys(ShelterChangeKind::Added), vec!["A", "B"]);
+    doc.assert_consistent();
+
+    doc.replace("one", "uno");
+    let delta = doc.update();
+    assert_eq!(delta.keys(ShelterChangeKind::Changed), vec!["A"]);
+    assert!(delta.removed.is_empty());
+    doc.assert_consistent();
+
+    doc.replace("B=", "C=");
+    let delta = doc.update();
+    assert_eq!(delta.keys(ShelterChangeKind::Added), vec!["C"]);
+    assert_eq!(delta.removed, vec![initial.changes[1].id]);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_documents_report_value_encoding() {
+    let options = ShelterMaskOptions {
+        dialect: ShelterDialect::Yaml as u8,
+        ..Default::default()
+    };
+    let mut doc = Document::with_options(
+        "kind: Secret\ndata:\n  password: aHVudGVyMg==\nstringData:\n  user: admin\n",
+        options,
+    );
+    let initial = doc.update();
+    let base64 = ShelterValueEncoding::Base64 as u8;
+    let encodings = |entries: &[EntrySnapshot]| -> Vec<(String, u8)> {
+        entries
+            .iter()
+            .map(|e| (e.key.clone(), e.encoding))
+            .collect()
+    };
+    let expected = vec![
+        ("data.password".to_string(), base64),
+        ("stringData.user".to_string(), 0),
+    ];
+
+    let changes: Vec<EntrySnapshot> = initial.changes.into_iter().map(|c| c.entry).collect();
+    assert_eq!(encodings(&changes), expected);
+    assert_eq!(encodings(&doc.entries().0), expected);
+    assert_eq!(encodings(&doc.entries_in_lines(3, 3)), expected[..1]);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_entries_in_lines() {
+    let mut doc = Document::new("A=1\n# B=2\nC=\"x\ny\"\nD=${A}\n");
+    doc.update();
+
+    let keys = |first, last| -> Vec<String> {
+        doc.entries_in_lines(first, last)
+            .into_iter()
+            .map(|e| e.key)
+            .collect()
+    };
+    assert_eq!(keys(1, 1), vec!["A"]);
+    assert_eq!(keys(2, 3), vec!["B", "C"]);
+    // A value continuing onto a line does not start there
+    assert_eq!(keys(4, 4), Vec::<String>::new());
+    assert_eq!(keys(5, 100), vec!["D"]);
+    assert_eq!(keys(100, 200), Vec::<String>::new());
+
+    unsafe {
+        let result = shelter_document_entries_in_lines(doc.doc, 5, 5);
+        let entry = &*(*result).entries;
+        assert_eq!(CStr::from_ptr(entry.resolved).to_str(), Ok("1"));
+        assert_eq!(entry.is_reference, 1);
+        shelter_free_result(result);
+    }
+}
+
+#[test]
+fn test_reset_diffs_against_previous_content() {
+    let mut doc = Document::new("A=1\nB=2\n");
+    let initial = doc.update();
+    let a_id = initial.changes[0].id;
+
+    let content = "B=2\nC=3\n";
+    let ok = unsafe { shelter_document_reset(doc.doc, content.as_ptr() as *const c_char, 8) };
+    assert_eq!(ok, 1);
+    doc.text = content.to_string();
+
+    let delta = doc.update();
+    assert_eq!(delta.keys(ShelterChangeKind::Added), vec!["C"]);
+    assert_eq!(delta.removed, vec![a_id]);
+    doc.assert_consistent();
+}
+
+#[test]
+fn test_invalid_edits_are_rejected() {
+    let mut doc = Document::new("KEY=caf\u{e9}\n");
+    doc.update();
+
+    unsafe {
+        // Out of bounds
+        assert_eq!(
+            shelter_document_edit(doc.doc, 100, 0, std::ptr::null(), 0),
+            0
+        );
+        // Splits the two-byte character
+        assert_eq!(shelter_document_edit(doc.doc, 8, 1, std::ptr::null(), 0), 0);
+        // Invalid UTF-8 replacement
+        let bad = [0xffu8];
+        assert_eq!(
+            shelter_document_edit(doc.doc, 0, 0, bad.as_ptr() as *const c_char, 1),
+            0
+        );
+    }
+
+    assert!(doc.update().is_empty());
+    doc.assert_consistent();
+}
+
+// =============================================================================
+// Consistency Tests
+// =============================================================================
+
+/// Small deterministic PRNG so the edit sequence is reproducible
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % bound.max(1)
+    }
+}
+
+fn char_boundary(text: &str, mut pos: usize) -> usize {
+    while !text.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    pos
+}
+
+#[test]
+fn test_random_edits_match_full_parse() {
+    const SNIPPETS: &[&str] = &[
+        "\"",
+        "'",
+        "=",
+        "#",
+        "\\",
+        "\n",
+        " ",
+        "export ",
+        "KEY",
+        "X=1\n",
+        "Y=\"a\nb\"\n",
+        "# Z=2",
+        "\\\n",
+        "\r\n",
+        " # c",
+    ];
+
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    let dialects = [ShelterDialect::Edf, ShelterDialect::NodeDotenv];
+    let files = [
+        "simple.env",
+        "multiline.env",
+        "comments.env",
+        "edge_cases.env",
+    ];
+    for (dialect, name) in dialects.into_iter().flat_map(|d| files.map(|f| (d, f))) {
+        let content = fs::read_to_string(fixtures.join(name)).unwrap();
+        let options = ShelterMaskOptions {
+            dialect: dialect as u8,
+            ..Default::default()
+        };
+        let mut doc = Document::with_options(&content, options);
+        let mut rng = Lcg(name.len() as u64);
+        let mut live: HashSet<u64> = HashSet::new();
+
+        for step in 0..300 {
+            let len = doc.text.len();
+            let start = char_boundary(&doc.text, rng.next(len + 1));
+            let end = char_boundary(&doc.text, (start + rng.next(8)).min(len));
+            let insert = if rng.next(3) == 0 {
+                ""
+            } else {
+                SNIPPETS[rng.next(SNIPPETS.len())]
+            };
+
+            doc.edit(start, end - start, insert);
+
+            // Batch a few edits between updates
+            if step % 3 != 0 {
+                continue;
+            }
+
+            let delta = doc.update();
+            for id in &delta.removed {
+                assert!(live.remove(id), "removed unknown id {}", id);
+            }
+            for change in &delta.changes {
+                if change.kind == ShelterChangeKind::Added as u8 {
+                    assert!(live.insert(change.id), "duplicate id {}", change.id);
+                } else {
+                    assert!(
+                        live.contains(&change.id),
+                        "changed unknown id {}",
+                        change.id
+                    );
+                }
+            }
+
+            doc.assert_consistent();
+            assert_eq!(live.len(), doc.entries().0.len(), "{}: live ids", name);
+
+            let first = rng.next(doc.text.lines().count() + 2);
+            let last = first + rng.next(4);
+            let in_lines: Vec<EntrySnapshot> = full_parse(&doc.text, dialect as u8)
+                .0
+                .into_iter()
+                .filter(|e| (first..=last).contains(&e.line_number))
+                .collect();
+            assert_eq!(doc.entries_in_lines(first, last), in_lines);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Integration tests for environment dump detection
+//!
+//! Verifies `printenv`, `docker inspect`, `kubectl describe` and
+//! `/proc/<pid>/environ` dumps, their spans and lines, and text that only
+//! looks like a dump.
+
+use std::ffi::c_char;
+
+use shelter_core::*;
+
+/// (format, key, value, line) of every entry
+fn find(input: &str) -> Vec<(u8, String, String, usize)> {
+    unsafe {
+        let result = shelter_find_env_dumps(input.as_ptr() as *const c_char, input.len());
+        let r = &*result;
+        assert!(r.error.is_null());
+        assert_eq!(r.line_count, input.matches('\n').count() + 1);
+        let entries = (0..r.count)
+            .map(|i| {
+                let d = &*r.entries.add(i);
+                let e = &d.entry;
+                let key = String::from_utf8_lossy(std::slice::from_raw_parts(
+                    e.key as *const u8,
+                    e.key_len,
+                ))
+                .into_owned();
+                let value = String::from_utf8_lossy(std::slice::from_raw_parts(
+                    e.value as *const u8,
+                    e.value_len,
+                ))
+                .into_owned();
+                assert_eq!(key, input[e.key_start..e.key_end]);
+                assert_eq!(e.quote_type, 0);
+                (d.format, key, value, e.line_number)
+            })
+            .collect();
+        shelter_free_dump_result(result);
+        entries
+    }
+}
+
+fn entry(
+    format: ShelterDumpFormat,
+    key: &str,
+    value: &str,
+    line: usize,
+) -> (u8, String, String, usize) {
+    (format as u8, key.to_string(), value.to_string(), line)
+}
+
+#[test]
+fn test_printenv_runs() {
+    let input = "$ printenv\n\
+                 HOME=/home/app\n\
+                 DATABASE_URL=postgres://app:s3cr3t@db/app\r\n\
+                 EMPTY=\n\
+                 EQUALS=a=b\n\
+                 $ echo done\n";
+    use ShelterDumpFormat::Printenv as P;
+    assert_eq!(
+        find(input),
+        vec![
+            entry(P, "HOME", "/home/app", 2),
+            entry(P, "DATABASE_URL", "postgres://app:s3cr3t@db/app", 3),
+            entry(P, "EQUALS", "a=b", 5),
+        ]
+    );
+
+    // Assignments scattered through code or prose are not a dump
+    assert_eq!(find("let a=1\nX=1\nY=2\n\nZ=3\n  W=4\nV=5\n"), vec![]);
+}
+
+#[test]
+fn test_docker_inspect_env() {
+    let input = r#"[
+    {
+        "Config": {
+            "Env": [
+                "PATH=/usr/local/bin:/usr/bin",
+                "API_TOKEN=tok\"en!",
+                "not a pair",
+                "NODE_ENV=production"
+            ],
+            "Cmd": ["node", "A=1"]
+        }
+    }
+]"#;
+    use ShelterDumpFormat::DockerInspect as D;
+    let entries = find(input);
+    assert_eq!(
+        entries,
+        vec![
+            entry(D, "PATH", "/usr/local/bin:/usr/bin", 5),
+            entry(D, "API_TOKEN", "tok\"en!", 6),
+            entry(D, "NODE_ENV", "production", 8),
+        ]
+    );
+
+    // Spans cover the escaped value as written
+    unsafe {
+        let result = shelter_find_env_dumps(input.as_ptr() as *const c_char, input.len());
+        let e = &(*(*result).entries.add(1)).entry;
+        assert_eq!(&input[e.value_start..e.value_end], r#"tok\"en!"#);
+        shelter_free_dump_result(result);
+    }
+
+    // Compact JSON on one line
+    assert_eq!(
+        find(r#"{"Env":["A=1","B=two"]}"#),
+        vec![entry(D, "A", "1", 1), entry(D, "B", "two", 1)]
+    );
+}
+
+#[test]
+fn test_kubectl_describe_env() {
+    let input = "Containers:\n\
+                 \x20 web:\n\
+                 \x20   Image:      app:1.2\n\
+                 \x20   Environment:\n\
+                 \x20     DATABASE_URL:  postgres://app:s3cr3t@db/app\n\
+                 \x20     DB_PASSWORD:   <set to the key 'password' in secret 'db'>  Optional: false\n\
+                 \x20     POD_IP:         (v1:status.podIP)\n\
+                 \x20     LOG_LEVEL:     debug  \n\
+                 \x20   Mounts:\n\
+                 \x20     /data from data (rw)\n\
+                 \x20 worker:\n\
+                 \x20   Environment:  <none>\n\
+                 \x20   Labels:  app=web\n";
+    use ShelterDumpFormat::Kubectl as K;
+    assert_eq!(
+        find(input),
+        vec![
+            entry(K, "DATABASE_URL", "postgres://app:s3cr3t@db/app", 5),
+            entry(K, "LOG_LEVEL", "debug", 8),
+        ]
+    );
+}
+
+#[test]
+fn test_proc_environ() {
+    let input = "$ cat /proc/1/environ\nPATH=/usr/bin\0SECRET=line1\nline2\0HOME=/root\0$ ls\n";
+    use ShelterDumpFormat::Environ as E;
+    assert_eq!(
+        find(input),
+        vec![
+            entry(E, "PATH", "/usr/bin", 2),
+            entry(E, "SECRET", "line1\nline2", 2),
+            entry(E, "HOME", "/root", 3),
+        ]
+    );
+
+    // An unterminated last record ends at its line
+    assert_eq!(
+        find("A=1\0B=2\nprompt$ "),
+        vec![entry(E, "A", "1", 1), entry(E, "B", "2", 1)]
+    );
+}
@@ -0,0 +1,175 @@
+//! Integration tests for shelter-core duplicate key detection
+//!
+//! Verifies which definition of a repeated key is effective per dialect and
+//! that every occurrence links to the others.
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+use ShelterDuplicateStatus as S;
+
+/// Key, duplicate status and the indices of the other definitions
+type Entry = (String, u8, Vec<usize>);
+
+unsafe fn read_entries(result: *mut ShelterResult) -> Vec<Entry> {
+    let r = &*result;
+    assert!(r.error.is_null());
+
+    let entries = (0..r.count)
+        .map(|i| {
+            let e = &*r.entries.add(i);
+            let others = (e.duplicate_start..e.duplicate_start + e.duplicate_count)
+                .map(|j| *r.duplicates.add(j))
+                .collect();
+            (
+                CStr::from_ptr(e.key).to_string_lossy().into_owned(),
+                e.duplicate_status,
+                others,
+            )
+        })
+        .collect();
+
+    shelter_free_result(result);
+    entries
+}
+
+fn parse(input: &str, dialect: ShelterDialect) -> Vec<Entry> {
+    let options = ShelterParseOptions {
+        dialect: dialect as u8,
+        ..Default::default()
+    };
+    unsafe {
+        read_entries(shelter_parse(
+            input.as_ptr() as *const c_char,
+            input.len(),
+            options,
+        ))
+    }
+}
+
+fn statuses(entries: &[Entry]) -> Vec<u8> {
+    entries.iter().map(|e| e.1).collect()
+}
+
+#[test]
+fn test_last_definition_wins() {
+    let entries = parse("A=1\nB=2\nA=3\nC=4\nA=5\n", ShelterDialect::Edf);
+
+    assert_eq!(
+        statuses(&entries),
+        vec![
+            S::Shadowed as u8,
+            S::Unique as u8,
+            S::Shadowed as u8,
+            S::Unique as u8,
+            S::Effective as u8
+        ]
+    );
+    assert_eq!(entries[0].2, vec![2, 4]);
+    assert_eq!(entries[2].2, vec![0, 4]);
+    assert_eq!(entries[4].2, vec![0, 2]);
+    assert!(entries[1].2.is_empty());
+}
+
+#[test]
+fn test_commented_out_entries_are_not_definitions() {
+    let entries = parse("A=1\n# A=2\n", ShelterDialect::Edf);
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(statuses(&entries), vec![S::Unique as u8, S::Unique as u8]);
+}
+
+#[test]
+fn test_dotenv_dialects_and_json_keep_the_last() {
+    for dialect in [
+        ShelterDialect::DockerCompose,
+        ShelterDialect::Systemd,
+        ShelterDialect::Shell,
+        ShelterDialect::PythonDotenv,
+        ShelterDialect::NodeDotenv,
+    ] {
+        let entries = parse("A=1\nA=2\n", dialect);
+        assert_eq!(
+            statuses(&entries),
+            vec![S::Shadowed as u8, S::Effective as u8],
+            "{:?}",
+            dialect
+        );
+    }
+
+    let entries = parse(
+        r#"{"db": {"pass": "a", "pass": "b"}}"#,
+        ShelterDialect::Json,
+    );
+    assert_eq!(entries[0].0, "db.pass");
+    assert_eq!(
+        statuses(&entries),
+        vec![S::Shadowed as u8, S::Effective as u8]
+    );
+}
+
+#[test]
+fn test_toml_and_yaml_duplicates_conflict() {
+    let entries = parse("[db]\npass = \"a\"\npass = \"b\"\n", ShelterDialect::Toml);
+    assert_eq!(
+        statuses(&entries),
+        vec![S::Conflict as u8, S::Conflict as u8]
+    );
+    assert_eq!(entries[0].2, vec![1]);
+
+    let entries = parse("env:\n  TOKEN: a\n  TOKEN: b\n", ShelterDialect::Yaml);
+    assert_eq!(
+        statuses(&entries),
+        vec![S::Conflict as u8, S::Conflict as u8]
+    );
+}
+
+#[test]
+fn test_ini_duplicates_conflict() {
+    let entries = parse("[db]\npass = a\npass = b\n", ShelterDialect::Ini);
+    assert_eq!(
+        statuses(&entries),
+        vec![S::Conflict as u8, S::Conflict as u8]
+    );
+}
+
+#[test]
+fn test_yaml_keys_clash_only_within_a_document() {
+    let secret = |password: &str| {
+        format!(
+            "apiVersion: v1\nkind: Secret\nmetadata:\n  name: db\ndata:\n  password: {}\n",
+            password
+        )
+    };
+    let input = format!("{}---\n{}", secret("YQ=="), secret("Yg=="));
+    let entries = parse(&input, ShelterDialect::Yaml);
+    let passwords: Vec<&Entry> = entries.iter().filter(|e| e.0 == "data.password").collect();
+    assert_eq!(passwords.len(), 2);
+    assert!(passwords.iter().all(|e| e.1 == S::Unique as u8));
+
+    let input = "env:\n  TOKEN: a\n---\nenv:\n  TOKEN: b\n  TOKEN: c\n";
+    let entries = parse(input, ShelterDialect::Yaml);
+    assert_eq!(
+        statuses(&entries),
+        vec![S::Unique as u8, S::Conflict as u8, S::Conflict as u8]
+    );
+    assert_eq!(entries[1].2, vec![2]);
+}
+
+#[test]
+fn test_document_entries_link_duplicates() {
+    let content = "A=1\nB=2\nA=3\n";
+    unsafe {
+        let doc = shelter_document_new(
+            content.as_ptr() as *const c_char,
+            content.len(),
+            ShelterMaskOptions::default(),
+            std::ptr::null_mut(),
+        );
+        let entries = read_entries(shelter_document_entries(doc));
+        shelter_document_free(doc);
+
+        assert_eq!(entries, parse(content, ShelterDialect::Edf));
+    }
+}
@@ -0,0 +1,257 @@
+//! Integration tests for shelter-core format-preserving edits
+//!
+//! Verifies that set, unset, rename and move only touch the edited entries,
+//! that new values read back unchanged in every dialect, and that edits of
+//! undefined keys fail.
+
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+use shelter_core::*;
+
+fn raw(kind: ShelterEditKind, key: &str, value: &str) -> ShelterEdit {
+    ShelterEdit {
+        key: key.as_ptr() as *const c_char,
+        key_len: key.len(),
+        value: value.as_ptr() as *const c_char,
+        value_len: value.len(),
+        kind: kind as u8,
+    }
+}
+
+fn edit(input: &str, dialect: ShelterDialect, edits: &[ShelterEdit]) -> Result<String, String> {
+    unsafe {
+        let mut error = ptr::null_mut();
+        let result = shelter_edit(
+            input.as_ptr() as *const c_char,
+            input.len(),
+            dialect as u8,
+            edits.as_ptr(),
+            edits.len(),
+            &mut error,
+        );
+        if result.is_null() {
+            let message = CStr::from_ptr(error).to_string_lossy().into_owned();
+            shelter_free_string(error);
+            return Err(message);
+        }
+        let text = CStr::from_ptr(result).to_string_lossy().into_owned();
+        shelter_free_string(result);
+        Ok(text)
+    }
+}
+
+fn values(input: &str, dialect: ShelterDialect) -> Vec<(String, String)> {
+    let options = ShelterParseOptions {
+        dialect: dialect as u8,
+        include_comments: 0,
+        ..Default::default()
+    };
+    unsafe {
+        let result = shelter_parse(input.as_ptr() as *const c_char, input.len(), options);
+        let r = &*result;
+        assert_eq!(r.diagnostic_count, 0, "{}", input);
+        let pairs = (0..r.count)
+            .map(|i| {
+                let e = &*r.entries.add(i);
+                (
+                    CStr::from_ptr(e.key).to_string_lossy().into_owned(),
+                    CStr::from_ptr(e.value).to_string_lossy().into_owned(),
+                )
+            })
+            .collect();
+        shelter_free_result(result);
+        pairs
+    }
+}
+
+const INPUT: &str = "# Database\n\
+                     export DB_URL='postgres://db/app' # primary\n\
+                     \n\
+                     API_KEY=\"sk_live_abc\"\n\
+                     # Server\n\
+                     PORT=3000\n";
+
+#[test]
+fn test_set_keeps_everything_else() {
+    let set = [raw(
+        ShelterEditKind::Set,
+        "DB_URL",
+        "postgres://replica/app",
+    )];
+    assert_eq!(
+        edit(INPUT, ShelterDialect::Edf, &set).unwrap(),
+        "# Database\n\
+         export DB_URL='postgres://replica/app' # primary\n\
+         \n\
+         API_KEY=\"sk_live_abc\"\n\
+         # Server\n\
+         PORT=3000\n"
+    );
+
+    let append = [raw(ShelterEditKind::Set, "NEW_KEY", "two words")];
+    let edited = edit("A=1", ShelterDialect::Edf, &append).unwrap();
+    assert_eq!(edited, "A=1\nNEW_KEY='two words'\n");
+
+    let exported = edit("export A=1\r\n", ShelterDialect::Shell, &append).unwrap();
+    assert_eq!(exported, "export A=1\r\nexport NEW_KEY='two words'\r\n");
+}
+
+#[test]
+fn test_unset_rename_and_move() {
+    let unset = [raw(ShelterEditKind::Unset, "DB_URL", "")];
+    assert_eq!(
+        edit(INPUT, ShelterDialect::Edf, &unset).unwrap(),
+        "# Database\n\nAPI_KEY=\"sk_live_abc\"\n# Server\nPORT=3000\n"
+    );
+
+    let rename = [raw(ShelterEditKind::Rename, "API_KEY", "STRIPE_KEY")];
+    assert_eq!(
+        edit(INPUT, ShelterDialect::Edf, &rename).unwrap(),
+        INPUT.replace("API_KEY", "STRIPE_KEY")
+    );
+
+    // The comment block above PORT moves with it
+    let to_front = [raw(ShelterEditKind::Move, "PORT", "DB_URL")];
+    assert_eq!(
+        edit(INPUT, ShelterDialect::Edf, &to_front).unwrap(),
+        "# Server\n\
+         PORT=3000\n\
+         # Database\n\
+         export DB_URL='postgres://db/app' # primary\n\
+         \n\
+         API_KEY=\"sk_live_abc\"\n"
+    );
+
+    let to_end = [raw(ShelterEditKind::Move, "DB_URL", "")];
+    assert_eq!(
+        edit(INPUT, ShelterDialect::Edf, &to_end).unwrap(),
+        "\n\
+         API_KEY=\"sk_live_abc\"\n\
+         # Server\n\
+         PORT=3000\n\
+         # Database\n\
+         export DB_URL='postgres://db/app' # primary\n"
+    );
+
+    // Multi-line values are removed as a whole
+    let multiline = "A=\"one\ntwo\"\nB=2\n";
+    let unset = [raw(ShelterEditKind::Unset, "A", "")];
+    assert_eq!(
+        edit(multiline, ShelterDialect::Edf, &unset).unwrap(),
+        "B=2\n"
+    );
+}
+
+#[test]
+fn test_set_requotes_for_every_dialect() {
+    let tricky = [
+        "two words",
+        "line one\nline two",
+        "value # not a comment",
+        "it's \"quoted\"",
+        "$HOME and `cmd`",
+        "",
+    ];
+    let dialects = [
+        ShelterDialect::Edf,
+        ShelterDialect::DockerCompose,
+        ShelterDialect::Systemd,
+        ShelterDialect::Direnv,
+        ShelterDialect::Shell,
+        ShelterDialect::PythonDotenv,
+        ShelterDialect::NodeDotenv,
+    ];
+
+    for dialect in dialects {
+        for value in tricky {
+            for input in ["# keep\nKEY=old\nOTHER=1\n", "OTHER=1\n"] {
+                let set = [raw(ShelterEditKind::Set, "KEY", value)];
+                let edited = edit(input, dialect, &set)
+                    .unwrap_or_else(|e| panic!("{:?} {:?}: {}", dialect, value, e));
+                let pairs = values(&edited, dialect);
+                assert!(
+                    pairs.contains(&("KEY".to_string(), value.to_string())),
+                    "{:?} {:?}: {:?}",
+                    dialect,
+                    value,
+                    edited
+                );
+                assert!(pairs.contains(&("OTHER".to_string(), "1".to_string())));
+            }
+        }
+
+        let set = [raw(ShelterEditKind::Set, "KEY", "two words # x")];
+        assert!(edit("KEY=old\n", dialect, &set).is_ok(), "{:?}", dialect);
+    }
+
+    let json = "{\n  \"KEY\": \"old\",\n  \"PORT\": 1\n}\n";
+    let set = [raw(ShelterEditKind::Set, "KEY", "line one\nline \"two\"")];
+    assert_eq!(
+        edit(json, ShelterDialect::Json, &set).unwrap(),
+        "{\n  \"KEY\": \"line one\\nline \\\"two\\\"\",\n  \"PORT\": 1\n}\n"
+    );
+}
+
+#[test]
+fn test_set_replaces_yaml_block_scalars() {
+    let yaml = "env:\n  TOKEN: |-\n    old\n    lines\n  PORT: 1\n";
+    let set = [raw(ShelterEditKind::Set, "env.TOKEN", "new\nvalue")];
+    let edited = edit(yaml, ShelterDialect::Yaml, &set).unwrap();
+    assert_eq!(edited, "env:\n  TOKEN: \"new\\nvalue\"\n  PORT: 1\n");
+
+    let yaml = "- name: x\n  password: > # folded\n    a\n    b\n";
+    let set = [raw(ShelterEditKind::Set, "[0].password", "c")];
+    let edited = edit(yaml, ShelterDialect::Yaml, &set).unwrap();
+    assert_eq!(edited, "- name: x\n  password: \"c\"\n");
+}
+
+#[test]
+fn test_invalid_edits_fail() {
+    let cases = [
+        (
+            raw(ShelterEditKind::Unset, "MISSING", ""),
+            "MISSING is not defined",
+        ),
+        (
+            raw(ShelterEditKind::Rename, "MISSING", "B"),
+            "MISSING is not defined",
+        ),
+        (
+            raw(ShelterEditKind::Move, "PORT", "MISSING"),
+            "MISSING is not defined",
+        ),
+        (
+            raw(ShelterEditKind::Rename, "PORT", "API_KEY"),
+            "API_KEY is already defined",
+        ),
+        (
+            raw(ShelterEditKind::Rename, "PORT", "BAD KEY"),
+            "Invalid key",
+        ),
+        (raw(ShelterEditKind::Set, "BAD=KEY", "1"), "Invalid key"),
+        (raw(ShelterEditKind::Set, "PORT", "80\080"), "NUL byte"),
+    ];
+    for (e, message) in cases {
+        let error = edit(INPUT, ShelterDialect::Edf, &[e]).unwrap_err();
+        assert!(error.contains(message), "{}", error);
+    }
+
+    // A failing edit discards the ones before it
+    let batch = [
+        raw(ShelterEditKind::Set, "PORT", "8080"),
+        raw(ShelterEditKind::Unset, "MISSING", ""),
+    ];
+    assert!(edit(INPUT, ShelterDialect::Edf, &batch).is_err());
+
+    let toml = "key = \"old\"\n";
+    let unset = [raw(ShelterEditKind::Unset, "key", "")];
+    assert!(edit(toml, ShelterDialect::Toml, &unset).is_err());
+    let set = [raw(ShelterEditKind::Set, "other", "1")];
+    assert!(edit(toml, ShelterDialect::Toml, &set).is_err());
+
+    // Output with a NUL byte is an error, never a truncated string
+    let set = [raw(ShelterEditKind::Set, "A", "2")];
+    let error = edit("# x\0y\nA=1\n", ShelterDialect::Edf, &set).unwrap_err();
+    assert!(error.contains("NUL byte"), "{}", error);
+}
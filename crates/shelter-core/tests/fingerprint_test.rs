@@ -0,0 +1,131 @@
+//! Integration tests for shelter-core value fingerprints
+//!
+//! Verifies tags are stable per key, differ between keys and values, and
+//! that the machine secret is created once in the data directory.
+
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+use shelter_core::*;
+
+fn fingerprint(value: &str, key: Option<&str>) -> Result<String, String> {
+    let (key_ptr, key_len) = key.map_or((ptr::null(), 0), |k| (k.as_ptr(), k.len()));
+    unsafe {
+        let mut error = ptr::null_mut();
+        let tag = shelter_fingerprint(
+            value.as_ptr() as *const c_char,
+            value.len(),
+            key_ptr as *const c_char,
+            key_len,
+            &mut error,
+        );
+        if tag.is_null() {
+            let message = CStr::from_ptr(error).to_string_lossy().into_owned();
+            shelter_free_string(error);
+            return Err(message);
+        }
+        let tag_str = CStr::from_ptr(tag).to_string_lossy().into_owned();
+        shelter_free_string(tag);
+        Ok(tag_str)
+    }
+}
+
+fn equal(a: &str, b: &str) -> bool {
+    unsafe {
+        shelter_fingerprint_equal(
+            a.as_ptr() as *const c_char,
+            a.len(),
+            b.as_ptr() as *const c_char,
+            b.len(),
+        ) != 0
+    }
+}
+
+/// Point the machine secret at a scratch directory; the secret is cached
+/// per process, so every test that needs it uses the same one
+fn use_scratch_data_dir() -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("shelter-fingerprint-{}", std::process::id()));
+    std::env::set_var("XDG_DATA_HOME", &dir);
+    dir
+}
+
+#[test]
+fn test_shared_key_tags() {
+    let a = fingerprint("sk_live_abc", Some("team secret")).unwrap();
+    assert_eq!(a.len(), 4);
+    assert!(a.bytes().all(|b| b.is_ascii_hexdigit()));
+
+    assert_eq!(a, fingerprint("sk_live_abc", Some("team secret")).unwrap());
+    assert_ne!(a, fingerprint("sk_live_abc", Some("other secret")).unwrap());
+    assert_ne!(a, fingerprint("sk_live_abd", Some("team secret")).unwrap());
+}
+
+#[test]
+fn test_machine_key_is_created_once() {
+    let dir = use_scratch_data_dir();
+
+    let a = fingerprint("hunter2", None).unwrap();
+    assert_eq!(a, fingerprint("hunter2", None).unwrap());
+
+    let key_file = dir.join("shelter").join("fingerprint.key");
+    let key = std::fs::read_to_string(&key_file).unwrap();
+    assert_eq!(key.len(), 64);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&key_file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn test_parse_adds_fingerprints_on_request() {
+    use_scratch_data_dir();
+    let input = "A=same\nB=same\nC=other\n# D=same\n";
+
+    unsafe {
+        let options = ShelterParseOptions {
+            fingerprint: 1,
+            ..Default::default()
+        };
+        let result = shelter_parse(input.as_ptr() as *const c_char, input.len(), options);
+        let r = &*result;
+        assert!(r.error.is_null());
+
+        let tags: Vec<Option<String>> = (0..r.count)
+            .map(|i| {
+                let entry = &*r.entries.add(i);
+                (!entry.fingerprint.is_null()).then(|| {
+                    CStr::from_ptr(entry.fingerprint)
+                        .to_string_lossy()
+                        .into_owned()
+                })
+            })
+            .collect();
+        shelter_free_result(result);
+
+        assert_eq!(tags[0], Some(fingerprint("same", None).unwrap()));
+        assert_eq!(tags[0], tags[1]);
+        assert_ne!(tags[0], tags[2]);
+        assert_eq!(tags[3], None);
+
+        // Off by default
+        let result = shelter_parse(
+            input.as_ptr() as *const c_char,
+            input.len(),
+            ShelterParseOptions::default(),
+        );
+        assert!((*(*result).entries).fingerprint.is_null());
+        shelter_free_result(result);
+    }
+}
+
+#[test]
+fn test_fingerprint_equal() {
+    assert!(equal("a3f9", "a3f9"));
+    assert!(!equal("a3f9", "a3f8"));
+    assert!(!equal("a3f9", "a3f"));
+    unsafe {
+        assert_eq!(shelter_fingerprint_equal(ptr::null(), 0, ptr::null(), 0), 0);
+    }
+}
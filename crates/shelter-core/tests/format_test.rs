@@ -0,0 +1,214 @@
+//! Integration tests for the shelter-core formatter
+//!
+//! Verifies quoting and whitespace normalization, both sort orders, that
+//! comment blocks stay with their keys, and that output is idempotent and
+//! holds the same values.
+
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+use shelter_core::*;
+
+fn format(input: &str, dialect: ShelterDialect, sort: ShelterFormatSort) -> Result<String, String> {
+    let options = ShelterFormatOptions {
+        dialect: dialect as u8,
+        sort: sort as u8,
+    };
+    unsafe {
+        let mut error = ptr::null_mut();
+        let result = shelter_format(
+            input.as_ptr() as *const c_char,
+            input.len(),
+            options,
+            &mut error,
+        );
+        if result.is_null() {
+            let message = CStr::from_ptr(error).to_string_lossy().into_owned();
+            shelter_free_string(error);
+            return Err(message);
+        }
+        let text = CStr::from_ptr(result).to_string_lossy().into_owned();
+        shelter_free_string(result);
+        Ok(text)
+    }
+}
+
+fn values(input: &str, dialect: ShelterDialect) -> Vec<(String, String)> {
+    let options = ShelterParseOptions {
+        dialect: dialect as u8,
+        include_comments: 0,
+        ..Default::default()
+    };
+    unsafe {
+        let result = shelter_parse(input.as_ptr() as *const c_char, input.len(), options);
+        let r = &*result;
+        assert_eq!(r.diagnostic_count, 0, "{}", input);
+        let mut pairs: Vec<(String, String)> = (0..r.count)
+            .map(|i| {
+                let e = &*r.entries.add(i);
+                (
+                    CStr::from_ptr(e.key).to_string_lossy().into_owned(),
+                    CStr::from_ptr(e.value).to_string_lossy().into_owned(),
+                )
+            })
+            .collect();
+        shelter_free_result(result);
+        pairs.sort();
+        pairs
+    }
+}
+
+const INPUT: &str = "\n\
+                     # App settings\n\
+                     \n\
+                     \n\
+                     PORT=\"3000\"\n\
+                     # Database\n\
+                     DB_URL='postgres://db/app'   # primary\n\
+                     export API_KEY=\"sk live\"\n\
+                     \n\
+                     DB_POOL=5\n\
+                     HOST=\"${DOMAIN}\"\n\
+                     DEBUG='true'";
+
+#[test]
+fn test_normalizes_quotes_and_whitespace() {
+    assert_eq!(
+        format(INPUT, ShelterDialect::Edf, ShelterFormatSort::None).unwrap(),
+        "# App settings\n\
+         \n\
+         PORT=3000\n\
+         # Database\n\
+         DB_URL=postgres://db/app # primary\n\
+         export API_KEY='sk live'\n\
+         \n\
+         DB_POOL=5\n\
+         HOST=\"${DOMAIN}\"\n\
+         DEBUG=true\n"
+    );
+
+    let spaced = "A = 1\r\nB=  'two words'\r\n";
+    assert_eq!(
+        format(
+            spaced,
+            ShelterDialect::DockerCompose,
+            ShelterFormatSort::None
+        )
+        .unwrap(),
+        "A=1\r\nB='two words'\r\n"
+    );
+}
+
+#[test]
+fn test_sorts_and_keeps_comments_attached() {
+    assert_eq!(
+        format(INPUT, ShelterDialect::Edf, ShelterFormatSort::Alphabetical).unwrap(),
+        "# App settings\n\
+         \n\
+         export API_KEY='sk live'\n\
+         # Database\n\
+         DB_URL=postgres://db/app # primary\n\
+         PORT=3000\n\
+         \n\
+         DB_POOL=5\n\
+         DEBUG=true\n\
+         HOST=\"${DOMAIN}\"\n"
+    );
+
+    assert_eq!(
+        format(INPUT, ShelterDialect::Edf, ShelterFormatSort::Grouped).unwrap(),
+        "# App settings\n\
+         \n\
+         export API_KEY='sk live'\n\
+         \n\
+         DB_POOL=5\n\
+         # Database\n\
+         DB_URL=postgres://db/app # primary\n\
+         \n\
+         DEBUG=true\n\
+         HOST=\"${DOMAIN}\"\n\
+         PORT=3000\n"
+    );
+
+    // The last definition of a duplicated key stays the effective one
+    let duplicates = "B=2\nA=first\nA=second\n";
+    assert_eq!(
+        format(
+            duplicates,
+            ShelterDialect::Edf,
+            ShelterFormatSort::Alphabetical
+        )
+        .unwrap(),
+        "A=first\nA=second\nB=2\n"
+    );
+}
+
+#[test]
+fn test_idempotent_and_value_preserving() {
+    let inputs = [
+        INPUT,
+        "MULTI=\"line one\nline two\"\nTAB=\"a\\tb\"\nQ=\"it's\"\n# OLD=secret\nE=\n",
+        "export A=1 # note\n\n\n# trailing comment\n",
+        "Z_B=1\nZ_A=2\nSOLO=3\n",
+    ];
+    let dialects = [
+        ShelterDialect::Edf,
+        ShelterDialect::Shell,
+        ShelterDialect::PythonDotenv,
+        ShelterDialect::NodeDotenv,
+    ];
+    let sorts = [
+        ShelterFormatSort::None,
+        ShelterFormatSort::Alphabetical,
+        ShelterFormatSort::Grouped,
+    ];
+
+    for dialect in dialects {
+        for input in inputs {
+            for sort in sorts {
+                let once = format(input, dialect, sort)
+                    .unwrap_or_else(|e| panic!("{:?} {:?}: {}", dialect, input, e));
+                let twice = format(&once, dialect, sort).unwrap();
+                assert_eq!(once, twice, "{:?} {:?}", dialect, sort);
+                assert_eq!(values(input, dialect), values(&once, dialect));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_refuses_what_it_cannot_format() {
+    let error = format("A=\"open\n", ShelterDialect::Edf, ShelterFormatSort::None).unwrap_err();
+    assert!(error.starts_with("Line 1:"), "{}", error);
+
+    let error = format("{\"a\": 1}", ShelterDialect::Json, ShelterFormatSort::None).unwrap_err();
+    assert!(error.contains("json"), "{}", error);
+
+    let error = format(
+        "# x\0y\nA=1\n",
+        ShelterDialect::Edf,
+        ShelterFormatSort::None,
+    )
+    .unwrap_err();
+    assert!(error.contains("NUL byte"), "{}", error);
+}
+
+#[test]
+fn test_refuses_to_drop_skipped_text() {
+    // The shell reads `value` as a command, so formatting would delete it
+    let error = format(
+        "A=1\nB=plain value\n",
+        ShelterDialect::Shell,
+        ShelterFormatSort::None,
+    )
+    .unwrap_err();
+    assert!(error.starts_with("Line 2:"), "{}", error);
+
+    let error = format(
+        "A=1\nnot an entry\n",
+        ShelterDialect::PythonDotenv,
+        ShelterFormatSort::None,
+    )
+    .unwrap_err();
+    assert!(error.starts_with("Line 2:"), "{}", error);
+}
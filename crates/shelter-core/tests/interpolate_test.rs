@@ -0,0 +1,232 @@
+//! Integration tests for shelter-core variable interpolation
+//!
+//! Verifies reference spans, resolved values, defaults, unresolved
+//! references and cycles as returned by `shelter_parse`.
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+use ShelterResolveStatus as S;
+
+#[derive(Debug)]
+struct Reference {
+    /// Source text of the whole reference
+    text: String,
+    /// Source text of the variable name
+    name: String,
+    kind: u8,
+    status: u8,
+    target: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: String,
+    value: String,
+    resolved: Option<String>,
+    status: u8,
+    is_reference: bool,
+    references: Vec<Reference>,
+}
+
+fn parse(input: &str) -> Vec<Entry> {
+    unsafe {
+        let result = shelter_parse(
+            input.as_ptr() as *const c_char,
+            input.len(),
+            ShelterParseOptions::default(),
+        );
+        let r = &*result;
+        assert!(r.error.is_null());
+
+        let entries = (0..r.count)
+            .map(|i| {
+                let e = &*r.entries.add(i);
+                let references = (e.reference_start..e.reference_start + e.reference_count)
+                    .map(|j| {
+                        let reference = &*r.references.add(j);
+                        Reference {
+                            text: input[reference.start..reference.end].to_string(),
+                            name: input[reference.name_start..reference.name_end].to_string(),
+                            kind: reference.kind,
+                            status: reference.status,
+                            target: (reference.has_target != 0).then_some(reference.target),
+                        }
+                    })
+                    .collect();
+                Entry {
+                    key: CStr::from_ptr(e.key).to_string_lossy().into_owned(),
+                    value: CStr::from_ptr(e.value).to_string_lossy().into_owned(),
+                    resolved: (!e.resolved.is_null())
+                        .then(|| CStr::from_ptr(e.resolved).to_string_lossy().into_owned()),
+                    status: e.resolve_status,
+                    is_reference: e.is_reference != 0,
+                    references,
+                }
+            })
+            .collect();
+
+        shelter_free_result(result);
+        entries
+    }
+}
+
+fn resolved(entries: &[Entry], key: &str) -> (Option<String>, u8) {
+    let entry = entries.iter().rev().find(|e| e.key == key).unwrap();
+    (entry.resolved.clone(), entry.status)
+}
+
+#[test]
+fn test_reference_spans_and_resolved_value() {
+    let input = "DB_HOST=localhost\nDB_PORT=5432\nDATABASE_URL=\"postgres://${DB_USER:-admin}@${DB_HOST}:$DB_PORT/app\"\n";
+    let entries = parse(input);
+
+    let url = &entries[2];
+    assert_eq!(
+        url.value,
+        "postgres://${DB_USER:-admin}@${DB_HOST}:$DB_PORT/app"
+    );
+    assert_eq!(
+        url.resolved.as_deref(),
+        Some("postgres://admin@localhost:5432/app")
+    );
+    assert_eq!(url.status, S::Defaulted as u8);
+    assert!(!url.is_reference);
+
+    let texts: Vec<&str> = url.references.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(texts, vec!["${DB_USER:-admin}", "${DB_HOST}", "$DB_PORT"]);
+    assert_eq!(url.references[0].name, "DB_USER");
+    assert_eq!(url.references[0].kind, ShelterReferenceKind::Default as u8);
+    assert_eq!(url.references[0].status, S::Defaulted as u8);
+    assert_eq!(url.references[0].target, None);
+    assert_eq!(url.references[1].target, Some(0));
+    assert_eq!(url.references[2].status, S::Resolved as u8);
+
+    // Values without references have nothing to resolve
+    assert_eq!(entries[0].resolved, None);
+    assert!(entries[0].references.is_empty());
+}
+
+#[test]
+fn test_pure_references_and_forward_references() {
+    let entries = parse("HOST=${DB_HOST}\nURL=http://$DB_HOST/\nDB_HOST=db\n");
+
+    assert!(entries[0].is_reference);
+    assert_eq!(entries[0].resolved.as_deref(), Some("db"));
+    assert!(!entries[1].is_reference);
+    assert_eq!(entries[1].resolved.as_deref(), Some("http://db/"));
+
+    // A default or alternate word is literal text, so never a pure reference
+    let entries = parse(
+        "PASS=${DB_PASSWORD:-hunter2supersecret}
+ALT=${DB_HOST:+s3cr3t}
+DB_HOST=db
+",
+    );
+    assert!(!entries[0].is_reference);
+    assert!(!entries[1].is_reference);
+}
+
+#[test]
+fn test_long_reference_chains() {
+    // Forward references thousands deep resolve without recursing
+    let input: String = (0..5000)
+        .map(|i| format!("A{}=${{A{}}}\n", i, i + 1))
+        .chain(std::iter::once("A5000=end\n".to_string()))
+        .collect();
+    let entries = parse(&input);
+    assert_eq!(
+        resolved(&entries, "A0"),
+        (Some("end".into()), S::Resolved as u8)
+    );
+
+    // A cycle closing at the end of the chain reaches its start
+    let input = input.replace("A5000=end", "A5000=${A0}");
+    let entries = parse(&input);
+    assert_eq!(resolved(&entries, "A0").1, S::Cycle as u8);
+    assert_eq!(resolved(&entries, "A4999").1, S::Cycle as u8);
+}
+
+#[test]
+fn test_unresolved_and_required_references() {
+    let entries = parse(
+        "A=${MISSING}\nB=\"${MISSING:?must be set}\"\nC=${EMPTY:-fallback}\nD=${EMPTY-kept}\nEMPTY=\n",
+    );
+
+    assert_eq!(
+        resolved(&entries, "A"),
+        (Some(String::new()), S::Unresolved as u8)
+    );
+    assert_eq!(
+        resolved(&entries, "B"),
+        (Some(String::new()), S::Unresolved as u8)
+    );
+    assert_eq!(
+        entries[1].references[0].kind,
+        ShelterReferenceKind::Required as u8
+    );
+    // `:-` treats empty as unset, `-` only unset
+    assert_eq!(
+        resolved(&entries, "C"),
+        (Some("fallback".into()), S::Defaulted as u8)
+    );
+    assert_eq!(
+        resolved(&entries, "D"),
+        (Some(String::new()), S::Resolved as u8)
+    );
+}
+
+#[test]
+fn test_alternate_and_nested_defaults() {
+    let entries = parse("SET=1\nA=${SET:+on}\nB=${UNSET:+on}\nC=${X:-${Y:-deep}}\nD=${X:-${Y}}\n");
+
+    assert_eq!(
+        resolved(&entries, "A"),
+        (Some("on".into()), S::Resolved as u8)
+    );
+    assert_eq!(
+        resolved(&entries, "B"),
+        (Some(String::new()), S::Resolved as u8)
+    );
+    assert_eq!(
+        resolved(&entries, "C"),
+        (Some("deep".into()), S::Defaulted as u8)
+    );
+    // An unresolved reference inside the default makes the whole reference unresolved
+    assert_eq!(
+        resolved(&entries, "D"),
+        (Some(String::new()), S::Unresolved as u8)
+    );
+    // Only top-level references are reported
+    assert_eq!(entries[3].references.len(), 1);
+}
+
+#[test]
+fn test_cycles() {
+    let entries = parse("A=${B}\nB=x${A}\nC=${A}\nSELF=${SELF}\n");
+
+    assert_eq!(resolved(&entries, "A").1, S::Cycle as u8);
+    assert_eq!(resolved(&entries, "B").1, S::Cycle as u8);
+    // Depending on a cycle is reported as one
+    assert_eq!(resolved(&entries, "C").1, S::Cycle as u8);
+    assert_eq!(resolved(&entries, "SELF").1, S::Cycle as u8);
+}
+
+#[test]
+fn test_self_reference_uses_earlier_definition() {
+    let entries = parse("PATH=/bin\nPATH=${PATH}:/usr/bin\n");
+
+    assert_eq!(entries[1].resolved.as_deref(), Some("/bin:/usr/bin"));
+    assert_eq!(entries[1].references[0].target, Some(0));
+}
+
+#[test]
+fn test_quotes_and_escapes() {
+    let entries = parse("B=b\nSINGLE='${B}'\nESCAPED=\"\\${B} ${B}\\n\"\nDOLLAR=cost$5\n");
+
+    assert_eq!(entries[1].resolved, None);
+    assert_eq!(entries[2].resolved.as_deref(), Some("${B} b\n"));
+    assert_eq!(entries[2].references.len(), 1);
+    assert_eq!(entries[3].resolved, None);
+}
@@ -0,0 +1,175 @@
+//! Integration tests for shelter-core layered env resolution
+//!
+//! Verifies load order per scheme, which file each key comes from and the
+//! definitions it shadows.
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+use ShelterLayerScheme as L;
+
+/// Key, origin (file, line) and shadowed (file, line) in precedence order
+type Key = (String, (usize, usize), Vec<(usize, usize)>);
+
+struct Layers {
+    keys: Vec<Key>,
+    /// (loaded, precedence, entry_count) per file
+    files: Vec<(bool, usize, usize)>,
+}
+
+fn resolve(files: &[(&str, &str)], scheme: L, mode: &str) -> Layers {
+    let raw: Vec<ShelterFile> = files
+        .iter()
+        .map(|(path, content)| ShelterFile {
+            path: path.as_ptr() as *const c_char,
+            path_len: path.len(),
+            content: content.as_ptr() as *const c_char,
+            content_len: content.len(),
+        })
+        .collect();
+
+    unsafe {
+        let result = shelter_resolve_layers(
+            raw.as_ptr(),
+            raw.len(),
+            scheme as u8,
+            mode.as_ptr() as *const c_char,
+            mode.len(),
+        );
+        let r = &*result;
+        assert!(r.error.is_null());
+
+        let keys = (0..r.key_count)
+            .map(|i| {
+                let k = &*r.keys.add(i);
+                let shadowed = (k.shadowed_start..k.shadowed_start + k.shadowed_count)
+                    .map(|j| {
+                        let o = &*r.shadowed.add(j);
+                        (o.file, o.line_number)
+                    })
+                    .collect();
+                (
+                    CStr::from_ptr(k.key).to_string_lossy().into_owned(),
+                    (k.origin.file, k.origin.line_number),
+                    shadowed,
+                )
+            })
+            .collect();
+        let files = (0..r.file_count)
+            .map(|i| {
+                let f = &*r.files.add(i);
+                (f.loaded != 0, f.precedence, f.entry_count)
+            })
+            .collect();
+
+        shelter_free_layer_result(result);
+        Layers { keys, files }
+    }
+}
+
+fn origin<'a>(layers: &'a Layers, key: &str) -> &'a Key {
+    layers.keys.iter().find(|k| k.0 == key).unwrap()
+}
+
+const DEV_FILES: [(&str, &str); 5] = [
+    ("app/.env.development.local", "A=4\n"),
+    ("app/.env.production", "A=prod\nPROD_ONLY=1\n"),
+    ("app/.env.local", "A=2\nLOCAL=1\n"),
+    ("app/.env.development", "A=3\nB=3\n"),
+    ("app/.env", "A=1\nB=1\nC=1\n"),
+];
+
+#[test]
+fn test_vite_precedence() {
+    let layers = resolve(&DEV_FILES, L::Vite, "development");
+
+    // .env < .env.local < .env.development < .env.development.local
+    assert_eq!(origin(&layers, "A").1, (0, 1));
+    assert_eq!(origin(&layers, "A").2, vec![(3, 1), (2, 1), (4, 1)]);
+    assert_eq!(origin(&layers, "B").1, (3, 2));
+    assert_eq!(origin(&layers, "B").2, vec![(4, 2)]);
+    assert_eq!(origin(&layers, "C").1, (4, 3));
+    assert!(origin(&layers, "C").2.is_empty());
+
+    // The production file is not loaded and contributes nothing
+    assert!(layers.keys.iter().all(|k| k.0 != "PROD_ONLY"));
+    assert_eq!(layers.files[1], (false, 0, 0));
+    assert_eq!(layers.files[4], (true, 0, 3));
+    assert_eq!(layers.files[0], (true, 3, 1));
+}
+
+#[test]
+fn test_next_and_rails_precedence() {
+    for scheme in [L::Next, L::Rails] {
+        let layers = resolve(&DEV_FILES, scheme, "development");
+
+        // .env.local wins over .env.development
+        assert_eq!(origin(&layers, "A").2, vec![(2, 1), (3, 1), (4, 1)]);
+        assert_eq!(layers.files[2].1, 2);
+        assert_eq!(layers.files[3].1, 1);
+    }
+}
+
+#[test]
+fn test_next_skips_local_in_test_mode() {
+    let files = [
+        (".env", "A=1\n"),
+        (".env.local", "A=2\n"),
+        (".env.test", "B=1\n"),
+    ];
+    let layers = resolve(&files, L::Next, "test");
+
+    assert_eq!(origin(&layers, "A").1, (0, 1));
+    assert!(!layers.files[1].0);
+    assert!(layers.files[2].0);
+
+    // Vite has no such exception
+    let layers = resolve(&files, L::Vite, "test");
+    assert_eq!(origin(&layers, "A").1, (1, 1));
+}
+
+#[test]
+fn test_compose_uses_list_order() {
+    let files = [
+        ("common.env", "A=1\nB=1\n"),
+        ("web.env", "A=2\n"),
+        ("override.env", "B=3\n"),
+    ];
+    let layers = resolve(&files, L::Compose, "");
+
+    assert_eq!(origin(&layers, "A").1, (1, 1));
+    assert_eq!(origin(&layers, "B").1, (2, 1));
+    assert_eq!(origin(&layers, "B").2, vec![(0, 2)]);
+    let keys: Vec<&str> = layers.keys.iter().map(|k| k.0.as_str()).collect();
+    assert_eq!(keys, vec!["A", "B"]);
+}
+
+#[test]
+fn test_duplicates_within_a_file_are_shadowed() {
+    let layers = resolve(&[(".env", "A=1\n# A=x\nA=2\n")], L::Vite, "");
+
+    assert_eq!(origin(&layers, "A").1, (0, 3));
+    assert_eq!(origin(&layers, "A").2, vec![(0, 1)]);
+}
+
+#[test]
+fn test_invalid_scheme_and_utf8() {
+    unsafe {
+        let result = shelter_resolve_layers(std::ptr::null(), 0, 9, std::ptr::null(), 0);
+        assert!(!(*result).error.is_null());
+        shelter_free_layer_result(result);
+
+        let content = [b'A', b'=', 0xff];
+        let file = ShelterFile {
+            path: ".env".as_ptr() as *const c_char,
+            path_len: 4,
+            content: content.as_ptr() as *const c_char,
+            content_len: content.len(),
+        };
+        let result = shelter_resolve_layers(&file, 1, 0, std::ptr::null(), 0);
+        let error = CStr::from_ptr((*result).error).to_string_lossy();
+        assert!(error.starts_with(".env: Invalid UTF-8"));
+        shelter_free_layer_result(result);
+    }
+}
@@ -0,0 +1,142 @@
+//! Integration tests for the shelter-core linter
+//!
+//! Verifies rule codes, severities and spans of findings, and that config
+//! formats only get the rules that apply to them.
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+/// (rule, severity, line, column, flagged text) of every finding
+fn lint(input: &str, dialect: ShelterDialect) -> Vec<(u8, u8, usize, usize, String)> {
+    unsafe {
+        let result = shelter_lint(input.as_ptr() as *const c_char, input.len(), dialect as u8);
+        let r = &*result;
+        assert!(r.error.is_null());
+        let findings = (0..r.count)
+            .map(|i| {
+                let f = &*r.findings.add(i);
+                assert!(!CStr::from_ptr(f.message).to_bytes().is_empty());
+                (
+                    f.rule,
+                    f.severity,
+                    f.line,
+                    f.column,
+                    input[f.start..f.end].to_string(),
+                )
+            })
+            .collect();
+        shelter_free_lint_result(result);
+        findings
+    }
+}
+
+fn rules(input: &str, dialect: ShelterDialect) -> Vec<ShelterLintRule> {
+    lint(input, dialect)
+        .into_iter()
+        .map(|f| match f.0 {
+            0 => ShelterLintRule::ParseError,
+            1 => ShelterLintRule::LowercaseKey,
+            2 => ShelterLintRule::NonPortableKey,
+            3 => ShelterLintRule::SpacesAroundSeparator,
+            4 => ShelterLintRule::UnquotedSpecialValue,
+            5 => ShelterLintRule::InconsistentQuotes,
+            6 => ShelterLintRule::MissingFinalNewline,
+            7 => ShelterLintRule::MixedExport,
+            8 => ShelterLintRule::EmptyRequiredValue,
+            other => panic!("unknown rule {}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn test_clean_file_has_no_findings() {
+    let input = "# Database\nexport DB_URL='postgres://db'\nexport PORT=5432 # default\n";
+    assert!(lint(input, ShelterDialect::Edf).is_empty());
+}
+
+#[test]
+fn test_key_and_separator_rules() {
+    let input = "api_key=1\nMY-KEY=2\nHOST = example.com\n";
+    let findings = lint(input, ShelterDialect::DockerCompose);
+    let warning = ShelterLintSeverity::Warning as u8;
+    assert_eq!(
+        findings,
+        vec![
+            (
+                ShelterLintRule::LowercaseKey as u8,
+                warning,
+                1,
+                0,
+                "api_key".to_string()
+            ),
+            (
+                ShelterLintRule::NonPortableKey as u8,
+                warning,
+                2,
+                0,
+                "MY-KEY".to_string()
+            ),
+            (
+                ShelterLintRule::SpacesAroundSeparator as u8,
+                warning,
+                3,
+                4,
+                " = ".to_string()
+            ),
+        ]
+    );
+
+    // EDF rejects the spaces outright
+    assert_eq!(
+        rules("HOST = x\n", ShelterDialect::Edf)[0],
+        ShelterLintRule::ParseError
+    );
+}
+
+#[test]
+fn test_value_rules() {
+    // Ties go to the first quoted value; D needs its double quotes
+    let input = "A='one'\nB='two'\nC=\"three\"\nD=\"it's\"\nE=a#b\nF=a b\n";
+    let findings = lint(input, ShelterDialect::DockerCompose);
+    let flagged: Vec<_> = findings.iter().map(|f| (f.0, f.4.as_str())).collect();
+    assert_eq!(
+        flagged,
+        vec![
+            (ShelterLintRule::InconsistentQuotes as u8, "\"three\""),
+            (ShelterLintRule::UnquotedSpecialValue as u8, "a#b"),
+            (ShelterLintRule::UnquotedSpecialValue as u8, "a b"),
+        ]
+    );
+    assert_eq!(findings[0].1, ShelterLintSeverity::Info as u8);
+
+    // Single quotes are needed for a value with `$`
+    let input = "A=\"one\"\nB=\"two\"\nC='$HOME'\n";
+    assert!(lint(input, ShelterDialect::PythonDotenv).is_empty());
+}
+
+#[test]
+fn test_file_rules() {
+    let input =
+        "export A=1\nexport B=2\nC=3\n# @required\nexport D=\nexport E= # @required\nexport F=";
+    assert_eq!(
+        rules(input, ShelterDialect::Shell),
+        vec![
+            ShelterLintRule::MixedExport,
+            ShelterLintRule::EmptyRequiredValue,
+            ShelterLintRule::EmptyRequiredValue,
+            ShelterLintRule::MissingFinalNewline,
+        ]
+    );
+    let findings = lint(input, ShelterDialect::Shell);
+    assert_eq!(findings[0].4, "C");
+    assert_eq!(findings[1].1, ShelterLintSeverity::Error as u8);
+    assert_eq!((findings[3].2, findings[3].3), (7, 9));
+
+    // Keys and quoting follow other conventions in config formats
+    let json = "{\"db_url\": \"x\", \"Port\": \"y z\"}";
+    assert_eq!(
+        rules(json, ShelterDialect::Json),
+        vec![ShelterLintRule::MissingFinalNewline]
+    );
+}
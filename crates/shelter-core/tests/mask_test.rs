@@ -0,0 +1,362 @@
+//! Integration tests for shelter-core masking
+//!
+//! Verifies the built-in modes (full, partial, none) through `shelter_mask`
+//! and `shelter_mask_value`, which the Lua modes delegate to.
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+/// Helper to safely mask content and extract results
+unsafe fn mask_content(content: &str, options: ShelterMaskOptions) -> MaskResult {
+    let result = shelter_mask(content.as_ptr() as *const c_char, content.len(), options);
+
+    assert!(!result.is_null(), "shelter_mask returned null");
+
+    let result_ref = &*result;
+
+    if !result_ref.error.is_null() {
+        let error_msg = CStr::from_ptr(result_ref.error)
+            .to_string_lossy()
+            .into_owned();
+        shelter_free_mask_result(result);
+        panic!("Mask error: {}", error_msg);
+    }
+
+    let mut masks = Vec::new();
+    for i in 0..result_ref.count {
+        let mask = &*result_ref.masks.add(i);
+        masks.push(MaskedEntry {
+            mask: CStr::from_ptr(mask.mask).to_string_lossy().into_owned(),
+            value_start: mask.value_start,
+            value_end: mask.value_end,
+            line_number: mask.line_number,
+            value_end_line: mask.value_end_line,
+            value_len: mask.value_len,
+            masked_start: mask.masked_start,
+            masked_end: mask.masked_end,
+            quote_type: mask.quote_type,
+            is_comment: mask.is_comment != 0,
+        });
+    }
+
+    let line_count = result_ref.line_count;
+
+    shelter_free_mask_result(result);
+
+    MaskResult { masks, line_count }
+}
+
+fn mode(mode: ShelterMaskMode) -> ShelterMaskOptions {
+    ShelterMaskOptions {
+        mode: ShelterModeConfig {
+            mode: mode as u8,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MaskedEntry {
+    mask: String,
+    value_start: usize,
+    value_end: usize,
+    line_number: usize,
+    value_end_line: usize,
+    value_len: usize,
+    masked_start: usize,
+    masked_end: usize,
+    quote_type: u8,
+    is_comment: bool,
+}
+
+#[derive(Debug)]
+struct MaskResult {
+    masks: Vec<MaskedEntry>,
+    line_count: usize,
+}
+
+// =============================================================================
+// Full Mode Tests
+// =============================================================================
+
+#[test]
+fn test_full_mode_preserves_length() {
+    let result = unsafe { mask_content("SECRET=mysecret", mode(ShelterMaskMode::Full)) };
+
+    assert_eq!(result.masks.len(), 1);
+    let mask = &result.masks[0];
+    assert_eq!(mask.mask, "********");
+    assert_eq!(mask.value_len, 8);
+    assert_eq!(mask.masked_start, 0);
+    assert_eq!(mask.masked_end, 8);
+}
+
+#[test]
+fn test_full_mode_fixed_length() {
+    let mut options = mode(ShelterMaskMode::Full);
+    options.mode.fixed_length = 4;
+    let result = unsafe { mask_content("SECRET=mysecret", options) };
+
+    assert_eq!(result.masks[0].mask, "****");
+    // The whole value is still covered by the mask
+    assert_eq!(result.masks[0].masked_end, 8);
+}
+
+#[test]
+fn test_full_mode_custom_mask_char() {
+    let mut options = mode(ShelterMaskMode::Full);
+    options.mode.mask_char = b'#';
+    let result = unsafe { mask_content("KEY=abc", options) };
+
+    assert_eq!(result.masks[0].mask, "###");
+}
+
+#[test]
+fn test_full_mode_skips_empty_and_identical_values() {
+    // Mask identical to value produces no record, like the Lua engine
+    let result = unsafe { mask_content("EMPTY=\nSTARS=***", mode(ShelterMaskMode::Full)) };
+
+    assert!(result.masks.is_empty());
+}
+
+// =============================================================================
+// Partial Mode Tests
+// =============================================================================
+
+#[test]
+fn test_partial_mode_shows_start_and_end() {
+    let mut options = mode(ShelterMaskMode::Partial);
+    options.mode.show_start = 2;
+    options.mode.show_end = 2;
+    let result = unsafe { mask_content("AUTH_TOKEN=secrettoken", options) };
+
+    let mask = &result.masks[0];
+    assert_eq!(mask.mask, "se*******en");
+    assert_eq!(mask.masked_start, 2);
+    assert_eq!(mask.masked_end, 9);
+}
+
+#[test]
+fn test_partial_mode_zero_show_end() {
+    let mut options = mode(ShelterMaskMode::Partial);
+    options.mode.show_end = 0;
+    let result = unsafe { mask_content("KEY=abcdefgh", options) };
+
+    assert_eq!(result.masks[0].mask, "abc*****");
+    assert_eq!(result.masks[0].masked_end, 8);
+}
+
+#[test]
+fn test_partial_mode_short_value_falls_back_to_full() {
+    // 3 + 3 + 3 = 9 bytes required for partial masking
+    let result = unsafe { mask_content("KEY=short", mode(ShelterMaskMode::Partial)) };
+
+    assert_eq!(result.masks[0].mask, "*****");
+    assert_eq!(result.masks[0].masked_start, 0);
+    assert_eq!(result.masks[0].masked_end, 5);
+}
+
+#[test]
+fn test_partial_mode_short_value_falls_back_to_none() {
+    let mut options = mode(ShelterMaskMode::Partial);
+    options.mode.fallback_mode = ShelterMaskMode::None as u8;
+    let result = unsafe { mask_content("KEY=short\nLONG=longer_value", options) };
+
+    assert_eq!(result.masks.len(), 1);
+    assert_eq!(result.masks[0].mask, "lon******lue");
+}
+
+#[test]
+fn test_mask_single_value() {
+    let mask = |value: &[u8], config: ShelterModeConfig| unsafe {
+        let mask = shelter_mask_value(value.as_ptr() as *const c_char, value.len(), config);
+        let text = (!mask.is_null()).then(|| CStr::from_ptr(mask).to_bytes().to_vec());
+        shelter_free_string(mask);
+        text
+    };
+    let partial = ShelterModeConfig {
+        mode: ShelterMaskMode::Partial as u8,
+        ..Default::default()
+    };
+
+    assert_eq!(mask(b"abcdefghij", partial), Some(b"abc****hij".to_vec()));
+    assert_eq!(
+        mask(
+            b"abcdefghij",
+            ShelterModeConfig {
+                show_end: 0,
+                ..partial
+            }
+        ),
+        Some(b"abc*******".to_vec())
+    );
+    // Raw bytes, like Lua strings
+    assert_eq!(
+        mask(b"\xff\xfe-secret-\xfd", partial),
+        Some(b"\xff\xfe-*****t-\xfd".to_vec())
+    );
+    // Identical masks are reported as null
+    assert_eq!(mask(b"***", ShelterModeConfig::default()), None);
+    assert_eq!(mask(b"", ShelterModeConfig::default()), None);
+    let none = ShelterModeConfig {
+        mode: ShelterMaskMode::None as u8,
+        ..Default::default()
+    };
+    assert_eq!(mask(b"secret", none), None);
+
+    // NUL bytes would cut the C string short, so they are masked too
+    assert_eq!(mask(b"ab\0defgh\0j", partial), Some(b"ab*****h*j".to_vec()));
+    let nul_char = ShelterModeConfig {
+        mask_char: 0,
+        ..Default::default()
+    };
+    assert_eq!(mask(b"secret", nul_char), Some(b"******".to_vec()));
+}
+
+// =============================================================================
+// None Mode Tests
+// =============================================================================
+
+#[test]
+fn test_none_mode_produces_no_masks() {
+    let result = unsafe { mask_content("KEY1=secret1\nKEY2=secret2", mode(ShelterMaskMode::None)) };
+
+    assert!(result.masks.is_empty());
+    assert_eq!(result.line_count, 2);
+}
+
+// =============================================================================
+// Span and Comment Tests
+// =============================================================================
+
+#[test]
+fn test_mask_spans_and_quote_type() {
+    let result = unsafe { mask_content("A=1\nKEY='secret'", mode(ShelterMaskMode::Full)) };
+
+    assert_eq!(result.masks.len(), 2);
+    let mask = &result.masks[1];
+    assert_eq!(mask.line_number, 2);
+    assert_eq!(mask.value_end_line, 2);
+    assert_eq!(mask.value_start, 8); // Opening quote
+    assert_eq!(mask.value_end, 16); // Past closing quote
+    assert_eq!(mask.quote_type, 1);
+    assert_eq!(mask.mask, "******");
+}
+
+#[test]
+fn test_mask_multiline_value() {
+    let result = unsafe { mask_content("KEY=\"first\nsecond\"", mode(ShelterMaskMode::Full)) };
+
+    let mask = &result.masks[0];
+    assert_eq!(mask.line_number, 1);
+    assert_eq!(mask.value_end_line, 2);
+    assert_eq!(mask.mask.len(), "first\nsecond".len());
+}
+
+#[test]
+fn test_mask_skip_comments() {
+    let content = "#COMMENTED=secret\nREAL=value";
+
+    let skipped = unsafe { mask_content(content, mode(ShelterMaskMode::Full)) };
+    assert!(skipped.masks.iter().all(|m| !m.is_comment));
+
+    let mut options = mode(ShelterMaskMode::Full);
+    options.skip_comments = 0;
+    let included = unsafe { mask_content(content, options) };
+    assert!(included.masks.iter().any(|m| m.is_comment));
+}
+
+#[test]
+fn test_mask_skips_pure_references() {
+    let content = "HOST=${DB_HOST}\nURL=http://${DB_HOST}/\nRAW='${DB_HOST}'\nDB_HOST=db";
+    let result = unsafe { mask_content(content, mode(ShelterMaskMode::Full)) };
+
+    let lines: Vec<usize> = result.masks.iter().map(|m| m.line_number).collect();
+    assert_eq!(lines, vec![2, 3, 4]);
+
+    // A default or alternate word is written out in the file, so it is masked
+    let content =
+        "PASS=${DB_PASSWORD:-hunter2supersecret}\nALT=${DB_HOST:+s3cr3t}\nBARE=$DB_HOST\nDB_HOST=db";
+    let result = unsafe { mask_content(content, mode(ShelterMaskMode::Full)) };
+
+    let lines: Vec<usize> = result.masks.iter().map(|m| m.line_number).collect();
+    assert_eq!(lines, vec![1, 2, 4]);
+}
+
+#[test]
+fn test_mask_reference_shaped_secrets() {
+    // Nothing defines `Sup3rSecret`, so the value is not a reference
+    let content = "A=1\nB=$Sup3rSecret\n";
+    let result = unsafe { mask_content(content, mode(ShelterMaskMode::Full)) };
+    let masks: Vec<&str> = result.masks.iter().map(|m| m.mask.as_str()).collect();
+    assert_eq!(masks, vec!["*", "************"]);
+
+    // Config formats and dialects whose loaders do not expand references
+    // read `$` as literal text
+    let cases = [
+        (
+            ShelterDialect::Json,
+            "{\"password\": \"$Sup3rSecret\", \"Sup3rSecret\": \"x\"}",
+        ),
+        (
+            ShelterDialect::Toml,
+            "password = \"$Sup3rSecret\"\nSup3rSecret = \"x\"\n",
+        ),
+        (
+            ShelterDialect::Yaml,
+            "password: $Sup3rSecret\nSup3rSecret: x\n",
+        ),
+        (
+            ShelterDialect::Ini,
+            "password = $Sup3rSecret\nSup3rSecret = x\n",
+        ),
+        (
+            ShelterDialect::NodeDotenv,
+            "password=$Sup3rSecret\nSup3rSecret=x\n",
+        ),
+    ];
+    for (dialect, content) in cases {
+        let mut options = mode(ShelterMaskMode::Full);
+        options.dialect = dialect as u8;
+        let result = unsafe { mask_content(content, options) };
+        let masks: Vec<&str> = result.masks.iter().map(|m| m.mask.as_str()).collect();
+        assert_eq!(masks, vec!["************", "*"], "{:?}", dialect);
+    }
+}
+
+#[test]
+fn test_mask_follows_dialect() {
+    let content = "{\"db\": {\"password\": \"hunter2\"}, \"host\": \"${DB_HOST}\"}";
+
+    // As EDF the JSON is one malformed line
+    let edf = unsafe { mask_content(content, mode(ShelterMaskMode::Full)) };
+    assert!(edf.masks.is_empty());
+
+    let mut options = mode(ShelterMaskMode::Full);
+    options.dialect = ShelterDialect::Json as u8;
+    let json = unsafe { mask_content(content, options) };
+    // JSON strings are never references
+    assert_eq!(json.masks.len(), 2);
+    assert_eq!(json.masks[0].mask, "*******");
+    assert_eq!(json.masks[1].mask, "**********");
+    assert_eq!(
+        &content[json.masks[0].value_start..json.masks[0].value_end],
+        "\"hunter2\""
+    );
+
+    options.dialect = ShelterDialect::Auto as u8;
+    let auto = unsafe { mask_content(content, options) };
+    assert_eq!(auto.masks.len(), 2);
+}
+
+#[test]
+fn test_mask_null_input() {
+    unsafe {
+        let result = shelter_mask(std::ptr::null(), 0, ShelterMaskOptions::default());
+        assert!(!result.is_null());
+        assert!(!(*result).error.is_null());
+        shelter_free_mask_result(result);
+    }
+}
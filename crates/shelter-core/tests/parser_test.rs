@@ -15,6 +15,8 @@ unsafe fn parse_content(content: &str) -> ParseResult {
     let opts = ShelterParseOptions {
         include_comments: 1,
         track_positions: 1,
+        dialect: ShelterDialect::Edf as u8,
+        fingerprint: 0,
     };
 
     let result = shelter_parse(content.as_ptr() as *const c_char, content.len(), opts);
@@ -48,6 +50,21 @@ unsafe fn parse_content(content: &str) -> ParseResult {
             quote_type: entry.quote_type,
             is_exported: entry.is_exported != 0,
             is_comment: entry.is_comment != 0,
+            inline_comment: (entry.has_inline_comment != 0)
+                .then_some((entry.inline_comment_start, entry.inline_comment_end)),
+        });
+    }
+
+    // Extract comments
+    let mut comments = Vec::new();
+    for i in 0..result_ref.comment_count {
+        let comment = &*result_ref.comments.add(i);
+        comments.push(ParsedComment {
+            text: CStr::from_ptr(comment.text).to_string_lossy().into_owned(),
+            start: comment.start,
+            end: comment.end,
+            line_number: comment.line_number,
+            is_inline: comment.is_inline != 0,
         });
     }
 
@@ -57,11 +74,29 @@ unsafe fn parse_content(content: &str) -> ParseResult {
         line_offsets.push(*result_ref.line_offsets.add(i));
     }
 
+    // Extract diagnostics
+    let mut diagnostics = Vec::new();
+    for i in 0..result_ref.diagnostic_count {
+        let diag = &*result_ref.diagnostics.add(i);
+        diagnostics.push(ParsedDiagnostic {
+            message: CStr::from_ptr(diag.message).to_string_lossy().into_owned(),
+            start: diag.start,
+            end: diag.end,
+            line: diag.line,
+            column: diag.column,
+            end_line: diag.end_line,
+            end_column: diag.end_column,
+            code: diag.code,
+        });
+    }
+
     shelter_free_result(result);
 
     ParseResult {
         entries,
         line_offsets,
+        comments,
+        diagnostics,
     }
 }
 
@@ -78,12 +113,36 @@ struct ParsedEntry {
     quote_type: u8,
     is_exported: bool,
     is_comment: bool,
+    inline_comment: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone)]
+struct ParsedComment {
+    text: String,
+    start: usize,
+    end: usize,
+    line_number: usize,
+    is_inline: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ParsedDiagnostic {
+    message: String,
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+    end_line: usize,
+    end_column: usize,
+    code: u8,
 }
 
 #[derive(Debug)]
 struct ParseResult {
     entries: Vec<ParsedEntry>,
     line_offsets: Vec<usize>,
+    comments: Vec<ParsedComment>,
+    diagnostics: Vec<ParsedDiagnostic>,
 }
 
 // =============================================================================
@@ -438,6 +497,8 @@ fn test_parse_null_input() {
         let opts = ShelterParseOptions {
             include_comments: 1,
             track_positions: 1,
+            dialect: ShelterDialect::Edf as u8,
+            fingerprint: 0,
         };
 
         let result = shelter_parse(std::ptr::null(), 0, opts);
@@ -469,6 +530,8 @@ fn test_double_free_safety() {
         let opts = ShelterParseOptions {
             include_comments: 1,
             track_positions: 1,
+            dialect: ShelterDialect::Edf as u8,
+            fingerprint: 0,
         };
 
         let result = shelter_parse(content.as_ptr() as *const c_char, content.len(), opts);
@@ -536,3 +599,157 @@ fn test_quoted_value_span_includes_quotes() {
     // The value string itself should NOT contain quotes
     assert_eq!(entry.value, "secret");
 }
+
+// =============================================================================
+// Comment Tests
+// =============================================================================
+
+#[test]
+fn test_parse_comments_fixture_comment_entries() {
+    let content = fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/comments.env"),
+    )
+    .unwrap();
+    let result = unsafe { parse_content(&content) };
+
+    let comments: Vec<(&str, usize, bool)> = result
+        .comments
+        .iter()
+        .map(|c| (c.text.as_str(), c.line_number, c.is_inline))
+        .collect();
+    assert_eq!(
+        comments,
+        vec![
+            ("# This is a pure comment line", 1, false),
+            (
+                "#COMMENTED_KEY=should_not_be_masked_when_skip_comments_true",
+                4,
+                false
+            ),
+            ("# this inline comment stays visible", 6, true),
+            ("# with comment", 7, true),
+        ]
+    );
+
+    for comment in &result.comments {
+        assert_eq!(&content[comment.start..comment.end], comment.text);
+    }
+}
+
+#[test]
+fn test_inline_comment_span_on_entry() {
+    let content = "KEY_WITH_INLINE=value # this inline comment stays visible\nPLAIN=1\n";
+    let result = unsafe { parse_content(content) };
+
+    let inline = &result.entries[0];
+    let (start, end) = inline.inline_comment.expect("inline comment span");
+    assert_eq!(&content[start..end], "# this inline comment stays visible");
+    assert_eq!(result.entries[1].inline_comment, None);
+}
+
+#[test]
+fn test_inline_comment_after_multiline_value() {
+    let content = "CERT=\"line1\nline2\" # trailing\n";
+    let result = unsafe { parse_content(content) };
+
+    assert_eq!(result.comments.len(), 1);
+    assert_eq!(result.comments[0].line_number, 2);
+    assert!(result.comments[0].is_inline);
+
+    let (start, end) = result.entries[0].inline_comment.unwrap();
+    assert_eq!(&content[start..end], "# trailing");
+}
+
+#[test]
+fn test_comment_on_next_line_is_not_inline() {
+    let content = "KEY=value\n# standalone\n#DISABLED=x # note\n";
+    let result = unsafe { parse_content(content) };
+
+    assert_eq!(result.entries[0].inline_comment, None);
+    assert!(result.comments.iter().all(|c| !c.is_inline));
+
+    // Commented-out pairs never own an inline comment
+    let disabled = result.entries.iter().find(|e| e.key == "DISABLED").unwrap();
+    assert!(disabled.is_comment);
+    assert_eq!(disabled.inline_comment, None);
+}
+
+#[test]
+fn test_hash_inside_value_is_not_a_comment() {
+    let result = unsafe { parse_content("KEY=value#notcomment\nQUOTED=\"a # b\"\n") };
+
+    assert!(result.comments.is_empty(), "{:?}", result.comments);
+    assert_eq!(result.entries[0].value, "value#notcomment");
+    assert_eq!(result.entries[1].value, "a # b");
+}
+
+// =============================================================================
+// Diagnostics Tests
+// =============================================================================
+
+#[test]
+fn test_valid_content_has_no_diagnostics() {
+    let content =
+        fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/simple.env"))
+            .unwrap();
+    let result = unsafe { parse_content(&content) };
+
+    assert!(result.diagnostics.is_empty(), "{:?}", result.diagnostics);
+}
+
+#[test]
+fn test_diagnostic_unclosed_quote_covers_rest_of_input() {
+    let content = "A=1\nB=\"open\nC=3\n";
+    let result = unsafe { parse_content(content) };
+
+    // The unclosed quote swallows C
+    assert_eq!(result.entries.len(), 1);
+    assert_eq!(result.diagnostics.len(), 1);
+
+    let diag = &result.diagnostics[0];
+    assert_eq!(diag.code, ShelterDiagnosticCode::UnclosedQuote as u8);
+    assert_eq!(diag.message, "Unclosed double quote");
+    assert_eq!(diag.start, 6);
+    assert_eq!(diag.end, content.len());
+    assert_eq!((diag.line, diag.column), (2, 2));
+    assert_eq!((diag.end_line, diag.end_column), (4, 0));
+}
+
+#[test]
+fn test_diagnostic_whitespace_around_equals() {
+    let content = "GOOD=1\nBAD =2\nNEXT=3\n";
+    let result = unsafe { parse_content(content) };
+
+    // Parsing recovers on the next line
+    let keys: Vec<&str> = result.entries.iter().map(|e| e.key.as_str()).collect();
+    assert_eq!(keys, vec!["GOOD", "NEXT"]);
+
+    assert_eq!(result.diagnostics.len(), 1);
+    let diag = &result.diagnostics[0];
+    assert_eq!(diag.code, ShelterDiagnosticCode::ForbiddenWhitespace as u8);
+    assert_eq!(diag.line, 2);
+    assert_eq!(diag.end_line, 2);
+    assert_eq!(diag.end, 13, "line-local errors end at the line end");
+}
+
+#[test]
+fn test_diagnostic_double_equals() {
+    let result = unsafe { parse_content("KEY==value\n") };
+
+    assert_eq!(result.diagnostics.len(), 1);
+    assert_eq!(
+        result.diagnostics[0].code,
+        ShelterDiagnosticCode::DoubleEquals as u8
+    );
+    assert!(!result.diagnostics[0].message.contains("at byte"));
+}
+
+#[test]
+fn test_diagnostic_invalid_key() {
+    let result = unsafe { parse_content("OK=1\n1BAD=2\n") };
+
+    assert_eq!(result.entries.len(), 1);
+    assert_eq!(result.diagnostics.len(), 1);
+    assert_eq!(result.diagnostics[0].line, 2);
+    assert_eq!(result.diagnostics[0].column, 0);
+}
@@ -0,0 +1,284 @@
+//! Integration tests for shelter-core pattern sets
+//!
+//! Verifies glob syntax, negation, regex rules and deterministic
+//! ranking of key and source rules.
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+/// Owned pattern set that frees itself on drop
+struct PatternSet(*mut ShelterPatternSet);
+
+impl Drop for PatternSet {
+    fn drop(&mut self) {
+        unsafe { shelter_pattern_set_free(self.0) };
+    }
+}
+
+fn rules(pairs: &[(&str, &str)]) -> Vec<ShelterPatternRule> {
+    pairs
+        .iter()
+        .map(|(pattern, mode)| ShelterPatternRule {
+            pattern: pattern.as_ptr() as *const c_char,
+            pattern_len: pattern.len(),
+            mode: mode.as_ptr() as *const c_char,
+            mode_len: mode.len(),
+        })
+        .collect()
+}
+
+/// Helper to build a pattern set, returning the error message on failure
+fn build(
+    patterns: &[(&str, &str)],
+    sources: &[(&str, &str)],
+    default_mode: &str,
+) -> Result<PatternSet, String> {
+    let patterns = rules(patterns);
+    let sources = rules(sources);
+    let mut error: *mut c_char = std::ptr::null_mut();
+
+    unsafe {
+        let set = shelter_pattern_set_new(
+            patterns.as_ptr(),
+            patterns.len(),
+            sources.as_ptr(),
+            sources.len(),
+            default_mode.as_ptr() as *const c_char,
+            default_mode.len(),
+            &mut error,
+        );
+
+        if set.is_null() {
+            let message = CStr::from_ptr(error).to_string_lossy().into_owned();
+            shelter_free_string(error);
+            return Err(message);
+        }
+
+        Ok(PatternSet(set))
+    }
+}
+
+unsafe fn to_option(mode: *const c_char) -> Option<String> {
+    if mode.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(mode).to_string_lossy().into_owned())
+    }
+}
+
+fn resolve(set: &PatternSet, key: &str, source: Option<&str>) -> String {
+    let (source_ptr, source_len) = source.map_or((std::ptr::null(), 0), |s| (s.as_ptr(), s.len()));
+    unsafe {
+        to_option(shelter_pattern_set_resolve(
+            set.0,
+            key.as_ptr() as *const c_char,
+            key.len(),
+            source_ptr as *const c_char,
+            source_len,
+        ))
+        .expect("resolve never returns null for a valid set")
+    }
+}
+
+fn match_key(set: &PatternSet, key: &str) -> Option<String> {
+    unsafe {
+        to_option(shelter_pattern_set_match_key(
+            set.0,
+            key.as_ptr() as *const c_char,
+            key.len(),
+        ))
+    }
+}
+
+// =============================================================================
+// Key Pattern Tests
+// =============================================================================
+
+#[test]
+fn test_default_mode_when_nothing_matches() {
+    let set = build(&[("*_TOKEN", "partial")], &[], "full").unwrap();
+
+    assert_eq!(resolve(&set, "DATABASE_URL", None), "full");
+    assert_eq!(match_key(&set, "DATABASE_URL"), None);
+}
+
+#[test]
+fn test_star_glob() {
+    let set = build(&[("*_TOKEN", "partial"), ("API_*", "none")], &[], "full").unwrap();
+
+    assert_eq!(resolve(&set, "AUTH_TOKEN", None), "partial");
+    assert_eq!(resolve(&set, "API_KEY", None), "none");
+}
+
+#[test]
+fn test_specificity_order() {
+    let set = build(
+        &[
+            ("*", "none"),
+            ("SECRET_*", "partial"),
+            ("SECRET_KEY", "full"),
+        ],
+        &[],
+        "full",
+    )
+    .unwrap();
+
+    assert_eq!(resolve(&set, "SECRET_KEY", None), "full");
+    assert_eq!(resolve(&set, "SECRET_OTHER", None), "partial");
+    assert_eq!(resolve(&set, "PORT", None), "none");
+}
+
+#[test]
+fn test_ties_are_independent_of_rule_order() {
+    // Same specificity: the lexicographically smaller pattern wins
+    let forward = build(&[("A_*", "partial"), ("*_B", "none")], &[], "full").unwrap();
+    let reverse = build(&[("*_B", "none"), ("A_*", "partial")], &[], "full").unwrap();
+
+    assert_eq!(resolve(&forward, "A_B", None), "none");
+    assert_eq!(resolve(&reverse, "A_B", None), "none");
+}
+
+#[test]
+fn test_question_mark_and_character_class() {
+    let set = build(
+        &[("KEY_?", "none"), ("DB_[0-9]_PASS", "partial")],
+        &[],
+        "full",
+    )
+    .unwrap();
+
+    assert_eq!(resolve(&set, "KEY_A", None), "none");
+    assert_eq!(resolve(&set, "KEY_AB", None), "full");
+    assert_eq!(resolve(&set, "DB_1_PASS", None), "partial");
+    assert_eq!(resolve(&set, "DB_X_PASS", None), "full");
+}
+
+#[test]
+fn test_negated_character_class() {
+    let set = build(&[("[!A-Z]*", "none")], &[], "full").unwrap();
+
+    assert_eq!(resolve(&set, "lower_key", None), "none");
+    assert_eq!(resolve(&set, "UPPER_KEY", None), "full");
+}
+
+#[test]
+fn test_brace_alternation() {
+    let set = build(&[("*_{KEY,TOKEN,SECRET}", "partial")], &[], "none").unwrap();
+
+    assert_eq!(resolve(&set, "API_KEY", None), "partial");
+    assert_eq!(resolve(&set, "AUTH_TOKEN", None), "partial");
+    assert_eq!(resolve(&set, "APP_SECRET", None), "partial");
+    assert_eq!(resolve(&set, "APP_NAME", None), "none");
+}
+
+#[test]
+fn test_nested_brace_alternation() {
+    let set = build(&[("{AWS_{ACCESS,SECRET},GCP}_*", "full")], &[], "none").unwrap();
+
+    assert_eq!(resolve(&set, "AWS_ACCESS_KEY", None), "full");
+    assert_eq!(resolve(&set, "AWS_SECRET_KEY", None), "full");
+    assert_eq!(resolve(&set, "GCP_TOKEN", None), "full");
+    assert_eq!(resolve(&set, "AWS_REGION", None), "none");
+}
+
+#[test]
+fn test_negated_rule() {
+    let set = build(&[("!PUBLIC_*", "full")], &[], "none").unwrap();
+
+    assert_eq!(resolve(&set, "SECRET", None), "full");
+    assert_eq!(resolve(&set, "PUBLIC_URL", None), "none");
+}
+
+#[test]
+fn test_negated_rules_rank_after_positive_rules() {
+    let set = build(&[("*_KEY", "partial"), ("!*_PUBLIC", "full")], &[], "none").unwrap();
+
+    // `!*_PUBLIC` has the longer body but must not take over `*_KEY` matches
+    assert_eq!(resolve(&set, "DB_KEY", None), "partial");
+    assert_eq!(resolve(&set, "DB_PASSWORD", None), "full");
+    assert_eq!(resolve(&set, "DB_PUBLIC", None), "none");
+}
+
+#[test]
+fn test_regex_rule() {
+    let set = build(&[("re:^(AWS|GCP)_[A-Z]+_KEY$", "partial")], &[], "full").unwrap();
+
+    assert_eq!(resolve(&set, "AWS_ACCESS_KEY", None), "partial");
+    assert_eq!(resolve(&set, "AZURE_ACCESS_KEY", None), "full");
+
+    // Regex rules match the whole key, like globs
+    let set = build(&[("re:KEY|TOKEN", "partial")], &[], "full").unwrap();
+    assert_eq!(resolve(&set, "KEY", None), "partial");
+    assert_eq!(resolve(&set, "TOKEN", None), "partial");
+    assert_eq!(resolve(&set, "MY_KEY_X", None), "full");
+    assert_eq!(resolve(&set, "TOKENS", None), "full");
+}
+
+#[test]
+fn test_invalid_regex_reports_error() {
+    let err = build(&[("re:(unclosed", "partial")], &[], "full")
+        .err()
+        .unwrap();
+
+    assert!(err.contains("re:(unclosed"), "unexpected error: {}", err);
+}
+
+// =============================================================================
+// Source Pattern Tests
+// =============================================================================
+
+#[test]
+fn test_source_matches_basename() {
+    let set = build(&[], &[("*.sh", "none"), (".env.*", "partial")], "full").unwrap();
+
+    assert_eq!(resolve(&set, "KEY", Some("/home/user/script.sh")), "none");
+    assert_eq!(resolve(&set, "KEY", Some("project/.env.local")), "partial");
+    assert_eq!(resolve(&set, "KEY", Some("project/.env")), "full");
+}
+
+#[test]
+fn test_source_with_slash_matches_path() {
+    let set = build(&[], &[("**/fixtures/*.env", "none")], "full").unwrap();
+
+    assert_eq!(
+        resolve(&set, "KEY", Some("/repo/tests/fixtures/a.env")),
+        "none"
+    );
+    assert_eq!(
+        resolve(&set, "KEY", Some("/repo/tests/fixtures/deep/a.env")),
+        "full"
+    );
+    assert_eq!(
+        resolve(&set, "KEY", Some("C:\\repo\\fixtures\\a.env")),
+        "none"
+    );
+}
+
+#[test]
+fn test_source_rules_are_deterministic() {
+    // Both rules match; the more specific one wins regardless of order
+    let forward = build(
+        &[],
+        &[("*.env.*", "none"), (".env.local", "partial")],
+        "full",
+    )
+    .unwrap();
+    let reverse = build(
+        &[],
+        &[(".env.local", "partial"), ("*.env.*", "none")],
+        "full",
+    )
+    .unwrap();
+
+    assert_eq!(resolve(&forward, "KEY", Some(".env.local")), "partial");
+    assert_eq!(resolve(&reverse, "KEY", Some(".env.local")), "partial");
+}
+
+#[test]
+fn test_key_rules_win_over_source_rules() {
+    let set = build(&[("DEBUG", "none")], &[("*.env", "partial")], "full").unwrap();
+
+    assert_eq!(resolve(&set, "DEBUG", Some(".env")), "none");
+    assert_eq!(resolve(&set, "SECRET", Some("prod.env")), "partial");
+}
@@ -0,0 +1,162 @@
+//! Integration tests for schema validation
+//!
+//! Verifies every value type, required keys and defaults, patterns, how
+//! file annotations override a `.env.schema` file, and rule errors.
+
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+use shelter_core::*;
+
+/// (code, key, line, flagged text) of every violation, or the error
+fn validate(input: &str, schema: Option<&str>) -> Result<Vec<(u8, String, usize, String)>, String> {
+    unsafe {
+        let (schema_ptr, schema_len) = match schema {
+            Some(s) => (s.as_ptr() as *const c_char, s.len()),
+            None => (ptr::null(), 0),
+        };
+        let result = shelter_validate(
+            input.as_ptr() as *const c_char,
+            input.len(),
+            schema_ptr,
+            schema_len,
+            ShelterDialect::Edf as u8,
+        );
+        let r = &*result;
+        let out = if r.error.is_null() {
+            Ok((0..r.count)
+                .map(|i| {
+                    let v = &*r.violations.add(i);
+                    assert!(!CStr::from_ptr(v.message).to_bytes().is_empty());
+                    (
+                        v.code,
+                        CStr::from_ptr(v.key).to_string_lossy().into_owned(),
+                        v.line,
+                        input[v.start..v.end].to_string(),
+                    )
+                })
+                .collect())
+        } else {
+            Err(CStr::from_ptr(r.error).to_string_lossy().into_owned())
+        };
+        shelter_free_schema_result(result);
+        out
+    }
+}
+
+fn codes(input: &str, schema: Option<&str>) -> Vec<u8> {
+    validate(input, schema)
+        .unwrap()
+        .into_iter()
+        .map(|v| v.0)
+        .collect()
+}
+
+#[test]
+fn test_value_types() {
+    let cases = [
+        ("int", "-42", "4.2"),
+        ("bool", "Yes", "maybe"),
+        ("url", "postgres://db:5432/app", "db:5432"),
+        ("email", "ops@example.com", "ops@"),
+        ("enum(debug, info)", "info", "trace"),
+        ("duration", "1h30m", "90 minutes"),
+        ("duration", "250ms", "5x"),
+        ("port", "8080", "70000"),
+        ("port", "443", "0"),
+        ("base64", "aGVsbG8=", "not base64!"),
+        ("json", "{\"a\": [1, 2]}", "{a: 1"),
+        ("string", "anything at all", ""),
+    ];
+    for (spec, good, bad) in cases {
+        let input = format!("# @type {}\nVALUE='{}'\n", spec, good);
+        assert_eq!(codes(&input, None), vec![], "{} {}", spec, good);
+        if bad.is_empty() {
+            continue;
+        }
+        let input = format!("# @type {}\nVALUE='{}'\n", spec, bad);
+        let expected = match spec.starts_with("enum") {
+            true => ShelterSchemaCode::NotInEnum,
+            false => ShelterSchemaCode::InvalidType,
+        };
+        assert_eq!(
+            validate(&input, None).unwrap(),
+            vec![(expected as u8, "VALUE".to_string(), 2, format!("'{}'", bad))],
+            "{} {}",
+            spec,
+            bad
+        );
+    }
+}
+
+#[test]
+fn test_required_defaults_and_patterns() {
+    let input = "# @required\nEMPTY=\n\
+                 # @type int\nOPTIONAL=\n\
+                 KEY=pk_test_1 # @pattern sk_(live|test)_[0-9]+\n";
+    let required = ShelterSchemaCode::Required as u8;
+    assert_eq!(
+        validate(input, None).unwrap(),
+        vec![
+            (required, "EMPTY".to_string(), 2, "EMPTY".to_string()),
+            (
+                ShelterSchemaCode::PatternMismatch as u8,
+                "KEY".to_string(),
+                5,
+                "pk_test_1".to_string()
+            ),
+        ]
+    );
+
+    // Missing keys point at the start of the file; a default satisfies them
+    let schema = "# @required\nDATABASE_URL=\n# @required\n# @default info\nLOG_LEVEL=\n";
+    assert_eq!(
+        validate("OTHER=1\n", Some(schema)).unwrap(),
+        vec![
+            (required, "DATABASE_URL".to_string(), 1, String::new()),
+            (
+                ShelterSchemaCode::UndeclaredKey as u8,
+                "OTHER".to_string(),
+                1,
+                "OTHER".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_file_annotations_override_schema() {
+    let schema = "# @type port\n# @required\nPORT=\n# @type bool\nDEBUG=\n";
+    assert_eq!(codes("PORT=8080\nDEBUG=on\n", Some(schema)), vec![]);
+    assert_eq!(
+        codes("PORT=http\nDEBUG=on\n", Some(schema)),
+        vec![ShelterSchemaCode::InvalidType as u8]
+    );
+
+    // The file relaxes the requirement but keeps the schema's type
+    let input = "# @optional\nPORT=\nDEBUG=sometimes # @type enum(sometimes, never)\n";
+    assert_eq!(codes(input, Some(schema)), vec![]);
+    assert_eq!(
+        codes("# @optional\nPORT=x\n", Some(schema)),
+        vec![ShelterSchemaCode::InvalidType as u8]
+    );
+
+    // Only the effective definition counts
+    assert_eq!(codes("# @type int\nN=x\nN=1\n", None), vec![]);
+}
+
+#[test]
+fn test_invalid_rules_are_errors() {
+    let error = validate("# @type number\nA=1\n", None).unwrap_err();
+    assert!(
+        error.contains("Line 2") && error.contains("number"),
+        "{}",
+        error
+    );
+
+    let error = validate("A=1\n", Some("A= # @pattern [a-\n")).unwrap_err();
+    assert!(error.contains("Schema line 1"), "{}", error);
+
+    let error = validate("A=1\n", Some("# @type port\n# @default 99999\nA=\n")).unwrap_err();
+    assert!(error.contains("99999"), "{}", error);
+}
@@ -0,0 +1,221 @@
+//! Integration tests for the known-value stream masker
+//!
+//! Verifies which values are loaded, masking of values split across chunks
+//! without leaking a prefix, span offsets, overlapping values, and stream
+//! resets.
+
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+use shelter_core::*;
+
+const ENV: &str = "DB_PASSWORD=s3cr3t-Pa55w0rd\n\
+                   API_KEY=q8Zr4Lm2Xv9Tn1Kp\n\
+                   DEBUG=true\n\
+                   SHORT=ab1\n\
+                   DATABASE_URL=postgres://app:${DB_PASSWORD}@db:5432/app\n\
+                   # OLD_KEY=commented-out-value\n";
+
+struct Masker(*mut ShelterStreamMasker);
+
+impl Masker {
+    fn new(files: &[(&str, &str)], options: ShelterStreamOptions) -> Self {
+        let raw: Vec<ShelterFile> = files
+            .iter()
+            .map(|(path, content)| ShelterFile {
+                path: path.as_ptr() as *const c_char,
+                path_len: path.len(),
+                content: content.as_ptr() as *const c_char,
+                content_len: content.len(),
+            })
+            .collect();
+        let mut error = ptr::null_mut();
+        let masker =
+            unsafe { shelter_stream_masker_new(raw.as_ptr(), raw.len(), options, &mut error) };
+        assert!(!masker.is_null() && error.is_null());
+        Masker(masker)
+    }
+
+    fn count(&self) -> usize {
+        unsafe { shelter_stream_masker_value_count(self.0) }
+    }
+
+    fn feed(&self, chunk: &[u8]) -> (Vec<u8>, Vec<(usize, usize)>) {
+        unsafe {
+            take(shelter_stream_masker_feed(
+                self.0,
+                chunk.as_ptr() as *const c_char,
+                chunk.len(),
+            ))
+        }
+    }
+
+    fn finish(&self) -> (Vec<u8>, Vec<(usize, usize)>) {
+        unsafe { take(shelter_stream_masker_finish(self.0)) }
+    }
+
+    fn redact(&self, text: &str) -> String {
+        let (output, _) = unsafe {
+            take(shelter_stream_masker_redact(
+                self.0,
+                text.as_ptr() as *const c_char,
+                text.len(),
+            ))
+        };
+        String::from_utf8(output).unwrap()
+    }
+}
+
+impl Drop for Masker {
+    fn drop(&mut self) {
+        unsafe { shelter_stream_masker_free(self.0) }
+    }
+}
+
+/// Output bytes and spans of a chunk, freeing it
+unsafe fn take(chunk: *mut ShelterStreamChunk) -> (Vec<u8>, Vec<(usize, usize)>) {
+    let c = &*chunk;
+    let output = match c.output_len {
+        0 => Vec::new(),
+        len => std::slice::from_raw_parts(c.output, len).to_vec(),
+    };
+    let spans = (0..c.span_count)
+        .map(|i| {
+            let s = &*c.spans.add(i);
+            (s.start, s.end)
+        })
+        .collect();
+    shelter_free_stream_chunk(chunk);
+    (output, spans)
+}
+
+fn small_values() -> ShelterStreamOptions {
+    ShelterStreamOptions {
+        min_length: 1,
+        skip_benign: 0,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_loads_active_and_resolved_values() {
+    let masker = Masker::new(&[(".env", ENV)], ShelterStreamOptions::default());
+    // The password, the key, the raw and the expanded URL
+    assert_eq!(masker.count(), 4);
+    assert_eq!(
+        masker.redact("connecting to postgres://app:s3cr3t-Pa55w0rd@db:5432/app with DEBUG=true"),
+        format!("connecting to {} with DEBUG=true", "*".repeat(42))
+    );
+    assert_eq!(
+        masker.redact("ab1 commented-out-value"),
+        "ab1 commented-out-value"
+    );
+
+    // Dictionary passwords are loaded even when benign values are skipped
+    let masker = Masker::new(
+        &[(".env", "DEBUG=true\nD=changeme\nE=correcthorse\n")],
+        ShelterStreamOptions {
+            min_length: 1,
+            ..Default::default()
+        },
+    );
+    assert_eq!(masker.count(), 2);
+    assert_eq!(
+        masker.redact("tried changeme then correcthorse"),
+        "tried ******** then ************"
+    );
+
+    // Lines of a multi-line value are masked on their own
+    let pem = "KEY=\"-----BEGIN KEY-----\nMIIBOgIBAAJBAKj34GkxFhD9\n-----END KEY-----\"\n";
+    let masker = Masker::new(&[(".env", pem)], ShelterStreamOptions::default());
+    assert_eq!(
+        masker.redact("got MIIBOgIBAAJBAKj34GkxFhD9 back"),
+        "got ************************ back"
+    );
+}
+
+#[test]
+fn test_values_split_across_chunks() {
+    let masker = Masker::new(&[(".env", ENV)], ShelterStreamOptions::default());
+    let stream = b"log: token=q8Zr4Lm2Xv9Tn1Kp, pass=s3cr3t-Pa55w0rd!\n";
+    let expected = b"log: token=****************, pass=***************!\n";
+
+    for size in 1..stream.len() {
+        let mut output = Vec::new();
+        let mut spans = Vec::new();
+        for chunk in stream.chunks(size) {
+            let (text, chunk_spans) = masker.feed(chunk);
+            // No prefix of a value is ever emitted before it is decided
+            assert!(!String::from_utf8_lossy(&text).contains("q8Z"));
+            assert!(!String::from_utf8_lossy(&text).contains("s3c"));
+            output.extend(text);
+            spans.extend(chunk_spans);
+        }
+        output.extend(masker.finish().0);
+        assert_eq!(output, expected, "chunk size {}", size);
+        assert_eq!(spans, vec![(11, 27), (34, 49)], "chunk size {}", size);
+    }
+}
+
+#[test]
+fn test_overlapping_values_and_raw_bytes() {
+    let masker = Masker::new(&[(".env", "A=bc\nB=abcd\nC=cdef\n")], small_values());
+    assert_eq!(masker.count(), 3);
+    assert_eq!(masker.redact("xbcx abcx abcdef"), "x**x a**x ******");
+
+    // A longer value completing after a shorter one reports the wider span
+    assert_eq!(masker.feed(b"abc"), (vec![], vec![(1, 3)]));
+    assert_eq!(masker.feed(b"d"), (b"****".to_vec(), vec![(0, 4)]));
+    assert_eq!(masker.finish().0, b"");
+
+    // The state after a masked value can be a longer prefix reaching back
+    // into bytes that were already emitted
+    let longer = Masker::new(&[(".env", "A=aQ7zk9Lm\nB=Q7zk9LmXw3R\n")], small_values());
+    assert_eq!(
+        longer.feed(b"aQ7zk9Lm"),
+        (b"********".to_vec(), vec![(0, 8)])
+    );
+    assert_eq!(longer.feed(b"X"), (vec![], vec![]));
+    assert_eq!(longer.feed(b"w3R!"), (b"****!".to_vec(), vec![(1, 12)]));
+    assert_eq!(longer.finish().0, b"");
+    longer.feed(b"aQ7zk9Lm");
+    assert_eq!(longer.feed(b"X").0, b"");
+    assert_eq!(longer.finish().0, b"X");
+
+    // Output is raw bytes, NUL and invalid UTF-8 included
+    let (mut output, _) = masker.feed(b"\0\xffbc\0");
+    output.extend(masker.finish().0);
+    assert_eq!(output, b"\0\xff**\0");
+}
+
+#[test]
+fn test_finish_resets_the_stream() {
+    let masker = Masker::new(&[(".env", ENV)], ShelterStreamOptions::default());
+    let (text, spans) = masker.feed(b"x s3cr3t-Pa");
+    assert_eq!((text, spans), (b"x ".to_vec(), vec![]));
+
+    // The held-back prefix is released as is: it never became a value
+    assert_eq!(masker.finish().0, b"s3cr3t-Pa");
+    let (text, spans) = masker.feed(b"55w0rd q8Zr4Lm2Xv9Tn1Kp");
+    assert_eq!(text, b"55w0rd ****************");
+    assert_eq!(spans, vec![(7, 23)]);
+
+    // Without values, everything passes straight through
+    let masker = Masker::new(&[], ShelterStreamOptions::default());
+    assert_eq!(masker.count(), 0);
+    assert_eq!(masker.feed(b"anything").0, b"anything");
+
+    let error_file = ShelterFile {
+        path: ".env".as_ptr() as *const c_char,
+        path_len: 4,
+        content: b"\xff".as_ptr() as *const c_char,
+        content_len: 1,
+    };
+    unsafe {
+        let mut error = ptr::null_mut();
+        let masker = shelter_stream_masker_new(&error_file, 1, Default::default(), &mut error);
+        assert!(masker.is_null());
+        assert!(CStr::from_ptr(error).to_string_lossy().contains(".env"));
+        shelter_free_string(error);
+    }
+}
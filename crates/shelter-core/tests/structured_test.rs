@@ -0,0 +1,338 @@
+//! Integration tests for shelter-core JSON, TOML and INI parsing
+//!
+//! Verifies dotted key paths, scalar value spans, line numbers, comments,
+//! diagnostics and format detection.
+
+use std::ffi::{c_char, CStr};
+
+use shelter_core::*;
+
+use ShelterDialect as D;
+
+/// Key path, value, the source text of the value span and its line
+#[derive(Debug, PartialEq)]
+struct Entry {
+    key: String,
+    value: String,
+    raw: String,
+    line: usize,
+    quote_type: u8,
+}
+
+struct Parsed {
+    entries: Vec<Entry>,
+    comments: Vec<(String, bool)>,
+    diagnostics: Vec<(u8, String)>,
+    line_count: usize,
+}
+
+fn parse(input: &str, dialect: D) -> Parsed {
+    let options = ShelterParseOptions {
+        dialect: dialect as u8,
+        ..Default::default()
+    };
+
+    unsafe {
+        let result = shelter_parse(input.as_ptr() as *const c_char, input.len(), options);
+        let r = &*result;
+        assert!(r.error.is_null());
+        assert_eq!(r.dialect, dialect as u8);
+
+        let entries = (0..r.count)
+            .map(|i| {
+                let e = &*r.entries.add(i);
+                Entry {
+                    key: CStr::from_ptr(e.key).to_string_lossy().into_owned(),
+                    value: CStr::from_ptr(e.value).to_string_lossy().into_owned(),
+                    raw: input[e.value_start..e.value_end].to_string(),
+                    line: e.line_number,
+                    quote_type: e.quote_type,
+                }
+            })
+            .collect();
+        let comments = (0..r.comment_count)
+            .map(|i| {
+                let c = &*r.comments.add(i);
+                (
+                    CStr::from_ptr(c.text).to_string_lossy().into_owned(),
+                    c.is_inline != 0,
+                )
+            })
+            .collect();
+        let diagnostics = (0..r.diagnostic_count)
+            .map(|i| {
+                let d = &*r.diagnostics.add(i);
+                let message = CStr::from_ptr(d.message).to_string_lossy().into_owned();
+                (d.code, message)
+            })
+            .collect();
+        let line_count = r.line_count;
+
+        shelter_free_result(result);
+        Parsed {
+            entries,
+            comments,
+            diagnostics,
+            line_count,
+        }
+    }
+}
+
+fn keys_values(parsed: &Parsed) -> Vec<(&str, &str)> {
+    parsed
+        .entries
+        .iter()
+        .map(|e| (e.key.as_str(), e.value.as_str()))
+        .collect()
+}
+
+fn detect(source: &str, content: &str) -> u8 {
+    unsafe {
+        shelter_detect_dialect(
+            source.as_ptr() as *const c_char,
+            source.len(),
+            content.as_ptr() as *const c_char,
+            content.len(),
+        )
+    }
+}
+
+// =============================================================================
+// JSON Tests
+// =============================================================================
+
+#[test]
+fn test_json_key_paths() {
+    let input = r#"{
+  "database": { "password": "hunter2", "port": 5432 },
+  "servers": [ { "host": "a" }, "b" ],
+  "enabled": true,
+  "empty": null
+}"#;
+    let parsed = parse(input, D::Json);
+
+    assert_eq!(
+        keys_values(&parsed),
+        vec![
+            ("database.password", "hunter2"),
+            ("database.port", "5432"),
+            ("servers[0].host", "a"),
+            ("servers[1]", "b"),
+            ("enabled", "true"),
+            ("empty", "null"),
+        ]
+    );
+    assert!(parsed.diagnostics.is_empty());
+    assert_eq!(parsed.line_count, 6);
+}
+
+#[test]
+fn test_json_value_spans_and_lines() {
+    let input = "{\n  \"api\": {\n    \"key\": \"s\\u00e9cr\\\"et\"\n  }\n}";
+    let entry = &parse(input, D::Json).entries[0];
+
+    assert_eq!(entry.key, "api.key");
+    assert_eq!(entry.value, "sécr\"et");
+    // The span covers the string as written, quotes included
+    assert_eq!(entry.raw, "\"s\\u00e9cr\\\"et\"");
+    assert_eq!(entry.quote_type, 2);
+    assert_eq!(entry.line, 3);
+}
+
+#[test]
+fn test_json_keys_needing_quotes() {
+    let parsed = parse(r#"{"a.b": {"with space": 1}}"#, D::Json);
+    assert_eq!(parsed.entries[0].key, r#""a.b"."with space""#);
+}
+
+#[test]
+fn test_jsonc_comments_and_trailing_commas() {
+    let input = "{\n  // Connection settings\n  \"ConnectionString\": \"Server=db\", // prod\n  /* block */ \"Retries\": 3,\n}";
+    let parsed = parse(input, D::Json);
+
+    assert_eq!(
+        keys_values(&parsed),
+        vec![("ConnectionString", "Server=db"), ("Retries", "3")]
+    );
+    assert_eq!(
+        parsed.comments,
+        vec![
+            ("// Connection settings".to_string(), false),
+            ("// prod".to_string(), true)
+        ]
+    );
+}
+
+#[test]
+fn test_json_syntax_error_keeps_earlier_entries() {
+    let parsed = parse("{\"a\": 1, \"b\": tru, \"c\": 3}", D::Json);
+
+    assert_eq!(keys_values(&parsed), vec![("a", "1")]);
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(
+        parsed.diagnostics[0].0,
+        ShelterDiagnosticCode::Expected as u8
+    );
+
+    let parsed = parse("{\"a\": \"open", D::Json);
+    assert_eq!(
+        parsed.diagnostics[0].0,
+        ShelterDiagnosticCode::UnclosedQuote as u8
+    );
+}
+
+// =============================================================================
+// TOML Tests
+// =============================================================================
+
+#[test]
+fn test_toml_tables_and_dotted_keys() {
+    let input = r#"title = "app"
+
+[database]
+password = "hunter2" # inline
+connection.timeout = 30
+
+[servers.alpha]
+ip = '10.0.0.1'
+"#;
+    let parsed = parse(input, D::Toml);
+
+    assert_eq!(
+        keys_values(&parsed),
+        vec![
+            ("title", "app"),
+            ("database.password", "hunter2"),
+            ("database.connection.timeout", "30"),
+            ("servers.alpha.ip", "10.0.0.1"),
+        ]
+    );
+    assert_eq!(parsed.entries[1].raw, "\"hunter2\"");
+    assert_eq!(parsed.entries[1].line, 4);
+    assert_eq!(parsed.entries[3].quote_type, 1);
+    assert_eq!(parsed.comments, vec![("# inline".to_string(), true)]);
+}
+
+#[test]
+fn test_toml_arrays_and_inline_tables() {
+    let input = r#"ports = [ 8000, 8001 ]
+creds = { user = "admin", pass = "s3cret" }
+
+[[users]]
+name = "a"
+
+[[users]]
+name = "b"
+[users.auth]
+token = "t"
+"#;
+    let parsed = parse(input, D::Toml);
+
+    assert_eq!(
+        keys_values(&parsed),
+        vec![
+            ("ports[0]", "8000"),
+            ("ports[1]", "8001"),
+            ("creds.user", "admin"),
+            ("creds.pass", "s3cret"),
+            ("users[0].name", "a"),
+            ("users[1].name", "b"),
+            ("users[1].auth.token", "t"),
+        ]
+    );
+}
+
+#[test]
+fn test_toml_multiline_strings_and_dates() {
+    let input = "key = \"\"\"\n-----BEGIN KEY-----\nabc\n-----END KEY-----\"\"\"\nraw = '''\nC:\\path'''\nwhen = 1979-05-27 07:32:00Z\n";
+    let parsed = parse(input, D::Toml);
+
+    let key = &parsed.entries[0];
+    assert_eq!(key.value, "-----BEGIN KEY-----\nabc\n-----END KEY-----");
+    assert!(key.raw.starts_with("\"\"\"") && key.raw.ends_with("\"\"\""));
+    assert_eq!(parsed.entries[1].value, "C:\\path");
+    assert_eq!(parsed.entries[2].value, "1979-05-27 07:32:00Z");
+}
+
+#[test]
+fn test_toml_errors_skip_the_line() {
+    let parsed = parse("a = 1\nb = = 2\nc = 3\n", D::Toml);
+
+    assert_eq!(keys_values(&parsed), vec![("a", "1"), ("c", "3")]);
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.diagnostics[0].1, "Expected a value");
+}
+
+// =============================================================================
+// INI Tests
+// =============================================================================
+
+#[test]
+fn test_ini_sections() {
+    let input = "; global\nname = app\n\n[database]\npassword = hunter2 ; inline\nuser: admin\n\n[client.tls]\nkey = \"quoted value\" # note\n";
+    let parsed = parse(input, D::Ini);
+
+    assert_eq!(
+        keys_values(&parsed),
+        vec![
+            ("name", "app"),
+            ("database.password", "hunter2"),
+            ("database.user", "admin"),
+            ("client.tls.key", "quoted value"),
+        ]
+    );
+    assert_eq!(parsed.entries[1].raw, "hunter2");
+    assert_eq!(parsed.entries[3].raw, "\"quoted value\"");
+    assert_eq!(parsed.entries[3].quote_type, 2);
+    assert_eq!(
+        parsed.comments,
+        vec![
+            ("; global".to_string(), false),
+            ("; inline".to_string(), true),
+            ("# note".to_string(), true),
+        ]
+    );
+}
+
+#[test]
+fn test_ini_values_keep_unspaced_comment_chars() {
+    let parsed = parse("[db]\nurl = mysql://u:p@h/db;x#y\n", D::Ini);
+    assert_eq!(parsed.entries[0].value, "mysql://u:p@h/db;x#y");
+}
+
+#[test]
+fn test_ini_reports_lines_without_separator() {
+    let parsed = parse("[s]\njunk line\nok = 1\n", D::Ini);
+
+    assert_eq!(keys_values(&parsed), vec![("s.ok", "1")]);
+    assert_eq!(
+        parsed.diagnostics[0].0,
+        ShelterDiagnosticCode::Expected as u8
+    );
+}
+
+// =============================================================================
+// Detection Tests
+// =============================================================================
+
+#[test]
+fn test_detect_formats_from_filename() {
+    assert_eq!(detect("appsettings.json", ""), D::Json as u8);
+    assert_eq!(detect("terraform.tfstate", ""), D::Json as u8);
+    assert_eq!(detect("config.toml", ""), D::Toml as u8);
+    assert_eq!(detect("setup.cfg", ""), D::Ini as u8);
+    assert_eq!(detect("php.ini", ""), D::Ini as u8);
+}
+
+#[test]
+fn test_detect_formats_from_content() {
+    assert_eq!(detect("", "{\n  \"a\": 1\n}"), D::Json as u8);
+    assert_eq!(detect("", "[\n  {\"a\": 1}\n]"), D::Json as u8);
+    assert_eq!(
+        detect("", "[db]\nport = 5432\nhost = \"x\"\n"),
+        D::Toml as u8
+    );
+    assert_eq!(detect("", "[[users]]\nname = \"a\"\n"), D::Toml as u8);
+    assert_eq!(detect("", "[db]\nhost = localhost\n"), D::Ini as u8);
+    assert_eq!(detect("", "A=1\n"), D::Edf as u8);
+}
@@ -34,11 +34,15 @@ local M = {}
 ---@field mask_char? string Character used for masking (default: "*")
 ---@field highlight_group? string Highlight group for masked text
 ---@field skip_comments? boolean Whether to skip masking in comments
----@field default_mode? "full"|"partial"|"none"|string Default masking mode
+---@field default_mode? "full"|"partial"|"none"|"auto"|string Default masking mode
 ---@field modes? table<string, ShelterModeConfig> Mode configurations and custom mode definitions
 ---@field env_filetypes? string[] Filetypes to mask (default: {"dotenv", "edf"})
 ---@field patterns? table<string, string> Key patterns to mode mapping
 ---@field sources? table<string, string> Source file patterns to mode mapping
+---@field credentials? table<ShelterCredentialKind, string> Detected credential type to mode mapping
+---@field dialect? "auto"|ShelterDialect Dotenv dialect, "auto" detects it from the file name and content
+---@field fingerprint? boolean Show a keyed fingerprint tag after masked values (default: false)
+---@field lint? boolean Publish lint findings and schema violations of env buffers through vim.diagnostic (default: false)
 ---@field modules? ShelterModulesConfig Module toggles
 ---@field buffer? ShelterBufferConfig Buffer-specific settings
 
@@ -61,10 +65,18 @@ local DEFAULT_CONFIG = {
 			fallback_mode = "full",
 		},
 		none = {},
+		auto = {
+			mode = "full",
+			mask_unknown = true,
+		},
 	},
 	env_filetypes = { "dotenv", "edf" },
 	patterns = {},
 	sources = {},
+	credentials = {},
+	dialect = "auto",
+	fingerprint = false,
+	lint = false,
 	modules = {
 		files = true, -- Can be boolean or { shelter_on_leave = true, disable_cmp = true }
 		telescope_previewer = false,
@@ -110,6 +122,10 @@ function M.validate()
 		env_filetypes = { config.env_filetypes, "table" },
 		patterns = { config.patterns, "table" },
 		sources = { config.sources, "table" },
+		credentials = { config.credentials, "table" },
+		dialect = { config.dialect, "string" },
+		fingerprint = { config.fingerprint, "boolean" },
+		lint = { config.lint, "boolean" },
 		modules = { config.modules, "table" },
 		buffer = { config.buffer, "table" },
 		modes = { config.modes, "table" },
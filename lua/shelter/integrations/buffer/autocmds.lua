@@ -25,6 +25,7 @@ end
 ---@field on_buf_enter fun(ev: table) Called on BufEnter event
 ---@field on_buf_leave fun(ev: table) Called on BufLeave event
 ---@field on_insert_leave fun(ev: table) Called on InsertLeave event
+---@field on_schema_write fun(ev: table) Called on BufWritePost of a `.env.schema` file
 
 ---Setup buffer autocmds
 ---@param callbacks ShelterAutocmdCallbacks
@@ -53,7 +54,7 @@ function M.setup(callbacks)
 		callback = callbacks.on_buf_leave,
 	})
 
-	-- Note: TextChanged/TextChangedI replaced by nvim_buf_attach on_lines callback
+	-- Note: TextChanged/TextChangedI replaced by nvim_buf_attach on_bytes callback
 	-- for line-specific re-masking performance
 
 	-- InsertLeave - ensure masks are applied when exiting insert mode
@@ -61,6 +62,13 @@ function M.setup(callbacks)
 		group = augroup,
 		callback = callbacks.on_insert_leave,
 	})
+
+	-- BufWritePost - drop the cached schema of a written `.env.schema` file
+	nvim_create_autocmd("BufWritePost", {
+		pattern = ".env.schema",
+		group = augroup,
+		callback = callbacks.on_schema_write,
+	})
 end
 
 ---Cleanup autocmds
@@ -52,6 +52,73 @@ function M.clear_range(bufnr, start_line, end_line)
 	nvim_buf_clear_namespace(bufnr, ns, start_line, end_line)
 end
 
+---Show the decoded value over an encoded one while its line is peeked
+---Only single-line values whose decoded text fits on one line are shown
+---@param bufnr number
+---@param ns number Namespace ID
+---@param mask_info ShelterMaskedLine
+---@param line_offsets number[]
+local function apply_decoded(bufnr, ns, mask_info, line_offsets)
+	local value = mask_info.value
+	if mask_info.value_end_line ~= mask_info.line_number or value:find("%c") then
+		return
+	end
+
+	local line_start_offset = line_offsets[mask_info.line_number] or 0
+	local col_start = mask_info.value_start - line_start_offset
+	local col_end = mask_info.value_end - line_start_offset
+	if mask_info.quote_type and mask_info.quote_type > 0 then
+		col_start = col_start + 1
+		col_end = col_end - 1
+	end
+
+	-- Base64 is always longer than what it decodes to; pad over the rest
+	local width = col_end - col_start
+	if #value > width then
+		return
+	end
+
+	nvim_buf_set_extmark(bufnr, ns, mask_info.line_number - 1, col_start, {
+		end_col = col_end,
+		virt_text = { { value .. string.rep(" ", width - #value) } },
+		virt_text_pos = "overlay",
+		priority = 9999,
+		strict = false,
+	})
+end
+
+---Show what a peeked value expands to at the end of its last line
+---@param bufnr number
+---@param ns number Namespace ID
+---@param mask_info ShelterMaskedLine
+local function apply_resolved(bufnr, ns, mask_info)
+	local resolved = mask_info.resolved
+	if resolved == mask_info.value or resolved:find("%c") then
+		return
+	end
+
+	nvim_buf_set_extmark(bufnr, ns, mask_info.value_end_line - 1, 0, {
+		virt_text = { { " → " .. resolved, "Comment" } },
+		virt_text_pos = "eol",
+		priority = 9999,
+		strict = false,
+	})
+end
+
+---Show the fingerprint tag of a masked value at the end of its last line
+---@param bufnr number
+---@param ns number Namespace ID
+---@param mask_info ShelterMaskedLine
+---@param hl_group string
+local function apply_fingerprint(bufnr, ns, mask_info, hl_group)
+	nvim_buf_set_extmark(bufnr, ns, mask_info.value_end_line - 1, 0, {
+		virt_text = { { " [#" .. mask_info.fingerprint .. "]", hl_group } },
+		virt_text_pos = "eol",
+		priority = 9999,
+		strict = false,
+	})
+end
+
 ---Apply a single mask directly to buffer (no intermediate collection)
 ---This fuses the old process_mask + extmark application into one step
 ---@param bufnr number
@@ -68,6 +135,14 @@ local function apply_mask_direct(bufnr, ns, mask_info, lines, line_offsets, hl_g
 	-- Skip if any line in range is revealed
 	for ln = mask_info.line_number, mask_info.value_end_line do
 		if state.is_line_revealed(ln) then
+			-- Peeking an encoded value shows what it decodes to
+			if mask_info.encoding then
+				apply_decoded(bufnr, ns, mask_info, line_offsets)
+			end
+			-- Peeking a value with references shows what it expands to
+			if mask_info.resolved then
+				apply_resolved(bufnr, ns, mask_info)
+			end
 			return
 		end
 	end
@@ -79,6 +154,10 @@ local function apply_mask_direct(bufnr, ns, mask_info, lines, line_offsets, hl_g
 
 	local start_line = lines[start_line_idx + 1]
 
+	if mask_info.fingerprint then
+		apply_fingerprint(bufnr, ns, mask_info, hl_group)
+	end
+
 	-- Calculate column from byte offset using pre-built offsets
 	local line_start_offset = line_offsets[mask_info.line_number] or 0
 	local value_col = mask_info.value_start - line_start_offset
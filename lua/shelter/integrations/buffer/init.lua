@@ -14,6 +14,7 @@ local peek = require("shelter.integrations.buffer.peek")
 local completion = require("shelter.integrations.buffer.completion")
 local paste = require("shelter.integrations.buffer.paste")
 local autocmds = require("shelter.integrations.buffer.autocmds")
+local lint = require("shelter.integrations.buffer.lint")
 
 -- Fast locals for hot path
 local api = vim.api
@@ -135,7 +136,24 @@ local function setup_buffer_options(bufnr, winid)
 	completion.disable(bufnr)
 end
 
----Attach to buffer for line change tracking via nvim_buf_attach
+---Text an `on_bytes` edit inserted, or nil if it cannot be read back
+---@param buf number
+---@param start_row number
+---@param start_col number
+---@param new_end_row number
+---@param new_end_col number
+---@param new_end_byte number
+---@return string|nil
+local function inserted_text(buf, start_row, start_col, new_end_row, new_end_col, new_end_byte)
+	if new_end_byte == 0 then
+		return ""
+	end
+	local end_col = new_end_row == 0 and start_col + new_end_col or new_end_col
+	local ok, text = pcall(api.nvim_buf_get_text, buf, start_row, start_col, start_row + new_end_row, end_col, {})
+	return ok and table_concat(text, "\n") or nil
+end
+
+---Attach to buffer for byte-level change tracking via nvim_buf_attach
 ---@param bufnr number
 local function attach_buffer(bufnr)
 	if buffer_attached[bufnr] then
@@ -143,7 +161,28 @@ local function attach_buffer(bufnr)
 	end
 
 	api.nvim_buf_attach(bufnr, false, {
-		on_lines = function(_, buf, _, first_line, last_line, last_line_updated)
+		on_bytes = function(
+			_,
+			buf,
+			_,
+			start_row,
+			start_col,
+			start_byte,
+			old_end_row,
+			_,
+			old_end_byte,
+			new_end_row,
+			new_end_col,
+			new_end_byte
+		)
+			-- Every edit reaches the native document, masked or not, so it stays in step
+			local new_text = inserted_text(buf, start_row, start_col, new_end_row, new_end_col, new_end_byte)
+			if new_text then
+				masking.edit_document(buf, start_byte, old_end_byte, new_text)
+			else
+				masking.release_document(buf)
+			end
+
 			-- Skip if not enabled or paste in progress
 			if not state.is_enabled("files") then
 				return
@@ -153,7 +192,7 @@ local function attach_buffer(bufnr)
 			end
 
 			-- Check if line count changed (lines added/removed)
-			if last_line ~= last_line_updated then
+			if old_end_row ~= new_end_row then
 				-- Line count changed - must do full re-mask
 				buffer_content_hashes[buf] = nil
 				invalidate_buffer_cache(buf)
@@ -165,8 +204,8 @@ local function attach_buffer(bufnr)
 			else
 				-- Same line count - can use incremental path
 				local line_range = {
-					min_line = first_line,
-					max_line = last_line_updated,
+					min_line = start_row,
+					max_line = start_row + new_end_row + 1,
 				}
 				M.shelter_buffer(buf, true, line_range)
 			end
@@ -174,6 +213,7 @@ local function attach_buffer(bufnr)
 
 		on_detach = function(_, buf)
 			buffer_attached[buf] = nil
+			masking.release_document(buf)
 		end,
 	})
 
@@ -231,7 +271,8 @@ function M.shelter_buffer(bufnr, sync, line_range)
 			content,
 			bufname,
 			{ min_line = parse_start, max_line = parse_end },
-			cache.masks
+			cache.masks,
+			bufnr
 		)
 
 		-- Update cache with merged masks
@@ -239,6 +280,7 @@ function M.shelter_buffer(bufnr, sync, line_range)
 
 		-- Apply ONLY the new/changed masks
 		extmarks.apply_masks(bufnr, result.masks_to_apply, result.line_offsets, lines, sync)
+		lint.publish(bufnr, content, bufname)
 		return
 	end
 
@@ -276,6 +318,7 @@ function M.shelter_buffer(bufnr, sync, line_range)
 
 	-- Apply all masks
 	extmarks.apply_masks(bufnr, result.masks, line_offsets, lines, sync)
+	lint.publish(bufnr, content, bufname)
 end
 
 ---Shelter a preview buffer (for picker integrations)
@@ -329,6 +372,7 @@ function M.unshelter_buffer(bufnr)
 	restore_wrap(winid)
 
 	extmarks.clear(bufnr)
+	lint.clear(bufnr)
 	completion.restore(bufnr)
 
 	-- Clear content hash and mask cache so re-sheltering will apply masks
@@ -478,7 +522,7 @@ function M.setup()
 			end
 		end,
 
-		-- TextChanged/TextChangedI no longer needed - using nvim_buf_attach on_lines
+		-- TextChanged/TextChangedI no longer needed - using nvim_buf_attach on_bytes
 
 		on_insert_leave = function(ev)
 			if not env_file.is_env_buffer(ev.buf) then
@@ -490,6 +534,10 @@ function M.setup()
 			-- Full re-mask on InsertLeave to ensure consistency
 			M.shelter_buffer(ev.buf, true)
 		end,
+
+		on_schema_write = function(ev)
+			lint.invalidate_schema(ev.match)
+		end,
 	})
 end
 
@@ -0,0 +1,120 @@
+---@class ShelterBufferLint
+---Publish lint findings and schema violations for env buffers through vim.diagnostic
+local M = {}
+
+local config = require("shelter.config")
+local masking = require("shelter.masking")
+local debounce = require("shelter.utils.debounce")
+
+local ns_id = nil
+
+---Get or create the namespace
+---@return number
+local function get_namespace()
+	if not ns_id then
+		ns_id = vim.api.nvim_create_namespace("shelter_lint")
+	end
+	return ns_id
+end
+
+-- Milliseconds without edits before a buffer's findings are published
+local PUBLISH_DELAY = 200
+
+---Content of the `.env.schema` file of each directory seen, false if it has none
+---@type table<string, string|false>
+local schemas = {}
+
+---Read the `.env.schema` file next to a buffer's file, if there is one
+---Schemas are cached per directory until `invalidate_schema` is called for them
+---@param source string
+---@return string|nil
+local function read_schema(source)
+	if source == "" then
+		return nil
+	end
+	local dir = vim.fs.normalize(vim.fs.dirname(source))
+	local cached = schemas[dir]
+	if cached ~= nil then
+		return cached or nil
+	end
+
+	local path = vim.fs.joinpath(dir, ".env.schema")
+	local schema = false
+	if path ~= vim.fs.normalize(source) and vim.fn.filereadable(path) == 1 then
+		local file = io.open(path, "r")
+		if file then
+			schema = file:read("*a")
+			file:close()
+		end
+	end
+	schemas[dir] = schema
+	return schema or nil
+end
+
+---Forget the cached schema of the directory holding `path`, e.g. after it was written
+---@param path string Path of a `.env.schema` file
+function M.invalidate_schema(path)
+	schemas[vim.fs.normalize(vim.fs.dirname(path))] = nil
+end
+
+---Lint buffer content, validate it against its annotations and the schema, and publish the findings
+---@param bufnr number
+---@param content string
+---@param source string
+local function publish_now(bufnr, content, source)
+	if not vim.api.nvim_buf_is_valid(bufnr) then
+		return
+	end
+
+	-- The joined lines lack the final newline Neovim writes to disk
+	if vim.bo[bufnr].eol or vim.bo[bufnr].fixeol then
+		content = content .. "\n"
+	end
+
+	local ok, findings = pcall(masking.lint, content, source)
+	if not ok then
+		return
+	end
+
+	local schema_ok, violations = pcall(masking.validate, content, read_schema(source), source)
+	if schema_ok then
+		for _, violation in ipairs(violations) do
+			-- `@required` in the file itself is a lint rule too
+			local duplicate = false
+			for _, finding in ipairs(findings) do
+				if finding.start_byte == violation.start_byte and finding.message == violation.message then
+					duplicate = true
+					break
+				end
+			end
+			if not duplicate then
+				table.insert(findings, violation)
+			end
+		end
+	end
+	vim.diagnostic.set(get_namespace(), bufnr, findings)
+end
+
+---Lint buffer content, validate it against its annotations and a `.env.schema` file
+---next to it, and publish the findings (only with `lint = true`)
+---Edits come in bursts, so publishing waits until the buffer has been quiet for a moment.
+---@param bufnr number
+---@param content string Buffer lines joined with "\n"
+---@param source string Buffer name, used to detect the dialect
+function M.publish(bufnr, content, source)
+	if not config.get().lint then
+		return
+	end
+	debounce.create(publish_now, PUBLISH_DELAY, "lint_" .. bufnr)(bufnr, content, source)
+end
+
+---Remove published findings from a buffer
+---@param bufnr number
+function M.clear(bufnr)
+	debounce.cancel("lint_" .. bufnr)
+	if ns_id then
+		vim.diagnostic.reset(ns_id, bufnr)
+	end
+end
+
+return M
@@ -12,6 +12,21 @@ local LRU_SIZE = 200
 local lru = require("shelter.cache.lru")
 local parsed_cache = lru.new(LRU_SIZE)
 
+-- Native documents per buffer (or source), kept in step with buffer edits and
+-- re-parsed incrementally between them
+---@class ShelterDocumentState
+---@field doc ShelterNativeDocument
+---@field dialect ShelterDialect
+---@field source string|nil
+---@field text string|nil Content at the last sync, when no edit has been fed since
+---@field len number Byte length of the document text
+---@field masks table<string, string> Mode masks of live entries, by key and value
+---@field refs table<string, number> Number of live entries per `masks` slot
+---@field ids table<number, string> `masks` slot of each live entry id
+
+---@type table<any, ShelterDocumentState>
+local documents = {}
+
 -- Fast locals for hot path
 local string_byte = string.byte
 local string_format = string.format
@@ -87,6 +102,7 @@ end
 ---Clear all caches
 function M.clear_caches()
 	parsed_cache:clear()
+	documents = {}
 	-- Note: mask_cache is intentionally not cleared - mask strings are reusable
 	-- across content changes since they only depend on mask_char + length
 end
@@ -95,37 +111,181 @@ end
 ---@field entries ShelterParsedEntry[]
 ---@field line_offsets number[]
 
+---Resolve the configured dialect for a file
+---@param content string
+---@param source string|nil
+---@return ShelterDialect
+local function resolve_dialect(content, source)
+	local dialect = config.get().dialect or "auto"
+	if dialect == "auto" then
+		return native.detect_dialect(source, content)
+	end
+	return dialect
+end
+
 ---Parse buffer content with caching
 ---Returns both entries and pre-computed line offsets from Rust
 ---@param content string
+---@param source? string|nil Source file path, used to detect the dialect
 ---@return ShelterParsedContent
-function M.parse_content(content)
-	local cache_key = hash_content(content)
+function M.parse_content(content, source)
+	local dialect = resolve_dialect(content, source)
+	local fingerprint = config.get().fingerprint
+	-- The same content parses differently per dialect
+	local cache_key = dialect .. (fingerprint and "#:" or ":") .. hash_content(content)
 	local cached = parsed_cache:get(cache_key)
 	if cached then
 		return cached
 	end
 
 	-- native.parse now returns {entries, line_offsets}
-	local result = native.parse(content)
+	local result = native.parse(content, { dialect = dialect, fingerprint = fingerprint })
 	parsed_cache:put(cache_key, result)
 	return result
 end
 
+---Slot of an entry in a document's mask memo: mode masks depend only on the
+---key and the value
+---@param entry ShelterParsedEntry
+---@return string
+local function mask_slot(entry)
+	return entry.key .. "\0" .. entry.value
+end
+
+---Drop one live entry's claim on a mask slot, forgetting the mask with the last one
+---@param state ShelterDocumentState
+---@param slot string|nil
+local function release_slot(state, slot)
+	local count = slot and state.refs[slot]
+	if not count then
+		return
+	elseif count > 1 then
+		state.refs[slot] = count - 1
+	else
+		state.refs[slot] = nil
+		state.masks[slot] = nil
+	end
+end
+
+---Track the live entries of a document from an update delta, so masks of
+---removed or changed entries are forgotten and only changed entries are re-masked
+---@param state ShelterDocumentState
+---@param delta ShelterNativeDelta
+local function apply_delta(state, delta)
+	for _, id in ipairs(delta.removed) do
+		release_slot(state, state.ids[id])
+		state.ids[id] = nil
+	end
+	for _, change in ipairs(delta.changes) do
+		release_slot(state, state.ids[change.id])
+		local slot = mask_slot(change.entry)
+		state.ids[change.id] = slot
+		state.refs[slot] = (state.refs[slot] or 0) + 1
+	end
+end
+
+---Get the document state of `key`, brought up to date with `content`
+---Edits fed through `edit_document` are re-parsed incrementally; content that
+---differs in any other way is diffed against the document. A document is created
+---on first use, and again when the dialect changes.
+---@param content string
+---@param source string|nil
+---@param key any Buffer number or source the document belongs to
+---@return ShelterDocumentState
+local function sync_document(content, source, key)
+	local dialect = resolve_dialect(content, source)
+	local state = documents[key]
+
+	if state and state.dialect == dialect then
+		-- Fed edits must add up to the content; without any, it must be unchanged
+		local in_step = state.len == #content and (state.text == nil or state.text == content)
+		if in_step or state.doc:reset(content) then
+			if state.source ~= source then
+				-- Modes follow the source, so its masks no longer hold
+				state.source = source
+				state.masks = {}
+			end
+			state.text = content
+			state.len = #content
+			apply_delta(state, state.doc:update())
+			return state
+		end
+	end
+
+	-- Only entries are read from the document; masks come from the Lua modes
+	local doc = native.document(content, "none", nil, nil, dialect)
+	state = {
+		doc = doc,
+		dialect = dialect,
+		source = source,
+		text = content,
+		len = #content,
+		masks = {},
+		refs = {},
+		ids = {},
+	}
+	documents[key] = state
+	apply_delta(state, doc:update())
+	return state
+end
+
+---Feed a buffer edit, as reported by `on_bytes`, to the document of `key`
+---A document that cannot take the edit is dropped and rebuilt on next use
+---@param key any Buffer number or source the document belongs to
+---@param start_byte number 0-indexed byte offset of the edit
+---@param old_len number Length of the replaced text
+---@param new_text string Replacement text
+function M.edit_document(key, start_byte, old_len, new_text)
+	local state = documents[key]
+	if not state then
+		return
+	end
+	if state.doc:edit(start_byte, old_len, new_text) then
+		state.text = nil
+		state.len = state.len + #new_text - old_len
+	else
+		documents[key] = nil
+	end
+end
+
+---Drop the document of `key`, e.g. when its buffer is wiped
+---@param key any Buffer number or source the document belongs to
+function M.release_document(key)
+	documents[key] = nil
+end
+
+---Lint content in the configured dialect
+---@param content string
+---@param source? string|nil Source file path, used to detect the dialect
+---@return ShelterLintFinding[]
+function M.lint(content, source)
+	return native.lint(content, resolve_dialect(content, source))
+end
+
+---Validate content in the configured dialect against its annotations and a schema
+---@param content string
+---@param schema? string Content of a `.env.schema` file
+---@param source? string|nil Source file path, used to detect the dialect
+---@return ShelterSchemaViolation[]
+function M.validate(content, schema, source)
+	return native.validate(content, schema, resolve_dialect(content, source))
+end
+
 ---Determine masking mode for a key based on patterns (uses pattern cache)
 ---@param key string
----@param source_basename string|nil Pre-computed basename of source file
+---@param source string|nil Source file path or basename
+---@param credential? string Detected credential type of the value
 ---@return string mode_name
-function M.determine_mode(key, source_basename)
+function M.determine_mode(key, source, credential)
 	-- Use pre-compiled pattern cache if available
 	if pattern_cache.is_compiled() then
-		return pattern_cache.determine_mode(key, source_basename)
+		return pattern_cache.determine_mode(key, source, credential)
 	end
 
 	-- Fallback: compile on demand (shouldn't happen after setup)
 	local cfg = config.get()
 	pattern_cache.compile(cfg)
-	return pattern_cache.determine_mode(key, source_basename)
+	return pattern_cache.determine_mode(key, source, credential)
 end
 
 ---@class ShelterMaskContext
@@ -159,6 +319,9 @@ end
 ---@field value string
 ---@field is_comment boolean
 ---@field quote_type number 0=none, 1=single, 2=double
+---@field encoding "base64"|nil Encoding of the source text; value is decoded
+---@field resolved string|nil Value with `${VAR}` references expanded
+---@field fingerprint string|nil Keyed fingerprint tag of the value (with `fingerprint = true`)
 
 ---@class ShelterMaskResult
 ---@field masks ShelterMaskedLine[]
@@ -172,13 +335,10 @@ end
 function M.generate_masks(content, source)
 	local cfg = config.get()
 	local skip_comments = cfg.skip_comments
-	local parsed = M.parse_content(content)
+	local parsed = M.parse_content(content, source)
 	local masks = {}
 	local mask_count = 0
 
-	-- Cache source basename once (avoid vim.fn.fnamemodify per entry)
-	local source_basename = source and vim.fn.fnamemodify(source, ":t") or nil
-
 	-- Memoize key→mode name mapping for this batch
 	local mode_name_memo = {}
 
@@ -192,21 +352,47 @@ function M.generate_masks(content, source)
 		line_number = nil,
 		quote_type = nil,
 		is_comment = nil,
+		secret_class = nil,
 		config = cfg,
 		value = nil,
 	}
 
+	-- Built-in full and partial masks come from one shelter_mask pass per mode,
+	-- keyed by value start; values it leaves unmasked have no key
+	local native_masks = {}
+	local dialect = resolve_dialect(content, source)
+	local function native_mask(mode_name, mode, entry)
+		local by_start = native_masks[mode_name]
+		if not by_start then
+			by_start = {}
+			local result = native.mask(content, mode_name, mode.options, skip_comments, dialect)
+			for _, m in ipairs(result.masks) do
+				by_start[m.value_start] = m.mask
+			end
+			native_masks[mode_name] = by_start
+		end
+		return by_start[entry.value_start] or entry.value
+	end
+
 	for _, entry in ipairs(parsed.entries) do
 		-- Skip comments only if skip_comments is true
 		-- When skip_comments is false, we mask values in comments too
-		local should_skip = entry.is_comment and skip_comments
+		-- Values that are just a `${VAR}` resolving to another entry reveal nothing,
+		-- so they are left as is
+		local should_skip = (entry.is_comment and skip_comments) or entry.is_reference
 
 		if not should_skip then
 			-- Check memoized mode name first
-			local mode_name = mode_name_memo[entry.key]
-			if not mode_name then
-				mode_name = pattern_cache.determine_mode(entry.key, source_basename)
-				mode_name_memo[entry.key] = mode_name
+			local mode_name
+			if entry.credential then
+				-- Credential mappings depend on the value, so skip the per-key memo
+				mode_name = pattern_cache.determine_mode(entry.key, source, entry.credential)
+			else
+				mode_name = mode_name_memo[entry.key]
+				if not mode_name then
+					mode_name = pattern_cache.determine_mode(entry.key, source)
+					mode_name_memo[entry.key] = mode_name
+				end
 			end
 
 			-- Get cached mode instance (avoids modes.get lookup per entry)
@@ -222,9 +408,15 @@ function M.generate_masks(content, source)
 			context.line_number = entry.line_number
 			context.quote_type = entry.quote_type
 			context.is_comment = entry.is_comment
-
-			-- Call mode:apply directly (skip modes.apply overhead)
-			local mask = mode:apply(context)
+			context.secret_class = entry.secret_class
+
+			local mask
+			if modes.is_native(mode_name) then
+				mask = native_mask(mode_name, mode, entry)
+			else
+				-- Call mode:apply directly (skip modes.apply overhead)
+				mask = mode:apply(context)
+			end
 
 			if mask ~= entry.value then
 				mask_count = mask_count + 1
@@ -236,6 +428,9 @@ function M.generate_masks(content, source)
 					value_end = entry.value_end,
 					quote_type = entry.quote_type,
 					value = entry.value,
+					encoding = entry.encoding,
+					resolved = entry.resolved,
+					fingerprint = entry.fingerprint,
 				}
 			end
 		end
@@ -248,29 +443,22 @@ function M.generate_masks(content, source)
 end
 
 ---Generate masks for specific line range only (incremental update)
----Only processes entries in the affected range, merges with cached masks
+---Re-parses only the edited part of the document, re-masks the entries the update
+---changed and merges the masks of the affected range with cached masks
 ---@param content string Full buffer content
 ---@param source string|nil
 ---@param line_range {min_line: number, max_line: number} 1-indexed line range
 ---@param cached_masks ShelterMaskedLine[] Previously cached masks
+---@param bufnr? number Buffer whose edits are fed to `edit_document` (default: keyed by source)
 ---@return ShelterMaskResult
-function M.generate_masks_incremental(content, source, line_range, cached_masks)
+function M.generate_masks_incremental(content, source, line_range, cached_masks, bufnr)
 	local cfg = config.get()
 	local skip_comments = cfg.skip_comments
-	local parsed = M.parse_content(content)
-
-	-- Filter entries to only those in affected range
-	local affected_entries = {}
-	local affected_count = 0
-	for _, entry in ipairs(parsed.entries) do
-		if entry.line_number >= line_range.min_line and entry.line_number <= line_range.max_line then
-			affected_count = affected_count + 1
-			affected_entries[affected_count] = entry
-		end
-	end
+	local doc_state = sync_document(content, source, bufnr or source or "")
+	local parsed = doc_state.doc:entries_in_lines(line_range.min_line, line_range.max_line)
+	local affected_entries = parsed.entries
 
 	-- Generate masks ONLY for affected entries
-	local source_basename = source and vim.fn.fnamemodify(source, ":t") or nil
 	local mode_name_memo = {}
 	local mode_instance_cache = {}
 	local new_masks = {}
@@ -283,17 +471,27 @@ function M.generate_masks_incremental(content, source, line_range, cached_masks)
 		line_number = nil,
 		quote_type = nil,
 		is_comment = nil,
+		secret_class = nil,
 		config = cfg,
 		value = nil,
 	}
 
 	for _, entry in ipairs(affected_entries) do
-		local should_skip = entry.is_comment and skip_comments
-		if not should_skip then
-			local mode_name = mode_name_memo[entry.key]
-			if not mode_name then
-				mode_name = pattern_cache.determine_mode(entry.key, source_basename)
-				mode_name_memo[entry.key] = mode_name
+		local should_skip = (entry.is_comment and skip_comments) or entry.is_reference
+		local slot = not should_skip and mask_slot(entry)
+		-- Entries the update left unchanged keep their mask
+		local mask = slot and doc_state.masks[slot]
+		if slot and not mask then
+			local mode_name
+			if entry.credential then
+				-- Credential mappings depend on the value, so skip the per-key memo
+				mode_name = pattern_cache.determine_mode(entry.key, source, entry.credential)
+			else
+				mode_name = mode_name_memo[entry.key]
+				if not mode_name then
+					mode_name = pattern_cache.determine_mode(entry.key, source)
+					mode_name_memo[entry.key] = mode_name
+				end
 			end
 
 			local mode = mode_instance_cache[mode_name]
@@ -307,24 +505,31 @@ function M.generate_masks_incremental(content, source, line_range, cached_masks)
 			context.line_number = entry.line_number
 			context.quote_type = entry.quote_type
 			context.is_comment = entry.is_comment
+			context.secret_class = entry.secret_class
 
-			local mask = mode:apply(context)
-
-			-- Skip entries where mask is identical to original value (e.g., "none" mode)
-			-- Avoids overlaying unchanged text with highlight group
-			if mask ~= entry.value then
-				new_mask_count = new_mask_count + 1
-				new_masks[new_mask_count] = {
-					line_number = entry.line_number,
-					value_end_line = entry.value_end_line,
-					mask = mask,
-					value_start = entry.value_start,
-					value_end = entry.value_end,
-					quote_type = entry.quote_type,
-					value = entry.value, -- Keep for tests/diagnostics (string ref, no copy)
-				}
+			mask = mode:apply(context)
+			if doc_state.refs[slot] then
+				doc_state.masks[slot] = mask
 			end
 		end
+
+		-- Skip entries where mask is identical to original value (e.g., "none" mode)
+		-- Avoids overlaying unchanged text with highlight group
+		if mask and mask ~= entry.value then
+			new_mask_count = new_mask_count + 1
+			new_masks[new_mask_count] = {
+				line_number = entry.line_number,
+				value_end_line = entry.value_end_line,
+				mask = mask,
+				value_start = entry.value_start,
+				value_end = entry.value_end,
+				quote_type = entry.quote_type,
+				value = entry.value, -- Keep for tests/diagnostics (string ref, no copy)
+				encoding = entry.encoding,
+				resolved = entry.resolved,
+				fingerprint = cfg.fingerprint and not entry.is_comment and native.fingerprint(entry.value) or nil,
+			}
+		end
 	end
 
 	-- Merge with cached masks (keep masks outside range)
@@ -370,6 +575,11 @@ function M.reload_patterns()
 	-- Reload modes with updated config
 	modes.reset()
 	modes.setup(cfg)
+
+	-- Masks remembered by documents came from the old modes
+	for _, state in pairs(documents) do
+		state.masks = {}
+	end
 end
 
 return M
@@ -7,10 +7,14 @@ local modes = require("shelter.modes")
 
 -- Engine functions
 M.parse_content = engine.parse_content
+M.lint = engine.lint
+M.validate = engine.validate
 M.determine_mode = engine.determine_mode
 M.mask_value = engine.mask_value
 M.generate_masks = engine.generate_masks
 M.generate_masks_incremental = engine.generate_masks_incremental
+M.edit_document = engine.edit_document
+M.release_document = engine.release_document
 M.clear_caches = engine.clear_caches
 M.init = engine.init
 M.reload_patterns = engine.reload_patterns
@@ -21,6 +21,7 @@ M.__index = M
 ---@field line_number number Line in file
 ---@field quote_type number 0=none, 1=single, 2=double
 ---@field is_comment boolean Whether in a comment
+---@field secret_class? ShelterSecretClass Native classification of the value (nil for ad-hoc values)
 ---@field config table Full plugin config
 
 ---@class ShelterModeDefinition
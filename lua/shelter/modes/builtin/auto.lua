@@ -0,0 +1,79 @@
+---@class ShelterAutoMode
+---Auto masking mode - masks values that look like secrets, reveals the rest
+local Base = require("shelter.modes.base")
+local native = require("shelter.native")
+
+-- Lazy-loaded to avoid a require cycle with the mode registry
+local modes = nil
+local function get_modes()
+	if not modes then
+		modes = require("shelter.modes")
+	end
+	return modes
+end
+
+---@type ShelterModeDefinition
+local definition = {
+	name = "auto",
+	description = "Mask values that look like secrets, show benign values like true or 3000",
+
+	schema = {
+		mode = {
+			type = "string",
+			default = "full",
+			description = "Mode used to mask values classified as secrets",
+		},
+		mask_unknown = {
+			type = "boolean",
+			default = true,
+			description = "Mask values that are neither clearly secret nor clearly benign",
+		},
+	},
+
+	default_options = {
+		mode = "full",
+		mask_unknown = true,
+	},
+
+	---@param self ShelterModeBase
+	---@param ctx ShelterModeContext
+	---@return string
+	apply = function(self, ctx)
+		local opts = self.options
+		local value = ctx.value
+
+		-- Entries from the parser carry their class; score ad-hoc values natively
+		local class = ctx.secret_class or native.score_value(value).class
+
+		if class == "likely_benign" or (class == "unknown" and not opts.mask_unknown) then
+			return value
+		end
+
+		return get_modes().get(opts.mode):apply(ctx)
+	end,
+
+	---@param options table
+	---@return boolean, string?
+	validate = function(options)
+		if options.mode == "auto" then
+			return false, "mode cannot be 'auto'"
+		end
+		return true
+	end,
+}
+
+---Create a new auto mode instance
+---@param options? table<string, any>
+---@return ShelterModeBase
+local function create(options)
+	local mode = Base.new(definition)
+	if options then
+		mode:configure(options)
+	end
+	return mode
+end
+
+return {
+	definition = definition,
+	create = create,
+}
@@ -1,15 +1,7 @@
 ---@class ShelterFullMode
 ---Full masking mode - replaces all characters with mask character
 local Base = require("shelter.modes.base")
-
--- Lazy-loaded engine for cached mask access
-local engine = nil
-local function get_engine()
-	if not engine then
-		engine = require("shelter.masking.engine")
-	end
-	return engine
-end
+local native = require("shelter.native")
 
 ---@type ShelterModeDefinition
 local definition = {
@@ -44,13 +36,8 @@ local definition = {
 	---@param ctx ShelterModeContext
 	---@return string
 	apply = function(self, ctx)
-		-- Direct property access - options pre-resolved at config time
-		local opts = self.options
-		local mask_char = opts.mask_char
-		local length = opts.fixed_length or #ctx.value
-
-		-- Use cached mask strings to avoid repeated string.rep()
-		return get_engine().get_cached_mask(mask_char, length)
+		-- Masks come from the native masker, which shelter_mask also uses
+		return native.mask_value(ctx.value, "full", self.options)
 	end,
 
 	---@param options table
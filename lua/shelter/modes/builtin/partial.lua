@@ -1,15 +1,7 @@
 ---@class ShelterPartialMode
 ---Partial masking mode - shows start/end characters, masks middle
 local Base = require("shelter.modes.base")
-
--- Lazy-loaded engine for cached mask access
-local engine = nil
-local function get_engine()
-	if not engine then
-		engine = require("shelter.masking.engine")
-	end
-	return engine
-end
+local native = require("shelter.native")
 
 ---@type ShelterModeDefinition
 local definition = {
@@ -60,32 +52,8 @@ local definition = {
 	---@param ctx ShelterModeContext
 	---@return string
 	apply = function(self, ctx)
-		-- Direct property access - options pre-resolved at config time
-		local opts = self.options
-		local mask_char = opts.mask_char
-		local show_start = opts.show_start
-		local show_end = opts.show_end
-		local min_mask = opts.min_mask
-		local get_cached_mask = get_engine().get_cached_mask
-
-		local value = ctx.value
-		local value_len = #value
-
-		-- Check if value is long enough for partial masking
-		local min_length = show_start + show_end + min_mask
-		if value_len < min_length then
-			-- Use fallback mode for short values
-			local fallback = opts.fallback_mode
-			if fallback == "none" then
-				return value
-			end
-			-- Full mask with cached string
-			return get_cached_mask(mask_char, value_len)
-		end
-
-		-- Partial masking with cached mask string
-		local mask_len = value_len - show_start - show_end
-		return value:sub(1, show_start) .. get_cached_mask(mask_char, mask_len) .. value:sub(-show_end)
+		-- Masks come from the native masker, which shelter_mask also uses
+		return native.mask_value(ctx.value, "partial", self.options)
 	end,
 
 	---@param options table
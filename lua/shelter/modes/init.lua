@@ -28,6 +28,7 @@ local builtins = {
 	full = require("shelter.modes.builtin.full"),
 	partial = require("shelter.modes.builtin.partial"),
 	none = require("shelter.modes.builtin.none"),
+	auto = require("shelter.modes.builtin.auto"),
 }
 
 ---@type table<string, ShelterModeDefinition>
@@ -209,6 +210,15 @@ function M.is_builtin(name)
 	return builtins[name] ~= nil
 end
 
+---Check if a mode is the built-in `full` or `partial` mode, whose masks
+---`shelter_mask` can produce for a whole file at once
+---@param name string Mode name
+---@return boolean
+function M.is_native(name)
+	ensure_initialized()
+	return (name == "full" or name == "partial") and definitions[name] == builtins[name].definition
+end
+
 ---Get list of all registered mode names
 ---@return string[]
 function M.list()
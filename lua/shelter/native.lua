@@ -2,6 +2,7 @@
 ---LuaJIT FFI bindings for shelter-core native library
 local M = {}
 
+local bit = require("bit")
 local ffi = require("ffi")
 local platform = require("shelter.utils.platform")
 
@@ -21,30 +22,455 @@ typedef struct {
     size_t value_end;
     size_t line_number;
     size_t value_end_line;
+    size_t inline_comment_start;
+    size_t inline_comment_end;
+    double entropy;
+    char* resolved;
+    size_t resolved_len;
+    size_t reference_start;
+    size_t reference_count;
+    size_t duplicate_start;
+    size_t duplicate_count;
+    char* fingerprint;
     uint8_t quote_type;
     uint8_t is_exported;
     uint8_t is_comment;
+    uint8_t has_inline_comment;
+    uint8_t char_classes;
+    uint8_t secret_class;
+    uint8_t credential_kind;
+    uint8_t encoding;
+    uint8_t resolve_status;
+    uint8_t is_reference;
+    uint8_t duplicate_status;
 } ShelterEntry;
 
+typedef struct {
+    size_t start;
+    size_t end;
+    size_t name_start;
+    size_t name_end;
+    size_t target;
+    uint8_t kind;
+    uint8_t status;
+    uint8_t has_target;
+} ShelterReference;
+
+typedef struct {
+    char* text;
+    size_t text_len;
+    size_t start;
+    size_t end;
+    size_t line_number;
+    uint8_t is_inline;
+} ShelterComment;
+
+typedef struct {
+    char* message;
+    size_t message_len;
+    size_t start;
+    size_t end;
+    size_t line;
+    size_t column;
+    size_t end_line;
+    size_t end_column;
+    uint8_t code;
+} ShelterDiagnostic;
+
 typedef struct {
     ShelterEntry* entries;
     size_t count;
     size_t* line_offsets;
     size_t line_count;
+    ShelterComment* comments;
+    size_t comment_count;
+    ShelterDiagnostic* diagnostics;
+    size_t diagnostic_count;
+    ShelterReference* references;
+    size_t reference_count;
+    size_t* duplicates;
+    size_t duplicate_count;
     char* error;
+    uint8_t dialect;
 } ShelterResult;
 
 typedef struct {
     uint8_t include_comments;
     uint8_t track_positions;
+    uint8_t dialect;
+    uint8_t fingerprint;
 } ShelterParseOptions;
 
+typedef struct {
+    uint8_t dialect;
+    uint8_t placeholder;
+    uint8_t keep_benign;
+} ShelterExampleOptions;
+
+typedef struct {
+    uint8_t dialect;
+    uint8_t sort;
+} ShelterFormatOptions;
+
+typedef struct {
+    const char* key;
+    size_t key_len;
+    const char* value;
+    size_t value_len;
+    uint8_t kind;
+} ShelterEdit;
+
+typedef struct {
+    size_t fixed_length;
+    size_t show_start;
+    size_t show_end;
+    size_t min_mask;
+    uint8_t mode;
+    uint8_t mask_char;
+    uint8_t preserve_length;
+    uint8_t fallback_mode;
+} ShelterModeConfig;
+
+typedef struct {
+    ShelterModeConfig mode;
+    uint8_t skip_comments;
+    uint8_t dialect;
+} ShelterMaskOptions;
+
+typedef struct {
+    char* mask;
+    size_t mask_len;
+    size_t value_start;
+    size_t value_end;
+    size_t line_number;
+    size_t value_end_line;
+    size_t value_len;
+    size_t masked_start;
+    size_t masked_end;
+    uint8_t quote_type;
+    uint8_t is_comment;
+} ShelterMask;
+
+typedef struct {
+    ShelterMask* masks;
+    size_t count;
+    size_t* line_offsets;
+    size_t line_count;
+    char* error;
+} ShelterMaskResult;
+
+typedef struct {
+    const char* pattern;
+    size_t pattern_len;
+    const char* mode;
+    size_t mode_len;
+} ShelterPatternRule;
+
+typedef struct ShelterPatternSet ShelterPatternSet;
+
+typedef struct {
+    ShelterEntry entry;
+    char* mask;
+    size_t mask_len;
+    size_t masked_start;
+    size_t masked_end;
+    size_t value_start_col;
+    size_t value_end_col;
+    uint64_t id;
+    uint8_t kind;
+} ShelterEntryChange;
+
+typedef struct {
+    ShelterEntryChange* changes;
+    size_t change_count;
+    uint64_t* removed;
+    size_t removed_count;
+    char* error;
+} ShelterDocumentDelta;
+
+typedef struct ShelterDocument ShelterDocument;
+
+typedef struct {
+    const char* path;
+    size_t path_len;
+    const char* content;
+    size_t content_len;
+} ShelterFile;
+
+typedef struct {
+    size_t file;
+    size_t line_number;
+    size_t key_start;
+    size_t key_end;
+    size_t value_start;
+    size_t value_end;
+} ShelterLayerOccurrence;
+
+typedef struct {
+    char* key;
+    size_t key_len;
+    ShelterLayerOccurrence origin;
+    size_t shadowed_start;
+    size_t shadowed_count;
+} ShelterLayerKey;
+
+typedef struct {
+    size_t precedence;
+    size_t entry_count;
+    size_t diagnostic_count;
+    uint8_t dialect;
+    uint8_t loaded;
+} ShelterLayerFile;
+
+typedef struct {
+    ShelterLayerKey* keys;
+    size_t key_count;
+    ShelterLayerOccurrence* shadowed;
+    size_t shadowed_count;
+    ShelterLayerFile* files;
+    size_t file_count;
+    char* error;
+} ShelterLayerResult;
+
+typedef struct {
+    size_t line_number;
+    size_t group;
+    uint8_t status;
+} ShelterCompareCell;
+
+typedef struct {
+    char* key;
+    size_t key_len;
+    size_t group_count;
+    size_t missing_count;
+} ShelterCompareKey;
+
+typedef struct {
+    ShelterCompareKey* keys;
+    size_t key_count;
+    ShelterCompareCell* cells;
+    size_t cell_count;
+    size_t file_count;
+    char* error;
+} ShelterCompareResult;
+
+typedef struct {
+    char* message;
+    size_t message_len;
+    size_t start;
+    size_t end;
+    size_t line;
+    size_t column;
+    size_t end_line;
+    size_t end_column;
+    uint8_t rule;
+    uint8_t severity;
+} ShelterLintFinding;
+
+typedef struct {
+    ShelterLintFinding* findings;
+    size_t count;
+    char* error;
+} ShelterLintResult;
+
+typedef struct {
+    char* key;
+    size_t key_len;
+    char* message;
+    size_t message_len;
+    size_t start;
+    size_t end;
+    size_t line;
+    size_t column;
+    size_t end_line;
+    size_t end_column;
+    uint8_t code;
+    uint8_t severity;
+} ShelterSchemaViolation;
+
+typedef struct {
+    ShelterSchemaViolation* violations;
+    size_t count;
+    char* error;
+} ShelterSchemaResult;
+
+typedef struct {
+    char* key;
+    size_t key_len;
+    char* value;
+    size_t value_len;
+    size_t key_start;
+    size_t key_end;
+    size_t value_start;
+    size_t value_end;
+    size_t line_number;
+    size_t value_end_line;
+    double confidence;
+    uint8_t rule;
+    uint8_t credential_kind;
+} ShelterScanFinding;
+
+typedef struct {
+    ShelterScanFinding* findings;
+    size_t count;
+    size_t* line_offsets;
+    size_t line_count;
+    char* error;
+} ShelterScanResult;
+
+typedef struct {
+    size_t min_length;
+    uint8_t mask_char;
+    uint8_t skip_benign;
+} ShelterStreamOptions;
+
+typedef struct {
+    size_t start;
+    size_t end;
+} ShelterStreamSpan;
+
+typedef struct {
+    uint8_t* output;
+    size_t output_len;
+    ShelterStreamSpan* spans;
+    size_t span_count;
+} ShelterStreamChunk;
+
+typedef struct ShelterStreamMasker ShelterStreamMasker;
+
+typedef struct {
+    ShelterEntry entry;
+    uint8_t format;
+} ShelterDumpEntry;
+
+typedef struct {
+    ShelterDumpEntry* entries;
+    size_t count;
+    size_t* line_offsets;
+    size_t line_count;
+    char* error;
+} ShelterDumpResult;
+
+typedef struct {
+    double entropy;
+    size_t length;
+    uint8_t char_classes;
+    uint8_t class;
+    uint8_t credential;
+} ShelterValueScore;
+
 // Parsing functions
 ShelterResult* shelter_parse(const char* input, size_t input_len, ShelterParseOptions options);
 void shelter_free_result(ShelterResult* result);
+uint8_t shelter_detect_dialect(const char* source, size_t source_len, const char* input, size_t input_len);
+
+// Masking functions
+ShelterMaskResult* shelter_mask(const char* input, size_t input_len, ShelterMaskOptions options);
+char* shelter_mask_value(const char* value, size_t value_len, ShelterModeConfig mode);
+void shelter_free_mask_result(ShelterMaskResult* result);
+
+// Layer functions
+ShelterLayerResult* shelter_resolve_layers(
+    const ShelterFile* files, size_t file_count,
+    uint8_t scheme,
+    const char* mode, size_t mode_len);
+void shelter_free_layer_result(ShelterLayerResult* result);
+
+// Compare functions
+ShelterCompareResult* shelter_compare(const ShelterFile* files, size_t file_count);
+void shelter_free_compare_result(ShelterCompareResult* result);
+
+// Lint functions
+ShelterLintResult* shelter_lint(const char* input, size_t input_len, uint8_t dialect);
+void shelter_free_lint_result(ShelterLintResult* result);
+
+// Schema functions
+ShelterSchemaResult* shelter_validate(
+    const char* input, size_t input_len,
+    const char* schema, size_t schema_len,
+    uint8_t dialect);
+void shelter_free_schema_result(ShelterSchemaResult* result);
+
+// Scan functions
+ShelterScanResult* shelter_scan_text(const char* input, size_t input_len, uint32_t ruleset);
+void shelter_free_scan_result(ShelterScanResult* result);
+
+// Stream functions
+ShelterStreamMasker* shelter_stream_masker_new(
+    const ShelterFile* files, size_t file_count,
+    ShelterStreamOptions options,
+    char** error_out);
+size_t shelter_stream_masker_value_count(const ShelterStreamMasker* masker);
+ShelterStreamChunk* shelter_stream_masker_feed(ShelterStreamMasker* masker, const char* chunk, size_t chunk_len);
+ShelterStreamChunk* shelter_stream_masker_finish(ShelterStreamMasker* masker);
+ShelterStreamChunk* shelter_stream_masker_redact(ShelterStreamMasker* masker, const char* input, size_t input_len);
+void shelter_free_stream_chunk(ShelterStreamChunk* chunk);
+void shelter_stream_masker_free(ShelterStreamMasker* masker);
+
+// Dump functions
+ShelterDumpResult* shelter_find_env_dumps(const char* input, size_t input_len);
+void shelter_free_dump_result(ShelterDumpResult* result);
+
+// Pattern functions
+ShelterPatternSet* shelter_pattern_set_new(
+    const ShelterPatternRule* patterns, size_t pattern_count,
+    const ShelterPatternRule* sources, size_t source_count,
+    const char* default_mode, size_t default_mode_len,
+    char** error_out);
+const char* shelter_pattern_set_resolve(
+    const ShelterPatternSet* set,
+    const char* key, size_t key_len,
+    const char* source, size_t source_len);
+const char* shelter_pattern_set_match_key(const ShelterPatternSet* set, const char* key, size_t key_len);
+const char* shelter_pattern_set_match_source(const ShelterPatternSet* set, const char* source, size_t source_len);
+void shelter_pattern_set_free(ShelterPatternSet* set);
+
+// Scoring functions
+ShelterValueScore shelter_score_value(const char* value, size_t value_len);
+
+// Fingerprint functions
+char* shelter_fingerprint(
+    const char* value, size_t value_len,
+    const char* key, size_t key_len,
+    char** error_out);
+uint8_t shelter_fingerprint_equal(const char* a, size_t a_len, const char* b, size_t b_len);
+
+// Document functions
+ShelterDocument* shelter_document_new(
+    const char* input, size_t input_len,
+    ShelterMaskOptions options,
+    char** error_out);
+uint8_t shelter_document_edit(
+    ShelterDocument* doc,
+    size_t start_byte, size_t old_len,
+    const char* new_text, size_t new_len);
+uint8_t shelter_document_reset(ShelterDocument* doc, const char* input, size_t input_len);
+ShelterDocumentDelta* shelter_document_update(ShelterDocument* doc);
+ShelterResult* shelter_document_entries(const ShelterDocument* doc);
+ShelterResult* shelter_document_entries_in_lines(
+    const ShelterDocument* doc,
+    size_t first_line, size_t last_line);
+void shelter_free_document_delta(ShelterDocumentDelta* delta);
+void shelter_document_free(ShelterDocument* doc);
+
+// Transform functions
+char* shelter_generate_example(
+    const char* input, size_t input_len,
+    ShelterExampleOptions options,
+    char** error_out);
+char* shelter_edit(
+    const char* input, size_t input_len,
+    uint8_t dialect,
+    const ShelterEdit* edits, size_t edit_count,
+    char** error_out);
+char* shelter_format(
+    const char* input, size_t input_len,
+    ShelterFormatOptions options,
+    char** error_out);
 
 // Utility functions
 const char* shelter_version(void);
+void shelter_free_string(char* s);
 ]]
 )
 
@@ -125,14 +551,259 @@ end
 ---@field quote_type number
 ---@field is_exported boolean
 ---@field is_comment boolean
+---@field inline_comment_start number|nil Byte offset of the `#` of a trailing inline comment
+---@field inline_comment_end number|nil
+---@field entropy number Shannon entropy of the value in bits per byte
+---@field char_classes number Bitmask of character classes (1=lower, 2=upper, 4=digit, 8=other)
+---@field secret_class ShelterSecretClass
+---@field credential ShelterCredentialKind|nil Known credential format of the value
+---@field encoding "base64"|nil Encoding the value was decoded from (value is the decoded text, the span the source)
+---@field resolved string|nil Value with `${VAR}` references expanded (nil if it has none)
+---@field resolve_status ShelterResolveStatus|nil Most severe status of the references
+---@field is_reference boolean Whether the value is a single `$VAR` or `${VAR}` resolving to another entry
+---@field references ShelterReference[]|nil References in the value (only from parse)
+---@field duplicate ShelterDuplicateStatus|nil Set if the key is defined more than once in the file
+---@field duplicates number[]|nil 1-based indices of the other entries with the same key (only from parse)
+---@field fingerprint string|nil Keyed fingerprint tag of the value (only with the `fingerprint` parse option)
+
+---@class ShelterReference
+---@field start_byte number Byte offset of the `$`
+---@field end_byte number Byte offset after the reference
+---@field name_start number Byte offset where the variable name starts
+---@field name_end number
+---@field kind "plain"|"default"|"required"|"alternate"
+---@field status ShelterResolveStatus
+---@field target number|nil 1-based index of the entry the name resolved to
+
+---@class ShelterParsedComment
+---@field text string Comment text including the leading `#`
+---@field start_byte number Byte offset of the `#`
+---@field end_byte number Byte offset of the end of the line (excluding newline)
+---@field line_number number
+---@field is_inline boolean Whether the comment trails a value on the same line
+
+---@class ShelterDiagnostic
+---@field code string Error code, e.g. "unclosed_quote" (see DIAGNOSTIC_CODES)
+---@field message string
+---@field start_byte number Byte offset where the affected range starts
+---@field end_byte number Byte offset where the affected range ends (end of input for unclosed quotes)
+---@field line number 1-based line of start
+---@field column number 0-based byte column of start
+---@field end_line number 1-based line of end
+---@field end_column number 0-based byte column of end
 
 ---@class ShelterParseResult
 ---@field entries ShelterParsedEntry[]
 ---@field line_offsets number[] Byte offset where each line starts (1-indexed, line_offsets[1] = offset of line 1)
+---@field comments ShelterParsedComment[] Full-line and inline comments
+---@field diagnostics ShelterDiagnostic[] Parse errors korni recovered from
+---@field dialect ShelterDialect Dialect the content was parsed as
+
+---@alias ShelterDialect "edf"|"docker_compose"|"systemd"|"direnv"|"shell"|"python_dotenv"|"node_dotenv"|"json"|"toml"|"ini"|"yaml"
+
+-- ShelterDialect value to name (1 = auto is only ever an input)
+local DIALECTS = {
+	[0] = "edf",
+	[2] = "docker_compose",
+	[3] = "systemd",
+	[4] = "direnv",
+	[5] = "shell",
+	[6] = "python_dotenv",
+	[7] = "node_dotenv",
+	[8] = "json",
+	[9] = "toml",
+	[10] = "ini",
+	[11] = "yaml",
+}
+
+-- Dialect name to ShelterDialect value
+local DIALECT_VALUES = { auto = 1 }
+for value, name in pairs(DIALECTS) do
+	DIALECT_VALUES[name] = value
+end
+
+---@alias ShelterSecretClass "unknown"|"likely_secret"|"likely_benign"
+
+-- ShelterSecretClass value to name
+local SECRET_CLASSES = {
+	[0] = "unknown",
+	[1] = "likely_secret",
+	[2] = "likely_benign",
+}
+
+---@alias ShelterCredentialKind "aws_access_key"|"github_token"|"gitlab_token"|"npm_token"|"stripe_key"|"slack_token"|"slack_webhook"|"jwt"|"google_api_key"|"sendgrid_key"|"private_key"
+
+-- ShelterCredentialKind value to name (0 = none)
+local CREDENTIAL_KINDS = {
+	[1] = "aws_access_key",
+	[2] = "github_token",
+	[3] = "gitlab_token",
+	[4] = "npm_token",
+	[5] = "stripe_key",
+	[6] = "slack_token",
+	[7] = "slack_webhook",
+	[8] = "jwt",
+	[9] = "google_api_key",
+	[10] = "sendgrid_key",
+	[11] = "private_key",
+}
+
+-- ShelterValueEncoding value to name (0 = plain)
+local ENCODINGS = {
+	[1] = "base64",
+}
+
+---@alias ShelterResolveStatus "resolved"|"defaulted"|"unresolved"|"cycle"
 
----Parse EDF content
+-- ShelterResolveStatus value to name
+local RESOLVE_STATUSES = {
+	[0] = "resolved",
+	[1] = "defaulted",
+	[2] = "unresolved",
+	[3] = "cycle",
+}
+
+---@alias ShelterDuplicateStatus "effective"|"shadowed"|"conflict"
+
+-- ShelterDuplicateStatus value to name (0 = unique)
+local DUPLICATE_STATUSES = {
+	[1] = "effective",
+	[2] = "shadowed",
+	[3] = "conflict",
+}
+
+-- ShelterReferenceKind value to name
+local REFERENCE_KINDS = {
+	[0] = "plain",
+	[1] = "default",
+	[2] = "required",
+	[3] = "alternate",
+}
+
+-- ShelterDiagnosticCode value to name
+local DIAGNOSTIC_CODES = {
+	[0] = "generic",
+	[1] = "unclosed_quote",
+	[2] = "invalid_key",
+	[3] = "forbidden_whitespace",
+	[4] = "double_equals",
+	[5] = "invalid_bom",
+	[6] = "expected",
+	[7] = "invalid_utf8",
+	[8] = "io",
+}
+
+---Convert a ShelterEntry into a Lua table
+---@param entry ffi.cdata*
+---@return ShelterParsedEntry
+local function entry_to_table(entry)
+	return {
+		key = ffi.string(entry.key, entry.key_len),
+		value = ffi.string(entry.value, entry.value_len),
+		key_start = tonumber(entry.key_start),
+		key_end = tonumber(entry.key_end),
+		value_start = tonumber(entry.value_start),
+		value_end = tonumber(entry.value_end),
+		line_number = tonumber(entry.line_number),
+		value_end_line = tonumber(entry.value_end_line),
+		quote_type = tonumber(entry.quote_type),
+		is_exported = entry.is_exported ~= 0,
+		is_comment = entry.is_comment ~= 0,
+		inline_comment_start = entry.has_inline_comment ~= 0 and tonumber(entry.inline_comment_start) or nil,
+		inline_comment_end = entry.has_inline_comment ~= 0 and tonumber(entry.inline_comment_end) or nil,
+		entropy = tonumber(entry.entropy),
+		char_classes = tonumber(entry.char_classes),
+		secret_class = SECRET_CLASSES[entry.secret_class] or "unknown",
+		credential = CREDENTIAL_KINDS[entry.credential_kind],
+		encoding = ENCODINGS[entry.encoding],
+		resolved = entry.resolved ~= nil and ffi.string(entry.resolved, entry.resolved_len) or nil,
+		resolve_status = entry.resolved ~= nil and RESOLVE_STATUSES[entry.resolve_status] or nil,
+		is_reference = entry.is_reference ~= 0,
+		duplicate = DUPLICATE_STATUSES[entry.duplicate_status],
+		fingerprint = entry.fingerprint ~= nil and ffi.string(entry.fingerprint) or nil,
+	}
+end
+
+---Extract entries, line offsets, comments and diagnostics from a ShelterResult
+---@param result ffi.cdata*
+---@return ShelterParseResult
+local function result_to_table(result)
+	local entries = {}
+	local entry_count = tonumber(result.count)
+	for i = 0, entry_count - 1 do
+		local entry = result.entries[i]
+		local t = entry_to_table(entry)
+		local reference_start = tonumber(entry.reference_start)
+		for j = 0, tonumber(entry.reference_count) - 1 do
+			local r = result.references[reference_start + j]
+			t.references = t.references or {}
+			t.references[j + 1] = {
+				start_byte = tonumber(r.start),
+				end_byte = tonumber(r["end"]),
+				name_start = tonumber(r.name_start),
+				name_end = tonumber(r.name_end),
+				kind = REFERENCE_KINDS[r.kind] or "plain",
+				status = RESOLVE_STATUSES[r.status] or "resolved",
+				target = r.has_target ~= 0 and tonumber(r.target) + 1 or nil,
+			}
+		end
+		local duplicate_start = tonumber(entry.duplicate_start)
+		for j = 0, tonumber(entry.duplicate_count) - 1 do
+			t.duplicates = t.duplicates or {}
+			t.duplicates[j + 1] = tonumber(result.duplicates[duplicate_start + j]) + 1
+		end
+		entries[i + 1] = t
+	end
+
+	-- Extract line offsets (pre-computed in Rust)
+	local line_offsets = {}
+	local line_count = tonumber(result.line_count) or 0
+	-- FFI null pointer check: use ffi.cast to check for NULL
+	if line_count > 0 and result.line_offsets ~= ffi.cast("size_t*", 0) then
+		for i = 0, line_count - 1 do
+			line_offsets[i + 1] = tonumber(result.line_offsets[i])
+		end
+	end
+
+	local comments = {}
+	for i = 0, tonumber(result.comment_count) - 1 do
+		local c = result.comments[i]
+		comments[i + 1] = {
+			text = ffi.string(c.text, c.text_len),
+			start_byte = tonumber(c.start),
+			end_byte = tonumber(c["end"]),
+			line_number = tonumber(c.line_number),
+			is_inline = c.is_inline ~= 0,
+		}
+	end
+
+	local diagnostics = {}
+	for i = 0, tonumber(result.diagnostic_count) - 1 do
+		local d = result.diagnostics[i]
+		diagnostics[i + 1] = {
+			code = DIAGNOSTIC_CODES[d.code] or "generic",
+			message = ffi.string(d.message, d.message_len),
+			start_byte = tonumber(d.start),
+			end_byte = tonumber(d["end"]),
+			line = tonumber(d.line),
+			column = tonumber(d.column),
+			end_line = tonumber(d.end_line),
+			end_column = tonumber(d.end_column),
+		}
+	end
+
+	return {
+		entries = entries,
+		line_offsets = line_offsets,
+		comments = comments,
+		diagnostics = diagnostics,
+		dialect = DIALECTS[result.dialect] or "edf",
+	}
+end
+
+---Parse dotenv content
 ---@param content string The content to parse
----@param opts? {include_comments?: boolean, track_positions?: boolean}
+---@param opts? {include_comments?: boolean, track_positions?: boolean, dialect?: ShelterDialect|"auto", fingerprint?: boolean}
 ---@return ShelterParseResult
 function M.parse(content, opts)
 	local l = ensure_lib()
@@ -141,6 +812,8 @@ function M.parse(content, opts)
 	local parse_opts = ffi.new("ShelterParseOptions", {
 		include_comments = opts.include_comments ~= false and 1 or 0,
 		track_positions = opts.track_positions ~= false and 1 or 0,
+		dialect = DIALECT_VALUES[opts.dialect or "edf"] or 0,
+		fingerprint = opts.fingerprint and 1 or 0,
 	})
 
 	local result = l.shelter_parse(content, #content, parse_opts)
@@ -152,42 +825,1065 @@ function M.parse(content, opts)
 		error("Parse error: " .. err_msg)
 	end
 
-	-- Convert entries to Lua tables
+	-- Convert everything to Lua tables
+	local parsed = result_to_table(result)
+	l.shelter_free_result(result)
+
+	return parsed
+end
+
+---@class ShelterExampleOpts
+---@field dialect? ShelterDialect|"auto" Dialect of the content (default: "edf")
+---@field hints? boolean Replace values with type hints (`<url>`, `<int>`) instead of empty strings
+---@field keep_benign? boolean Keep values classified as likely benign
+
+---Generate a .env.example version of a file
+---Only values change; `# @example <value>` comments set a value's placeholder.
+---Errors on content that does not parse rather than copying it through.
+---@param content string
+---@param opts? ShelterExampleOpts
+---@return string
+function M.generate_example(content, opts)
+	local l = ensure_lib()
+	opts = opts or {}
+
+	local example_opts = ffi.new("ShelterExampleOptions", {
+		dialect = DIALECT_VALUES[opts.dialect or "edf"] or 0,
+		placeholder = opts.hints and 1 or 0,
+		keep_benign = opts.keep_benign and 1 or 0,
+	})
+	local err = ffi.new("char*[1]")
+	local example = l.shelter_generate_example(content, #content, example_opts, err)
+
+	if example == nil then
+		local err_msg = err[0] ~= nil and ffi.string(err[0]) or "unknown error"
+		l.shelter_free_string(err[0])
+		error("Example error: " .. err_msg)
+	end
+
+	local result = ffi.string(example)
+	l.shelter_free_string(example)
+	return result
+end
+
+-- ShelterLintRule value to stable code and name
+local LINT_RULES = {
+	[0] = { "SH000", "parse-error" },
+	[1] = { "SH001", "lowercase-key" },
+	[2] = { "SH002", "non-portable-key" },
+	[3] = { "SH003", "spaces-around-separator" },
+	[4] = { "SH004", "unquoted-special-value" },
+	[5] = { "SH005", "inconsistent-quotes" },
+	[6] = { "SH006", "missing-final-newline" },
+	[7] = { "SH007", "mixed-export" },
+	[8] = { "SH008", "empty-required-value" },
+}
+
+---@class ShelterLintFinding
+---Fields match `vim.diagnostic.Diagnostic`, so findings can be passed to `vim.diagnostic.set`
+---@field lnum number 0-based line of the start
+---@field col number 0-based byte column of the start
+---@field end_lnum number 0-based line of the end
+---@field end_col number 0-based byte column of the end
+---@field severity number `vim.diagnostic.severity` (1 = error, 2 = warning, 3 = info)
+---@field message string
+---@field code string Stable rule code, e.g. "SH001"
+---@field name string Stable rule name, e.g. "lowercase-key"
+---@field source "shelter"
+---@field start_byte number
+---@field end_byte number
+
+---Lint a file: non-portable keys, risky quoting, mixed `export`, empty `@required` values...
+---@param content string
+---@param dialect? ShelterDialect|"auto" Dialect of the content (default: "edf")
+---@return ShelterLintFinding[]
+function M.lint(content, dialect)
+	local l = ensure_lib()
+	local result = l.shelter_lint(content, #content, DIALECT_VALUES[dialect or "edf"] or 0)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_lint_result(result)
+		error("Lint error: " .. err_msg)
+	end
+
+	local findings = {}
+	for i = 0, tonumber(result.count) - 1 do
+		local f = result.findings[i]
+		local rule = LINT_RULES[f.rule] or LINT_RULES[0]
+		findings[i + 1] = {
+			lnum = tonumber(f.line) - 1,
+			col = tonumber(f.column),
+			end_lnum = tonumber(f.end_line) - 1,
+			end_col = tonumber(f.end_column),
+			severity = tonumber(f.severity),
+			message = ffi.string(f.message, f.message_len),
+			code = rule[1],
+			name = rule[2],
+			source = "shelter",
+			start_byte = tonumber(f.start),
+			end_byte = tonumber(f["end"]),
+		}
+	end
+
+	l.shelter_free_lint_result(result)
+	return findings
+end
+
+-- ShelterSchemaCode value to stable code and name
+local SCHEMA_CODES = {
+	[0] = { "SH101", "required" },
+	[1] = { "SH102", "invalid-type" },
+	[2] = { "SH103", "not-in-enum" },
+	[3] = { "SH104", "pattern-mismatch" },
+	[4] = { "SH105", "undeclared-key" },
+}
+
+---@class ShelterSchemaViolation: ShelterLintFinding
+---@field key string Key the violation is about
+
+---Validate a file against `@type`, `@required`, `@optional`, `@default` and `@pattern` annotations
+---Annotations in the content override those in the schema key by key. Missing keys are
+---reported at the start of the file; with a schema, undeclared keys are reported as warnings.
+---@param content string
+---@param schema? string Content of a `.env.schema` file
+---@param dialect? ShelterDialect|"auto" Dialect of the content (default: "edf")
+---@return ShelterSchemaViolation[]
+function M.validate(content, schema, dialect)
+	local l = ensure_lib()
+	local result = l.shelter_validate(
+		content,
+		#content,
+		schema,
+		schema and #schema or 0,
+		DIALECT_VALUES[dialect or "edf"] or 0
+	)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_schema_result(result)
+		error("Schema error: " .. err_msg)
+	end
+
+	local violations = {}
+	for i = 0, tonumber(result.count) - 1 do
+		local v = result.violations[i]
+		local code = SCHEMA_CODES[v.code] or SCHEMA_CODES[1]
+		violations[i + 1] = {
+			lnum = tonumber(v.line) - 1,
+			col = tonumber(v.column),
+			end_lnum = tonumber(v.end_line) - 1,
+			end_col = tonumber(v.end_column),
+			severity = tonumber(v.severity),
+			message = ffi.string(v.message, v.message_len),
+			code = code[1],
+			name = code[2],
+			source = "shelter",
+			start_byte = tonumber(v.start),
+			end_byte = tonumber(v["end"]),
+			key = ffi.string(v.key, v.key_len),
+		}
+	end
+
+	l.shelter_free_schema_result(result)
+	return violations
+end
+
+---@alias ShelterScanRule ShelterCredentialKind|"url_password"|"secret_assignment"
+
+-- ShelterScanRule value to id (the bit of the rule in a ruleset)
+local SCAN_RULES = {
+	[0] = "aws_access_key",
+	[1] = "github_token",
+	[2] = "gitlab_token",
+	[3] = "npm_token",
+	[4] = "stripe_key",
+	[5] = "slack_token",
+	[6] = "slack_webhook",
+	[7] = "jwt",
+	[8] = "google_api_key",
+	[9] = "sendgrid_key",
+	[10] = "private_key",
+	[11] = "url_password",
+	[12] = "secret_assignment",
+}
+
+-- Scan rule id to bit
+local SCAN_RULE_BITS = {}
+for value, id in pairs(SCAN_RULES) do
+	SCAN_RULE_BITS[id] = bit.lshift(1, value)
+end
+
+---@class ShelterScanFinding
+---Span fields match `ShelterParsedEntry`, so findings can be masked like entries
+---@field key string Name the secret is assigned to ("" if none)
+---@field value string The secret
+---@field key_start number|nil
+---@field key_end number|nil
+---@field value_start number
+---@field value_end number
+---@field line_number number 1-based line where the secret starts
+---@field value_end_line number 1-based line where the secret ends
+---@field quote_type number Always 0 (the span is the secret itself)
+---@field is_comment boolean Always false
+---@field secret_class "likely_secret"
+---@field credential ShelterCredentialKind|nil
+---@field rule ShelterScanRule
+---@field confidence number 0.0 to 1.0
+
+---@class ShelterScanResult
+---@field entries ShelterScanFinding[]
+---@field line_offsets number[] Byte offset where each line starts (as in `ShelterParseResult`)
+
+---Find secrets in any text: known credential formats, URL passwords and secret-looking assignments
+---Findings never overlap. Fast enough to run on every change of a large buffer.
+---@param content string
+---@param ruleset? ShelterScanRule[] Rules to run (default: all)
+---@return ShelterScanResult
+function M.scan_text(content, ruleset)
+	local l = ensure_lib()
+
+	local mask = 0xFFFFFFFF
+	if ruleset then
+		mask = 0
+		for _, id in ipairs(ruleset) do
+			local rule_bit = SCAN_RULE_BITS[id]
+			if not rule_bit then
+				error("Unknown scan rule: " .. tostring(id))
+			end
+			mask = bit.bor(mask, rule_bit)
+		end
+	end
+
+	local result = l.shelter_scan_text(content, #content, mask)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_scan_result(result)
+		error("Scan error: " .. err_msg)
+	end
+
 	local entries = {}
-	local entry_count = tonumber(result.count)
-	for i = 0, entry_count - 1 do
-		local entry = result.entries[i]
+	for i = 0, tonumber(result.count) - 1 do
+		local f = result.findings[i]
+		local has_key = f.key_len > 0
 		entries[i + 1] = {
-			key = ffi.string(entry.key, entry.key_len),
-			value = ffi.string(entry.value, entry.value_len),
-			key_start = tonumber(entry.key_start),
-			key_end = tonumber(entry.key_end),
-			value_start = tonumber(entry.value_start),
-			value_end = tonumber(entry.value_end),
-			line_number = tonumber(entry.line_number),
-			value_end_line = tonumber(entry.value_end_line),
-			quote_type = tonumber(entry.quote_type),
-			is_exported = entry.is_exported ~= 0,
-			is_comment = entry.is_comment ~= 0,
+			key = ffi.string(f.key, f.key_len),
+			value = ffi.string(f.value, f.value_len),
+			key_start = has_key and tonumber(f.key_start) or nil,
+			key_end = has_key and tonumber(f.key_end) or nil,
+			value_start = tonumber(f.value_start),
+			value_end = tonumber(f.value_end),
+			line_number = tonumber(f.line_number),
+			value_end_line = tonumber(f.value_end_line),
+			quote_type = 0,
+			is_comment = false,
+			secret_class = "likely_secret",
+			credential = CREDENTIAL_KINDS[f.credential_kind],
+			rule = SCAN_RULES[f.rule],
+			confidence = tonumber(f.confidence),
+		}
+	end
+
+	local line_offsets = {}
+	for i = 0, tonumber(result.line_count) - 1 do
+		line_offsets[i + 1] = tonumber(result.line_offsets[i])
+	end
+
+	l.shelter_free_scan_result(result)
+	return { entries = entries, line_offsets = line_offsets }
+end
+
+---@alias ShelterDumpFormat "printenv"|"docker_inspect"|"kubectl"|"environ"
+
+-- ShelterDumpFormat value to name
+local DUMP_FORMATS = {
+	[0] = "printenv",
+	[1] = "docker_inspect",
+	[2] = "kubectl",
+	[3] = "environ",
+}
+
+---@class ShelterDumpEntry: ShelterParsedEntry
+---@field format ShelterDumpFormat Dump the entry was found in
+
+---@class ShelterDumpResult
+---@field entries ShelterDumpEntry[]
+---@field line_offsets number[] Byte offset where each line starts (as in `ShelterParseResult`)
+
+---Find the KEY=VALUE pairs of environment dumps in any text
+---Recognizes `printenv`/`env` output, `docker inspect` "Env" arrays, `kubectl describe`
+---Environment sections and NUL-separated /proc/<pid>/environ data. Entries are shaped like
+---parsed entries, so pattern rules decide their masks; value spans never include quotes.
+---@param content string
+---@return ShelterDumpResult
+function M.find_env_dumps(content)
+	local l = ensure_lib()
+
+	local result = l.shelter_find_env_dumps(content, #content)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_dump_result(result)
+		error("Dump error: " .. err_msg)
+	end
+
+	local entries = {}
+	for i = 0, tonumber(result.count) - 1 do
+		local d = result.entries[i]
+		local entry = entry_to_table(d.entry)
+		entry.format = DUMP_FORMATS[d.format]
+		entries[i + 1] = entry
+	end
+
+	local line_offsets = {}
+	for i = 0, tonumber(result.line_count) - 1 do
+		line_offsets[i + 1] = tonumber(result.line_offsets[i])
+	end
+
+	l.shelter_free_dump_result(result)
+	return { entries = entries, line_offsets = line_offsets }
+end
+
+-- Edit operation to ShelterEditKind value and the field holding its argument
+local EDIT_KINDS = {
+	set = { 0, "value" },
+	unset = { 1 },
+	rename = { 2, "to" },
+	move = { 3, "before" },
+}
+
+---@class ShelterEdit
+---@field op "set"|"unset"|"rename"|"move"
+---@field key string
+---@field value? string New value (set)
+---@field to? string New key (rename)
+---@field before? string Key to move before (move; default: end of file)
+
+---Apply edits to a file, keeping comments, quoting, `export` prefixes and blank lines
+---Edits run in order; if any fails, none are applied.
+---Config formats (json, toml, ini, yaml) only support setting existing keys.
+---@param content string
+---@param edits ShelterEdit[]
+---@param dialect? ShelterDialect|"auto" Dialect of the content (default: "edf")
+---@return string
+function M.edit(content, edits, dialect)
+	local l = ensure_lib()
+
+	local array = ffi.new("ShelterEdit[?]", math.max(#edits, 1))
+	for i, edit in ipairs(edits) do
+		local kind = EDIT_KINDS[edit.op]
+		if not kind then
+			error("Unknown edit: " .. tostring(edit.op))
+		end
+		local value = kind[2] and edit[kind[2]] or ""
+		local item = array[i - 1]
+		item.key = edit.key
+		item.key_len = #edit.key
+		item.value = value
+		item.value_len = #value
+		item.kind = kind[1]
+	end
+
+	local err = ffi.new("char*[1]")
+	local edited = l.shelter_edit(content, #content, DIALECT_VALUES[dialect or "edf"] or 0, array, #edits, err)
+
+	if edited == nil then
+		local err_msg = err[0] ~= nil and ffi.string(err[0]) or "unknown error"
+		l.shelter_free_string(err[0])
+		error("Edit error: " .. err_msg)
+	end
+
+	local result = ffi.string(edited)
+	l.shelter_free_string(edited)
+	return result
+end
+
+-- Sort order name to ShelterFormatSort value
+local FORMAT_SORTS = {
+	none = 0,
+	alpha = 1,
+	group = 2,
+}
+
+---@class ShelterFormatOpts
+---@field dialect? ShelterDialect|"auto" Dialect of the content (default: "edf")
+---@field sort? "none"|"alpha"|"group" Sort keys within sections, or all keys grouped by prefix (default: "none")
+
+---Format an env file: normalize quoting and whitespace, optionally sort keys
+---Comment lines directly above a key move with it; the values stay exactly the same.
+---@param content string
+---@param opts? ShelterFormatOpts
+---@return string
+function M.format(content, opts)
+	local l = ensure_lib()
+	opts = opts or {}
+
+	local sort = FORMAT_SORTS[opts.sort or "none"]
+	if not sort then
+		error("Unknown sort order: " .. tostring(opts.sort))
+	end
+	local format_opts = ffi.new("ShelterFormatOptions", {
+		dialect = DIALECT_VALUES[opts.dialect or "edf"] or 0,
+		sort = sort,
+	})
+	local err = ffi.new("char*[1]")
+	local formatted = l.shelter_format(content, #content, format_opts, err)
+
+	if formatted == nil then
+		local err_msg = err[0] ~= nil and ffi.string(err[0]) or "unknown error"
+		l.shelter_free_string(err[0])
+		error("Format error: " .. err_msg)
+	end
+
+	local result = ffi.string(formatted)
+	l.shelter_free_string(formatted)
+	return result
+end
+
+---Detect the dialect of a file from its name, falling back to its content
+---@param source string|nil File path or basename
+---@param content string
+---@return ShelterDialect
+function M.detect_dialect(source, content)
+	local l = ensure_lib()
+	local value = l.shelter_detect_dialect(source, source and #source or 0, content, #content)
+	return DIALECTS[value] or "edf"
+end
+
+-- Mode name to ShelterMaskMode value
+local MASK_MODES = {
+	full = 0,
+	partial = 1,
+	none = 2,
+}
+
+---@class ShelterNativeModeOptions
+---@field mask_char? string Single mask character (default: "*")
+---@field preserve_length? boolean
+---@field fixed_length? number Fixed mask length for full mode
+---@field show_start? number
+---@field show_end? number
+---@field min_mask? number
+---@field fallback_mode? "full"|"none"
+
+---@class ShelterNativeMask
+---@field mask string
+---@field value_start number
+---@field value_end number
+---@field line_number number
+---@field value_end_line number
+---@field value_len number
+---@field masked_start number Start of masked range within the value (0-indexed)
+---@field masked_end number End of masked range within the value (exclusive)
+---@field quote_type number 0=none, 1=single, 2=double
+---@field is_comment boolean
+
+---@class ShelterNativeMaskResult
+---@field masks ShelterNativeMask[]
+---@field line_offsets number[]
+
+---Build the fields of a ShelterModeConfig for a built-in mode
+---@param mode_name "full"|"partial"|"none"
+---@param mode_opts? ShelterNativeModeOptions
+---@return table
+local function build_mode_config(mode_name, mode_opts)
+	mode_opts = mode_opts or {}
+
+	local mode = MASK_MODES[mode_name]
+	if not mode then
+		error("Unknown native mode: " .. tostring(mode_name))
+	end
+
+	return {
+		fixed_length = mode_opts.fixed_length or 0,
+		show_start = mode_opts.show_start or 3,
+		show_end = mode_opts.show_end or 3,
+		min_mask = mode_opts.min_mask or 3,
+		mode = mode,
+		mask_char = string.byte(mode_opts.mask_char or "*"),
+		preserve_length = mode_opts.preserve_length ~= false and 1 or 0,
+		fallback_mode = mode_opts.fallback_mode == "none" and MASK_MODES.none or MASK_MODES.full,
+	}
+end
+
+---Build ShelterMaskOptions for a built-in mode
+---@param mode_name "full"|"partial"|"none"
+---@param mode_opts? ShelterNativeModeOptions
+---@param skip_comments? boolean
+---@param dialect? ShelterDialect|"auto"
+---@return ffi.cdata*
+local function build_mask_options(mode_name, mode_opts, skip_comments, dialect)
+	return ffi.new("ShelterMaskOptions", {
+		mode = build_mode_config(mode_name, mode_opts),
+		skip_comments = skip_comments ~= false and 1 or 0,
+		dialect = DIALECT_VALUES[dialect or "edf"] or 0,
+	})
+end
+
+---Mask a single value with a built-in mode
+---The built-in Lua modes delegate here, so every mask comes from the native masker.
+---@param value string
+---@param mode_name "full"|"partial"|"none"
+---@param mode_opts? ShelterNativeModeOptions
+---@return string mask The mask, or `value` itself when masking leaves it unchanged
+function M.mask_value(value, mode_name, mode_opts)
+	local l = ensure_lib()
+	local config = ffi.new("ShelterModeConfig", build_mode_config(mode_name, mode_opts))
+
+	local mask = l.shelter_mask_value(value, #value, config)
+	if mask == nil then
+		return value
+	end
+
+	local result = ffi.string(mask)
+	l.shelter_free_string(mask)
+	return result
+end
+
+---Generate masks natively using a built-in mode
+---@param content string The content to mask
+---@param mode_name "full"|"partial"|"none"
+---@param mode_opts? ShelterNativeModeOptions
+---@param skip_comments? boolean Skip values inside comments (default: true)
+---@param dialect? ShelterDialect|"auto" Dialect of the content (default: "edf")
+---@return ShelterNativeMaskResult
+function M.mask(content, mode_name, mode_opts, skip_comments, dialect)
+	local l = ensure_lib()
+	local mask_opts = build_mask_options(mode_name, mode_opts, skip_comments, dialect)
+
+	local result = l.shelter_mask(content, #content, mask_opts)
+
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_mask_result(result)
+		error("Mask error: " .. err_msg)
+	end
+
+	local masks = {}
+	for i = 0, tonumber(result.count) - 1 do
+		local m = result.masks[i]
+		masks[i + 1] = {
+			mask = ffi.string(m.mask, m.mask_len),
+			value_start = tonumber(m.value_start),
+			value_end = tonumber(m.value_end),
+			line_number = tonumber(m.line_number),
+			value_end_line = tonumber(m.value_end_line),
+			value_len = tonumber(m.value_len),
+			masked_start = tonumber(m.masked_start),
+			masked_end = tonumber(m.masked_end),
+			quote_type = tonumber(m.quote_type),
+			is_comment = m.is_comment ~= 0,
 		}
 	end
 
-	-- Extract line offsets (pre-computed in Rust)
 	local line_offsets = {}
 	local line_count = tonumber(result.line_count) or 0
-	-- FFI null pointer check: use ffi.cast to check for NULL
 	if line_count > 0 and result.line_offsets ~= ffi.cast("size_t*", 0) then
 		for i = 0, line_count - 1 do
 			line_offsets[i + 1] = tonumber(result.line_offsets[i])
 		end
 	end
 
-	l.shelter_free_result(result)
+	l.shelter_free_mask_result(result)
 
 	return {
-		entries = entries,
+		masks = masks,
 		line_offsets = line_offsets,
 	}
 end
 
+---@alias ShelterLayerScheme "vite"|"next"|"rails"|"compose"
+
+-- Layer scheme name to ShelterLayerScheme value
+local LAYER_SCHEMES = {
+	vite = 0,
+	next = 1,
+	rails = 2,
+	compose = 3,
+}
+
+---@class ShelterLayerOccurrence
+---@field file number 1-based index into the files passed in
+---@field line_number number
+---@field key_start number
+---@field key_end number
+---@field value_start number
+---@field value_end number
+
+---@class ShelterLayerKey
+---@field key string
+---@field origin ShelterLayerOccurrence The definition that wins
+---@field shadowed ShelterLayerOccurrence[] Definitions it overrides, highest precedence first
+
+---@class ShelterLayerFile
+---@field path string
+---@field loaded boolean Whether the scheme loads the file in this mode
+---@field precedence number|nil 1 loads first and has the lowest precedence
+---@field dialect ShelterDialect
+---@field entry_count number
+---@field diagnostic_count number
+
+---@class ShelterLayerResult
+---@field keys ShelterLayerKey[] Effective keys, in the order they are first defined
+---@field files ShelterLayerFile[] One per file passed in, in the same order
+
+---@param o ffi.cdata* ShelterLayerOccurrence
+---@return ShelterLayerOccurrence
+local function occurrence_to_table(o)
+	return {
+		file = tonumber(o.file) + 1,
+		line_number = tonumber(o.line_number),
+		key_start = tonumber(o.key_start),
+		key_end = tonumber(o.key_end),
+		value_start = tonumber(o.value_start),
+		value_end = tonumber(o.value_end),
+	}
+end
+
+---Convert a list of files into a ShelterFile array
+---The array points into the Lua strings, so `files` must stay referenced while it is used
+---@param files {path: string, content: string}[]
+---@return ffi.cdata*
+local function to_file_array(files)
+	local array = ffi.new("ShelterFile[?]", math.max(#files, 1))
+	for i, file in ipairs(files) do
+		local item = array[i - 1]
+		item.path = file.path
+		item.path_len = #file.path
+		item.content = file.content
+		item.content_len = #file.content
+	end
+	return array
+end
+
+---@class ShelterStreamSpan
+---@field start_byte number Byte offset counted from the start of the stream (may lie in an earlier chunk)
+---@field end_byte number Byte offset where the value ends
+
+---@class ShelterNativeStreamMasker
+---@field feed fun(self: ShelterNativeStreamMasker, chunk: string): string, ShelterStreamSpan[]
+---@field finish fun(self: ShelterNativeStreamMasker): string
+---@field redact fun(self: ShelterNativeStreamMasker, text: string): string, ShelterStreamSpan[]
+---@field count fun(self: ShelterNativeStreamMasker): number
+
+---@class ShelterStreamOptions
+---@field min_length? number Values shorter than this many bytes are not loaded (default: 6)
+---@field mask_char? string Character masked bytes are replaced with (default: "*")
+---@field skip_benign? boolean Skip values like `true` or `localhost` (default: true)
+
+---Convert a ShelterStreamChunk into output and spans, freeing it
+---@param l ffi.namespace*
+---@param chunk ffi.cdata*
+---@return string, ShelterStreamSpan[]
+local function stream_chunk(l, chunk)
+	local output = chunk.output_len > 0 and ffi.string(chunk.output, chunk.output_len) or ""
+	local spans = {}
+	for i = 0, tonumber(chunk.span_count) - 1 do
+		local s = chunk.spans[i]
+		spans[i + 1] = { start_byte = tonumber(s.start), end_byte = tonumber(s["end"]) }
+	end
+	l.shelter_free_stream_chunk(chunk)
+	return output, spans
+end
+
+---Create a masker for the values of the given env files wherever they show up in output
+---Feed it terminal or log output in chunks of any size; a value split across chunks is
+---still masked, and bytes that could start a value are held back until they are decided.
+---The values live only in Rust and are zeroized when the masker is garbage collected.
+---@param files {path: string, content: string}[]
+---@param opts? ShelterStreamOptions
+---@return ShelterNativeStreamMasker
+function M.stream_masker(files, opts)
+	local l = ensure_lib()
+	opts = opts or {}
+
+	local options = ffi.new("ShelterStreamOptions")
+	options.min_length = opts.min_length or 6
+	options.mask_char = string.byte(opts.mask_char or "*")
+	options.skip_benign = opts.skip_benign == false and 0 or 1
+
+	local array = to_file_array(files)
+	local err = ffi.new("char*[1]")
+	local handle = l.shelter_stream_masker_new(array, #files, options, err)
+	if handle == nil then
+		local err_msg = err[0] ~= nil and ffi.string(err[0]) or "unknown error"
+		l.shelter_free_string(err[0])
+		error("Stream error: " .. err_msg)
+	end
+
+	handle = ffi.gc(handle, l.shelter_stream_masker_free)
+
+	return {
+		feed = function(_, chunk)
+			return stream_chunk(l, l.shelter_stream_masker_feed(handle, chunk, #chunk))
+		end,
+		finish = function(_)
+			return (stream_chunk(l, l.shelter_stream_masker_finish(handle)))
+		end,
+		redact = function(_, text)
+			return stream_chunk(l, l.shelter_stream_masker_redact(handle, text, #text))
+		end,
+		count = function(_)
+			return tonumber(l.shelter_stream_masker_value_count(handle))
+		end,
+	}
+end
+
+---Work out which file each key of a layered env file set comes from
+---Files are recognised by name (`.env`, `.env.local`, `.env.<mode>`, `.env.<mode>.local`);
+---compose loads every file in list order. Values are never returned.
+---@param files {path: string, content: string}[]
+---@param scheme ShelterLayerScheme
+---@param mode? string Vite mode, NODE_ENV or RAILS_ENV (default: "development")
+---@return ShelterLayerResult
+function M.resolve_layers(files, scheme, mode)
+	local l = ensure_lib()
+	local scheme_value = LAYER_SCHEMES[scheme]
+	if not scheme_value then
+		error("Unknown layer scheme: " .. tostring(scheme))
+	end
+
+	local array = to_file_array(files)
+	local result = l.shelter_resolve_layers(array, #files, scheme_value, mode, mode and #mode or 0)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_layer_result(result)
+		error("Layer error: " .. err_msg)
+	end
+
+	local keys = {}
+	for i = 0, tonumber(result.key_count) - 1 do
+		local k = result.keys[i]
+		local shadowed = {}
+		local start = tonumber(k.shadowed_start)
+		for j = 0, tonumber(k.shadowed_count) - 1 do
+			shadowed[j + 1] = occurrence_to_table(result.shadowed[start + j])
+		end
+		keys[i + 1] = {
+			key = ffi.string(k.key, k.key_len),
+			origin = occurrence_to_table(k.origin),
+			shadowed = shadowed,
+		}
+	end
+
+	local layer_files = {}
+	for i = 0, tonumber(result.file_count) - 1 do
+		local f = result.files[i]
+		local loaded = f.loaded ~= 0
+		layer_files[i + 1] = {
+			path = files[i + 1].path,
+			loaded = loaded,
+			precedence = loaded and tonumber(f.precedence) + 1 or nil,
+			dialect = DIALECTS[f.dialect] or "edf",
+			entry_count = tonumber(f.entry_count),
+			diagnostic_count = tonumber(f.diagnostic_count),
+		}
+	end
+
+	l.shelter_free_layer_result(result)
+	return { keys = keys, files = layer_files }
+end
+
+---@alias ShelterCellStatus "missing"|"same"|"different"
+
+-- ShelterCellStatus value to name
+local CELL_STATUSES = {
+	[0] = "missing",
+	[1] = "same",
+	[2] = "different",
+}
+
+---@class ShelterCompareCell
+---@field status ShelterCellStatus
+---@field line_number number|nil Line of the effective definition (nil if missing)
+---@field group number|nil Files with equal values share a group, numbered from 1 (nil if missing)
+
+---@class ShelterCompareKey
+---@field key string
+---@field group_count number Number of distinct values
+---@field missing_count number Number of files without the key
+---@field cells ShelterCompareCell[] One per file passed in, in the same order
+
+---Compare env files key by key
+---Values are compared by fingerprints that only hold within one call; no value is returned.
+---@param files {path: string, content: string}[]
+---@return ShelterCompareKey[] keys In the order they first appear
+function M.compare(files)
+	local l = ensure_lib()
+	local array = to_file_array(files)
+	local result = l.shelter_compare(array, #files)
+	if result.error ~= nil then
+		local err_msg = ffi.string(result.error)
+		l.shelter_free_compare_result(result)
+		error("Compare error: " .. err_msg)
+	end
+
+	local file_count = tonumber(result.file_count)
+	local keys = {}
+	for i = 0, tonumber(result.key_count) - 1 do
+		local k = result.keys[i]
+		local cells = {}
+		for j = 0, file_count - 1 do
+			local c = result.cells[i * file_count + j]
+			local missing = c.status == 0
+			cells[j + 1] = {
+				status = CELL_STATUSES[c.status] or "missing",
+				line_number = not missing and tonumber(c.line_number) or nil,
+				group = not missing and tonumber(c.group) + 1 or nil,
+			}
+		end
+		keys[i + 1] = {
+			key = ffi.string(k.key, k.key_len),
+			group_count = tonumber(k.group_count),
+			missing_count = tonumber(k.missing_count),
+			cells = cells,
+		}
+	end
+
+	l.shelter_free_compare_result(result)
+	return keys
+end
+
+---Convert a { [pattern] = mode } table into a ShelterPatternRule array
+---@param rules table<string, string>
+---@return ffi.cdata*|nil array, number count
+local function to_rule_array(rules)
+	local list = {}
+	for pattern, mode in pairs(rules or {}) do
+		list[#list + 1] = { pattern, mode }
+	end
+
+	if #list == 0 then
+		return nil, 0
+	end
+
+	local array = ffi.new("ShelterPatternRule[?]", #list)
+	for i, rule in ipairs(list) do
+		local item = array[i - 1]
+		item.pattern = rule[1]
+		item.pattern_len = #rule[1]
+		item.mode = rule[2]
+		item.mode_len = #rule[2]
+	end
+	return array, #list
+end
+
+---Convert a mode name pointer owned by a pattern set into a Lua string
+---@param ptr ffi.cdata*
+---@return string|nil
+local function mode_or_nil(ptr)
+	if ptr == nil then
+		return nil
+	end
+	return ffi.string(ptr)
+end
+
+---@class ShelterNativePatternSet
+---@field resolve fun(self: ShelterNativePatternSet, key: string, source?: string): string
+---@field match_key fun(self: ShelterNativePatternSet, key: string): string|nil
+---@field match_source fun(self: ShelterNativePatternSet, source: string): string|nil
+
+---Compile key and source patterns into a native pattern set
+---Rule order does not matter: rules are ranked by specificity in Rust.
+---@param patterns table<string, string> Key pattern to mode mapping
+---@param sources table<string, string> Source pattern to mode mapping
+---@param default_mode string
+---@return ShelterNativePatternSet
+function M.pattern_set(patterns, sources, default_mode)
+	local l = ensure_lib()
+
+	-- Keep the Lua tables referenced while Rust copies the strings
+	local pattern_rules, pattern_count = to_rule_array(patterns)
+	local source_rules, source_count = to_rule_array(sources)
+	local err = ffi.new("char*[1]")
+
+	local handle = l.shelter_pattern_set_new(
+		pattern_rules,
+		pattern_count,
+		source_rules,
+		source_count,
+		default_mode,
+		#default_mode,
+		err
+	)
+
+	if handle == nil then
+		local err_msg = err[0] ~= nil and ffi.string(err[0]) or "unknown error"
+		l.shelter_free_string(err[0])
+		error("Pattern error: " .. err_msg)
+	end
+
+	handle = ffi.gc(handle, l.shelter_pattern_set_free)
+
+	return {
+		resolve = function(_, key, source)
+			return ffi.string(l.shelter_pattern_set_resolve(handle, key, #key, source, source and #source or 0))
+		end,
+		match_key = function(_, key)
+			return mode_or_nil(l.shelter_pattern_set_match_key(handle, key, #key))
+		end,
+		match_source = function(_, source)
+			return mode_or_nil(l.shelter_pattern_set_match_source(handle, source, #source))
+		end,
+	}
+end
+
+---@class ShelterValueScore
+---@field entropy number Shannon entropy in bits per byte
+---@field length number Length in characters
+---@field char_classes number Bitmask of character classes (1=lower, 2=upper, 4=digit, 8=other)
+---@field class ShelterSecretClass
+---@field credential ShelterCredentialKind|nil
+
+---Score a value by entropy, character classes and length, and detect known credential formats
+---Values like `true`, `3000` or `localhost` score as likely benign.
+---@param value string
+---@return ShelterValueScore
+function M.score_value(value)
+	local l = ensure_lib()
+	local score = l.shelter_score_value(value, #value)
+
+	return {
+		entropy = tonumber(score.entropy),
+		length = tonumber(score.length),
+		char_classes = tonumber(score.char_classes),
+		class = SECRET_CLASSES[score.class] or "unknown",
+		credential = CREDENTIAL_KINDS[score.credential],
+	}
+end
+
+---Compute a short keyed fingerprint tag of a value, e.g. "a3f9"
+---Without `key` the tag is keyed with a per-machine secret kept in the user data directory;
+---a shared `key` makes tags agree across machines.
+---@param value string
+---@param key? string Shared secret
+---@return string
+function M.fingerprint(value, key)
+	local l = ensure_lib()
+	local err = ffi.new("char*[1]")
+	local tag = l.shelter_fingerprint(value, #value, key, key and #key or 0, err)
+
+	if tag == nil then
+		local err_msg = err[0] ~= nil and ffi.string(err[0]) or "unknown error"
+		l.shelter_free_string(err[0])
+		error("Fingerprint error: " .. err_msg)
+	end
+
+	local result = ffi.string(tag)
+	l.shelter_free_string(tag)
+	return result
+end
+
+---Compare two fingerprint tags in constant time
+---@param a string
+---@param b string
+---@return boolean
+function M.fingerprint_equal(a, b)
+	local l = ensure_lib()
+	return l.shelter_fingerprint_equal(a, #a, b, #b) ~= 0
+end
+
+---@class ShelterNativeChange
+---@field id number Stable entry id
+---@field kind "added"|"changed"
+---@field entry ShelterParsedEntry
+---@field mask string|nil Nil if the value is left unmasked
+---@field masked_start number
+---@field masked_end number
+---@field value_start_col number Byte column of value start on entry.line_number
+---@field value_end_col number Byte column of value end on entry.value_end_line
+
+---@class ShelterNativeDelta
+---@field changes ShelterNativeChange[]
+---@field removed number[] Ids of entries that no longer exist
+
+---@class ShelterNativeDocument
+---@field edit fun(self: ShelterNativeDocument, start_byte: number, old_len: number, new_text: string): boolean
+---@field reset fun(self: ShelterNativeDocument, content: string): boolean
+---@field update fun(self: ShelterNativeDocument): ShelterNativeDelta
+---@field entries fun(self: ShelterNativeDocument): ShelterParseResult
+---@field entries_in_lines fun(self: ShelterNativeDocument, first_line: number, last_line: number): ShelterParseResult
+
+local CHANGE_KINDS = { [0] = "added", [1] = "changed" }
+
+---Create a persistent document for incremental re-parsing
+---Feed it `on_bytes` edits; `update()` returns only added, changed or removed entries.
+---The first `update()` reports every entry as added.
+---@param content string Initial content
+---@param mode_name "full"|"partial"|"none"
+---@param mode_opts? ShelterNativeModeOptions
+---@param skip_comments? boolean Skip values inside comments (default: true)
+---@param dialect? ShelterDialect|"auto" Dialect of the content, resolved once (default: "edf")
+---@return ShelterNativeDocument
+function M.document(content, mode_name, mode_opts, skip_comments, dialect)
+	local l = ensure_lib()
+	local mask_opts = build_mask_options(mode_name, mode_opts, skip_comments, dialect)
+	local err = ffi.new("char*[1]")
+
+	local handle = l.shelter_document_new(content, #content, mask_opts, err)
+	if handle == nil then
+		local err_msg = err[0] ~= nil and ffi.string(err[0]) or "unknown error"
+		l.shelter_free_string(err[0])
+		error("Document error: " .. err_msg)
+	end
+
+	handle = ffi.gc(handle, l.shelter_document_free)
+
+	return {
+		edit = function(_, start_byte, old_len, new_text)
+			return l.shelter_document_edit(handle, start_byte, old_len, new_text, #new_text) == 1
+		end,
+		reset = function(_, new_content)
+			return l.shelter_document_reset(handle, new_content, #new_content) == 1
+		end,
+		update = function(_)
+			local delta = l.shelter_document_update(handle)
+
+			if delta.error ~= nil then
+				local err_msg = ffi.string(delta.error)
+				l.shelter_free_document_delta(delta)
+				error("Document error: " .. err_msg)
+			end
+
+			local changes = {}
+			for i = 0, tonumber(delta.change_count) - 1 do
+				local c = delta.changes[i]
+				changes[i + 1] = {
+					id = tonumber(c.id),
+					kind = CHANGE_KINDS[c.kind],
+					entry = entry_to_table(c.entry),
+					mask = c.mask ~= nil and ffi.string(c.mask, c.mask_len) or nil,
+					masked_start = tonumber(c.masked_start),
+					masked_end = tonumber(c.masked_end),
+					value_start_col = tonumber(c.value_start_col),
+					value_end_col = tonumber(c.value_end_col),
+				}
+			end
+
+			local removed = {}
+			for i = 0, tonumber(delta.removed_count) - 1 do
+				removed[i + 1] = tonumber(delta.removed[i])
+			end
+
+			l.shelter_free_document_delta(delta)
+
+			return {
+				changes = changes,
+				removed = removed,
+			}
+		end,
+		entries = function(_)
+			local result = l.shelter_document_entries(handle)
+			local parsed = result_to_table(result)
+			l.shelter_free_result(result)
+			return parsed
+		end,
+		-- Entries starting on lines first_line..last_line (1-indexed), without
+		-- comments, diagnostics or duplicate links
+		entries_in_lines = function(_, first_line, last_line)
+			local result = l.shelter_document_entries_in_lines(handle, first_line, last_line)
+			local parsed = result_to_table(result)
+			l.shelter_free_result(result)
+			return parsed
+		end,
+	}
+end
+
 return M
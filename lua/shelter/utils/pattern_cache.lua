@@ -1,107 +1,47 @@
 ---@class ShelterPatternCache
----Pre-compiled pattern matching for O(1) lookups after O(n) setup
+---Pre-compiled pattern matching backed by a native pattern set
+---Supports globs (`*`, `**`, `?`, `[A-Z]`, `{a,b}`), `!` negation and `re:` regex rules.
+---Rules are ranked by specificity in Rust, so matching is deterministic.
 local M = {}
 
----@class CompiledPattern
----@field lua_pattern string
----@field mode string
----@field specificity number
+local native = require("shelter.native")
 
----@type table<string, CompiledPattern>
-local _key_patterns = {}
+---@type ShelterNativePatternSet|nil
+local _pattern_set = nil
 
 ---@type table<string, string>
-local _source_patterns = {}
+local _credentials = {}
 
 ---@type string
 local _default_mode = "full"
 
----@type boolean
-local _compiled = false
-
----Convert glob pattern to Lua pattern (cached at module level)
----@param glob string
----@return string
-local function glob_to_pattern(glob)
-	local pattern = glob
-	-- Escape special Lua pattern characters (except *)
-	pattern = pattern:gsub("([%.%+%-%?%^%$%(%)%[%]%%])", "%%%1")
-	-- Convert * to .*
-	pattern = pattern:gsub("%*", ".*")
-	return "^" .. pattern .. "$"
-end
-
----Calculate pattern specificity (higher = more specific)
----@param pattern string
----@return number
-local function calculate_specificity(pattern)
-	-- Longer patterns are more specific
-	-- Wildcards reduce specificity
-	local wildcards = 0
-	for _ in pattern:gmatch("%*") do
-		wildcards = wildcards + 1
-	end
-	return #pattern - (wildcards * 10)
-end
-
 ---Compile all patterns from config (call once at setup)
----@param config table The config table with patterns, sources, default_mode
+---@param config table The config table with patterns, sources, credentials, default_mode
 function M.compile(config)
-	_key_patterns = {}
-	_source_patterns = {}
 	_default_mode = config.default_mode or "full"
-
-	-- Pre-compile key patterns
-	for pattern, mode in pairs(config.patterns or {}) do
-		_key_patterns[pattern] = {
-			lua_pattern = glob_to_pattern(pattern),
-			mode = mode,
-			specificity = calculate_specificity(pattern),
-		}
-	end
-
-	-- Pre-compile source patterns
-	for pattern, mode in pairs(config.sources or {}) do
-		_source_patterns[pattern] = glob_to_pattern(pattern)
-		-- Store mode separately since we use pattern as key
-		_source_patterns[pattern .. "_mode"] = mode
-	end
-
-	_compiled = true
+	_credentials = config.credentials or {}
+	_pattern_set = native.pattern_set(config.patterns or {}, config.sources or {}, _default_mode)
 end
 
 ---Match a key against pre-compiled patterns
 ---@param key string
 ---@return string|nil mode
 function M.match_key(key)
-	local best_match = nil
-	local best_specificity = -math.huge -- Start with negative infinity so any match wins
-
-	for _, compiled in pairs(_key_patterns) do
-		if key:match(compiled.lua_pattern) then
-			if compiled.specificity > best_specificity then
-				best_specificity = compiled.specificity
-				best_match = compiled.mode
-			end
-		end
+	if not _pattern_set then
+		return nil
 	end
-
-	return best_match
+	return _pattern_set:match_key(key)
 end
 
----Match a source filename against pre-compiled patterns
----@param source_name string The basename of the source file
+---Match a source file against pre-compiled patterns
+---Patterns containing "/" match the full path, others the basename
+---@param source string Source file path or basename
 ---@return string|nil mode
-function M.match_source(source_name)
-	for pattern, lua_pattern in pairs(_source_patterns) do
-		-- Skip the _mode entries
-		if not pattern:match("_mode$") then
-			if source_name:match(lua_pattern) then
-				return _source_patterns[pattern .. "_mode"]
-			end
-		end
+function M.match_source(source)
+	if not _pattern_set then
+		return nil
 	end
-	return nil
+	return _pattern_set:match_source(source)
 end
 
 ---Get the default masking mode
@@ -112,37 +52,34 @@ end
 
 ---Determine masking mode for a key (uses pre-compiled patterns)
 ---@param key string
----@param source_basename string|nil Pre-computed basename of source file (caller should cache this)
+---@param source string|nil Source file path or basename
+---@param credential? string Detected credential type of the value
 ---@return string mode
-function M.determine_mode(key, source_basename)
-	-- Check key patterns first (most specific wins)
-	local key_mode = M.match_key(key)
-	if key_mode then
-		return key_mode
+function M.determine_mode(key, source, credential)
+	-- Credential mapping applies unless a key pattern names the key explicitly
+	local credential_mode = credential and _credentials[credential]
+	if credential_mode and not M.match_key(key) then
+		return credential_mode
 	end
 
-	-- Check source patterns (basename already extracted by caller)
-	if source_basename then
-		local source_mode = M.match_source(source_basename)
-		if source_mode then
-			return source_mode
-		end
+	if not _pattern_set then
+		return _default_mode
 	end
 
-	return _default_mode
+	-- Key patterns first (most specific wins), then source patterns, then default
+	return _pattern_set:resolve(key, source)
 end
 
 ---Check if patterns are compiled
 ---@return boolean
 function M.is_compiled()
-	return _compiled
+	return _pattern_set ~= nil
 end
 
 ---Clear compiled patterns (for config reload)
 function M.clear()
-	_key_patterns = {}
-	_source_patterns = {}
-	_compiled = false
+	_pattern_set = nil
+	_credentials = {}
 end
 
 return M